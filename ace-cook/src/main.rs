@@ -0,0 +1,100 @@
+#![deny(nonstandard_style)]
+#![deny(rust_2018_idioms)]
+#![deny(future_incompatible)]
+
+//! Cooks an asset directory into engine-ready files (see `asset_management::cook`), for the engine to mount over the sources.
+
+use asset_management::cook::{CookError, CookSettings, Cooker};
+use std::env;
+use std::path::PathBuf;
+use std::process;
+use thiserror::Error;
+
+const USAGE: &str = "Usage: ace-cook <asset directory> <output directory> [--no-mips] [--no-optimize] [--no-validate] [--keep-comments] [--force]
+
+Textures are compressed along with their mip chain (unless --no-mips), OBJ meshes are interleaved and
+their duplicate vertices merged (unless --no-optimize), and shaders are validated (unless --no-validate)
+with their comments stripped (unless --keep-comments). Files that were cooked before from the same
+contents with the same options are left alone, unless --force is passed.";
+
+fn main() {
+    match run(env::args().skip(1).collect()) {
+        Ok(true) => {},
+        Ok(false) => process::exit(1),
+        Err(err) => {
+            eprintln!("ace-cook: {}", err);
+            process::exit(1);
+        },
+    }
+}
+
+struct Options {
+    asset_dir: PathBuf,
+    output_dir: PathBuf,
+    settings: CookSettings,
+    force: bool,
+}
+
+fn parse_args(args: Vec<String>) -> Result<Options, CookCliError> {
+    let mut positional = Vec::new();
+    let mut settings = CookSettings::default();
+    let mut force = false;
+    for arg in args {
+        match arg.as_str() {
+            "-h" | "--help" => return Err(CookCliError::Usage),
+            "--no-mips" => settings.generate_mips = false,
+            "--no-optimize" => settings.optimize_meshes = false,
+            "--no-validate" => settings.validate_shaders = false,
+            "--keep-comments" => settings.strip_shader_comments = false,
+            "--force" => force = true,
+            _ if arg.starts_with("--") => return Err(CookCliError::Usage),
+            _ => positional.push(PathBuf::from(arg)),
+        }
+    }
+    match <[PathBuf; 2]>::try_from(positional) {
+        Ok([asset_dir, output_dir]) => Ok(Options {
+            asset_dir: asset_dir,
+            output_dir: output_dir,
+            settings: settings,
+            force: force,
+        }),
+        Err(_) => Err(CookCliError::Usage),
+    }
+}
+
+/// Whether every file cooked.
+fn run(args: Vec<String>) -> Result<bool, CookCliError> {
+    let options = parse_args(args)?;
+    let mut cooker = Cooker::new(&options.asset_dir, &options.output_dir, options.settings);
+    if options.force {
+        cooker.clear_cache();
+    }
+    let report = cooker.cook_all()?;
+
+    for path in &report.cooked {
+        println!("cooked   {}", path.display());
+    }
+    for path in &report.removed {
+        println!("removed  {}", path.display());
+    }
+    for (path, err) in &report.failed {
+        eprintln!("failed   {}: {}", path.display(), err);
+    }
+    println!("Cooked {} files into {} ({} up to date, {} failed)",
+        report.cooked.len(), options.output_dir.display(), report.cached.len(), report.failed.len());
+    Ok(report.failed.is_empty())
+}
+
+#[derive(Error, Debug)]
+enum CookCliError {
+    #[error("{}", USAGE)]
+    Usage,
+    #[error("{0}")]
+    CookError(CookError),
+}
+
+impl From<CookError> for CookCliError {
+    fn from(err: CookError) -> Self {
+        CookCliError::CookError(err)
+    }
+}
@@ -1,34 +1,35 @@
-use crate::{gl};
 use crate::types::*;
 use std::rc::Rc;
-use std::ptr;
 
 pub struct Renderable {
-    gl_ctx: Rc<gl::Gl>,
+    gl_ctx: Rc<GLContext>,
     model: Rc<dyn Model>,
+    material: Rc<Material>,
 }
 
 impl Renderable {
 
-    pub fn new(gl_ctx: Rc<gl::Gl>, model: Rc<dyn Model>) -> Result<Renderable, OpenGLError> {
+    /// Fails if the material has no program, or if the program reads attributes the model doesn't have.
+    pub fn new(gl_ctx: Rc<GLContext>, model: Rc<dyn Model>, material: Rc<Material>) -> Result<Renderable, OpenGLError> {
+        let program = material.program().ok_or_else(|| OpenGLError::MaterialWithoutProgram(material.name.clone()))?;
+        model.get_vertex_layout().validate_against(program.as_ref())?;
         Ok(Renderable {
             gl_ctx: gl_ctx,
             model: model,
-        })    
+            material: material,
+        })
     }
 
-    pub fn render(&self, array_dmode: GLMode, uniform_set: impl Fn(&CompiledShaderProgram) -> ()) -> Result<(), OpenGLError> {
-        let shader = self.model.get_shader();
-        shader.use_program();
+    /// Draws the model with its material's program, textures, parameters and render state.
+    pub fn render(&self, array_dmode: GLMode, uniform_set: impl Fn(&CompiledShaderProgram) -> Result<(), OpenGLError>) -> Result<(), OpenGLError> {
+        let shader = self.material.bind(&self.gl_ctx)?;
 
-        // Sets per-frame uniforms
+        // Sets whatever changes per draw rather than per material
         // For example, MVP matricies (specifically view and projection, since model should be passed into the program through the model data)
-        uniform_set(shader.as_ref());
+        uniform_set(shader.as_ref())?;
 
         self.model.get_vert_array_obj().bind();
-        unsafe {
-            self.gl_ctx.DrawElements(array_dmode as u32, self.model.get_indices().len() as i32, GLType::UnsignedInt.into(), ptr::null());
-        }
+        self.gl_ctx.draw_elements(array_dmode, self.model.get_elem_array_obj());
         Ok(())
     }
 }
\ No newline at end of file
@@ -1,37 +1,61 @@
-pub const CUBE_VERTICES: [f32; 192] = [
-    // positions       // colors       // tex coords
-    -1.0, -1.0,  1.0,  1.0, 0.0, 0.0,  0.0, 0.0, // front
-     1.0, -1.0,  1.0,  1.0, 0.0, 0.0,  1.0, 0.0,
-     1.0,  1.0,  1.0,  1.0, 0.0, 0.0,  1.0, 1.0,
-    -1.0,  1.0,  1.0,  1.0, 0.0, 0.0,  0.0, 1.0,
+use crate::types::Vertex;
 
-    -1.0,  1.0,  1.0,  1.0, 0.0, 0.0,  0.0, 0.0, // top
-     1.0,  1.0,  1.0,  1.0, 0.0, 0.0,  1.0, 0.0,
-     1.0,  1.0, -1.0,  1.0, 0.0, 0.0,  1.0, 1.0,
-    -1.0,  1.0, -1.0,  1.0, 0.0, 0.0,  0.0, 1.0,
+#[repr(C)]
+#[derive(Vertex, Debug, Clone, Copy)]
+pub struct CubeVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+    pub tex_coord: [f32; 2],
+}
 
-     1.0, -1.0, -1.0,  1.0, 0.0, 0.0,  0.0, 0.0, // back
-    -1.0, -1.0, -1.0,  1.0, 0.0, 0.0,  1.0, 0.0,
-    -1.0,  1.0, -1.0,  1.0, 0.0, 0.0,  1.0, 1.0,
-     1.0,  1.0, -1.0,  1.0, 0.0, 0.0,  0.0, 1.0,
+const fn cube_vertex(position: [f32; 3], color: [f32; 3], tex_coord: [f32; 2]) -> CubeVertex {
+    CubeVertex { position, color, tex_coord }
+}
 
-    -1.0, -1.0, -1.0,  1.0, 0.0, 0.0,  0.0, 0.0, //bottom
-     1.0, -1.0, -1.0,  1.0, 0.0, 0.0,  1.0, 0.0,
-     1.0, -1.0,  1.0,  1.0, 0.0, 0.0,  1.0, 1.0,
-    -1.0, -1.0,  1.0,  1.0, 0.0, 0.0,  0.0, 1.0,
+#[repr(C)]
+#[derive(Vertex, Debug, Clone, Copy)]
+pub struct QuadVertex {
+    pub position: [f32; 2],
+    pub tex_coord: [f32; 2],
+}
 
-    -1.0, -1.0, -1.0,  1.0, 0.0, 0.0,  0.0, 0.0, // left
-    -1.0, -1.0,  1.0,  1.0, 0.0, 0.0,  1.0, 0.0,
-    -1.0,  1.0,  1.0,  1.0, 0.0, 0.0,  1.0, 1.0,
-    -1.0,  1.0, -1.0,  1.0, 0.0, 0.0,  0.0, 1.0,
+const fn quad_vertex(position: [f32; 2], tex_coord: [f32; 2]) -> QuadVertex {
+    QuadVertex { position, tex_coord }
+}
 
-     1.0, -1.0,  1.0,  1.0, 0.0, 0.0,  0.0, 0.0, // right
-     1.0, -1.0, -1.0,  1.0, 0.0, 0.0,  1.0, 0.0,
-     1.0,  1.0, -1.0,  1.0, 0.0, 0.0,  1.0, 1.0,
-     1.0,  1.0,  1.0,  1.0, 0.0, 0.0,  0.0, 1.0,
+pub const CUBE_VERTICES: [CubeVertex; 24] = [
+    cube_vertex([-1.0, -1.0,  1.0], [1.0, 0.0, 0.0], [0.0, 0.0]), // front
+    cube_vertex([ 1.0, -1.0,  1.0], [1.0, 0.0, 0.0], [1.0, 0.0]),
+    cube_vertex([ 1.0,  1.0,  1.0], [1.0, 0.0, 0.0], [1.0, 1.0]),
+    cube_vertex([-1.0,  1.0,  1.0], [1.0, 0.0, 0.0], [0.0, 1.0]),
+
+    cube_vertex([-1.0,  1.0,  1.0], [1.0, 0.0, 0.0], [0.0, 0.0]), // top
+    cube_vertex([ 1.0,  1.0,  1.0], [1.0, 0.0, 0.0], [1.0, 0.0]),
+    cube_vertex([ 1.0,  1.0, -1.0], [1.0, 0.0, 0.0], [1.0, 1.0]),
+    cube_vertex([-1.0,  1.0, -1.0], [1.0, 0.0, 0.0], [0.0, 1.0]),
+
+    cube_vertex([ 1.0, -1.0, -1.0], [1.0, 0.0, 0.0], [0.0, 0.0]), // back
+    cube_vertex([-1.0, -1.0, -1.0], [1.0, 0.0, 0.0], [1.0, 0.0]),
+    cube_vertex([-1.0,  1.0, -1.0], [1.0, 0.0, 0.0], [1.0, 1.0]),
+    cube_vertex([ 1.0,  1.0, -1.0], [1.0, 0.0, 0.0], [0.0, 1.0]),
+
+    cube_vertex([-1.0, -1.0, -1.0], [1.0, 0.0, 0.0], [0.0, 0.0]), // bottom
+    cube_vertex([ 1.0, -1.0, -1.0], [1.0, 0.0, 0.0], [1.0, 0.0]),
+    cube_vertex([ 1.0, -1.0,  1.0], [1.0, 0.0, 0.0], [1.0, 1.0]),
+    cube_vertex([-1.0, -1.0,  1.0], [1.0, 0.0, 0.0], [0.0, 1.0]),
+
+    cube_vertex([-1.0, -1.0, -1.0], [1.0, 0.0, 0.0], [0.0, 0.0]), // left
+    cube_vertex([-1.0, -1.0,  1.0], [1.0, 0.0, 0.0], [1.0, 0.0]),
+    cube_vertex([-1.0,  1.0,  1.0], [1.0, 0.0, 0.0], [1.0, 1.0]),
+    cube_vertex([-1.0,  1.0, -1.0], [1.0, 0.0, 0.0], [0.0, 1.0]),
+
+    cube_vertex([ 1.0, -1.0,  1.0], [1.0, 0.0, 0.0], [0.0, 0.0]), // right
+    cube_vertex([ 1.0, -1.0, -1.0], [1.0, 0.0, 0.0], [1.0, 0.0]),
+    cube_vertex([ 1.0,  1.0, -1.0], [1.0, 0.0, 0.0], [1.0, 1.0]),
+    cube_vertex([ 1.0,  1.0,  1.0], [1.0, 0.0, 0.0], [0.0, 1.0]),
 ];
 
-pub const CUBE_INDICES: [u32; 36] = [
+pub const CUBE_INDICES: [u16; 36] = [
     // front
     0,  1,  2,
     2,  3,  0,
@@ -52,17 +76,16 @@ pub const CUBE_INDICES: [u32; 36] = [
     22, 23, 20,
 ];
 
-pub const QUAD_VERTICIES: [f32; 24] = [
-    // positions    // texCoords
-    -1.0,  1.0,     0.0, 1.0,
-    -1.0, -1.0,     0.0, 0.0,
-     1.0, -1.0,     1.0, 0.0,
+pub const QUAD_VERTICIES: [QuadVertex; 6] = [
+    quad_vertex([-1.0,  1.0], [0.0, 1.0]),
+    quad_vertex([-1.0, -1.0], [0.0, 0.0]),
+    quad_vertex([ 1.0, -1.0], [1.0, 0.0]),
 
-    -1.0,  1.0,     0.0, 1.0,
-     1.0, -1.0,     1.0, 0.0,
-     1.0,  1.0,     1.0, 1.0
+    quad_vertex([-1.0,  1.0], [0.0, 1.0]),
+    quad_vertex([ 1.0, -1.0], [1.0, 0.0]),
+    quad_vertex([ 1.0,  1.0], [1.0, 1.0]),
 ];
 
-pub const QUAD_INDICIES: [u32; 6] = [
+pub const QUAD_INDICIES: [u16; 6] = [
     0, 1, 2, 3, 4, 5
 ];
\ No newline at end of file
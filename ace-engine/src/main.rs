@@ -5,21 +5,29 @@
 use ace_gl_types as types;
 use ace_gl_types::gl;
 use asset_management::asset_loading;
+use asset_management::asset_loading::ManifestError;
+use asset_management::hl_assets::ObjLoadError;
+use asset_management::vfs::Layer;
 use components::*;
 use glfw::{Action, Context, Key};
 use image;
 use nalgebra_glm as glm;
+use std::fs;
 use std::io;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::time::Duration;
 use thiserror::Error;
 use types::*;
 
 mod actions;
 mod components;
 mod debug;
+mod profiler;
 mod utils;
 
 fn main() -> Result<(), RustyAceError> {
+    env_logger::init();
     let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS).unwrap();
     debug::init_debug_context(&mut glfw);
 
@@ -33,15 +41,16 @@ fn main() -> Result<(), RustyAceError> {
     // Migrated GL context to a ref-counted pointer inside all buffer/rendering structs.
     // This isn't as efficient as passing around references, and should eventually be migrated to lifetimes.
     let gl_context = gl::Gl::load_with(|s| window.get_proc_address(s) as *const _);
-    unsafe {
-        gl_context.Viewport(0, 0, 300, 300);
-    }
 
     let mut assets = asset_loading::AssetContainer::new("./assets", gl_context);
+    // Built by `ace-cook ./assets ./cooked`; resource packs go over it, so their sources win over stale cooked files.
+    if Path::new("./cooked").is_dir() {
+        assets.mount("cooked", Layer::Directory(PathBuf::from("./cooked")));
+    }
+    mount_resource_packs(&mut assets, "./resourcepacks");
+    assets.gl_ctx().viewport(0, 0, 300, 300);
     debug::init_debug_functionality(assets.gl_ctx());
 
-    //let obj_model = ObjModel::from_file(assets.gl_ctx(), "./assets/test/backpack.obj");
-
     // TODO: Develop a framebuffer container?
     // Not sure how we should be properly managing framebuffers tbh
     // esp. considering screen size can change... the framebuffer might need to realloc every time the screen size changes which is yikes
@@ -67,52 +76,22 @@ fn main() -> Result<(), RustyAceError> {
     );
     single_pass_fbo.unbind();
 
-    // TODO: develop an asset container
-    // We shouldn't have to manually specify all of the assets the program uses in the main function
-    // An asset container should be used to store it all
-    let tex_config = TexConfig::new(
-        TextureType::Texture2D,
-        InternalStorage::RGB,
-        PixelDataFormat::RGB,
-        PixelDataType::UnsignedByte,
-    );
-    assets.add_texture("texture1", "texture1.jpg", tex_config.clone())?;
-    assets.add_texture("texture2", "texture2.png", tex_config.clone())?;
-
-    let tex_config_cm = TexConfig::new(
-        TextureType::TextureCubeMap,
-        InternalStorage::RGB,
-        PixelDataFormat::RGB,
-        PixelDataType::UnsignedByte,
-    );
-    assets.add_cubemap("skybox", "skybox", tex_config_cm)?;
-    let assembled_shader = assets.add_program(
-        "shader_basic",
-        "basic/tex_norm/vertex_tex_norm.vert",
-        "basic/tex_norm/fragment_tex_norm.frag",
-        None,
-    )?;
-    assembled_shader.use_program();
-    assembled_shader.assign_texture_to_unit("texture1", types::TextureUnit::Slot0);
-    assembled_shader.assign_texture_to_unit("texture2", types::TextureUnit::Slot1);
-
-    let screenspace_shader = assets.add_program(
-        "screenspace_shader",
-        "frame/framebuffer.vert",
-        "frame/framebuffer.frag",
-        None,
-    )?;
-    screenspace_shader.use_program();
-    screenspace_shader.assign_texture_to_unit("screenTexture", types::TextureUnit::Slot0);
-
-    let skybox_shader = assets.add_program(
-        "skybox_shader",
-        "skybox/skybox.vert",
-        "skybox/skybox.frag",
-        None,
-    )?;
-    skybox_shader.use_program();
-    skybox_shader.assign_texture_to_unit("skybox", TextureUnit::Slot0);
+    // Textures, cubemaps and programs are listed in the manifest instead of being loaded one by one here.
+    // Every entry that fails is reported at once, rather than just the first.
+    assets.load_manifest("manifest.json")?;
+    let screenspace_shader = find_program(&assets, "core:screenspace_shader")?;
+    // The cube and skybox materials (assets/materials) say which textures go to which sampler.
+    // Sampler units are allocated by the program itself when it links, so there's nothing to assign here.
+    let cube_material = find_material(&assets, "core:materials/cube")?;
+    let skybox_material = find_material(&assets, "core:materials/skybox")?;
+    // The screen quad samples whatever was rendered into the framebuffer, which isn't an asset, so its material is made here.
+    let screenspace_material = Material::new("screenspace").with_render_state(RenderState::screenspace());
+    screenspace_material.set_program(screenspace_shader.clone());
+    let screenspace_material = Rc::new(screenspace_material);
+
+    // Only the backpack's .mtl and ambient occlusion map are checked in; drop backpack.obj and its other textures into assets/test,
+    // uncomment their maps in backpack.mtl, and list it under the manifest's models with
+    // { "name": "core:test/backpack", "program": "core:shader_basic" }
 
     // TODO: Develop a model file format or use a pre-existing one (.obj comes to mind)
     // The only reason we would develop our own model file format is that this is designed to be a voxel engine;
@@ -121,69 +100,24 @@ fn main() -> Result<(), RustyAceError> {
     // Note that any voxel format would still need to specify extended surfaces; rendering a ton of individual cubes might be rough on the GPU
     // Although instanced rendering might be able to help reduce the issue
     // We could also implement both a voxel-model format and a normal model format, to make it easier to develop voxel models while also allowing model flexibility.
+    // Vertex layouts now come from the vertex types themselves (see renderable::CubeVertex),
+    // and are checked against the attributes each material's shader actually reads when the renderables are made.
     let cube_model = Rc::new(ResidentModel::new(
         assets.gl_ctx(),
         &renderable::CUBE_VERTICES,
-        &renderable::CUBE_INDICES,
-        assembled_shader,
-        |vao| {
-            //TODO: is this the best way to have configurable attribute indices?
-            // Is there a more elegant solution?
-            // Or this this the most elegant solution?
-            // We seem to be using callbacks at least twice, including this one.
-            // This might be the best solution, although some form of interchange between shaders and VAO's would maybe be good (since shaders define what they accept)
-
-            // Now that VAO's are associated with models, they can be configured on a per-model basis.
-            // _Most_ models should load in with the same set of vertices (position, color?, texture, normal)
-            // HOWEVER, there are special cases! Thus, ResidentModel should probably keep the VAO configuration, and file loaded models should automagically configure the VAO.
-            // Esp. considering that OBJ files have the same vertex formatting!
-
-            vao.configure_index(
-                0,
-                AttributeProperties::new(AttributeComponentSize::Three, GLType::Float, false, 8, 0),
-            );
-
-            vao.configure_index(
-                1,
-                AttributeProperties::new(AttributeComponentSize::Three, GLType::Float, false, 8, 3),
-            );
-
-            vao.configure_index(
-                2,
-                AttributeProperties::new(AttributeComponentSize::Two, GLType::Float, false, 8, 6),
-            );
-        },
+        &renderable::CUBE_INDICES[..],
     ));
 
     let screenspace_quad = Rc::new(ResidentModel::new(
         assets.gl_ctx(),
         &renderable::QUAD_VERTICIES,
-        &renderable::QUAD_INDICIES,
-        screenspace_shader,
-        |vao| {
-            vao.configure_index(
-                0,
-                AttributeProperties::new(AttributeComponentSize::Two, GLType::Float, false, 4, 0),
-            );
-
-            vao.configure_index(
-                1,
-                AttributeProperties::new(AttributeComponentSize::Two, GLType::Float, false, 4, 2),
-            );
-        },
+        &renderable::QUAD_INDICIES[..],
     ));
 
     let skybox_model = Rc::new(ResidentModel::new(
         assets.gl_ctx(),
         &renderable::CUBE_VERTICES,
-        &renderable::CUBE_INDICES,
-        skybox_shader,
-        |vao| {
-            vao.configure_index(
-                0,
-                AttributeProperties::new(AttributeComponentSize::Three, GLType::Float, false, 8, 0),
-            );
-        },
+        &renderable::CUBE_INDICES[..],
     ));
 
     let mut camera = camera::Camera::new(
@@ -196,9 +130,9 @@ fn main() -> Result<(), RustyAceError> {
         45.0,
     );
 
-    let cube_render = renderable::Renderable::new(assets.gl_ctx(), cube_model)?;
-    let quad_render = renderable::Renderable::new(assets.gl_ctx(), screenspace_quad.clone())?;
-    let skybox_render = renderable::Renderable::new(assets.gl_ctx(), skybox_model)?;
+    let cube_render = renderable::Renderable::new(assets.gl_ctx(), cube_model, cube_material)?;
+    let quad_render = renderable::Renderable::new(assets.gl_ctx(), screenspace_quad.clone(), screenspace_material)?;
+    let skybox_render = renderable::Renderable::new(assets.gl_ctx(), skybox_model, skybox_material)?;
 
     window.set_key_polling(true);
     window.set_framebuffer_size_polling(true);
@@ -212,6 +146,9 @@ fn main() -> Result<(), RustyAceError> {
     let mut last_mouse_x = 0.0;
     let mut last_mouse_y = 0.0;
 
+    let mut last_stats_report: f32 = 0.0;
+    let mut profiler = profiler::FrameProfiler::new(assets.gl_ctx());
+
     while !window.should_close() {
         let current_frame = glfw.get_time() as f32;
         delta_t = current_frame - last_frame;
@@ -234,21 +171,27 @@ fn main() -> Result<(), RustyAceError> {
 
         process_input(&mut entry_context);
 
+        // Pick up edits to the shaders, textures and models under ./assets without restarting.
+        for failure in assets.poll_changes().failures {
+            log::error!("Reloading {} failed: {}", failure.name, failure.error);
+        }
+        // Upload what finished loading in the background, spending at most a couple of milliseconds of the frame on it.
+        for (name, error) in assets.finish_loads(Duration::from_millis(2)).failed {
+            log::error!("Loading {} failed: {}", name, error);
+        }
+
         let (width, height) = window.get_size();
         let view_matrix = camera.generate_view_matrix();
         let projection_matrix = camera.generate_projection_matrix(width as f32, height as f32);
 
         // --- BEGIN RENDER PASS ---
         // todo: this will all be moved into a render system, but for now, we're leaving it as it is
+        profiler.begin_frame();
+        profiler.begin_pass("scene");
         single_pass_fbo.bind(FrameBufferRDBehavior::RD);
         // We're now rendering inside the FBO
-        unsafe {
-            // TODO: move this into it's own function
-            let gl_ctx = assets.gl_ctx();
-            gl_ctx.Enable(gl::DEPTH_TEST);
-            gl_ctx.ClearColor(0.0f32, 0.2f32, 0.0f32, 1.0f32);
-            gl_ctx.Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-        }
+        assets.gl_ctx().clear_color(0.0, 0.2, 0.0, 1.0);
+        assets.gl_ctx().clear(ClearMask::COLOR | ClearMask::DEPTH);
 
         // TODO: Convert this to a real ECS system and implement physics.
         // Like _that's_ going to be easy.
@@ -260,15 +203,7 @@ fn main() -> Result<(), RustyAceError> {
             // This is a function that allows per-frame uniform setting. This will become important with transformations,
             // As this can be used to change the position of an object per-frame...
             // However, it could be wrapped in an optional member or perhaps another method to allow for rendering with shaders that do not have uniforms without passing in an empty closure
-            assets
-                .find_texture("texture1")
-                .expect("Failed to find texture")
-                .bind(TextureUnit::Slot0);
-            assets
-                .find_texture("texture2")
-                .expect("Failed to find texture")
-                .bind(TextureUnit::Slot1);
-
+            // The textures come with the material.
             let model = glm::rotate(
                 &glm::Mat4::identity(),
                 (glfw.get_time() as f32) * utils::radians(50.0),
@@ -277,58 +212,92 @@ fn main() -> Result<(), RustyAceError> {
             shdr.set_uniform("model", &model);
             shdr.set_uniform("view", &view_matrix);
             shdr.set_uniform("projection", &projection_matrix);
+            Ok(())
         })?;
+        profiler.end_pass();
 
         // -- render skybox here --
-        unsafe {
-            let gl_ctx = assets.gl_ctx();
-            gl_ctx.DepthFunc(gl::LEQUAL);
-        }
-
-        skybox_render.render(GLMode::Triangles, |shdr| {
-            assets
-                .find_texture("skybox")
-                .expect("Failed to find texture")
-                .bind(TextureUnit::Slot0);
-
-            let view = glm::mat3_to_mat4(&glm::mat4_to_mat3(&view_matrix));
-            shdr.set_uniform("view", &view);
-            shdr.set_uniform("projection", &projection_matrix);
+        // The skybox material carries its own render state (LEQUAL depth), so nothing to toggle here.
+
+        profiler.pass("skybox", || {
+            skybox_render.render(GLMode::Triangles, |shdr| {
+                let view = glm::mat3_to_mat4(&glm::mat4_to_mat3(&view_matrix));
+                shdr.set_uniform("view", &view);
+                shdr.set_uniform("projection", &projection_matrix);
+                Ok(())
+            })
         })?;
 
-        unsafe {
-            let gl_ctx = assets.gl_ctx();
-            gl_ctx.DepthFunc(gl::LESS);
-        }
-
+        profiler.begin_pass("screenspace");
         single_pass_fbo.unbind();
         // We're no longer rendering inside the FBO.
-        unsafe {
-            // TODO: move this into it's own function
-            let gl_ctx = assets.gl_ctx();
-            gl_ctx.Disable(gl::DEPTH_TEST);
-            gl_ctx.ClearColor(1.0, 1.0, 1.0, 1.0);
-            gl_ctx.Clear(gl::COLOR_BUFFER_BIT);
-        }
-        quad_render.render(GLMode::Triangles, |_| {
-            // There are no uniforms for this!
-            single_pass_fbo.get_texture(0).bind(TextureUnit::Slot0);
+        assets.gl_ctx().clear_color(1.0, 1.0, 1.0, 1.0);
+        assets.gl_ctx().clear(ClearMask::COLOR);
+        quad_render.render(GLMode::Triangles, |shdr| {
+            // There are no uniforms for this, just the scene we rendered into the framebuffer.
+            shdr.bind_texture("screenTexture", single_pass_fbo.get_texture(0))
         })?;
+        profiler.end_pass();
 
         window.swap_buffers();
 
         // --- END RENDER PASS ---
+
+        // Report how many state changes the cache saved us this frame, how long each pass is taking,
+        // and how many frames the GPU fell too far behind to be timed.
+        // Refreshed once a second, since retitling the window every frame isn't free either.
+        let state_stats = assets.gl_ctx().end_frame();
+        if current_frame - last_stats_report >= 1.0 {
+            last_stats_report = current_frame;
+            let pass_times: Vec<String> = profiler
+                .report()
+                .iter()
+                .map(|pass| format!("{} {:.2}/{:.2}ms", pass.name, pass.cpu_ms, pass.gpu_ms))
+                .collect();
+            window.set_title(&format!(
+                "RustyAce - {} GL state changes, {} redundant skipped - cpu/gpu: {} ({} frames without GPU times)",
+                state_stats.issued,
+                state_stats.skipped,
+                pass_times.join(", "),
+                profiler.dropped_frames()
+            ));
+        }
     }
 
+    // Set ACE_TRACE to a path to dump the last few hundred frames as a Chrome trace on exit.
+    if let Some(trace_path) = std::env::var_os("ACE_TRACE") {
+        profiler.write_chrome_trace(io::BufWriter::new(std::fs::File::create(trace_path)?))?;
+    }
+
+    // Tear the scene down while the window (and so the GL context) is still alive,
+    // then check that everything actually released its GL objects.
+    let gl_ctx = assets.gl_ctx();
+    drop((profiler, cube_render, quad_render, skybox_render, screenspace_quad, single_pass_fbo, assets));
+    gl_ctx.report_leaks();
+
     Ok(())
 }
 
+fn find_program(assets: &asset_loading::AssetContainer, name: &str) -> Result<Rc<CompiledShaderProgram>, RustyAceError> {
+    assets
+        .find_program(name)
+        .and_then(|handle| assets.get(handle))
+        .ok_or_else(|| RustyAceError::AssetNotFound(name.to_string()))
+}
+
+fn find_material(assets: &asset_loading::AssetContainer, name: &str) -> Result<Rc<Material>, RustyAceError> {
+    assets
+        .find_material(name)
+        .and_then(|handle| assets.get(handle))
+        .ok_or_else(|| RustyAceError::AssetNotFound(name.to_string()))
+}
+
 struct EntryContext<'a> {
     dt: f32,
     first_mouse: &'a mut bool,
     last_mouse_x: &'a mut f32,
     last_mouse_y: &'a mut f32,
-    gl_context: Rc<gl::Gl>,
+    gl_context: Rc<GLContext>,
     window: &'a mut glfw::Window,
     camera: &'a mut camera::Camera,
 }
@@ -340,9 +309,9 @@ fn handle_window_event(ctx: &mut EntryContext<'_>, event: glfw::WindowEvent) {
         glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _) => {
             ctx.window.set_should_close(true)
         }
-        glfw::WindowEvent::FramebufferSize(width, height) => unsafe {
-            ctx.gl_context.Viewport(0, 0, width, height);
-        },
+        glfw::WindowEvent::FramebufferSize(width, height) => {
+            ctx.gl_context.viewport(0, 0, width, height);
+        }
         glfw::WindowEvent::CursorPos(x, y) => {
             let x = x as f32;
             let y = y as f32;
@@ -384,6 +353,26 @@ fn process_input(ctx: &mut EntryContext<'_>) {
     }
 }
 
+/// Mounts every resource pack in `dir` over the base assets: directories laid out like `assets`, and archives built by
+/// `ace-pack`. Packs are mounted in name order, so a pack overrides the ones that sort before it.
+fn mount_resource_packs<P: AsRef<Path>>(assets: &mut asset_loading::AssetContainer, dir: P) {
+    let mut packs: Vec<_> = match fs::read_dir(dir) {
+        Ok(entries) => entries.flatten().map(|entry| entry.path()).collect(),
+        Err(_) => return,
+    };
+    packs.sort();
+    for pack in packs {
+        if pack.is_dir() {
+            let name = pack.display().to_string();
+            assets.mount(name, Layer::Directory(pack));
+        } else if pack.extension().is_some_and(|ext| ext == "pak") {
+            if let Err(err) = assets.mount_archive(&pack) {
+                log::warn!("Skipping resource pack {}: {}", pack.display(), err);
+            }
+        }
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum RustyAceError {
     #[error("OpenGL Failed: {0}")]
@@ -396,6 +385,10 @@ pub enum RustyAceError {
     TextureError(TextureError),
     #[error("Shader compile error: {0}")]
     ShaderCompileError(ShaderCompileError),
+    #[error("Model load failed: {0}")]
+    ObjLoadError(ObjLoadError),
+    #[error("Asset manifest failed: {0}")]
+    ManifestError(ManifestError),
     #[error("Asset not found: {0}")]
     AssetNotFound(String),
 }
@@ -427,3 +420,18 @@ impl From<ShaderCompileError> for RustyAceError {
         RustyAceError::ShaderCompileError(err)
     }
 }
+
+impl From<ManifestError> for RustyAceError {
+    fn from(err: ManifestError) -> Self {
+        RustyAceError::ManifestError(err)
+    }
+}
+
+impl From<ObjLoadError> for RustyAceError {
+    fn from(err: ObjLoadError) -> Self {
+        match err {
+            ObjLoadError::OpenGLError(err_o) => RustyAceError::OpenGLError(err_o),
+            _ => RustyAceError::ObjLoadError(err),
+        }
+    }
+}
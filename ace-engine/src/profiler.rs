@@ -0,0 +1,352 @@
+use crate::types::*;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// How many frames of queries are kept in flight before their results are read back.
+/// Reading them any sooner would make the CPU wait on the GPU.
+const FRAMES_IN_FLIGHT: usize = 3;
+/// How many samples the rolling averages cover.
+const ROLLING_WINDOW: usize = 60;
+/// How many frames of events are kept around for trace export.
+const TRACE_HISTORY: usize = 600;
+
+const CPU_TRACK: u32 = 1;
+const GPU_TRACK: u32 = 2;
+
+/// Times named render passes on both the CPU and the GPU.
+///
+/// Passes are bracketed with `begin_pass`/`end_pass` (or `pass`) and can nest.
+/// Each pass is also pushed as a GL debug group, so it shows up by name in RenderDoc captures.
+/// GPU times come from timestamp queries which are read back `FRAMES_IN_FLIGHT` frames later,
+/// so the numbers lag slightly behind but measuring never stalls the pipeline.
+pub struct FrameProfiler {
+    gl_ctx: Rc<GLContext>,
+    epoch: Instant,
+    frame: u64,
+    slots: Vec<FrameSlot>,
+    open_passes: Vec<usize>,
+    stats: HashMap<String, PassTimes>,
+    // Order passes were first seen in, so reports don't shuffle around between frames.
+    pass_order: Vec<String>,
+    trace: VecDeque<TraceEvent>,
+    gpu_epoch: Option<u64>,
+    dropped_frames: u64,
+}
+
+struct FrameSlot {
+    passes: Vec<PassRecord>,
+    // Reused between frames; each pass takes a (start, end) pair.
+    queries: Vec<(Query, Query)>,
+}
+
+struct PassRecord {
+    name: String,
+    cpu_start: Duration,
+    cpu_end: Option<Duration>,
+}
+
+#[derive(Default)]
+struct PassTimes {
+    cpu: RollingAverage,
+    gpu: RollingAverage,
+}
+
+/// Rolling CPU and GPU time of one pass, in milliseconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PassReport {
+    pub name: String,
+    pub cpu_ms: f64,
+    pub gpu_ms: f64,
+}
+
+/// A single complete ("X") event in the Chrome trace event format. Times are in microseconds.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TraceEvent {
+    pub name: String,
+    pub cat: &'static str,
+    pub ph: &'static str,
+    pub ts: f64,
+    pub dur: f64,
+    pub pid: u32,
+    pub tid: u32,
+}
+
+#[derive(Serialize)]
+struct TraceMetadata {
+    name: &'static str,
+    ph: &'static str,
+    pid: u32,
+    tid: u32,
+    args: TraceThreadName,
+}
+
+#[derive(Serialize)]
+struct TraceThreadName {
+    name: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum TraceRecord<'a> {
+    Metadata(TraceMetadata),
+    Event(&'a TraceEvent),
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TraceFile<'a> {
+    trace_events: Vec<TraceRecord<'a>>,
+    display_time_unit: &'static str,
+}
+
+impl FrameProfiler {
+    pub fn new(gl_ctx: Rc<GLContext>) -> FrameProfiler {
+        let slots = (0..FRAMES_IN_FLIGHT)
+            .map(|_| FrameSlot {
+                passes: Vec::new(),
+                queries: Vec::new(),
+            })
+            .collect();
+
+        FrameProfiler {
+            gl_ctx: gl_ctx,
+            epoch: Instant::now(),
+            frame: 0,
+            slots: slots,
+            open_passes: Vec::new(),
+            stats: HashMap::new(),
+            pass_order: Vec::new(),
+            trace: VecDeque::new(),
+            gpu_epoch: None,
+            dropped_frames: 0,
+        }
+    }
+
+    fn slot_index(&self) -> usize {
+        (self.frame as usize) % FRAMES_IN_FLIGHT
+    }
+
+    /// Starts a new frame, collecting whatever the slot being reused measured `FRAMES_IN_FLIGHT` frames ago.
+    pub fn begin_frame(&mut self) {
+        debug_assert!(self.open_passes.is_empty(), "frame ended with passes still open");
+        self.open_passes.clear();
+
+        self.frame += 1;
+        let idx = self.slot_index();
+        self.collect_slot(idx);
+    }
+
+    pub fn begin_pass(&mut self, name: &str) {
+        self.gl_ctx.push_debug_group(name);
+        let cpu_start = self.epoch.elapsed();
+        let idx = self.slot_index();
+        let gl_ctx = self.gl_ctx.clone();
+        let slot = &mut self.slots[idx];
+
+        let pass_idx = slot.passes.len();
+        if slot.queries.len() <= pass_idx {
+            slot.queries.push((Query::new(gl_ctx.clone(), QueryTarget::Timestamp), Query::new(gl_ctx, QueryTarget::Timestamp)));
+        }
+        slot.queries[pass_idx].0.record_timestamp();
+        slot.passes.push(PassRecord {
+            name: name.to_owned(),
+            cpu_start: cpu_start,
+            cpu_end: None,
+        });
+        self.open_passes.push(pass_idx);
+    }
+
+    pub fn end_pass(&mut self) {
+        let pass_idx = self.open_passes.pop().expect("end_pass called without a matching begin_pass");
+        let idx = self.slot_index();
+        let slot = &mut self.slots[idx];
+        slot.queries[pass_idx].1.record_timestamp();
+        slot.passes[pass_idx].cpu_end = Some(self.epoch.elapsed());
+        self.gl_ctx.pop_debug_group();
+    }
+
+    /// Runs `f` bracketed as a pass named `name`.
+    pub fn pass<T>(&mut self, name: &str, f: impl FnOnce() -> T) -> T {
+        self.begin_pass(name);
+        let result = f();
+        self.end_pass();
+        result
+    }
+
+    fn collect_slot(&mut self, idx: usize) {
+        let passes: Vec<PassRecord> = self.slots[idx].passes.drain(..).collect();
+        if passes.is_empty() {
+            return;
+        }
+
+        let gpu_times: Option<Vec<(u64, u64)>> = {
+            let queries = &self.slots[idx].queries;
+            (0..passes.len())
+                .map(|i| Some((queries[i].0.try_result()?, queries[i].1.try_result()?)))
+                .collect()
+        };
+        if gpu_times.is_none() {
+            // The GPU is more than FRAMES_IN_FLIGHT frames behind. Rather than wait, drop this frame's GPU numbers.
+            self.dropped_frames += 1;
+        }
+
+        for (i, pass) in passes.into_iter().enumerate() {
+            let cpu_end = match pass.cpu_end {
+                Some(end) => end,
+                None => continue,
+            };
+            let cpu_dur = cpu_end - pass.cpu_start;
+
+            if !self.stats.contains_key(&pass.name) {
+                self.pass_order.push(pass.name.clone());
+            }
+            let times = self.stats.entry(pass.name.clone()).or_default();
+            times.cpu.push(cpu_dur.as_secs_f64() * 1000.0);
+            self.trace.push_back(TraceEvent {
+                name: pass.name.clone(),
+                cat: "cpu",
+                ph: "X",
+                ts: pass.cpu_start.as_secs_f64() * 1_000_000.0,
+                dur: cpu_dur.as_secs_f64() * 1_000_000.0,
+                pid: 1,
+                tid: CPU_TRACK,
+            });
+
+            if let Some(gpu_times) = &gpu_times {
+                let (gpu_start, gpu_end) = gpu_times[i];
+                let gpu_epoch = *self.gpu_epoch.get_or_insert(gpu_start);
+                let gpu_dur = gpu_end.saturating_sub(gpu_start);
+                times.gpu.push(gpu_dur as f64 / 1_000_000.0);
+                self.trace.push_back(TraceEvent {
+                    name: pass.name,
+                    cat: "gpu",
+                    ph: "X",
+                    ts: gpu_start.saturating_sub(gpu_epoch) as f64 / 1000.0,
+                    dur: gpu_dur as f64 / 1000.0,
+                    pid: 1,
+                    tid: GPU_TRACK,
+                });
+            }
+        }
+
+        // Roughly TRACE_HISTORY frames worth, assuming the pass count stays about the same.
+        let max_events = TRACE_HISTORY * self.pass_order.len().max(1) * 2;
+        while self.trace.len() > max_events {
+            self.trace.pop_front();
+        }
+    }
+
+    /// Rolling averages for every pass seen so far, in the order they were first run.
+    pub fn report(&self) -> Vec<PassReport> {
+        self.pass_order.iter()
+            .map(|name| {
+                let times = &self.stats[name];
+                PassReport {
+                    name: name.clone(),
+                    cpu_ms: times.cpu.average(),
+                    gpu_ms: times.gpu.average(),
+                }
+            })
+            .collect()
+    }
+
+    /// Frames whose GPU results weren't ready in time and were thrown away.
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped_frames
+    }
+
+    /// Writes the recorded history as Chrome trace JSON, loadable in chrome://tracing or Perfetto.
+    /// CPU and GPU passes show up as two tracks; the GPU track's zero is the first pass it measured.
+    pub fn write_chrome_trace<W: io::Write>(&self, writer: W) -> io::Result<()> {
+        write_chrome_trace(self.trace.iter(), writer)
+    }
+}
+
+fn write_chrome_trace<'a, W: io::Write>(events: impl Iterator<Item = &'a TraceEvent>, writer: W) -> io::Result<()> {
+    let mut records = vec![
+        TraceRecord::Metadata(TraceMetadata { name: "thread_name", ph: "M", pid: 1, tid: CPU_TRACK, args: TraceThreadName { name: "CPU" } }),
+        TraceRecord::Metadata(TraceMetadata { name: "thread_name", ph: "M", pid: 1, tid: GPU_TRACK, args: TraceThreadName { name: "GPU" } }),
+    ];
+    records.extend(events.map(TraceRecord::Event));
+
+    let file = TraceFile {
+        trace_events: records,
+        display_time_unit: "ms",
+    };
+    serde_json::to_writer(writer, &file).map_err(io::Error::from)
+}
+
+#[derive(Default)]
+struct RollingAverage {
+    samples: VecDeque<f64>,
+    sum: f64,
+}
+
+impl RollingAverage {
+    fn push(&mut self, sample: f64) {
+        self.samples.push_back(sample);
+        self.sum += sample;
+        if self.samples.len() > ROLLING_WINDOW {
+            if let Some(old) = self.samples.pop_front() {
+                self.sum -= old;
+            }
+        }
+    }
+
+    fn average(&self) -> f64 {
+        if self.samples.is_empty() {
+            0.0
+        } else {
+            self.sum / self.samples.len() as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rolling_average_forgets_old_samples() {
+        let mut avg = RollingAverage::default();
+        assert_eq!(avg.average(), 0.0);
+
+        for _ in 0..ROLLING_WINDOW {
+            avg.push(10.0);
+        }
+        assert_eq!(avg.average(), 10.0);
+
+        for _ in 0..ROLLING_WINDOW {
+            avg.push(2.0);
+        }
+        assert!((avg.average() - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn chrome_trace_has_both_tracks() {
+        let events = vec![TraceEvent {
+            name: "scene".to_owned(),
+            cat: "gpu",
+            ph: "X",
+            ts: 10.0,
+            dur: 2.5,
+            pid: 1,
+            tid: GPU_TRACK,
+        }];
+
+        let mut out = Vec::new();
+        write_chrome_trace(events.iter(), &mut out).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+
+        let trace_events = json["traceEvents"].as_array().unwrap();
+        assert_eq!(trace_events.len(), 3);
+        assert_eq!(trace_events[0]["args"]["name"], "CPU");
+        assert_eq!(trace_events[1]["args"]["name"], "GPU");
+        assert_eq!(trace_events[2]["name"], "scene");
+        assert_eq!(trace_events[2]["dur"], 2.5);
+        assert_eq!(json["displayTimeUnit"], "ms");
+    }
+}
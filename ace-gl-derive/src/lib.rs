@@ -0,0 +1,193 @@
+#![deny(nonstandard_style)]
+#![deny(rust_2018_idioms)]
+#![deny(future_incompatible)]
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Lit, Meta, NestedMeta};
+
+/// Derives `ace_gl_types::Vertex` for a `#[repr(C)]` struct with named fields.
+///
+/// Every field becomes one attribute of the layout, with locations assigned in declaration order.
+/// Attributes that take up several locations, like `[f64; 3]`, push the next field's location along by as many.
+/// Two fields sharing a location is a compile error.
+/// Fields can be tweaked with `#[vertex(location = N)]`, `#[vertex(normalized)]` or skipped entirely with `#[vertex(skip)]`
+/// (for padding, mostly).
+#[proc_macro_derive(Vertex, attributes(vertex))]
+pub fn derive_vertex(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_vertex(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+struct FieldOptions {
+    location: Option<u32>,
+    normalized: bool,
+    skip: bool,
+}
+
+fn expand_vertex(input: DeriveInput) -> syn::Result<TokenStream2> {
+    if !has_repr_c(&input) {
+        return Err(syn::Error::new_spanned(&input.ident, "Vertex can only be derived for #[repr(C)] structs, otherwise the field offsets are not stable"));
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(named) => &named.named,
+            _ => return Err(syn::Error::new_spanned(&input.ident, "Vertex can only be derived for structs with named fields")),
+        },
+        _ => return Err(syn::Error::new_spanned(&input.ident, "Vertex can only be derived for structs")),
+    };
+
+    let mut used = Vec::new();
+    let mut vertex_fields = Vec::new();
+    for field in fields {
+        let options = field_options(field)?;
+        if options.skip {
+            continue;
+        }
+        let field_name = field.ident.as_ref().expect("named field without an identifier");
+        if let Some(location) = options.location {
+            if let Some((_, other)) = used.iter().find(|(used, _)| *used == location) {
+                return Err(syn::Error::new_spanned(field_name, format!("vertex location {} is already used by `{}`", location, other)));
+            }
+            used.push((location, field_name));
+        }
+        vertex_fields.push((field_name, &field.ty, options));
+    }
+
+    // Fields without an explicit location follow the previous one, which can only be done once the types are known.
+    // So the locations are worked out in a const block, which is also where overlaps turn into compile errors.
+    let location_names: Vec<_> = (0..vertex_fields.len()).map(|idx| format_ident!("location_{}", idx)).collect();
+    let mut locations = Vec::new();
+    for (idx, (_, field_ty, options)) in vertex_fields.iter().enumerate() {
+        let name = &location_names[idx];
+        let location = match (options.location, idx) {
+            (Some(location), _) => quote! { #location },
+            (None, 0) => quote! { 0u32 },
+            (None, _) => {
+                let (prev, prev_ty) = (&location_names[idx - 1], vertex_fields[idx - 1].1);
+                quote! { #prev + <#prev_ty as ::ace_gl_types::VertexAttributeType>::LOCATIONS }
+            },
+        };
+        locations.push(quote! {
+            let #name: u32 = #location;
+        });
+
+        for prev in 0..idx {
+            let (prev_field, prev_ty, _) = &vertex_fields[prev];
+            let prev_name = &location_names[prev];
+            let message = format!("vertex fields `{}` and `{}` share a location", prev_field, vertex_fields[idx].0);
+            locations.push(quote! {
+                if #prev_name < #name + <#field_ty as ::ace_gl_types::VertexAttributeType>::LOCATIONS
+                    && #name < #prev_name + <#prev_ty as ::ace_gl_types::VertexAttributeType>::LOCATIONS {
+                    panic!(#message);
+                }
+            });
+        }
+    }
+
+    let mut attributes = Vec::new();
+    for (idx, (field_name, field_ty, options)) in vertex_fields.iter().enumerate() {
+        let normalized = options.normalized;
+        attributes.push(quote! {
+            let offset = unsafe {
+                (::std::ptr::addr_of!((*base).#field_name) as *const u8).offset_from(base as *const u8) as usize
+            };
+            layout.push(::ace_gl_types::VertexAttribute {
+                location: locations[#idx],
+                components: <#field_ty as ::ace_gl_types::VertexAttributeType>::COMPONENTS,
+                attrib_type: <#field_ty as ::ace_gl_types::VertexAttributeType>::GL_TYPE,
+                normalized: #normalized,
+                offset: offset,
+            });
+        });
+    }
+
+    let count = vertex_fields.len();
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::ace_gl_types::Vertex for #name #ty_generics #where_clause {
+            fn layout() -> ::ace_gl_types::VertexLayout {
+                // Offsets are computed from an uninitialized value; nothing is ever read from it.
+                let uninit = ::std::mem::MaybeUninit::<Self>::uninit();
+                let base = uninit.as_ptr();
+                let locations: [u32; #count] = const {
+                    #(#locations)*
+                    [#(#location_names),*]
+                };
+                let mut layout = ::ace_gl_types::VertexLayout::new(::std::mem::size_of::<Self>());
+                #(#attributes)*
+                layout
+            }
+        }
+    })
+}
+
+fn has_repr_c(input: &DeriveInput) -> bool {
+    input.attrs.iter()
+        .filter(|attr| attr.path.is_ident("repr"))
+        .filter_map(|attr| attr.parse_meta().ok())
+        .any(|meta| match meta {
+            Meta::List(list) => list.nested.iter().any(|nested| match nested {
+                NestedMeta::Meta(Meta::Path(path)) => path.is_ident("C"),
+                _ => false,
+            }),
+            _ => false,
+        })
+}
+
+fn field_options(field: &syn::Field) -> syn::Result<FieldOptions> {
+    let mut options = FieldOptions {
+        location: None,
+        normalized: false,
+        skip: false,
+    };
+
+    for attr in field.attrs.iter().filter(|attr| attr.path.is_ident("vertex")) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            other => return Err(syn::Error::new_spanned(other, "expected #[vertex(...)]")),
+        };
+
+        for nested in list.nested.iter() {
+            match nested {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("normalized") => options.normalized = true,
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => options.skip = true,
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("location") => match &nv.lit {
+                    Lit::Int(int) => options.location = Some(int.base10_parse()?),
+                    other => return Err(syn::Error::new_spanned(other, "vertex location must be an integer")),
+                },
+                other => return Err(syn::Error::new_spanned(other, "unknown vertex option, expected `location = N`, `normalized` or `skip`")),
+            }
+        }
+    }
+
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn explicit_locations_cant_repeat() {
+        let input: DeriveInput = parse_quote! {
+            #[repr(C)]
+            struct Doubled {
+                #[vertex(location = 1)]
+                position: [f32; 3],
+                #[vertex(location = 1)]
+                normal: [f32; 3],
+            }
+        };
+        let err = expand_vertex(input).unwrap_err();
+        assert_eq!(err.to_string(), "vertex location 1 is already used by `position`");
+    }
+}
@@ -9,7 +9,8 @@ fn main() {
     let dest = env::var("OUT_DIR").unwrap();
     let mut file = File::create(&Path::new(&dest).join("bindings.rs")).unwrap();
 
-    Registry::new(Api::Gl, (4, 6), Profile::Core, Fallbacks::All, [])
+    // S3TC isn't core, but every desktop driver has it; cooked textures are stored in it.
+    Registry::new(Api::Gl, (4, 6), Profile::Core, Fallbacks::All, ["GL_EXT_texture_compression_s3tc", "GL_EXT_texture_sRGB"])
         .write_bindings(StructGenerator, &mut file)
         .unwrap();
 }
\ No newline at end of file
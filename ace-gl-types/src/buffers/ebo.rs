@@ -1,40 +1,90 @@
 use crate::gl;
+use std::cell::Cell;
 use std::rc::Rc;
 use crate::types::*;
 
 pub struct ElementArrayObj {
-    gl_ctx: Rc<gl::Gl>,
-    id: u32,
+    gl_ctx: Rc<GLContext>,
+    id: Cell<u32>,
+    index_type: Cell<GLType>,
+    count: Cell<usize>,
 }
 
 impl ElementArrayObj {
-    pub fn new(gl_ctx: Rc<gl::Gl>) -> ElementArrayObj {
+    pub fn new(gl_ctx: Rc<GLContext>) -> ElementArrayObj {
         let mut gl_id: u32 = 0;
         unsafe {
-            gl_ctx.GenBuffers(1, &mut gl_id);
+            // DSA names are real objects straight away; Gen'd ones only become objects on first bind.
+            if gl_ctx.dsa() {
+                gl_ctx.CreateBuffers(1, &mut gl_id);
+            } else {
+                gl_ctx.GenBuffers(1, &mut gl_id);
+            }
         }
+        gl_ctx.track_object(GLObjectKind::Buffer, gl_id);
         ElementArrayObj {
             gl_ctx: gl_ctx,
-            id: gl_id,
+            id: Cell::new(gl_id),
+            index_type: Cell::new(GLType::UnsignedInt),
+            count: Cell::new(0),
         }
     }
 
+    /// Names the object in debug output and captures.
+    pub fn set_label(&self, label: &str) {
+        self.gl_ctx.label_object(GLObjectKind::Buffer, self.id.get(), label);
+    }
+
     pub fn bind(&self) {
-        unsafe {
-            self.gl_ctx.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.id);
-        }
+        self.gl_ctx.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, self.id.get());
     }
 
     pub fn unbind(&self) {
-        unsafe {
-            self.gl_ctx.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, 0);
-        }
+        self.gl_ctx.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, 0);
+    }
+
+    pub(crate) fn id(&self) -> u32 {
+        self.id.get()
     }
 
-    pub fn copy_to_buffer<T: TypedBuffer>(&self, indicies: T, draw_mode: DrawMode) {
-        self.bind();
+    /// Uploads the indices and remembers their type and count, so draws don't have to be told again.
+    /// Without DSA this binds the buffer, and with it into whatever VAO is bound.
+    pub fn copy_to_buffer<T: IndexBuffer>(&mut self, indicies: T, draw_mode: DrawMode) {
         unsafe {
-            self.gl_ctx.BufferData(gl::ELEMENT_ARRAY_BUFFER, indicies.size() as isize, indicies.ref_ptr(), draw_mode as u32);
+            if self.gl_ctx.dsa() {
+                self.gl_ctx.NamedBufferData(self.id.get(), indicies.size() as isize, indicies.ref_ptr(), draw_mode as u32);
+            } else {
+                self.bind();
+                self.gl_ctx.BufferData(gl::ELEMENT_ARRAY_BUFFER, indicies.size() as isize, indicies.ref_ptr(), draw_mode as u32);
+            }
         }
+        self.index_type.set(indicies.get_gl_type());
+        self.count.set(indicies.length());
+        self.gl_ctx.set_object_size(GLObjectKind::Buffer, self.id.get(), indicies.size());
+    }
+
+    pub fn index_type(&self) -> GLType {
+        self.index_type.get()
     }
-}
\ No newline at end of file
+
+    pub fn index_count(&self) -> usize {
+        self.count.get()
+    }
+
+    pub fn size(&self) -> usize {
+        self.count.get() * self.index_type.get().sizeof()
+    }
+
+    /// Trades GL buffers with `other`; `other` deletes the old one when it drops.
+    pub fn swap(&self, other: &ElementArrayObj) {
+        self.id.swap(&other.id);
+        self.index_type.swap(&other.index_type);
+        self.count.swap(&other.count);
+    }
+}
+
+impl Drop for ElementArrayObj {
+    fn drop(&mut self) {
+        self.gl_ctx.delete_object(GLObjectKind::Buffer, self.id.get());
+    }
+}
@@ -3,7 +3,7 @@ use crate::types::*;
 use crate::gl;
 
 pub struct FrameBuffer {
-    gl_ctx: Rc<gl::Gl>, 
+    gl_ctx: Rc<GLContext>, 
     id: u32,
     textures: Vec<Texture>,
     render_buffers: Vec<RenderBuffer>,
@@ -11,11 +11,16 @@ pub struct FrameBuffer {
 }
 
 impl FrameBuffer {
-    pub fn new(gl_ctx: Rc<gl::Gl>) -> FrameBuffer {
+    pub fn new(gl_ctx: Rc<GLContext>) -> FrameBuffer {
         let mut fbo_id = 0;
         unsafe {
-            gl_ctx.GenFramebuffers(1, &mut fbo_id);
+            if gl_ctx.dsa() {
+                gl_ctx.CreateFramebuffers(1, &mut fbo_id);
+            } else {
+                gl_ctx.GenFramebuffers(1, &mut fbo_id);
+            }
         }
+        gl_ctx.track_object(GLObjectKind::FrameBuffer, fbo_id);
         FrameBuffer {
             gl_ctx: gl_ctx,
             id: fbo_id,
@@ -24,31 +29,39 @@ impl FrameBuffer {
         }
     }
 
+    /// Names the object in debug output and captures.
+    pub fn set_label(&self, label: &str) {
+        self.gl_ctx.label_object(GLObjectKind::FrameBuffer, self.id, label);
+    }
+
     pub fn bind(&self, behavior: FrameBufferRDBehavior) {
-        unsafe {
-            self.gl_ctx.BindFramebuffer(behavior as u32, self.id);
-        }
+        self.gl_ctx.bind_framebuffer(behavior as u32, self.id);
     }
 
     pub fn unbind(&self) {
-        unsafe {
-            self.gl_ctx.BindFramebuffer(FrameBufferRDBehavior::RD as u32, 0);
-        }
+        self.gl_ctx.bind_framebuffer(FrameBufferRDBehavior::RD as u32, 0);
     }
 
+    /// Without DSA this checks whatever framebuffer is bound, so bind this one first.
     pub fn is_complete(&self) -> bool {
         unsafe {
-            self.gl_ctx.CheckFramebufferStatus(gl::FRAMEBUFFER) == gl::FRAMEBUFFER_COMPLETE
+            if self.gl_ctx.dsa() {
+                self.gl_ctx.CheckNamedFramebufferStatus(self.id, gl::FRAMEBUFFER) == gl::FRAMEBUFFER_COMPLETE
+            } else {
+                self.gl_ctx.CheckFramebufferStatus(gl::FRAMEBUFFER) == gl::FRAMEBUFFER_COMPLETE
+            }
         }
     }
 
+    /// Attaches a new texture. Without DSA this framebuffer is left bound afterwards.
     pub fn attach_texture(&mut self, width: i32, height: i32, tex_cfg: TexConfig, attachment: FrameBufferAttachment) {
-        let tex = Texture::from_framebuffer(self.gl_ctx.clone(), width, height, tex_cfg, attachment);
+        let tex = Texture::from_framebuffer(self.gl_ctx.clone(), self.id, width, height, tex_cfg, attachment);
         self.textures.push(tex);
     }
 
+    /// Attaches a new renderbuffer. Without DSA this framebuffer is left bound afterwards.
     pub fn attach_renderbuffer(&mut self, width: i32, height: i32, internal_storage: InternalStorage, attachment: FrameBufferAttachment) {
-        let rbo = RenderBuffer::from_framebuffer(self.gl_ctx.clone(), internal_storage, width, height, attachment);
+        let rbo = RenderBuffer::from_framebuffer(self.gl_ctx.clone(), self.id, internal_storage, width, height, attachment);
         self.render_buffers.push(rbo);
     }
 
@@ -57,6 +70,13 @@ impl FrameBuffer {
     }
 }
 
+impl Drop for FrameBuffer {
+    fn drop(&mut self) {
+        // The attached textures and renderbuffers are dropped (and deleted) after this, along with the rest of the fields.
+        self.gl_ctx.delete_object(GLObjectKind::FrameBuffer, self.id);
+    }
+}
+
 pub enum FrameBufferRDBehavior {
     ReadOnly    = gl::READ_FRAMEBUFFER as isize,
     DrawOnly    = gl::DRAW_FRAMEBUFFER as isize,
@@ -3,7 +3,7 @@ use crate::types::*;
 use crate::gl;
 
 pub struct RenderBuffer {
-    gl_ctx: Rc<gl::Gl>, 
+    gl_ctx: Rc<GLContext>, 
     id: u32,
     int_str: InternalStorage,
     attachment: FrameBufferAttachment,
@@ -22,14 +22,23 @@ impl RenderBuffer {
         }
     }
 
-    pub fn from_framebuffer(gl_ctx: Rc<gl::Gl>, int_str: InternalStorage, width: i32, height: i32, attachment: FrameBufferAttachment) -> RenderBuffer {
+    pub(crate) fn from_framebuffer(gl_ctx: Rc<GLContext>, fbo_id: u32, int_str: InternalStorage, width: i32, height: i32, attachment: FrameBufferAttachment) -> RenderBuffer {
         let mut rbo_id = 0;
         unsafe {
-            gl_ctx.GenRenderbuffers(1, &mut rbo_id);
-            gl_ctx.BindRenderbuffer(gl::RENDERBUFFER, rbo_id);
-            gl_ctx.RenderbufferStorage(gl::RENDERBUFFER, int_str as u32, width, height);
-            gl_ctx.FramebufferRenderbuffer(gl::FRAMEBUFFER, attachment.clone().into(), gl::RENDERBUFFER, rbo_id);
+            if gl_ctx.dsa() {
+                gl_ctx.CreateRenderbuffers(1, &mut rbo_id);
+                gl_ctx.NamedRenderbufferStorage(rbo_id, int_str.sized() as u32, width, height);
+                gl_ctx.NamedFramebufferRenderbuffer(fbo_id, attachment.clone().into(), gl::RENDERBUFFER, rbo_id);
+            } else {
+                gl_ctx.GenRenderbuffers(1, &mut rbo_id);
+                gl_ctx.BindRenderbuffer(gl::RENDERBUFFER, rbo_id);
+                gl_ctx.RenderbufferStorage(gl::RENDERBUFFER, int_str as u32, width, height);
+                gl_ctx.bind_framebuffer(gl::FRAMEBUFFER, fbo_id);
+                gl_ctx.FramebufferRenderbuffer(gl::FRAMEBUFFER, attachment.clone().into(), gl::RENDERBUFFER, rbo_id);
+            }
         }
+        gl_ctx.track_object(GLObjectKind::RenderBuffer, rbo_id);
+        gl_ctx.set_object_size(GLObjectKind::RenderBuffer, rbo_id, (width * height) as usize * int_str.bytes_per_pixel());
         
         RenderBuffer {
             gl_ctx: gl_ctx,
@@ -38,4 +47,10 @@ impl RenderBuffer {
             attachment: attachment
         }
     }
-}
\ No newline at end of file
+}
+
+impl Drop for RenderBuffer {
+    fn drop(&mut self) {
+        self.gl_ctx.delete_object(GLObjectKind::RenderBuffer, self.id);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::gl;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::rc::Rc;
 use crate::types::*;
@@ -24,49 +25,179 @@ impl AttributeProperties {
             offset: offset * (compute_size as u32),
         }
     }
+
+    /// Builds the properties of an attribute straight from a `VertexLayout`, where stride and offset are already in bytes.
+    pub fn from_layout(attribute: &VertexAttribute, stride: usize) -> AttributeProperties {
+        AttributeProperties {
+            attrib_size: attribute.components,
+            attrib_type: attribute.attrib_type,
+            normalized: attribute.normalized,
+            stride: stride as i32,
+            offset: attribute.offset as u32,
+        }
+    }
+}
+
+/// How the shader sees an attribute, which decides the GL call that sets it up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AttributeFormat {
+    /// Read as floats, converting (and maybe normalizing) whatever's stored.
+    Float,
+    /// Read as `int`/`uint` inputs, unconverted.
+    Integer,
+    /// Read as `double` inputs.
+    Double,
+}
+
+impl AttributeProperties {
+    fn format(&self) -> AttributeFormat {
+        match self.attrib_type {
+            // Normalized integers end up as floats in [0, 1] or [-1, 1].
+            _ if self.normalized => AttributeFormat::Float,
+            GLType::Byte | GLType::UnsignedByte | GLType::Short | GLType::UnsignedShort | GLType::Int | GLType::UnsignedInt => AttributeFormat::Integer,
+            GLType::Double => AttributeFormat::Double,
+            GLType::HalfFloat | GLType::Float | GLType::Fixed => AttributeFormat::Float,
+        }
+    }
 }
 
 pub struct VertexArrayObj {
-    gl_ctx: Rc<gl::Gl>,
-    id: u32,
-    attributes: HashMap<u32, AttributeProperties>,
+    gl_ctx: Rc<GLContext>,
+    id: Cell<u32>,
+    attributes: RefCell<HashMap<u32, AttributeProperties>>,
 }
 
 impl VertexArrayObj {
-    pub fn new(gl_ctx: Rc<gl::Gl>) -> VertexArrayObj {
+    pub fn new(gl_ctx: Rc<GLContext>) -> VertexArrayObj {
         let mut gl_id: u32 = 0;
         unsafe {
-            gl_ctx.GenVertexArrays(1, &mut gl_id);
+            if gl_ctx.dsa() {
+                gl_ctx.CreateVertexArrays(1, &mut gl_id);
+            } else {
+                gl_ctx.GenVertexArrays(1, &mut gl_id);
+            }
         }
+        gl_ctx.track_object(GLObjectKind::VertexArray, gl_id);
         VertexArrayObj {
             gl_ctx: gl_ctx,
-            id: gl_id,
-            attributes: HashMap::new()
+            id: Cell::new(gl_id),
+            attributes: RefCell::new(HashMap::new())
         }
     }
 
+    /// Names the object in debug output and captures.
+    pub fn set_label(&self, label: &str) {
+        self.gl_ctx.label_object(GLObjectKind::VertexArray, self.id.get(), label);
+    }
+
     pub fn bind(&self) {
-        unsafe {
-            self.gl_ctx.BindVertexArray(self.id)
-        }
+        self.gl_ctx.bind_vertex_array(self.id.get());
     }
 
     pub fn unbind(&self) {
+        self.gl_ctx.bind_vertex_array(0);
+    }
+
+    pub fn configure_index(&mut self, index: u32, prop: AttributeProperties) {
         unsafe {
-            self.gl_ctx.BindVertexArray(0)
+            match prop.format() {
+                AttributeFormat::Float => {
+                    let normalized = if prop.normalized { gl::TRUE } else { gl::FALSE };
+                    self.gl_ctx.VertexAttribPointer(index, prop.attrib_size as i32, prop.attrib_type.into(), normalized, prop.stride, prop.offset as *const _);
+                },
+                AttributeFormat::Integer => {
+                    self.gl_ctx.VertexAttribIPointer(index, prop.attrib_size as i32, prop.attrib_type.into(), prop.stride, prop.offset as *const _);
+                },
+                AttributeFormat::Double => {
+                    self.gl_ctx.VertexAttribLPointer(index, prop.attrib_size as i32, prop.attrib_type.into(), prop.stride, prop.offset as *const _);
+                },
+            }
+            self.gl_ctx.EnableVertexAttribArray(index);
         }
+        self.attributes.borrow_mut().insert(index, prop);
     }
 
-    pub fn configure_index(&mut self, index: u32, prop: AttributeProperties) {
+    /// Configures every attribute in the layout against the currently bound vertex buffer.
+    pub fn configure_from_layout(&mut self, layout: &VertexLayout) {
+        for attribute in layout.attributes() {
+            self.configure_index(attribute.location, AttributeProperties::from_layout(attribute, layout.stride()));
+        }
+    }
+
+    /// Sources every attribute in `layout` from `vbo`, at buffer binding point `binding`.
+    ///
+    /// With DSA this doesn't touch the current bindings. Without it, this VAO and the buffer are left bound.
+    pub fn attach_vertex_buffer(&mut self, binding: u32, vbo: &VertexBufferObj, layout: &VertexLayout) {
+        if !self.gl_ctx.dsa() {
+            self.bind();
+            vbo.bind();
+            self.configure_from_layout(layout);
+            return;
+        }
+
         unsafe {
-            if prop.normalized {
-                self.gl_ctx.VertexAttribPointer(index, prop.attrib_size as i32, prop.attrib_type.into(), gl::TRUE, prop.stride, prop.offset as *const _);
+            self.gl_ctx.VertexArrayVertexBuffer(self.id.get(), binding, vbo.id(), 0, layout.stride() as i32);
+        }
+        for attribute in layout.attributes() {
+            let prop = AttributeProperties::from_layout(attribute, layout.stride());
+            unsafe {
+                match prop.format() {
+                    AttributeFormat::Float => {
+                        let normalized = if prop.normalized { gl::TRUE } else { gl::FALSE };
+                        self.gl_ctx.VertexArrayAttribFormat(self.id.get(), attribute.location, prop.attrib_size as i32, prop.attrib_type.into(), normalized, prop.offset);
+                    },
+                    AttributeFormat::Integer => {
+                        self.gl_ctx.VertexArrayAttribIFormat(self.id.get(), attribute.location, prop.attrib_size as i32, prop.attrib_type.into(), prop.offset);
+                    },
+                    AttributeFormat::Double => {
+                        self.gl_ctx.VertexArrayAttribLFormat(self.id.get(), attribute.location, prop.attrib_size as i32, prop.attrib_type.into(), prop.offset);
+                    },
+                }
+                self.gl_ctx.VertexArrayAttribBinding(self.id.get(), attribute.location, binding);
+                self.gl_ctx.EnableVertexArrayAttrib(self.id.get(), attribute.location);
+            }
+            self.attributes.borrow_mut().insert(attribute.location, prop);
+        }
+    }
 
-            } else {
-                self.gl_ctx.VertexAttribPointer(index, prop.attrib_size as i32, prop.attrib_type.into(), gl::FALSE, prop.stride, prop.offset as *const _);
+    /// Makes `ebo` this VAO's index buffer. Without DSA this VAO is left bound.
+    pub fn attach_element_buffer(&mut self, ebo: &ElementArrayObj) {
+        if self.gl_ctx.dsa() {
+            unsafe {
+                self.gl_ctx.VertexArrayElementBuffer(self.id.get(), ebo.id());
             }
-            self.gl_ctx.EnableVertexAttribArray(index);
+        } else {
+            self.bind();
+            ebo.bind();
         }
-        self.attributes.insert(index, prop);
     }
-}
\ No newline at end of file
+
+    /// Trades GL vertex arrays with `other`; `other` deletes the old one when it drops.
+    pub fn swap(&self, other: &VertexArrayObj) {
+        self.id.swap(&other.id);
+        self.attributes.swap(&other.attributes);
+    }
+}
+
+impl Drop for VertexArrayObj {
+    fn drop(&mut self) {
+        self.gl_ctx.delete_object(GLObjectKind::VertexArray, self.id.get());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attributes_pick_their_format_by_type() {
+        let format = |attrib_type, normalized| AttributeProperties::new(AttributeComponentSize::Four, attrib_type, normalized, 0, 0).format();
+        assert_eq!(format(GLType::Float, false), AttributeFormat::Float);
+        assert_eq!(format(GLType::HalfFloat, false), AttributeFormat::Float);
+        assert_eq!(format(GLType::Int, false), AttributeFormat::Integer);
+        assert_eq!(format(GLType::UnsignedByte, false), AttributeFormat::Integer);
+        assert_eq!(format(GLType::UnsignedByte, true), AttributeFormat::Float);
+        assert_eq!(format(GLType::Short, true), AttributeFormat::Float);
+        assert_eq!(format(GLType::Double, false), AttributeFormat::Double);
+    }
+}
@@ -1,40 +1,88 @@
 use crate::gl;
+use std::cell::Cell;
 use std::rc::Rc;
+use std::mem;
 use crate::types::*;
 
 pub struct VertexBufferObj {
-    gl_ctx: Rc<gl::Gl>,
-    id: u32,
+    gl_ctx: Rc<GLContext>,
+    id: Cell<u32>,
+    size: Cell<usize>,
 }
 
 impl VertexBufferObj {
-    pub fn new(gl_ctx: Rc<gl::Gl>) -> VertexBufferObj {
+    pub fn new(gl_ctx: Rc<GLContext>) -> VertexBufferObj {
         let mut gl_id: u32 = 0;
         unsafe {
-            gl_ctx.GenBuffers(1, &mut gl_id);
-        }        
+            // DSA names are real objects straight away; Gen'd ones only become objects on first bind.
+            if gl_ctx.dsa() {
+                gl_ctx.CreateBuffers(1, &mut gl_id);
+            } else {
+                gl_ctx.GenBuffers(1, &mut gl_id);
+            }
+        }
+        gl_ctx.track_object(GLObjectKind::Buffer, gl_id);
         VertexBufferObj {
             gl_ctx: gl_ctx,
-            id: gl_id
+            id: Cell::new(gl_id),
+            size: Cell::new(0),
         }
     }
 
+    /// Names the object in debug output and captures.
+    pub fn set_label(&self, label: &str) {
+        self.gl_ctx.label_object(GLObjectKind::Buffer, self.id.get(), label);
+    }
+
     pub fn bind(&self) {
-        unsafe {
-            self.gl_ctx.BindBuffer(gl::ARRAY_BUFFER, self.id);
-        }
+        self.gl_ctx.bind_buffer(gl::ARRAY_BUFFER, self.id.get());
     }
 
     pub fn unbind(&self) {
+        self.gl_ctx.bind_buffer(gl::ARRAY_BUFFER, 0);
+    }
+
+    pub(crate) fn id(&self) -> u32 {
+        self.id.get()
+    }
+
+    /// (Re)allocates the buffer's storage. With DSA this leaves the current bindings alone;
+    /// otherwise the buffer ends up bound to GL_ARRAY_BUFFER.
+    fn buffer_data(&self, size: usize, data: *const std::ffi::c_void, draw_mode: DrawMode) {
         unsafe {
-            self.gl_ctx.BindBuffer(gl::ARRAY_BUFFER, 0);
+            if self.gl_ctx.dsa() {
+                self.gl_ctx.NamedBufferData(self.id.get(), size as isize, data, draw_mode as u32);
+            } else {
+                self.bind();
+                self.gl_ctx.BufferData(gl::ARRAY_BUFFER, size as isize, data, draw_mode as u32);
+            }
         }
+        self.size.set(size);
+        self.gl_ctx.set_object_size(GLObjectKind::Buffer, self.id.get(), size);
+    }
+
+    /// Size of the buffer's storage in bytes, as of the last upload.
+    pub fn size(&self) -> usize {
+        self.size.get()
+    }
+
+    /// Trades GL buffers with `other`; `other` deletes the old one when it drops.
+    pub fn swap(&self, other: &VertexBufferObj) {
+        self.id.swap(&other.id);
+        self.size.swap(&other.size);
     }
 
     pub fn copy_to_buffer<T: TypedBuffer>(&self, verts: T, draw_mode: DrawMode) {
-        self.bind();
-        unsafe {
-            self.gl_ctx.BufferData(gl::ARRAY_BUFFER, verts.size() as isize, verts.ref_ptr(), draw_mode as u32);
-        }
+        self.buffer_data(verts.size(), verts.ref_ptr(), draw_mode);
+    }
+
+    pub fn copy_vertices<V: Vertex>(&self, verts: &[V], draw_mode: DrawMode) {
+        self.buffer_data(mem::size_of_val(verts), verts.as_ptr() as *const _, draw_mode);
+    }
+}
+
+impl Drop for VertexBufferObj {
+    fn drop(&mut self) {
+        self.gl_ctx.delete_object(GLObjectKind::Buffer, self.id.get());
     }
 }
\ No newline at end of file
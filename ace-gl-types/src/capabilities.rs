@@ -0,0 +1,71 @@
+use crate::gl;
+use std::ffi::CStr;
+
+/// What the driver we're running on can do, beyond what the 4.6 core bindings promise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GLCapabilities {
+    pub version: (i32, i32),
+    pub extensions: Vec<String>,
+    /// `glCreate*`/`glNamed*` and friends: GL 4.5 or ARB_direct_state_access.
+    pub direct_state_access: bool,
+    /// GL_MAX_COMBINED_TEXTURE_IMAGE_UNITS, the number of units samplers across all stages can use.
+    pub max_texture_units: u32,
+}
+
+impl GLCapabilities {
+    /// Queries the current context. It must already be made current.
+    pub fn detect(gl: &gl::Gl) -> GLCapabilities {
+        let mut major = 0;
+        let mut minor = 0;
+        let mut extension_count = 0;
+        let mut max_texture_units = 0;
+        let mut extensions = Vec::new();
+        unsafe {
+            // Contexts older than 3.0 don't know these queries and leave the zeroes alone.
+            gl.GetIntegerv(gl::MAJOR_VERSION, &mut major);
+            gl.GetIntegerv(gl::MINOR_VERSION, &mut minor);
+            gl.GetIntegerv(gl::NUM_EXTENSIONS, &mut extension_count);
+            gl.GetIntegerv(gl::MAX_COMBINED_TEXTURE_IMAGE_UNITS, &mut max_texture_units);
+
+            if gl.GetStringi.is_loaded() {
+                for idx in 0..extension_count.max(0) as u32 {
+                    let name = gl.GetStringi(gl::EXTENSIONS, idx);
+                    if !name.is_null() {
+                        extensions.push(CStr::from_ptr(name as *const _).to_string_lossy().into_owned());
+                    }
+                }
+            }
+        }
+
+        let version = (major, minor);
+        let has_dsa_extension = extensions.iter().any(|ext| ext == "GL_ARB_direct_state_access");
+        // The bindings are loaded with fallbacks, so also make sure the entry points actually resolved.
+        let dsa_loaded = gl.CreateBuffers.is_loaded()
+            && gl.CreateVertexArrays.is_loaded()
+            && gl.CreateTextures.is_loaded()
+            && gl.CreateFramebuffers.is_loaded()
+            && gl.CreateRenderbuffers.is_loaded();
+
+        GLCapabilities {
+            version: version,
+            direct_state_access: (version >= (4, 5) || has_dsa_extension) && dsa_loaded,
+            // Every GL version we can run on guarantees at least 16.
+            max_texture_units: max_texture_units.max(16) as u32,
+            extensions: extensions,
+        }
+    }
+
+    /// Capabilities with every optional code path turned off; forces the bind-to-edit fallbacks.
+    pub fn legacy(version: (i32, i32)) -> GLCapabilities {
+        GLCapabilities {
+            version: version,
+            extensions: Vec::new(),
+            direct_state_access: false,
+            max_texture_units: 16,
+        }
+    }
+
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.iter().any(|ext| ext == name)
+    }
+}
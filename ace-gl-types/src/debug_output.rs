@@ -0,0 +1,248 @@
+use crate::gl;
+use std::collections::HashSet;
+use std::ffi::CStr;
+use std::fmt;
+use std::os::raw;
+use std::cell::RefCell;
+
+/// Message ids that are pure noise on common drivers (NVIDIA's buffer placement and usage hints).
+/// Ignored by default; `unignore_message` brings them back.
+pub const DEFAULT_IGNORED_IDS: [u32; 4] = [131169, 131185, 131218, 131204];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DebugSource {
+    Api,
+    WindowSystem,
+    ShaderCompiler,
+    ThirdParty,
+    Application,
+    Other,
+}
+
+impl DebugSource {
+    pub fn from_gl(source: u32) -> DebugSource {
+        match source {
+            gl::DEBUG_SOURCE_API                => DebugSource::Api,
+            gl::DEBUG_SOURCE_WINDOW_SYSTEM      => DebugSource::WindowSystem,
+            gl::DEBUG_SOURCE_SHADER_COMPILER    => DebugSource::ShaderCompiler,
+            gl::DEBUG_SOURCE_THIRD_PARTY        => DebugSource::ThirdParty,
+            gl::DEBUG_SOURCE_APPLICATION        => DebugSource::Application,
+            _                                   => DebugSource::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DebugType {
+    Error,
+    DeprecatedBehavior,
+    UndefinedBehavior,
+    Portability,
+    Performance,
+    Marker,
+    PushGroup,
+    PopGroup,
+    Other,
+}
+
+impl DebugType {
+    pub fn from_gl(gl_type: u32) -> DebugType {
+        match gl_type {
+            gl::DEBUG_TYPE_ERROR                => DebugType::Error,
+            gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR  => DebugType::DeprecatedBehavior,
+            gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR   => DebugType::UndefinedBehavior,
+            gl::DEBUG_TYPE_PORTABILITY          => DebugType::Portability,
+            gl::DEBUG_TYPE_PERFORMANCE          => DebugType::Performance,
+            gl::DEBUG_TYPE_MARKER               => DebugType::Marker,
+            gl::DEBUG_TYPE_PUSH_GROUP           => DebugType::PushGroup,
+            gl::DEBUG_TYPE_POP_GROUP            => DebugType::PopGroup,
+            _                                   => DebugType::Other,
+        }
+    }
+}
+
+/// Ordered from least to most severe, so severities can be compared against a threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DebugSeverity {
+    Notification,
+    Low,
+    Medium,
+    High,
+}
+
+impl DebugSeverity {
+    pub fn from_gl(severity: u32) -> DebugSeverity {
+        match severity {
+            gl::DEBUG_SEVERITY_HIGH     => DebugSeverity::High,
+            gl::DEBUG_SEVERITY_MEDIUM   => DebugSeverity::Medium,
+            gl::DEBUG_SEVERITY_LOW      => DebugSeverity::Low,
+            _                           => DebugSeverity::Notification,
+        }
+    }
+
+    fn log_level(&self) -> log::Level {
+        match self {
+            DebugSeverity::High         => log::Level::Error,
+            DebugSeverity::Medium       => log::Level::Warn,
+            DebugSeverity::Low          => log::Level::Info,
+            DebugSeverity::Notification => log::Level::Debug,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DebugMessage {
+    pub id: u32,
+    pub source: DebugSource,
+    pub msg_type: DebugType,
+    pub severity: DebugSeverity,
+    pub message: String,
+}
+
+impl fmt::Display for DebugMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{:?}/{:?}/{:?}] {}: {}", self.source, self.msg_type, self.severity, self.id, self.message)
+    }
+}
+
+/// Where debug messages that make it past the filters end up.
+pub enum DebugSink {
+    /// Through the `log` crate, under the `ace_gl_types::debug_output` target.
+    Log,
+    Callback(Box<dyn Fn(&DebugMessage)>),
+}
+
+/// Filtering and routing for the driver's debug messages. Owned by the `GLContext`.
+pub struct DebugOutput {
+    sink: DebugSink,
+    ignored_ids: HashSet<u32>,
+    min_severity: DebugSeverity,
+    panic_on_error: bool,
+    pending_error: Option<DebugMessage>,
+}
+
+impl DebugOutput {
+    pub(crate) fn new() -> DebugOutput {
+        DebugOutput {
+            sink: DebugSink::Log,
+            ignored_ids: DEFAULT_IGNORED_IDS.iter().copied().collect(),
+            min_severity: DebugSeverity::Notification,
+            panic_on_error: false,
+            pending_error: None,
+        }
+    }
+
+    pub fn set_sink(&mut self, sink: DebugSink) {
+        self.sink = sink;
+    }
+
+    pub fn ignore_message(&mut self, id: u32) {
+        self.ignored_ids.insert(id);
+    }
+
+    pub fn unignore_message(&mut self, id: u32) {
+        self.ignored_ids.remove(&id);
+    }
+
+    /// Drops anything below `severity`.
+    pub fn set_min_severity(&mut self, severity: DebugSeverity) {
+        self.min_severity = severity;
+    }
+
+    /// When set, the first `DebugType::Error` message makes `GLContext::check_debug_errors` panic.
+    /// Unwinding out of the driver's callback isn't allowed, so the panic happens there instead of on the spot.
+    pub fn set_panic_on_error(&mut self, panic_on_error: bool) {
+        self.panic_on_error = panic_on_error;
+    }
+
+    pub(crate) fn take_pending_error(&mut self) -> Option<DebugMessage> {
+        self.pending_error.take()
+    }
+
+    fn dispatch(&mut self, msg: DebugMessage) {
+        if self.ignored_ids.contains(&msg.id) || msg.severity < self.min_severity {
+            return;
+        }
+
+        match &self.sink {
+            DebugSink::Log => log::log!(target: "ace_gl_types::debug_output", msg.severity.log_level(), "{}", msg),
+            DebugSink::Callback(callback) => callback(&msg),
+        }
+
+        if self.panic_on_error && msg.msg_type == DebugType::Error && self.pending_error.is_none() {
+            self.pending_error = Some(msg);
+        }
+    }
+}
+
+/// Handed to `glDebugMessageCallback`; `user_param` points at the context's `RefCell<DebugOutput>`.
+pub(crate) extern "system" fn debug_callback(source: raw::c_uint, gl_type: raw::c_uint, id: raw::c_uint, severity: raw::c_uint, _: raw::c_int, msg: *const raw::c_char, user_param: *mut raw::c_void) {
+    if user_param.is_null() || msg.is_null() {
+        return;
+    }
+
+    let output = unsafe { &*(user_param as *const RefCell<DebugOutput>) };
+    let message = unsafe { CStr::from_ptr(msg) }.to_string_lossy().into_owned();
+
+    // A callback sink that makes GL calls of its own can land us back here; drop those rather than double borrowing.
+    if let Ok(mut output) = output.try_borrow_mut() {
+        output.dispatch(DebugMessage {
+            id: id,
+            source: DebugSource::from_gl(source),
+            msg_type: DebugType::from_gl(gl_type),
+            severity: DebugSeverity::from_gl(severity),
+            message: message,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    fn message(id: u32, msg_type: DebugType, severity: DebugSeverity) -> DebugMessage {
+        DebugMessage {
+            id: id,
+            source: DebugSource::Api,
+            msg_type: msg_type,
+            severity: severity,
+            message: "test".to_owned(),
+        }
+    }
+
+    fn capturing_output() -> (DebugOutput, Rc<RefCell<Vec<u32>>>) {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let sink_seen = seen.clone();
+        let mut output = DebugOutput::new();
+        output.set_sink(DebugSink::Callback(Box::new(move |msg| sink_seen.borrow_mut().push(msg.id))));
+        (output, seen)
+    }
+
+    #[test]
+    fn filters_ignored_ids_and_low_severities() {
+        let (mut output, seen) = capturing_output();
+        output.set_min_severity(DebugSeverity::Low);
+        output.ignore_message(7);
+
+        output.dispatch(message(DEFAULT_IGNORED_IDS[0], DebugType::Performance, DebugSeverity::High));
+        output.dispatch(message(7, DebugType::Error, DebugSeverity::High));
+        output.dispatch(message(8, DebugType::Other, DebugSeverity::Notification));
+        output.dispatch(message(9, DebugType::Error, DebugSeverity::Medium));
+
+        assert_eq!(*seen.borrow(), vec![9]);
+    }
+
+    #[test]
+    fn panic_on_error_keeps_the_first_error() {
+        let (mut output, _) = capturing_output();
+        output.dispatch(message(1, DebugType::Error, DebugSeverity::High));
+        assert!(output.take_pending_error().is_none());
+
+        output.set_panic_on_error(true);
+        output.dispatch(message(2, DebugType::Performance, DebugSeverity::High));
+        output.dispatch(message(3, DebugType::Error, DebugSeverity::High));
+        output.dispatch(message(4, DebugType::Error, DebugSeverity::High));
+        assert_eq!(output.take_pending_error().map(|msg| msg.id), Some(3));
+    }
+}
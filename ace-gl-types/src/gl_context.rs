@@ -0,0 +1,609 @@
+use crate::gl;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::ops::{BitOr, Deref};
+use std::ptr;
+use crate::types::*;
+
+/// The loaded GL function table, plus a shadow copy of the state we've set through it.
+///
+/// Every wrapper holds an `Rc<GLContext>` and goes through the cached setters below when binding,
+/// so binding the same program/VAO/texture twice in a row only reaches the driver once.
+/// Raw GL calls are still available through `Deref`, but anything they change behind the cache's back
+/// needs an `invalidate_state` afterwards.
+///
+/// In debug builds the context also keeps a registry of every GL object the wrappers have created,
+/// and `report_leaks` lists whatever is still alive.
+pub struct GLContext {
+    gl: gl::Gl,
+    caps: GLCapabilities,
+    state: RefCell<StateCache>,
+    frame_stats: Cell<StateStats>,
+    last_frame_stats: Cell<StateStats>,
+    objects: RefCell<ObjectRegistry>,
+    // Boxed so the driver can keep a pointer to it while the context itself moves around.
+    debug_output: Box<RefCell<DebugOutput>>,
+    debug_enabled: Cell<bool>,
+}
+
+/// How many state changes were sent to the driver, and how many were skipped as redundant.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StateStats {
+    pub issued: u32,
+    pub skipped: u32,
+}
+
+#[derive(Default)]
+struct StateCache {
+    program: Option<u32>,
+    vertex_array: Option<u32>,
+    // buffer target -> buffer id
+    buffers: HashMap<u32, u32>,
+    // framebuffer target (read/draw) -> framebuffer id
+    framebuffers: HashMap<u32, u32>,
+    active_texture: Option<u32>,
+    // (texture unit, texture target) -> texture id
+    textures: HashMap<(u32, u32), u32>,
+    // capability (DEPTH_TEST, BLEND, ...) -> enabled
+    capabilities: HashMap<u32, bool>,
+    depth_func: Option<u32>,
+    depth_mask: Option<bool>,
+    blend_equation: Option<(u32, u32)>,
+    blend_func: Option<(u32, u32, u32, u32)>,
+    cull_face: Option<u32>,
+    front_face: Option<u32>,
+    polygon_mode: Option<u32>,
+    stencil_func: Option<(u32, i32, u32)>,
+    stencil_op: Option<(u32, u32, u32)>,
+    stencil_mask: Option<u32>,
+    color_mask: Option<(bool, bool, bool, bool)>,
+    clear_color: Option<[u32; 4]>,
+    viewport: Option<(i32, i32, i32, i32)>,
+}
+
+impl GLContext {
+    pub fn new(gl: gl::Gl) -> GLContext {
+        let caps = GLCapabilities::detect(&gl);
+        GLContext::with_capabilities(gl, caps)
+    }
+
+    /// Skips detection, mostly so the legacy paths can be exercised on drivers that support DSA
+    /// (`GLCapabilities::legacy`).
+    pub fn with_capabilities(gl: gl::Gl, caps: GLCapabilities) -> GLContext {
+        GLContext {
+            gl: gl,
+            caps: caps,
+            state: RefCell::new(StateCache::default()),
+            frame_stats: Cell::new(StateStats::default()),
+            last_frame_stats: Cell::new(StateStats::default()),
+            objects: RefCell::new(ObjectRegistry::new()),
+            debug_output: Box::new(RefCell::new(DebugOutput::new())),
+            debug_enabled: Cell::new(false),
+        }
+    }
+
+    /// Returns the raw GL function table.
+    pub fn raw(&self) -> &gl::Gl {
+        &self.gl
+    }
+
+    pub fn caps(&self) -> &GLCapabilities {
+        &self.caps
+    }
+
+    /// Whether wrappers should edit objects through DSA instead of binding them first.
+    pub(crate) fn dsa(&self) -> bool {
+        self.caps.direct_state_access
+    }
+
+    fn record(&self, issued: bool) -> bool {
+        let mut stats = self.frame_stats.get();
+        if issued {
+            stats.issued += 1;
+        } else {
+            stats.skipped += 1;
+        }
+        self.frame_stats.set(stats);
+        issued
+    }
+
+    /// Closes out the current frame's counters and returns them.
+    /// Also where a deferred panic-on-error from the debug output goes off.
+    pub fn end_frame(&self) -> StateStats {
+        self.check_debug_errors();
+        let stats = self.frame_stats.replace(StateStats::default());
+        self.last_frame_stats.set(stats);
+        stats
+    }
+
+    pub fn last_frame_stats(&self) -> StateStats {
+        self.last_frame_stats.get()
+    }
+
+    /// Forgets everything the cache knows, forcing the next call of each setter through to the driver.
+    pub fn invalidate_state(&self) {
+        *self.state.borrow_mut() = StateCache::default();
+    }
+
+    pub fn use_program(&self, id: u32) {
+        let changed = self.state.borrow_mut().program.replace(id) != Some(id);
+        if self.record(changed) {
+            unsafe {
+                self.gl.UseProgram(id);
+            }
+        }
+    }
+
+    pub fn bind_vertex_array(&self, id: u32) {
+        let changed = {
+            let mut state = self.state.borrow_mut();
+            let changed = state.vertex_array.replace(id) != Some(id);
+            if changed {
+                // The element array binding is part of the VAO's state, so it changes with it.
+                state.buffers.remove(&gl::ELEMENT_ARRAY_BUFFER);
+            }
+            changed
+        };
+        if self.record(changed) {
+            unsafe {
+                self.gl.BindVertexArray(id);
+            }
+        }
+    }
+
+    pub fn bind_buffer(&self, target: u32, id: u32) {
+        let changed = self.state.borrow_mut().buffers.insert(target, id) != Some(id);
+        if self.record(changed) {
+            unsafe {
+                self.gl.BindBuffer(target, id);
+            }
+        }
+    }
+
+    pub fn bind_framebuffer(&self, target: u32, id: u32) {
+        let changed = {
+            let mut state = self.state.borrow_mut();
+            match target {
+                gl::FRAMEBUFFER => {
+                    let read = state.framebuffers.insert(gl::READ_FRAMEBUFFER, id) != Some(id);
+                    let draw = state.framebuffers.insert(gl::DRAW_FRAMEBUFFER, id) != Some(id);
+                    read || draw
+                },
+                _ => state.framebuffers.insert(target, id) != Some(id),
+            }
+        };
+        if self.record(changed) {
+            unsafe {
+                self.gl.BindFramebuffer(target, id);
+            }
+        }
+    }
+
+    /// Binds a texture to the given unit index (0 being GL_TEXTURE0), switching the active unit if needed.
+    /// With DSA the active unit is left alone.
+    pub fn bind_texture(&self, unit: u32, target: u32, id: u32) {
+        if self.dsa() && id != 0 {
+            let changed = self.state.borrow_mut().textures.insert((unit, target), id) != Some(id);
+            if self.record(changed) {
+                unsafe {
+                    self.gl.BindTextureUnit(unit, id);
+                }
+            }
+            return;
+        }
+
+        let (unit_changed, tex_changed) = {
+            let mut state = self.state.borrow_mut();
+            let unit_changed = state.active_texture.replace(unit) != Some(unit);
+            let tex_changed = state.textures.insert((unit, target), id) != Some(id);
+            (unit_changed, tex_changed)
+        };
+        if self.record(unit_changed) {
+            unsafe {
+                self.gl.ActiveTexture(gl::TEXTURE0 + unit);
+            }
+        }
+        if self.record(tex_changed) {
+            unsafe {
+                self.gl.BindTexture(target, id);
+            }
+        }
+    }
+
+    pub fn set_capability(&self, cap: u32, enabled: bool) {
+        let changed = self.state.borrow_mut().capabilities.insert(cap, enabled) != Some(enabled);
+        if self.record(changed) {
+            unsafe {
+                if enabled {
+                    self.gl.Enable(cap);
+                } else {
+                    self.gl.Disable(cap);
+                }
+            }
+        }
+    }
+
+    pub fn enable(&self, cap: u32) {
+        self.set_capability(cap, true);
+    }
+
+    pub fn disable(&self, cap: u32) {
+        self.set_capability(cap, false);
+    }
+
+    pub fn depth_func(&self, func: u32) {
+        let changed = self.state.borrow_mut().depth_func.replace(func) != Some(func);
+        if self.record(changed) {
+            unsafe {
+                self.gl.DepthFunc(func);
+            }
+        }
+    }
+
+    pub fn depth_mask(&self, write: bool) {
+        let changed = self.state.borrow_mut().depth_mask.replace(write) != Some(write);
+        if self.record(changed) {
+            unsafe {
+                self.gl.DepthMask(if write { gl::TRUE } else { gl::FALSE });
+            }
+        }
+    }
+
+    pub fn blend_equation(&self, rgb: u32, alpha: u32) {
+        let changed = self.state.borrow_mut().blend_equation.replace((rgb, alpha)) != Some((rgb, alpha));
+        if self.record(changed) {
+            unsafe {
+                self.gl.BlendEquationSeparate(rgb, alpha);
+            }
+        }
+    }
+
+    pub fn blend_func(&self, src_rgb: u32, dst_rgb: u32, src_alpha: u32, dst_alpha: u32) {
+        let funcs = (src_rgb, dst_rgb, src_alpha, dst_alpha);
+        let changed = self.state.borrow_mut().blend_func.replace(funcs) != Some(funcs);
+        if self.record(changed) {
+            unsafe {
+                self.gl.BlendFuncSeparate(src_rgb, dst_rgb, src_alpha, dst_alpha);
+            }
+        }
+    }
+
+    pub fn cull_face(&self, face: u32) {
+        let changed = self.state.borrow_mut().cull_face.replace(face) != Some(face);
+        if self.record(changed) {
+            unsafe {
+                self.gl.CullFace(face);
+            }
+        }
+    }
+
+    pub fn front_face(&self, winding: u32) {
+        let changed = self.state.borrow_mut().front_face.replace(winding) != Some(winding);
+        if self.record(changed) {
+            unsafe {
+                self.gl.FrontFace(winding);
+            }
+        }
+    }
+
+    pub fn polygon_mode(&self, mode: u32) {
+        let changed = self.state.borrow_mut().polygon_mode.replace(mode) != Some(mode);
+        if self.record(changed) {
+            unsafe {
+                self.gl.PolygonMode(gl::FRONT_AND_BACK, mode);
+            }
+        }
+    }
+
+    pub fn stencil_func(&self, func: u32, reference: i32, mask: u32) {
+        let params = (func, reference, mask);
+        let changed = self.state.borrow_mut().stencil_func.replace(params) != Some(params);
+        if self.record(changed) {
+            unsafe {
+                self.gl.StencilFunc(func, reference, mask);
+            }
+        }
+    }
+
+    pub fn stencil_op(&self, stencil_fail: u32, depth_fail: u32, pass: u32) {
+        let ops = (stencil_fail, depth_fail, pass);
+        let changed = self.state.borrow_mut().stencil_op.replace(ops) != Some(ops);
+        if self.record(changed) {
+            unsafe {
+                self.gl.StencilOp(stencil_fail, depth_fail, pass);
+            }
+        }
+    }
+
+    pub fn stencil_mask(&self, mask: u32) {
+        let changed = self.state.borrow_mut().stencil_mask.replace(mask) != Some(mask);
+        if self.record(changed) {
+            unsafe {
+                self.gl.StencilMask(mask);
+            }
+        }
+    }
+
+    pub fn color_mask(&self, red: bool, green: bool, blue: bool, alpha: bool) {
+        let mask = (red, green, blue, alpha);
+        let changed = self.state.borrow_mut().color_mask.replace(mask) != Some(mask);
+        if self.record(changed) {
+            let as_gl = |b: bool| if b { gl::TRUE } else { gl::FALSE };
+            unsafe {
+                self.gl.ColorMask(as_gl(red), as_gl(green), as_gl(blue), as_gl(alpha));
+            }
+        }
+    }
+
+    pub fn clear_color(&self, red: f32, green: f32, blue: f32, alpha: f32) {
+        // Compared bitwise, so the cache doesn't have to care about float equality.
+        let bits = [red.to_bits(), green.to_bits(), blue.to_bits(), alpha.to_bits()];
+        let changed = self.state.borrow_mut().clear_color.replace(bits) != Some(bits);
+        if self.record(changed) {
+            unsafe {
+                self.gl.ClearColor(red, green, blue, alpha);
+            }
+        }
+    }
+
+    pub fn viewport(&self, x: i32, y: i32, width: i32, height: i32) {
+        let rect = (x, y, width, height);
+        let changed = self.state.borrow_mut().viewport.replace(rect) != Some(rect);
+        if self.record(changed) {
+            unsafe {
+                self.gl.Viewport(x, y, width, height);
+            }
+        }
+    }
+
+    /// Clears the buffers of the currently bound framebuffer.
+    /// Clearing respects the write masks, so they are opened up first for whichever buffers are being cleared.
+    pub fn clear(&self, mask: ClearMask) {
+        if mask.0 & gl::COLOR_BUFFER_BIT != 0 {
+            self.color_mask(true, true, true, true);
+        }
+        if mask.0 & gl::DEPTH_BUFFER_BIT != 0 {
+            self.depth_mask(true);
+        }
+        if mask.0 & gl::STENCIL_BUFFER_BIT != 0 {
+            self.stencil_mask(!0);
+        }
+        unsafe {
+            self.gl.Clear(mask.0);
+        }
+    }
+
+    /// Registers a freshly generated GL name. Only does anything in debug builds.
+    pub(crate) fn track_object(&self, kind: GLObjectKind, id: u32) {
+        if cfg!(debug_assertions) {
+            self.objects.borrow_mut().track(kind, id);
+        }
+    }
+
+    /// Updates the memory an object is accounted for, after (re)allocating its storage.
+    pub(crate) fn set_object_size(&self, kind: GLObjectKind, id: u32, bytes: usize) {
+        if cfg!(debug_assertions) {
+            self.objects.borrow_mut().set_size(kind, id, bytes);
+        }
+    }
+
+    /// Deletes a GL object and scrubs it from the state cache.
+    ///
+    /// GL hands deleted names back out, so a cached binding to a dead name would otherwise make us skip
+    /// binding whatever new object gets that name next.
+    pub(crate) fn delete_object(&self, kind: GLObjectKind, id: u32) {
+        if id == 0 {
+            return;
+        }
+
+        {
+            let mut state = self.state.borrow_mut();
+            match kind {
+                GLObjectKind::Buffer => state.buffers.retain(|_, bound| *bound != id),
+                GLObjectKind::VertexArray => {
+                    if state.vertex_array == Some(id) {
+                        state.vertex_array = None;
+                        state.buffers.remove(&gl::ELEMENT_ARRAY_BUFFER);
+                    }
+                },
+                GLObjectKind::Texture => state.textures.retain(|_, bound| *bound != id),
+                GLObjectKind::FrameBuffer => state.framebuffers.retain(|_, bound| *bound != id),
+                GLObjectKind::Program => {
+                    if state.program == Some(id) {
+                        state.program = None;
+                    }
+                },
+                GLObjectKind::RenderBuffer | GLObjectKind::Shader | GLObjectKind::Query => {},
+            }
+        }
+
+        unsafe {
+            match kind {
+                GLObjectKind::Buffer        => self.gl.DeleteBuffers(1, &id),
+                GLObjectKind::VertexArray   => self.gl.DeleteVertexArrays(1, &id),
+                GLObjectKind::Texture       => self.gl.DeleteTextures(1, &id),
+                GLObjectKind::FrameBuffer   => self.gl.DeleteFramebuffers(1, &id),
+                GLObjectKind::RenderBuffer  => self.gl.DeleteRenderbuffers(1, &id),
+                GLObjectKind::Shader        => self.gl.DeleteShader(id),
+                GLObjectKind::Program       => self.gl.DeleteProgram(id),
+                GLObjectKind::Query         => self.gl.DeleteQueries(1, &id),
+            }
+        }
+
+        if cfg!(debug_assertions) && !self.objects.borrow_mut().forget(kind, id) {
+            log::warn!("GL {} {} was deleted without ever being tracked", kind, id);
+        }
+    }
+
+    /// Live objects by kind. Always empty in release builds.
+    pub fn live_objects(&self) -> BTreeMap<GLObjectKind, LiveObjectStats> {
+        self.objects.borrow().by_kind()
+    }
+
+    /// Estimated GPU memory held by live objects, in bytes. Always 0 in release builds.
+    pub fn live_object_bytes(&self) -> usize {
+        self.objects.borrow().total_bytes()
+    }
+
+    /// Logs every object that is still alive as a warning, returning whether there were any.
+    ///
+    /// Call it once everything that owns GL objects has been dropped, as `main` does before exiting. It isn't done when
+    /// the context drops: every wrapper holds an `Rc` to the context, so a leaked wrapper keeps it alive for good.
+    pub fn report_leaks(&self) -> bool {
+        let objects = self.objects.borrow();
+        if objects.is_empty() {
+            return false;
+        }
+
+        for (kind, stats) in objects.by_kind() {
+            log::warn!("leaked GL objects: {}: {} alive ({} bytes), names {:?}", kind, stats.count, stats.bytes, objects.live_ids(kind));
+        }
+        true
+    }
+
+    /// Routes the driver's debug messages to `sink`.
+    /// Returns false (and does nothing) if the context wasn't created as a debug context.
+    pub fn enable_debug_output(&self, sink: DebugSink) -> bool {
+        let mut gl_flags: i32 = 0;
+        unsafe {
+            self.gl.GetIntegerv(gl::CONTEXT_FLAGS, &mut gl_flags);
+        }
+        if (gl_flags as u32) & gl::CONTEXT_FLAG_DEBUG_BIT == 0 {
+            return false;
+        }
+
+        self.debug_output.borrow_mut().set_sink(sink);
+        let user_param: *const RefCell<DebugOutput> = &*self.debug_output;
+        unsafe {
+            self.gl.Enable(gl::DEBUG_OUTPUT);
+            self.gl.Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+            self.gl.DebugMessageCallback(Some(debug_callback), user_param as *const _);
+            self.gl.DebugMessageControl(gl::DONT_CARE, gl::DONT_CARE, gl::DONT_CARE, 0, ptr::null(), gl::TRUE);
+        }
+        self.debug_enabled.set(true);
+        true
+    }
+
+    /// Adjusts the debug output filters, e.g. `ctx.configure_debug_output(|out| out.ignore_message(131154))`.
+    pub fn configure_debug_output(&self, configure: impl FnOnce(&mut DebugOutput)) {
+        configure(&mut self.debug_output.borrow_mut());
+    }
+
+    /// Panics with the first GL error seen since the last check, if panic-on-error is turned on.
+    pub fn check_debug_errors(&self) {
+        let pending = self.debug_output.borrow_mut().take_pending_error();
+        if let Some(msg) = pending {
+            panic!("GL error reported by the debug output: {}", msg);
+        }
+    }
+
+    fn has_debug_labels(&self) -> bool {
+        self.gl.ObjectLabel.is_loaded() && self.gl.PushDebugGroup.is_loaded()
+    }
+
+    /// Names an object for debuggers like RenderDoc. A no-op where KHR_debug isn't available.
+    /// Objects made with `glGen*` only exist once they've been bound, so label after the first bind.
+    pub(crate) fn label_object(&self, kind: GLObjectKind, id: u32, label: &str) {
+        if !self.has_debug_labels() {
+            return;
+        }
+
+        let identifier = match kind {
+            GLObjectKind::Buffer        => gl::BUFFER,
+            GLObjectKind::VertexArray   => gl::VERTEX_ARRAY,
+            GLObjectKind::Texture       => gl::TEXTURE,
+            GLObjectKind::FrameBuffer   => gl::FRAMEBUFFER,
+            GLObjectKind::RenderBuffer  => gl::RENDERBUFFER,
+            GLObjectKind::Shader        => gl::SHADER,
+            GLObjectKind::Program       => gl::PROGRAM,
+            GLObjectKind::Query         => gl::QUERY,
+        };
+        unsafe {
+            self.gl.ObjectLabel(identifier, id, label.len() as i32, label.as_ptr() as *const _);
+        }
+    }
+
+    /// Opens a named group in captures and debug output. Prefer `debug_group`, which closes itself.
+    pub fn push_debug_group(&self, name: &str) {
+        if self.has_debug_labels() {
+            unsafe {
+                self.gl.PushDebugGroup(gl::DEBUG_SOURCE_APPLICATION, 0, name.len() as i32, name.as_ptr() as *const _);
+            }
+        }
+    }
+
+    pub fn pop_debug_group(&self) {
+        if self.has_debug_labels() {
+            unsafe {
+                self.gl.PopDebugGroup();
+            }
+        }
+    }
+
+    pub fn debug_group(&self, name: &str) -> DebugGroup<'_> {
+        self.push_debug_group(name);
+        DebugGroup { gl_ctx: self }
+    }
+
+    /// Draws every index in the element buffer of the currently bound VAO.
+    pub fn draw_elements(&self, mode: GLMode, indices: &ElementArrayObj) {
+        unsafe {
+            self.gl.DrawElements(mode as u32, indices.index_count() as i32, indices.index_type().into(), ptr::null());
+        }
+    }
+}
+
+/// Which buffers `GLContext::clear` should clear. Combine with `|`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClearMask(u32);
+
+impl ClearMask {
+    pub const COLOR: ClearMask = ClearMask(gl::COLOR_BUFFER_BIT);
+    pub const DEPTH: ClearMask = ClearMask(gl::DEPTH_BUFFER_BIT);
+    pub const STENCIL: ClearMask = ClearMask(gl::STENCIL_BUFFER_BIT);
+}
+
+impl BitOr for ClearMask {
+    type Output = ClearMask;
+
+    fn bitor(self, other: ClearMask) -> ClearMask {
+        ClearMask(self.0 | other.0)
+    }
+}
+
+impl Deref for GLContext {
+    type Target = gl::Gl;
+
+    fn deref(&self) -> &gl::Gl {
+        &self.gl
+    }
+}
+
+impl Drop for GLContext {
+    fn drop(&mut self) {
+        // The driver holds a pointer into debug_output, which is about to be freed.
+        if self.debug_enabled.get() {
+            unsafe {
+                self.gl.DebugMessageCallback(None, ptr::null());
+            }
+        }
+    }
+}
+
+/// Pops its debug group when dropped.
+pub struct DebugGroup<'a> {
+    gl_ctx: &'a GLContext,
+}
+
+impl Drop for DebugGroup<'_> {
+    fn drop(&mut self) {
+        self.gl_ctx.pop_debug_group();
+    }
+}
+
+impl fmt::Debug for GLContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "GL Context ({:?} last frame)", self.last_frame_stats.get())
+    }
+}
\ No newline at end of file
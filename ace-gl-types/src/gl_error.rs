@@ -9,4 +9,16 @@ pub enum OpenGLError {
     LinkerError(String),
     #[error("The shader program already contained a shader of the same type: {0}")]
     ProgramAlreadyContainedShader(shaders::ShaderType),
+    #[error("The shader program reads vertex attribute {0} at location {1}, but the vertex layout does not provide it")]
+    MissingVertexAttribute(String, u32),
+    #[error("The shader program uses {0} texture units worth of samplers, but only {1} are available")]
+    TooManySamplers(u32, u32),
+    #[error("The shader program has no sampler named {0}")]
+    UnknownSampler(String),
+    #[error("SPIR-V shaders need OpenGL 4.6 or GL_ARB_gl_spirv")]
+    SpirVUnsupported,
+    #[error("The shader binary is not a SPIR-V module")]
+    InvalidSpirV,
+    #[error("Material {0} has no shader program to draw with")]
+    MaterialWithoutProgram(String),
 }
\ No newline at end of file
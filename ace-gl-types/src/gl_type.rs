@@ -1,7 +1,7 @@
 use std::mem;
 use crate::gl;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GLType {
     Byte,
     UnsignedByte,
@@ -27,7 +27,8 @@ impl GLType {
             GLType::HalfFloat           => mem::size_of::<i16>(),
             GLType::Float               => mem::size_of::<f32>(),
             GLType::Double              => mem::size_of::<f64>(),
-            GLType::Fixed               => unimplemented!(),
+            // GLfixed is a 16.16 fixed point number packed into 32 bits
+            GLType::Fixed               => mem::size_of::<i32>(),
         }
     }
 }
@@ -31,6 +31,13 @@ mod gl_error;
 mod shaders;
 mod typed_buffer;
 mod model;
+mod vertex;
+mod gl_context;
+mod render_state;
+mod object_registry;
+mod query;
+mod debug_output;
+mod capabilities;
 
 // Re-exports for convienence
 pub use gl_tex_unit::*;
@@ -39,11 +46,24 @@ pub use gl_type::*;
 pub use attribute_component_size::*;
 pub use gl_mode::*;
 pub(crate) use uniform_type::*;
+pub use uniform_type::UniformValue;
 pub use self::texture::*;
 pub use self::buffers::*;
 pub use self::gl_error::*;
 pub use self::shaders::*;
 pub use self::typed_buffer::*;
 pub use self::model::*;
+pub use self::vertex::*;
+pub use self::gl_context::*;
+pub use self::render_state::*;
+pub use self::object_registry::*;
+pub use self::query::*;
+pub use self::debug_output::*;
+pub use self::capabilities::*;
+pub use ace_gl_derive::Vertex;
 
-pub use crate as types;
\ No newline at end of file
+pub use crate as types;
+
+// `#[derive(Vertex)]` names this crate by its path, which the derive tests in here need to resolve too.
+#[cfg(test)]
+extern crate self as ace_gl_types;
\ No newline at end of file
@@ -1,54 +1,164 @@
-use std::path::PathBuf;
-use std::collections::HashMap;
-use tobj;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+use crate::types::*;
 
+/// Everything a draw needs besides the geometry: the program, the textures bound to its samplers,
+/// the uniform values it's drawn with and the render state.
+///
+/// The program and textures are filled in by whoever loads the material, since they're assets of their own.
 pub struct Material {
     pub name: String,
-    colors: MatColor,
-    texture_paths: MatTexturePaths,
+    program: RefCell<Option<Rc<CompiledShaderProgram>>>,
+    textures: RefCell<BTreeMap<String, Rc<Texture>>>,
+    parameters: BTreeMap<String, UniformValue>,
+    // Files an MTL library named for each slot, relative to the library.
+    texture_paths: HashMap<MaterialSlot, PathBuf>,
     extra_parameters: HashMap<String, String>,
+    pub render_state: RenderState,
 }
 
-struct MatColor {
-    ambient: [f32; 3],
-    diffuse: [f32; 3],
-    specular: [f32; 3],
+/// The textures an MTL material can have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MaterialSlot {
+    Ambient,
+    Diffuse,
+    Specular,
+    Normal,
+    Shininess,
+    Dissolve,
+}
+
+impl MaterialSlot {
+    pub const ALL: [MaterialSlot; 6] = [
+        MaterialSlot::Ambient,
+        MaterialSlot::Diffuse,
+        MaterialSlot::Specular,
+        MaterialSlot::Normal,
+        MaterialSlot::Shininess,
+        MaterialSlot::Dissolve,
+    ];
 
-    shininess: f32,
-    dissolve: f32,
-    optical_density: f32,
+    /// Whether the slot holds colors, which are stored in sRGB, rather than data.
+    pub fn is_color(self) -> bool {
+        matches!(self, MaterialSlot::Ambient | MaterialSlot::Diffuse | MaterialSlot::Specular)
+    }
+
+    /// The sampler a converted MTL material binds the slot's texture to.
+    pub fn sampler(self) -> &'static str {
+        match self {
+            MaterialSlot::Ambient => "ambient_texture",
+            MaterialSlot::Diffuse => "diffuse_texture",
+            MaterialSlot::Specular => "specular_texture",
+            MaterialSlot::Normal => "normal_texture",
+            MaterialSlot::Shininess => "shininess_texture",
+            MaterialSlot::Dissolve => "dissolve_texture",
+        }
+    }
 }
 
-struct MatTexturePaths {
-    ambient: PathBuf,
-    diffuse: PathBuf,
-    specular: PathBuf,
-    normal: PathBuf,
-    shininess: PathBuf,
-    dissolve: PathBuf,
+impl Material {
+    /// An opaque material without a program, textures or parameters.
+    pub fn new<S: ToString>(name: S) -> Material {
+        Material {
+            name: name.to_string(),
+            program: RefCell::new(None),
+            textures: RefCell::new(BTreeMap::new()),
+            parameters: BTreeMap::new(),
+            texture_paths: HashMap::new(),
+            extra_parameters: HashMap::new(),
+            render_state: RenderState::opaque(),
+        }
+    }
+
+    pub fn with_parameter<S: ToString>(mut self, name: S, value: UniformValue) -> Material {
+        self.parameters.insert(name.to_string(), value);
+        self
+    }
+
+    pub fn with_render_state(mut self, render_state: RenderState) -> Material {
+        self.render_state = render_state;
+        self
+    }
+
+    pub fn parameter(&self, name: &str) -> Option<UniformValue> {
+        self.parameters.get(name).copied()
+    }
+
+    pub fn parameters(&self) -> impl Iterator<Item = (&str, UniformValue)> {
+        self.parameters.iter().map(|(name, value)| (name.as_str(), *value))
+    }
+
+    pub fn program(&self) -> Option<Rc<CompiledShaderProgram>> {
+        self.program.borrow().clone()
+    }
+
+    pub fn set_program(&self, program: Rc<CompiledShaderProgram>) {
+        *self.program.borrow_mut() = Some(program);
+    }
+
+    /// The file an MTL library named for a slot, relative to the library. `None` if the slot is empty.
+    pub fn texture_path(&self, slot: MaterialSlot) -> Option<&Path> {
+        self.texture_paths.get(&slot).map(PathBuf::as_path).filter(|path| !path.as_os_str().is_empty())
+    }
+
+    /// The texture bound to a sampler, if there is one.
+    pub fn texture(&self, sampler: &str) -> Option<Rc<Texture>> {
+        self.textures.borrow().get(sampler).cloned()
+    }
+
+    pub fn set_texture<S: ToString>(&self, sampler: S, texture: Rc<Texture>) {
+        self.textures.borrow_mut().insert(sampler.to_string(), texture);
+    }
+
+    /// Makes the material's program current, with its textures bound, its parameters set and its render state applied.
+    /// Returns the program, for the caller to set whatever changes per draw (like transforms).
+    ///
+    /// Textures for samplers the program doesn't have are skipped, the same way GL ignores unknown uniforms,
+    /// since a variant compiled with different defines may not sample everything the material has.
+    pub fn bind(&self, gl_ctx: &GLContext) -> Result<Rc<CompiledShaderProgram>, OpenGLError> {
+        let program = self.program().ok_or_else(|| OpenGLError::MaterialWithoutProgram(self.name.clone()))?;
+        program.use_program();
+        for (sampler, texture) in self.textures.borrow().iter() {
+            if let Some(binding) = program.sampler(sampler) {
+                texture.bind(binding.unit);
+            }
+        }
+        for (name, value) in &self.parameters {
+            value.assign(&program, name);
+        }
+        self.render_state.apply(gl_ctx);
+        Ok(program)
+    }
 }
 
 impl From<tobj::Material> for Material {
     fn from(mat: tobj::Material) -> Self {
-        Material {
-            name: mat.name,
-            colors: MatColor {
-                ambient: mat.ambient,
-                diffuse: mat.diffuse,
-                specular: mat.specular,
-                shininess: mat.shininess,
-                dissolve: mat.dissolve,
-                optical_density: mat.optical_density,
-            },
-            texture_paths: MatTexturePaths {
-                ambient: PathBuf::from(mat.ambient_texture),
-                diffuse: PathBuf::from(mat.diffuse_texture),
-                specular: PathBuf::from(mat.specular_texture),
-                normal: PathBuf::from(mat.normal_texture),
-                shininess: PathBuf::from(mat.shininess_texture),
-                dissolve: PathBuf::from(mat.dissolve_texture),
-            },
-            extra_parameters: mat.unknown_param,
-        }
+        // MTL has no notion of render state, but a dissolve below 1 means the material is see-through.
+        let render_state = if mat.dissolve < 1.0 {
+            RenderState::transparent()
+        } else {
+            RenderState::opaque()
+        };
+        let mut material = Material::new(&mat.name)
+            .with_parameter("ambient", UniformValue::Vec3(mat.ambient))
+            .with_parameter("diffuse", UniformValue::Vec3(mat.diffuse))
+            .with_parameter("specular", UniformValue::Vec3(mat.specular))
+            .with_parameter("shininess", UniformValue::Float(mat.shininess))
+            .with_parameter("dissolve", UniformValue::Float(mat.dissolve))
+            .with_parameter("optical_density", UniformValue::Float(mat.optical_density))
+            .with_render_state(render_state);
+        let paths = [
+            (MaterialSlot::Ambient, mat.ambient_texture),
+            (MaterialSlot::Diffuse, mat.diffuse_texture),
+            (MaterialSlot::Specular, mat.specular_texture),
+            (MaterialSlot::Normal, mat.normal_texture),
+            (MaterialSlot::Shininess, mat.shininess_texture),
+            (MaterialSlot::Dissolve, mat.dissolve_texture),
+        ];
+        material.texture_paths = paths.into_iter().map(|(slot, path)| (slot, PathBuf::from(path))).collect();
+        material.extra_parameters = mat.unknown_param;
+        material
     }
-}
\ No newline at end of file
+}
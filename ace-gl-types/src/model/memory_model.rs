@@ -1,54 +1,62 @@
 use crate::types::*;
-use crate::gl;
 use std::rc::Rc;
 
 pub struct ResidentModel {
-    vertices: Vec<f32>,
-    indices: Vec<u32>,
-    shader: Rc<CompiledShaderProgram>,
+    layout: VertexLayout,
     vao: VertexArrayObj,
     vbo: VertexBufferObj,
     eao: ElementArrayObj,
+    render_state: RenderState,
 }
 
 impl ResidentModel {
-    pub fn new(gl_ctx: Rc<gl::Gl>, vert: &[f32], indices: &[u32], shdr_prog: Rc<CompiledShaderProgram>, attrib_spec: impl Fn(&mut VertexArrayObj) -> ()) -> ResidentModel {
-        let vertices = vert.to_vec();
-        let indexes = indices.to_vec();
+    /// Uploads the vertices and indices and configures the VAO from the vertex type's layout.
+    /// Which program draws it is up to the material it's drawn with (see `Renderable::new`).
+    pub fn new<V: Vertex, I: IndexBuffer>(gl_ctx: Rc<GLContext>, vert: &[V], indices: I) -> ResidentModel {
+        ResidentModel::build(gl_ctx, V::layout(), |vbo| vbo.copy_vertices(vert, DrawMode::StaticDraw), indices)
+    }
+
+    /// Like `new`, for vertex data whose layout is only known at runtime, such as meshes from model files
+    /// where every attribute is optional.
+    pub fn from_layout<T: TypedBuffer, I: IndexBuffer>(gl_ctx: Rc<GLContext>, layout: VertexLayout, vert: T, indices: I) -> ResidentModel {
+        ResidentModel::build(gl_ctx, layout, |vbo| vbo.copy_to_buffer(vert, DrawMode::StaticDraw), indices)
+    }
 
+    fn build<I: IndexBuffer>(gl_ctx: Rc<GLContext>, layout: VertexLayout, upload: impl FnOnce(&VertexBufferObj), indices: I) -> ResidentModel {
         let mut vertex_array = VertexArrayObj::new(gl_ctx.clone());
         let vertex_buffer = VertexBufferObj::new(gl_ctx.clone());
-        let element_buffer = ElementArrayObj::new(gl_ctx.clone());
+        let mut element_buffer = ElementArrayObj::new(gl_ctx.clone());
 
-        vertex_array.bind();
-        vertex_buffer.bind();
-        element_buffer.bind();
-        vertex_buffer.copy_to_buffer(vert, DrawMode::StaticDraw);
+        upload(&vertex_buffer);
+        vertex_array.attach_vertex_buffer(0, &vertex_buffer, &layout);
+        vertex_array.attach_element_buffer(&element_buffer);
         element_buffer.copy_to_buffer(indices, DrawMode::StaticDraw);
-        attrib_spec(&mut vertex_array);
 
         ResidentModel {
-            vertices: vertices,
-            indices: indexes,
-            shader: shdr_prog,
+            layout: layout,
             vao: vertex_array,
             vbo: vertex_buffer,
             eao: element_buffer,
+            render_state: RenderState::default(),
         }
     }
-}
 
-impl Model for ResidentModel {
-    fn get_vertices(&self) -> &Vec<f32> {
-        &self.vertices
+    /// Names the model's buffers and vertex array in debug output and captures.
+    pub fn set_label(&self, label: &str) {
+        self.vao.set_label(label);
+        self.vbo.set_label(label);
+        self.eao.set_label(label);
     }
 
-    fn get_indices(&self) -> &Vec<u32> {
-        &self.indices
+    pub fn with_render_state(mut self, render_state: RenderState) -> ResidentModel {
+        self.render_state = render_state;
+        self
     }
+}
 
-    fn get_shader(&self) -> &Rc<CompiledShaderProgram> {
-        &self.shader
+impl Model for ResidentModel {
+    fn get_vertex_layout(&self) -> &VertexLayout {
+        &self.layout
     }
 
     fn get_vert_array_obj(&self) -> &VertexArrayObj {
@@ -62,4 +70,8 @@ impl Model for ResidentModel {
     fn get_elem_array_obj(&self) -> &ElementArrayObj {
         &self.eao
     }
+
+    fn get_render_state(&self) -> &RenderState {
+        &self.render_state
+    }
 }
\ No newline at end of file
@@ -1,5 +1,4 @@
 use crate::types::*;
-use std::rc::Rc;
 
 mod memory_model;
 mod material;
@@ -11,10 +10,27 @@ pub use self::material::*;
 // With nalgebra, we might be able to augment matricies by row (since matricies are column-major) in order to add more information
 // So then we could have separate color/lighting/texture matricies
 pub trait Model {
-    fn get_vertices(&self)          -> &Vec<f32>;
-    fn get_indices(&self)           -> &Vec<u32>;
-    fn get_shader(&self)            -> &Rc<CompiledShaderProgram>;
+    fn get_vertex_layout(&self)     -> &VertexLayout;
     fn get_vert_array_obj(&self)    -> &VertexArrayObj;
     fn get_vert_buffer_obj(&self)   -> &VertexBufferObj;
     fn get_elem_array_obj(&self)    -> &ElementArrayObj;
+    fn get_render_state(&self)      -> &RenderState;
+
+    /// GPU memory held by the model's vertex and index buffers.
+    fn gpu_bytes(&self) -> usize {
+        self.get_vert_buffer_obj().size() + self.get_elem_array_obj().size()
+    }
+
+    /// Takes over `other`'s vertex array and buffers, leaving `other` to delete the old ones when it drops.
+    /// Lets a model be reloaded while everything holding on to it keeps drawing it. Models with different
+    /// vertex layouts can't trade places; then nothing is swapped and this returns false.
+    fn swap_buffers(&self, other: &dyn Model) -> bool {
+        if self.get_vertex_layout() != other.get_vertex_layout() {
+            return false;
+        }
+        self.get_vert_array_obj().swap(other.get_vert_array_obj());
+        self.get_vert_buffer_obj().swap(other.get_vert_buffer_obj());
+        self.get_elem_array_obj().swap(other.get_elem_array_obj());
+        true
+    }
 }
\ No newline at end of file
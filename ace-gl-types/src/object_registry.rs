@@ -0,0 +1,124 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// The kinds of GL object the wrappers in this crate own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum GLObjectKind {
+    Buffer,
+    VertexArray,
+    Texture,
+    FrameBuffer,
+    RenderBuffer,
+    Shader,
+    Program,
+    Query,
+}
+
+impl fmt::Display for GLObjectKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// How many objects of one kind are alive, and roughly how much GPU memory they hold.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LiveObjectStats {
+    pub count: usize,
+    pub bytes: usize,
+}
+
+/// Bookkeeping for every GL name a wrapper has created but not yet deleted.
+///
+/// `GLContext` only feeds this in debug builds; in release builds it stays empty.
+#[derive(Debug, Default)]
+pub struct ObjectRegistry {
+    // (kind, GL name) -> size in bytes
+    objects: HashMap<(GLObjectKind, u32), usize>,
+}
+
+impl ObjectRegistry {
+    pub fn new() -> ObjectRegistry {
+        ObjectRegistry::default()
+    }
+
+    pub fn track(&mut self, kind: GLObjectKind, id: u32) {
+        self.objects.insert((kind, id), 0);
+    }
+
+    /// Records how much memory an object's storage takes. Re-uploading replaces the old size.
+    pub fn set_size(&mut self, kind: GLObjectKind, id: u32, bytes: usize) {
+        if let Some(size) = self.objects.get_mut(&(kind, id)) {
+            *size = bytes;
+        }
+    }
+
+    /// Returns false if the object was never tracked, which usually means it's being deleted twice.
+    pub fn forget(&mut self, kind: GLObjectKind, id: u32) -> bool {
+        self.objects.remove(&(kind, id)).is_some()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    pub fn total_bytes(&self) -> usize {
+        self.objects.values().sum()
+    }
+
+    pub fn by_kind(&self) -> BTreeMap<GLObjectKind, LiveObjectStats> {
+        let mut stats: BTreeMap<GLObjectKind, LiveObjectStats> = BTreeMap::new();
+        for ((kind, _), bytes) in self.objects.iter() {
+            let entry = stats.entry(*kind).or_default();
+            entry.count += 1;
+            entry.bytes += bytes;
+        }
+        stats
+    }
+
+    /// The names still alive of a given kind, sorted so reports are stable.
+    pub fn live_ids(&self, kind: GLObjectKind) -> Vec<u32> {
+        let mut ids: Vec<u32> = self.objects.keys()
+            .filter(|(k, _)| *k == kind)
+            .map(|(_, id)| *id)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_and_sizes_by_kind() {
+        let mut registry = ObjectRegistry::new();
+        registry.track(GLObjectKind::Buffer, 1);
+        registry.track(GLObjectKind::Buffer, 2);
+        registry.track(GLObjectKind::Texture, 1);
+        registry.set_size(GLObjectKind::Buffer, 1, 64);
+        registry.set_size(GLObjectKind::Buffer, 2, 32);
+        registry.set_size(GLObjectKind::Texture, 1, 1024);
+
+        let stats = registry.by_kind();
+        assert_eq!(stats[&GLObjectKind::Buffer], LiveObjectStats { count: 2, bytes: 96 });
+        assert_eq!(stats[&GLObjectKind::Texture], LiveObjectStats { count: 1, bytes: 1024 });
+        assert_eq!(registry.total_bytes(), 1120);
+    }
+
+    #[test]
+    fn forgetting_releases_the_entry() {
+        let mut registry = ObjectRegistry::new();
+        registry.track(GLObjectKind::VertexArray, 3);
+        assert!(registry.forget(GLObjectKind::VertexArray, 3));
+        assert!(!registry.forget(GLObjectKind::VertexArray, 3));
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn sizing_an_untracked_object_is_ignored() {
+        let mut registry = ObjectRegistry::new();
+        registry.set_size(GLObjectKind::RenderBuffer, 7, 128);
+        assert!(registry.is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+use crate::gl;
+use std::rc::Rc;
+use crate::types::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueryTarget {
+    TimeElapsed         = gl::TIME_ELAPSED as isize,
+    Timestamp           = gl::TIMESTAMP as isize,
+    SamplesPassed       = gl::SAMPLES_PASSED as isize,
+    AnySamplesPassed    = gl::ANY_SAMPLES_PASSED as isize,
+    PrimitivesGenerated = gl::PRIMITIVES_GENERATED as isize,
+}
+
+/// A GL query object.
+///
+/// Results come back asynchronously; reading one before the GPU has caught up stalls the pipeline,
+/// so poll `try_result` a frame or two later instead of calling `result` straight away.
+pub struct Query {
+    gl_ctx: Rc<GLContext>,
+    id: u32,
+    target: QueryTarget,
+}
+
+impl Query {
+    pub fn new(gl_ctx: Rc<GLContext>, target: QueryTarget) -> Query {
+        let mut gl_id: u32 = 0;
+        unsafe {
+            gl_ctx.GenQueries(1, &mut gl_id);
+        }
+        gl_ctx.track_object(GLObjectKind::Query, gl_id);
+        Query {
+            gl_ctx: gl_ctx,
+            id: gl_id,
+            target: target,
+        }
+    }
+
+    /// Names the object in debug output and captures.
+    pub fn set_label(&self, label: &str) {
+        self.gl_ctx.label_object(GLObjectKind::Query, self.id, label);
+    }
+
+    pub fn target(&self) -> QueryTarget {
+        self.target
+    }
+
+    /// Starts counting. Only one query per target can be active at a time, and timestamps can't be begun at all.
+    pub fn begin(&self) {
+        debug_assert!(self.target != QueryTarget::Timestamp, "timestamp queries are recorded with record_timestamp, not begin/end");
+        unsafe {
+            self.gl_ctx.BeginQuery(self.target as u32, self.id);
+        }
+    }
+
+    pub fn end(&self) {
+        debug_assert!(self.target != QueryTarget::Timestamp, "timestamp queries are recorded with record_timestamp, not begin/end");
+        unsafe {
+            self.gl_ctx.EndQuery(self.target as u32);
+        }
+    }
+
+    /// Records the GPU time (in nanoseconds) once every command issued before this has completed.
+    pub fn record_timestamp(&self) {
+        debug_assert!(self.target == QueryTarget::Timestamp, "only timestamp queries can record a timestamp");
+        unsafe {
+            self.gl_ctx.QueryCounter(self.id, gl::TIMESTAMP);
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        let mut available = 0;
+        unsafe {
+            self.gl_ctx.GetQueryObjectiv(self.id, gl::QUERY_RESULT_AVAILABLE, &mut available);
+        }
+        available != 0
+    }
+
+    /// The result, if the GPU has produced it yet. Never blocks.
+    pub fn try_result(&self) -> Option<u64> {
+        if self.is_available() {
+            Some(self.result())
+        } else {
+            None
+        }
+    }
+
+    /// The result, waiting on the GPU if it isn't ready.
+    /// Times are in nanoseconds, `AnySamplesPassed` is 0 or 1 and the rest are counts.
+    pub fn result(&self) -> u64 {
+        let mut result = 0;
+        unsafe {
+            self.gl_ctx.GetQueryObjectui64v(self.id, gl::QUERY_RESULT, &mut result);
+        }
+        result
+    }
+}
+
+impl Drop for Query {
+    fn drop(&mut self) {
+        self.gl_ctx.delete_object(GLObjectKind::Query, self.id);
+    }
+}
@@ -0,0 +1,249 @@
+use crate::gl;
+use crate::types::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompareFunc {
+    Never           = gl::NEVER as isize,
+    Less            = gl::LESS as isize,
+    Equal           = gl::EQUAL as isize,
+    LessEqual       = gl::LEQUAL as isize,
+    Greater         = gl::GREATER as isize,
+    NotEqual        = gl::NOTEQUAL as isize,
+    GreaterEqual    = gl::GEQUAL as isize,
+    Always          = gl::ALWAYS as isize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlendEquation {
+    Add             = gl::FUNC_ADD as isize,
+    Subtract        = gl::FUNC_SUBTRACT as isize,
+    ReverseSubtract = gl::FUNC_REVERSE_SUBTRACT as isize,
+    Min             = gl::MIN as isize,
+    Max             = gl::MAX as isize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlendFactor {
+    Zero                    = gl::ZERO as isize,
+    One                     = gl::ONE as isize,
+    SrcColor                = gl::SRC_COLOR as isize,
+    OneMinusSrcColor        = gl::ONE_MINUS_SRC_COLOR as isize,
+    DstColor                = gl::DST_COLOR as isize,
+    OneMinusDstColor        = gl::ONE_MINUS_DST_COLOR as isize,
+    SrcAlpha                = gl::SRC_ALPHA as isize,
+    OneMinusSrcAlpha        = gl::ONE_MINUS_SRC_ALPHA as isize,
+    DstAlpha                = gl::DST_ALPHA as isize,
+    OneMinusDstAlpha        = gl::ONE_MINUS_DST_ALPHA as isize,
+    ConstantColor           = gl::CONSTANT_COLOR as isize,
+    OneMinusConstantColor   = gl::ONE_MINUS_CONSTANT_COLOR as isize,
+    ConstantAlpha           = gl::CONSTANT_ALPHA as isize,
+    OneMinusConstantAlpha   = gl::ONE_MINUS_CONSTANT_ALPHA as isize,
+    SrcAlphaSaturate        = gl::SRC_ALPHA_SATURATE as isize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CullFace {
+    Front           = gl::FRONT as isize,
+    Back            = gl::BACK as isize,
+    FrontAndBack    = gl::FRONT_AND_BACK as isize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FrontFace {
+    Clockwise           = gl::CW as isize,
+    CounterClockwise    = gl::CCW as isize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PolygonMode {
+    Point   = gl::POINT as isize,
+    Line    = gl::LINE as isize,
+    Fill    = gl::FILL as isize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StencilOp {
+    Keep            = gl::KEEP as isize,
+    Zero            = gl::ZERO as isize,
+    Replace         = gl::REPLACE as isize,
+    Increment       = gl::INCR as isize,
+    IncrementWrap   = gl::INCR_WRAP as isize,
+    Decrement       = gl::DECR as isize,
+    DecrementWrap   = gl::DECR_WRAP as isize,
+    Invert          = gl::INVERT as isize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DepthState {
+    pub test: bool,
+    pub write: bool,
+    pub func: CompareFunc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlendState {
+    pub equation_rgb: BlendEquation,
+    pub equation_alpha: BlendEquation,
+    pub src_rgb: BlendFactor,
+    pub dst_rgb: BlendFactor,
+    pub src_alpha: BlendFactor,
+    pub dst_alpha: BlendFactor,
+}
+
+impl BlendState {
+    /// Classic "over" blending for straight (non-premultiplied) alpha.
+    pub fn alpha() -> BlendState {
+        BlendState {
+            equation_rgb: BlendEquation::Add,
+            equation_alpha: BlendEquation::Add,
+            src_rgb: BlendFactor::SrcAlpha,
+            dst_rgb: BlendFactor::OneMinusSrcAlpha,
+            src_alpha: BlendFactor::One,
+            dst_alpha: BlendFactor::OneMinusSrcAlpha,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StencilState {
+    pub func: CompareFunc,
+    pub reference: i32,
+    pub read_mask: u32,
+    pub write_mask: u32,
+    pub stencil_fail: StencilOp,
+    pub depth_fail: StencilOp,
+    pub pass: StencilOp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ColorMask {
+    pub red: bool,
+    pub green: bool,
+    pub blue: bool,
+    pub alpha: bool,
+}
+
+impl ColorMask {
+    pub fn all() -> ColorMask {
+        ColorMask {
+            red: true,
+            green: true,
+            blue: true,
+            alpha: true,
+        }
+    }
+}
+
+/// Everything about the fixed-function pipeline a draw depends on.
+///
+/// This is a plain value; attach one to a model or material and it is applied (through the `GLContext` cache,
+/// so unchanged state costs nothing) right before that thing is drawn.
+/// `None` for blend, cull or stencil means that stage is disabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RenderState {
+    pub depth: DepthState,
+    pub blend: Option<BlendState>,
+    pub cull: Option<CullFace>,
+    pub front_face: FrontFace,
+    pub polygon_mode: PolygonMode,
+    pub stencil: Option<StencilState>,
+    pub color_mask: ColorMask,
+}
+
+impl RenderState {
+    /// Depth tested and written, no blending. What most solid geometry wants.
+    pub fn opaque() -> RenderState {
+        RenderState {
+            depth: DepthState {
+                test: true,
+                write: true,
+                func: CompareFunc::Less,
+            },
+            blend: None,
+            cull: None,
+            front_face: FrontFace::CounterClockwise,
+            polygon_mode: PolygonMode::Fill,
+            stencil: None,
+            color_mask: ColorMask::all(),
+        }
+    }
+
+    /// Alpha blended, depth tested but not written.
+    pub fn transparent() -> RenderState {
+        RenderState {
+            depth: DepthState {
+                write: false,
+                ..RenderState::opaque().depth
+            },
+            blend: Some(BlendState::alpha()),
+            ..RenderState::opaque()
+        }
+    }
+
+    /// For skyboxes written at the far plane (`gl_Position = pos.xyww`), which need to pass at a depth of exactly 1.0.
+    pub fn skybox() -> RenderState {
+        RenderState {
+            depth: DepthState {
+                test: true,
+                write: true,
+                func: CompareFunc::LessEqual,
+            },
+            ..RenderState::opaque()
+        }
+    }
+
+    /// Fullscreen passes that ignore depth entirely.
+    pub fn screenspace() -> RenderState {
+        RenderState {
+            depth: DepthState {
+                test: false,
+                write: false,
+                func: CompareFunc::Always,
+            },
+            ..RenderState::opaque()
+        }
+    }
+
+    pub fn apply(&self, ctx: &GLContext) {
+        ctx.set_capability(gl::DEPTH_TEST, self.depth.test);
+        ctx.depth_mask(self.depth.write);
+        ctx.depth_func(self.depth.func as u32);
+
+        match self.blend {
+            Some(blend) => {
+                ctx.enable(gl::BLEND);
+                ctx.blend_equation(blend.equation_rgb as u32, blend.equation_alpha as u32);
+                ctx.blend_func(blend.src_rgb as u32, blend.dst_rgb as u32, blend.src_alpha as u32, blend.dst_alpha as u32);
+            },
+            None => ctx.disable(gl::BLEND),
+        }
+
+        match self.cull {
+            Some(face) => {
+                ctx.enable(gl::CULL_FACE);
+                ctx.cull_face(face as u32);
+            },
+            None => ctx.disable(gl::CULL_FACE),
+        }
+        ctx.front_face(self.front_face as u32);
+        ctx.polygon_mode(self.polygon_mode as u32);
+
+        match self.stencil {
+            Some(stencil) => {
+                ctx.enable(gl::STENCIL_TEST);
+                ctx.stencil_func(stencil.func as u32, stencil.reference, stencil.read_mask);
+                ctx.stencil_op(stencil.stencil_fail as u32, stencil.depth_fail as u32, stencil.pass as u32);
+                ctx.stencil_mask(stencil.write_mask);
+            },
+            None => ctx.disable(gl::STENCIL_TEST),
+        }
+
+        ctx.color_mask(self.color_mask.red, self.color_mask.green, self.color_mask.blue, self.color_mask.alpha);
+    }
+}
+
+impl Default for RenderState {
+    fn default() -> RenderState {
+        RenderState::opaque()
+    }
+}
\ No newline at end of file
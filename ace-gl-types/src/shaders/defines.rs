@@ -0,0 +1,66 @@
+/// Adds a `#define` for each of `defines` to a GLSL source, so one file can be compiled into several variants.
+/// The defines go right after the `#version` line, which has to stay first; a source without one gets them at the top.
+/// An empty value defines the name without a value, for `#ifdef`.
+pub fn inject_defines(src: &str, defines: &[(String, String)]) -> String {
+    if defines.is_empty() {
+        return src.to_owned();
+    }
+
+    let mut block = String::new();
+    for (name, value) in defines {
+        if value.is_empty() {
+            block.push_str(&format!("#define {}\n", name));
+        } else {
+            block.push_str(&format!("#define {} {}\n", name, value));
+        }
+    }
+
+    // Blank lines and comments (a license header, say) can come before the `#version` line.
+    let mut version_end = None;
+    let mut line_start = 0;
+    for line in src.split_inclusive('\n') {
+        if is_version_line(line) {
+            version_end = Some(line_start + line.len());
+            break;
+        }
+        line_start += line.len();
+    }
+    match version_end {
+        Some(idx) if src[..idx].ends_with('\n') => format!("{}{}{}", &src[..idx], block, &src[idx..]),
+        // Nothing after the `#version` line, not even a newline.
+        Some(_) => format!("{}\n{}", src, block),
+        None => format!("{}{}", block, src),
+    }
+}
+
+fn is_version_line(line: &str) -> bool {
+    // The preprocessor allows whitespace on either side of the `#`.
+    line.trim_start()
+        .strip_prefix('#')
+        .is_some_and(|directive| directive.trim_start().starts_with("version"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defines_go_after_the_version() {
+        let defines = vec![("USE_FOG".to_owned(), String::new()), ("LIGHTS".to_owned(), "4".to_owned())];
+        let src = inject_defines("#version 330 core\nvoid main() {}\n", &defines);
+        assert_eq!(src, "#version 330 core\n#define USE_FOG\n#define LIGHTS 4\nvoid main() {}\n");
+
+        let src = inject_defines("void main() {}\n", &defines);
+        assert_eq!(src, "#define USE_FOG\n#define LIGHTS 4\nvoid main() {}\n");
+
+        let src = inject_defines("#version 330 core", &defines);
+        assert_eq!(src, "#version 330 core\n#define USE_FOG\n#define LIGHTS 4\n");
+    }
+
+    #[test]
+    fn the_version_line_is_found_past_comments() {
+        let defines = vec![("LIGHTS".to_owned(), "4".to_owned())];
+        let src = inject_defines("\n// Licensed under MIT.\n// See LICENSE.\n  # version 450\nvoid main() {}\n", &defines);
+        assert_eq!(src, "\n// Licensed under MIT.\n// See LICENSE.\n  # version 450\n#define LIGHTS 4\nvoid main() {}\n");
+    }
+}
@@ -1,4 +1,5 @@
 use crate::gl;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use std::ptr;
 use std::fmt;
@@ -11,6 +12,15 @@ use std::path::Path;
 use crate::types::*;
 use thiserror::Error;
 
+mod sampler;
+mod validation;
+mod defines;
+
+pub use self::sampler::SamplerBinding;
+pub use self::validation::*;
+pub use self::defines::inject_defines;
+use self::sampler::*;
+
 #[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
 pub enum ShaderType {
     VertexShader,
@@ -20,6 +30,20 @@ pub enum ShaderType {
     FragmentShader,
 }
 
+impl ShaderType {
+    /// Guesses the stage from the conventional GLSL extensions (`.vert`, `.frag`, `.geom`, `.tesc`, `.tese`).
+    pub fn from_extension<P: AsRef<Path>>(path: P) -> Option<ShaderType> {
+        match path.as_ref().extension()?.to_str()? {
+            "vert"  => Some(ShaderType::VertexShader),
+            "tesc"  => Some(ShaderType::TessControlShader),
+            "tese"  => Some(ShaderType::TessEvaluationShader),
+            "geom"  => Some(ShaderType::GeometryShader),
+            "frag"  => Some(ShaderType::FragmentShader),
+            _       => None,
+        }
+    }
+}
+
 impl fmt::Display for ShaderType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{:?}", self)
@@ -38,24 +62,36 @@ impl Into<u32> for &ShaderType {
     } 
 }
 
+#[derive(Debug)]
+enum ShaderSource {
+    Glsl(String),
+    /// Already uploaded and specialized when the shader was created; there's nothing left to compile.
+    SpirV,
+}
+
 #[derive(Debug)]
 pub struct Shader {
-    gl_ctx: Rc<gl::Gl>,
+    gl_ctx: Rc<GLContext>,
     sdr_type: ShaderType,
     id: u32,
-    src: String,
+    src: ShaderSource,
 }
 
 impl Shader {
-    pub fn new(gl_ctx: Rc<gl::Gl>, src: &str, shader_type: ShaderType) -> Shader {
+    pub fn new(gl_ctx: Rc<GLContext>, src: &str, shader_type: ShaderType) -> Shader {
         Shader::new_from_string(gl_ctx, src.to_owned(), shader_type)
     }
 
-    fn new_from_string(gl_ctx: Rc<gl::Gl>, src: String, shader_type: ShaderType) -> Shader {
+    fn new_from_string(gl_ctx: Rc<GLContext>, src: String, shader_type: ShaderType) -> Shader {
+        Shader::create(gl_ctx, ShaderSource::Glsl(src), shader_type)
+    }
+
+    fn create(gl_ctx: Rc<GLContext>, src: ShaderSource, shader_type: ShaderType) -> Shader {
         let sdr_id;
         unsafe {
             sdr_id = gl_ctx.CreateShader((&shader_type).into());
         }
+        gl_ctx.track_object(GLObjectKind::Shader, sdr_id);
         Shader {
             gl_ctx: gl_ctx,
             sdr_type: shader_type,
@@ -64,7 +100,37 @@ impl Shader {
         }
     }
 
-    pub fn from_path<S: AsRef<Path>>(gl_ctx: Rc<gl::Gl>, loc: S, shader_type: ShaderType) -> io::Result<Shader> {
+    /// Loads a precompiled SPIR-V module (GL 4.6 or GL_ARB_gl_spirv) and specializes `entry_point`.
+    /// Specialization is SPIR-V's compile step, so errors that would come from `compile_shader` for GLSL come back here.
+    pub fn from_spirv(gl_ctx: Rc<GLContext>, binary: &[u8], entry_point: &str, shader_type: ShaderType) -> Result<Shader, OpenGLError> {
+        let caps = gl_ctx.caps();
+        let supported = (caps.version >= (4, 6) || caps.has_extension("GL_ARB_gl_spirv"))
+            && gl_ctx.ShaderBinary.is_loaded()
+            && gl_ctx.SpecializeShader.is_loaded();
+        if !supported {
+            return Err(OpenGLError::SpirVUnsupported);
+        }
+        // SPIR-V is a stream of 32-bit words, starting with the magic number.
+        if !binary.len().is_multiple_of(4) || binary.len() < 4 || binary[..4] != 0x0723_0203u32.to_le_bytes() {
+            return Err(OpenGLError::InvalidSpirV);
+        }
+
+        let shader = Shader::create(gl_ctx, ShaderSource::SpirV, shader_type);
+        let entry_str = CString::new(entry_point).expect("Internal NULL detected. Entry point failed to convert to C string.");
+        unsafe {
+            shader.gl_ctx.ShaderBinary(1, &shader.id, gl::SHADER_BINARY_FORMAT_SPIR_V, binary.as_ptr() as *const _, binary.len() as i32);
+            shader.gl_ctx.SpecializeShader(shader.id, entry_str.as_ptr(), 0, ptr::null(), ptr::null());
+        }
+        shader.check_compile_status()?;
+        Ok(shader)
+    }
+
+    pub fn from_spirv_path<S: AsRef<Path>>(gl_ctx: Rc<GLContext>, loc: S, entry_point: &str, shader_type: ShaderType) -> Result<Shader, ShaderCompileError> {
+        let binary = fs::read(loc)?;
+        Ok(Shader::from_spirv(gl_ctx, &binary, entry_point, shader_type)?)
+    }
+
+    pub fn from_path<S: AsRef<Path>>(gl_ctx: Rc<GLContext>, loc: S, shader_type: ShaderType) -> io::Result<Shader> {
         let file = fs::File::open(loc)?;
         let md = file.metadata()?;
         let buffered = io::BufReader::new(file);
@@ -79,53 +145,59 @@ impl Shader {
 
     }
 
+    /// Names the object in debug output and captures.
+    pub fn set_label(&self, label: &str) {
+        self.gl_ctx.label_object(GLObjectKind::Shader, self.id, label);
+    }
+
     pub fn compile_shader(&self) -> Result<(), OpenGLError> {
-        let src_str = CString::new(self.src.clone()).expect("Internal NULL detected. Shader failed to convert to C string.");
+        if let ShaderSource::Glsl(src) = &self.src {
+            let src_str = CString::new(src.clone()).expect("Internal NULL detected. Shader failed to convert to C string.");
+            unsafe {
+                self.gl_ctx.ShaderSource(self.id, 1, &(src_str.as_ptr() as *const i8), ptr::null());
+                self.gl_ctx.CompileShader(self.id);
+            }
+        }
+        self.check_compile_status()
+    }
+
+    fn check_compile_status(&self) -> Result<(), OpenGLError> {
+        let mut result_code: i32 = 0;
         unsafe {
-            self.gl_ctx.ShaderSource(self.id, 1, &(src_str.as_ptr() as *const i8), ptr::null());
-            self.gl_ctx.CompileShader(self.id);
-            let mut result_code: i32 = 0;
             self.gl_ctx.GetShaderiv(self.id, gl::COMPILE_STATUS, (&mut result_code) as *mut i32);
-            if result_code != (gl::TRUE as i32) {
-                let character_output = Vec::with_capacity(512);
-                let c_str = CString::new(character_output).expect("Failed to initialize blank CString");
-                let c_ptr = c_str.into_raw();
-                self.gl_ctx.GetShaderInfoLog(self.id, 512, ptr::null_mut(), c_ptr);
-                let c_str = CString::from_raw(c_ptr);
-                return Err(OpenGLError::CompileError(c_str.to_str().expect("Failed to convert from CString").to_owned()));
-            }
+        }
+        if result_code != (gl::TRUE as i32) {
+            return Err(OpenGLError::CompileError(shader_info_log(&self.gl_ctx, self.id)));
         }
         Ok(())
     }
 
+    /// Deletes the shader now rather than when it goes out of scope.
     pub fn delete_shader(self) {
-        unsafe {
-            self.gl_ctx.DeleteShader(self.id)
-        }
+        drop(self)
     }
 }
 
 impl Drop for Shader {
     fn drop(&mut self) {
-        unsafe {
-            self.gl_ctx.DeleteShader(self.id)
-        }
+        self.gl_ctx.delete_object(GLObjectKind::Shader, self.id);
     }
 }
 
 #[derive(Debug)]
 pub struct ShaderProgram<'a> {
-    gl_ctx: Rc<gl::Gl>,
+    gl_ctx: Rc<GLContext>,
     id: u32,
     loaded_phases: HashMap<ShaderType, &'a Shader>,
 }
 
 impl<'a> ShaderProgram<'a> {
-    pub fn new(gl_ctx: Rc<gl::Gl>) -> ShaderProgram<'a> {
+    pub fn new(gl_ctx: Rc<GLContext>) -> ShaderProgram<'a> {
         let prog_id;
         unsafe {
             prog_id = gl_ctx.CreateProgram();
         }
+        gl_ctx.track_object(GLObjectKind::Program, prog_id);
         ShaderProgram {
             gl_ctx: gl_ctx,
             id: prog_id,
@@ -149,85 +221,245 @@ impl<'a> ShaderProgram<'a> {
     }
 }
 
+impl Drop for ShaderProgram<'_> {
+    fn drop(&mut self) {
+        // A successful link hands the program over to CompiledShaderProgram and zeroes the id.
+        self.gl_ctx.delete_object(GLObjectKind::Program, self.id);
+    }
+}
+
 pub struct CompiledShaderProgram {
-    gl_ctx: Rc<gl::Gl>,
-    id: u32,
-    // there needs to be something here to restore texture state, since glBindTexture overwrites what's being bound in which slot.
-    // most likely a hashmap, but how do we store texture information?
-    // Rc's would likely work, but that's a lot more rc pointers to deal with.
+    gl_ctx: Rc<GLContext>,
+    id: Cell<u32>,
+    // Sampler name -> the unit it was given on link. Binding a texture by sampler name goes through here.
+    samplers: RefCell<HashMap<String, SamplerBinding>>,
 }
 
 impl CompiledShaderProgram {
-    pub fn compile_shader(gl_ctx: Rc<gl::Gl>, prog: ShaderProgram<'_>) -> Result<CompiledShaderProgram, (OpenGLError, ShaderProgram<'_>)> {
+    pub fn compile_shader(gl_ctx: Rc<GLContext>, mut prog: ShaderProgram<'_>) -> Result<CompiledShaderProgram, (OpenGLError, ShaderProgram<'_>)> {
         unsafe {
             gl_ctx.LinkProgram(prog.id);
             let mut result_code = 0;
             gl_ctx.GetProgramiv(prog.id, gl::LINK_STATUS, (&mut result_code) as *mut _);
             if result_code != (gl::TRUE as i32) {
-                let character_output = Vec::with_capacity(512);
-                let c_str = CString::new(character_output).expect("Failed to initialize blank CString");
-                let c_ptr = c_str.into_raw();
-                gl_ctx.GetProgramInfoLog(prog.id, 512, ptr::null_mut(), c_ptr);
-                let c_str = CString::from_raw(c_ptr);
-                return Err((OpenGLError::LinkerError(c_str.to_str().expect("Failed to convert from CString").to_owned()), prog));
+                let log = program_info_log(&gl_ctx, prog.id);
+                return Err((OpenGLError::LinkerError(log), prog));
             }
             for (_,v) in prog.loaded_phases.iter() {
                 gl_ctx.DetachShader(prog.id, v.id)
             }
-            Ok(CompiledShaderProgram {
-                gl_ctx: gl_ctx,
-                id: prog.id
-            })
         }
+
+        let samplers = match allocate_units(&active_samplers(&gl_ctx, prog.id), gl_ctx.caps().max_texture_units) {
+            Ok(samplers) => samplers,
+            Err(err) => return Err((err, prog)),
+        };
+        let prog_id = prog.id;
+        prog.id = 0;
+
+        let compiled = CompiledShaderProgram {
+            gl_ctx: gl_ctx,
+            id: Cell::new(prog_id),
+            samplers: RefCell::new(samplers),
+        };
+        compiled.assign_sampler_units();
+        Ok(compiled)
     }
 
-    pub fn generate_program<S: AsRef<Path>>(gl_ctx: Rc<gl::Gl>, vs_path: S, fs_path: S, gs_path: Option<S>) -> Result<CompiledShaderProgram, ShaderCompileError> {
-        let mut shdr_prog = ShaderProgram::new(gl_ctx.clone());
+    /// Points every sampler uniform at the unit it was allocated. Units never change after this,
+    /// so binding a texture is all that's left to do per draw.
+    fn assign_sampler_units(&self) {
+        // glProgramUniform (4.1) sets them without disturbing the current program.
+        let direct = self.gl_ctx.ProgramUniform1i.is_loaded();
+        if !direct {
+            self.use_program();
+        }
+        for binding in self.samplers.borrow().values() {
+            unsafe {
+                if direct {
+                    self.gl_ctx.ProgramUniform1i(self.id.get(), binding.location, binding.unit.index() as i32);
+                } else {
+                    self.gl_ctx.Uniform1i(binding.location, binding.unit.index() as i32);
+                }
+            }
+        }
+    }
+
+    pub fn generate_program<S: AsRef<Path>>(gl_ctx: Rc<GLContext>, vs_path: S, fs_path: S, gs_path: Option<S>) -> Result<CompiledShaderProgram, ShaderCompileError> {
         let vs_shdr = Shader::from_path(gl_ctx.clone(), vs_path, ShaderType::VertexShader)?;
         vs_shdr.compile_shader()?;
         let fs_shdr = Shader::from_path(gl_ctx.clone(), fs_path, ShaderType::FragmentShader)?;
         fs_shdr.compile_shader()?;
+        let gs_shdr = match gs_path {
+            Some(gs_loc) => Some(Shader::from_path(gl_ctx.clone(), gs_loc, ShaderType::GeometryShader)?),
+            None => None,
+        };
+
+        // The program borrows the shaders, so it has to be created (and dropped) inside their lifetime.
+        let mut shdr_prog = ShaderProgram::new(gl_ctx.clone());
         shdr_prog.attach_shader(&vs_shdr)?;
         shdr_prog.attach_shader(&fs_shdr)?;
-
-        match gs_path {
-            Some(gs_loc) => {
-                let gs_shdr = Shader::from_path(gl_ctx.clone(), gs_loc, ShaderType::GeometryShader)?;
-                shdr_prog.attach_shader(&gs_shdr)?;
-                Ok(CompiledShaderProgram::compile_shader(gl_ctx.clone(), shdr_prog).map_err(|(err, _)| err)?)
-            },
-            None => {
-                Ok(CompiledShaderProgram::compile_shader(gl_ctx.clone(), shdr_prog).map_err(|(err, _)| err)?)
-            },
+        if let Some(gs_shdr) = &gs_shdr {
+            shdr_prog.attach_shader(gs_shdr)?;
         }
 
-
+        Ok(CompiledShaderProgram::compile_shader(gl_ctx.clone(), shdr_prog).map_err(|(err, _)| err)?)
     }
 
     pub fn set_uniform<T: UniformType>(&self, name: &str, uniform: &T) {
         let loc: i32;
         unsafe {
             let c_str = CString::new(name).expect("Internal NULL detected. Uniform location failed to convert to valid CString");
-            loc = self.gl_ctx.GetUniformLocation(self.id, c_str.as_ptr());
+            loc = self.gl_ctx.GetUniformLocation(self.id.get(), c_str.as_ptr());
         }
         uniform.assign_to_current_program(self.gl_ctx.as_ref(), loc);
     }
 
-    pub fn assign_texture_to_unit(&self, name: &str, tex_unit: TextureUnit) {
-        self.set_uniform(name, &tex_unit)
+    /// Lists the vertex attributes the linked program actually reads.
+    /// Built-ins such as `gl_VertexID` are reported with a location of -1.
+    pub fn active_attributes(&self) -> Vec<ActiveAttribute> {
+        let mut attributes = Vec::new();
+        unsafe {
+            let mut count = 0;
+            self.gl_ctx.GetProgramiv(self.id.get(), gl::ACTIVE_ATTRIBUTES, &mut count);
+            let mut max_len = 0;
+            self.gl_ctx.GetProgramiv(self.id.get(), gl::ACTIVE_ATTRIBUTE_MAX_LENGTH, &mut max_len);
+
+            for idx in 0..count {
+                let mut name_buf: Vec<u8> = vec![0; max_len.max(1) as usize];
+                let mut length = 0;
+                let mut size = 0;
+                let mut gl_type = 0;
+                self.gl_ctx.GetActiveAttrib(self.id.get(), idx as u32, max_len, &mut length, &mut size, &mut gl_type, name_buf.as_mut_ptr() as *mut _);
+                name_buf.truncate(length as usize);
+
+                let c_name = CString::new(name_buf).expect("Internal NULL detected. Attribute name failed to convert to valid CString");
+                let location = self.gl_ctx.GetAttribLocation(self.id.get(), c_name.as_ptr());
+                attributes.push(ActiveAttribute {
+                    name: c_name.into_string().expect("Attribute name was not valid UTF-8"),
+                    location: location,
+                    gl_type: gl_type,
+                    size: size,
+                });
+            }
+        }
+        attributes
+    }
+
+    /// Binds `tex` to the unit the sampler `name` reads from.
+    pub fn bind_texture(&self, name: &str, tex: &Texture) -> Result<(), OpenGLError> {
+        let binding = self.sampler(name).ok_or_else(|| OpenGLError::UnknownSampler(name.to_owned()))?;
+        tex.bind(binding.unit);
+        Ok(())
+    }
+
+    pub fn sampler(&self, name: &str) -> Option<SamplerBinding> {
+        self.samplers.borrow().get(name).copied()
+    }
+
+    pub fn samplers(&self) -> Vec<(String, SamplerBinding)> {
+        self.samplers.borrow().iter().map(|(name, binding)| (name.clone(), *binding)).collect()
+    }
+
+    /// Trades linked programs with `other`, so this program runs the other's shaders and `other` deletes the old program
+    /// when it drops. Lets a program be recompiled while every model drawn with it keeps using it.
+    pub fn swap(&self, other: &CompiledShaderProgram) {
+        self.id.swap(&other.id);
+        self.samplers.swap(&other.samplers);
+    }
+
+    /// Names the object in debug output and captures.
+    pub fn set_label(&self, label: &str) {
+        self.gl_ctx.label_object(GLObjectKind::Program, self.id.get(), label);
     }
 
     pub fn use_program(&self) {
-        unsafe {
-            self.gl_ctx.UseProgram(self.id);
-        }
+        self.gl_ctx.use_program(self.id.get());
     }
 
     pub fn unbind_program(&self) {
-        unsafe {
-            self.gl_ctx.UseProgram(0);
+        self.gl_ctx.use_program(0);
+    }
+}
+
+impl Drop for CompiledShaderProgram {
+    fn drop(&mut self) {
+        self.gl_ctx.delete_object(GLObjectKind::Program, self.id.get());
+    }
+}
+
+/// The whole compile log of a shader, however long the driver made it.
+fn shader_info_log(gl_ctx: &GLContext, shader: u32) -> String {
+    let mut log_len = 0;
+    unsafe {
+        gl_ctx.GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut log_len);
+    }
+    let mut log_buf: Vec<u8> = vec![0; log_len.max(1) as usize];
+    let mut written = 0;
+    unsafe {
+        gl_ctx.GetShaderInfoLog(shader, log_buf.len() as i32, &mut written, log_buf.as_mut_ptr() as *mut _);
+    }
+    log_buf.truncate(written.max(0) as usize);
+    String::from_utf8_lossy(&log_buf).into_owned()
+}
+
+/// The whole link log of a program.
+fn program_info_log(gl_ctx: &GLContext, program: u32) -> String {
+    let mut log_len = 0;
+    unsafe {
+        gl_ctx.GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut log_len);
+    }
+    let mut log_buf: Vec<u8> = vec![0; log_len.max(1) as usize];
+    let mut written = 0;
+    unsafe {
+        gl_ctx.GetProgramInfoLog(program, log_buf.len() as i32, &mut written, log_buf.as_mut_ptr() as *mut _);
+    }
+    log_buf.truncate(written.max(0) as usize);
+    String::from_utf8_lossy(&log_buf).into_owned()
+}
+
+/// Lists the sampler uniforms of a linked program.
+fn active_samplers(gl_ctx: &GLContext, program: u32) -> Vec<ActiveSampler> {
+    let mut samplers = Vec::new();
+    unsafe {
+        let mut count = 0;
+        gl_ctx.GetProgramiv(program, gl::ACTIVE_UNIFORMS, &mut count);
+        let mut max_len = 0;
+        gl_ctx.GetProgramiv(program, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_len);
+
+        for idx in 0..count {
+            let mut name_buf: Vec<u8> = vec![0; max_len.max(1) as usize];
+            let mut length = 0;
+            let mut size = 0;
+            let mut gl_type = 0;
+            gl_ctx.GetActiveUniform(program, idx as u32, max_len, &mut length, &mut size, &mut gl_type, name_buf.as_mut_ptr() as *mut _);
+            if !is_sampler_type(gl_type) {
+                continue;
+            }
+            name_buf.truncate(length as usize);
+
+            let c_name = CString::new(name_buf).expect("Internal NULL detected. Uniform name failed to convert to valid CString");
+            let location = gl_ctx.GetUniformLocation(program, c_name.as_ptr());
+            samplers.push(ActiveSampler {
+                name: c_name.into_string().expect("Uniform name was not valid UTF-8"),
+                location: location,
+                size: size,
+                gl_type: gl_type,
+            });
         }
     }
+    // Uniform indices follow no particular order; sort by location so allocation is stable between runs.
+    samplers.sort_by_key(|sampler| sampler.location);
+    samplers
+}
+
+/// A vertex attribute reported by the linker as in use by a program.
+#[derive(Debug, Clone)]
+pub struct ActiveAttribute {
+    pub name: String,
+    pub location: i32,
+    pub gl_type: u32,
+    pub size: i32,
 }
 
 #[derive(Error, Debug)]
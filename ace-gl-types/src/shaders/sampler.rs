@@ -0,0 +1,114 @@
+use crate::gl;
+use crate::types::*;
+use std::collections::HashMap;
+
+/// Where one of a program's samplers reads from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SamplerBinding {
+    pub location: i32,
+    pub unit: TextureUnit,
+    /// The sampler's GLSL type, e.g. GL_SAMPLER_2D.
+    pub gl_type: u32,
+}
+
+/// A sampler uniform as reported by the linker. Arrays come through once, named `name[0]`, with `size` elements.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ActiveSampler {
+    pub name: String,
+    pub location: i32,
+    pub size: i32,
+    pub gl_type: u32,
+}
+
+pub(crate) fn is_sampler_type(gl_type: u32) -> bool {
+    matches!(gl_type,
+        gl::SAMPLER_1D | gl::SAMPLER_2D | gl::SAMPLER_3D | gl::SAMPLER_CUBE
+            | gl::SAMPLER_1D_SHADOW | gl::SAMPLER_2D_SHADOW | gl::SAMPLER_CUBE_SHADOW
+            | gl::SAMPLER_1D_ARRAY | gl::SAMPLER_2D_ARRAY | gl::SAMPLER_1D_ARRAY_SHADOW | gl::SAMPLER_2D_ARRAY_SHADOW
+            | gl::SAMPLER_2D_MULTISAMPLE | gl::SAMPLER_2D_MULTISAMPLE_ARRAY
+            | gl::SAMPLER_BUFFER | gl::SAMPLER_2D_RECT | gl::SAMPLER_2D_RECT_SHADOW
+            | gl::SAMPLER_CUBE_MAP_ARRAY | gl::SAMPLER_CUBE_MAP_ARRAY_SHADOW
+
+            | gl::INT_SAMPLER_1D | gl::INT_SAMPLER_2D | gl::INT_SAMPLER_3D | gl::INT_SAMPLER_CUBE
+            | gl::INT_SAMPLER_1D_ARRAY | gl::INT_SAMPLER_2D_ARRAY
+            | gl::INT_SAMPLER_2D_MULTISAMPLE | gl::INT_SAMPLER_2D_MULTISAMPLE_ARRAY
+            | gl::INT_SAMPLER_BUFFER | gl::INT_SAMPLER_2D_RECT | gl::INT_SAMPLER_CUBE_MAP_ARRAY
+
+            | gl::UNSIGNED_INT_SAMPLER_1D | gl::UNSIGNED_INT_SAMPLER_2D | gl::UNSIGNED_INT_SAMPLER_3D | gl::UNSIGNED_INT_SAMPLER_CUBE
+            | gl::UNSIGNED_INT_SAMPLER_1D_ARRAY | gl::UNSIGNED_INT_SAMPLER_2D_ARRAY
+            | gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE | gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE_ARRAY
+            | gl::UNSIGNED_INT_SAMPLER_BUFFER | gl::UNSIGNED_INT_SAMPLER_2D_RECT | gl::UNSIGNED_INT_SAMPLER_CUBE_MAP_ARRAY
+    )
+}
+
+/// Hands out consecutive units to the samplers, in the order the linker listed them.
+///
+/// Array elements each get their own unit and are registered as `name[i]`; the bare `name` refers to element 0.
+/// Uniform locations of array elements are consecutive, so `name[i]` lives at the base location plus `i`.
+pub(crate) fn allocate_units(samplers: &[ActiveSampler], max_units: u32) -> Result<HashMap<String, SamplerBinding>, OpenGLError> {
+    let needed: u32 = samplers.iter().map(|sampler| sampler.size.max(1) as u32).sum();
+    if needed > max_units {
+        return Err(OpenGLError::TooManySamplers(needed, max_units));
+    }
+
+    let mut bindings = HashMap::new();
+    let mut next_unit = 0;
+    for sampler in samplers {
+        let base_name = sampler.name.trim_end_matches("[0]");
+        for element in 0..sampler.size.max(1) {
+            let binding = SamplerBinding {
+                location: sampler.location + element,
+                unit: TextureUnit::new(next_unit),
+                gl_type: sampler.gl_type,
+            };
+            next_unit += 1;
+
+            if sampler.size > 1 {
+                bindings.insert(format!("{}[{}]", base_name, element), binding);
+            }
+            if element == 0 {
+                bindings.insert(base_name.to_owned(), binding);
+            }
+        }
+    }
+    Ok(bindings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sampler(name: &str, location: i32, size: i32) -> ActiveSampler {
+        ActiveSampler {
+            name: name.to_owned(),
+            location: location,
+            size: size,
+            gl_type: gl::SAMPLER_2D,
+        }
+    }
+
+    #[test]
+    fn units_are_allocated_in_order() {
+        let bindings = allocate_units(&[sampler("texture1", 3, 1), sampler("texture2", 7, 1)], 16).unwrap();
+        assert_eq!(bindings["texture1"].unit, TextureUnit::new(0));
+        assert_eq!(bindings["texture2"].unit, TextureUnit::new(1));
+        assert_eq!(bindings["texture2"].location, 7);
+    }
+
+    #[test]
+    fn array_elements_get_their_own_units() {
+        let bindings = allocate_units(&[sampler("shadow", 0, 1), sampler("lights[0]", 4, 3)], 16).unwrap();
+        assert_eq!(bindings["lights"].unit, TextureUnit::new(1));
+        assert_eq!(bindings["lights[0]"].unit, TextureUnit::new(1));
+        assert_eq!(bindings["lights[2]"].unit, TextureUnit::new(3));
+        assert_eq!(bindings["lights[2]"].location, 6);
+    }
+
+    #[test]
+    fn running_out_of_units_is_an_error() {
+        match allocate_units(&[sampler("a", 0, 1), sampler("b[0]", 1, 16)], 16) {
+            Err(OpenGLError::TooManySamplers(17, 16)) => {},
+            other => panic!("expected TooManySamplers, got {:?}", other.map(|b| b.len())),
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use naga::front::glsl;
+use naga::valid::{Capabilities, ValidationFlags, Validator};
+use crate::types::*;
+
+/// A problem found in a shader without going anywhere near a GPU.
+/// Lines and columns are 1-based and point into the source as it is on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShaderDiagnostic {
+    pub path: PathBuf,
+    pub line: u32,
+    pub column: u32,
+    pub message: String,
+}
+
+impl fmt::Display for ShaderDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}: {}", self.path.display(), self.line, self.column, self.message)
+    }
+}
+
+/// Parses and type-checks GLSL with naga. `path` is only used to label the diagnostics.
+/// An empty result means the shader is fine.
+///
+/// naga only understands Vulkan-flavoured GLSL 4.40+, so the source is translated first (see `vulkan_dialect`);
+/// geometry and tessellation shaders can't be checked at all and come back with a single diagnostic saying so.
+pub fn validate_glsl(path: &Path, src: &str, shader_type: ShaderType) -> Vec<ShaderDiagnostic> {
+    let stage = match shader_type {
+        ShaderType::VertexShader    => naga::ShaderStage::Vertex,
+        ShaderType::FragmentShader  => naga::ShaderStage::Fragment,
+        _ => return vec![ShaderDiagnostic {
+            path: path.to_owned(),
+            line: 1,
+            column: 1,
+            message: format!("{} can't be validated offline", shader_type),
+        }],
+    };
+
+    let (translated, shifts) = vulkan_dialect(src);
+    let diagnostic = |span: naga::Span, message: String| {
+        let location = span.location(&translated);
+        let shift = shifts.get(location.line_number as usize - 1).copied().unwrap_or(0);
+        ShaderDiagnostic {
+            path: path.to_owned(),
+            line: location.line_number,
+            column: location.line_position.saturating_sub(shift).max(1),
+            message: message,
+        }
+    };
+
+    let module = match glsl::Parser::default().parse(&glsl::Options::from(stage), &translated) {
+        Ok(module) => module,
+        Err(errors) => return errors.into_iter().map(|err| diagnostic(err.meta, err.kind.to_string())).collect(),
+    };
+
+    match Validator::new(ValidationFlags::all(), Capabilities::empty()).validate(&module) {
+        Ok(_) => Vec::new(),
+        Err(err) => {
+            // Spans run from the enclosing function down to the offending expression; the last is the most precise.
+            let span = err.spans().last().map(|(span, _)| *span).unwrap_or_default();
+            vec![diagnostic(span, error_chain(&err))]
+        },
+    }
+}
+
+/// Validates a single shader file, working out its stage from the extension.
+pub fn validate_shader_file<P: AsRef<Path>>(path: P) -> io::Result<Vec<ShaderDiagnostic>> {
+    let path = path.as_ref();
+    let shader_type = ShaderType::from_extension(path).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("{} does not have a shader extension", path.display()))
+    })?;
+    let src = fs::read_to_string(path)?;
+    Ok(validate_glsl(path, &src, shader_type))
+}
+
+/// Validates every vertex and fragment shader under `dir`, recursively.
+/// Files without a shader extension, and stages naga can't check, are skipped.
+pub fn validate_shader_dir<P: AsRef<Path>>(dir: P) -> io::Result<Vec<ShaderDiagnostic>> {
+    let mut diagnostics = Vec::new();
+    let mut entries = fs::read_dir(dir)?.map(|entry| entry.map(|e| e.path())).collect::<io::Result<Vec<_>>>()?;
+    // read_dir order is up to the filesystem; sort so reports come out the same every run.
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            diagnostics.extend(validate_shader_dir(&path)?);
+            continue;
+        }
+        match ShaderType::from_extension(&path) {
+            Some(ShaderType::VertexShader) | Some(ShaderType::FragmentShader) => diagnostics.extend(validate_shader_file(&path)?),
+            _ => {},
+        }
+    }
+    Ok(diagnostics)
+}
+
+/// naga's validation errors nest; flatten them into one line, outermost first.
+fn error_chain(err: &dyn Error) -> String {
+    let mut message = err.to_string();
+    let mut source = err.source();
+    while let Some(inner) = source {
+        message.push_str(": ");
+        message.push_str(&inner.to_string());
+        source = inner.source();
+    }
+    message
+}
+
+/// Rewrites the desktop GL GLSL in assets/shaders into something naga's front end accepts:
+///
+/// * the `#version` is bumped to 450, naga's oldest;
+/// * loose uniforms get a `layout(binding = N)`, since naga only knows about resources bound Vulkan-style;
+/// * combined samplers don't exist in Vulkan GLSL, so each is split into a texture and a sampler
+///   and every use of it becomes a `sampler2D(tex, smp)` constructor;
+/// * `in`/`out` variables without a location get the lowest free one, like glslang's auto-mapping.
+///
+/// Line count is preserved so diagnostics still point at the right line. Qualifiers are inserted at the start
+/// of a line, and the returned shifts (one per line) say by how many columns; columns on lines that use a
+/// split sampler can still be a little off.
+fn vulkan_dialect(src: &str) -> (String, Vec<u32>) {
+    let mut used_inputs = HashSet::new();
+    let mut used_outputs = HashSet::new();
+    for line in src.lines() {
+        if let Some((location, storage)) = explicit_location(line) {
+            match storage {
+                "in" => used_inputs.insert(location),
+                _ => used_outputs.insert(location),
+            };
+        }
+    }
+
+    let mut translated = String::with_capacity(src.len());
+    let mut shifts = Vec::new();
+    let mut depth = 0;
+    let mut next_binding = 0;
+    // (sampler uniform, its constructor) for every combined sampler split so far.
+    let mut split_samplers: Vec<(String, String)> = Vec::new();
+    for line in src.lines() {
+        let code = line.split("//").next().unwrap_or("").trim();
+        let mut prefix = String::new();
+        let mut line = line.to_owned();
+        if code.starts_with("#version") {
+            translated.push_str("#version 450 core\n");
+            shifts.push(0);
+            continue;
+        }
+
+        for (name, constructor) in &split_samplers {
+            line = replace_identifier(&line, name, constructor);
+        }
+
+        if depth == 0 && !code.starts_with("layout") {
+            let mut words = code.split_whitespace();
+            let storage = words.next().unwrap_or("");
+            match storage {
+                "uniform" => {
+                    let ty = words.next().unwrap_or("");
+                    let name = words.next().map(|name| name.trim_end_matches(';'));
+                    match (split_sampler_type(ty), name) {
+                        (Some((texture_type, sampler_type)), Some(name)) => {
+                            let constructor = format!("{}({}, {}_sampler)", ty, name, name);
+                            line = format!(
+                                "layout(binding = {}) uniform {} {}; layout(binding = {}) uniform {} {}_sampler;",
+                                next_binding, texture_type, name, next_binding + 1, sampler_type, name,
+                            );
+                            split_samplers.push((name.to_owned(), constructor));
+                            next_binding += 2;
+                        },
+                        _ => {
+                            prefix = format!("layout(binding = {}) ", next_binding);
+                            next_binding += 1;
+                        },
+                    }
+                },
+                "in" | "out" => {
+                    let used = if storage == "in" { &mut used_inputs } else { &mut used_outputs };
+                    let location = (0..).find(|loc| !used.contains(loc)).unwrap();
+                    used.insert(location);
+                    prefix = format!("layout(location = {}) ", location);
+                },
+                _ => {},
+            }
+        }
+        depth += code.matches('{').count() as i32 - code.matches('}').count() as i32;
+
+        shifts.push(prefix.chars().count() as u32);
+        translated.push_str(&prefix);
+        translated.push_str(&line);
+        translated.push('\n');
+    }
+    (translated, shifts)
+}
+
+/// The separate texture and sampler types a combined sampler type splits into.
+fn split_sampler_type(combined: &str) -> Option<(&'static str, &'static str)> {
+    match combined {
+        "sampler1D"             => Some(("texture1D", "sampler")),
+        "sampler2D"             => Some(("texture2D", "sampler")),
+        "sampler3D"             => Some(("texture3D", "sampler")),
+        "samplerCube"           => Some(("textureCube", "sampler")),
+        "sampler2DArray"        => Some(("texture2DArray", "sampler")),
+        "sampler2DShadow"       => Some(("texture2D", "samplerShadow")),
+        "samplerCubeShadow"     => Some(("textureCube", "samplerShadow")),
+        _ => None,
+    }
+}
+
+/// Replaces whole-word occurrences of `name` in `line`.
+fn replace_identifier(line: &str, name: &str, with: &str) -> String {
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(idx) = rest.find(name) {
+        let before = rest[..idx].chars().next_back();
+        let after = rest[idx + name.len()..].chars().next();
+        out.push_str(&rest[..idx]);
+        if before.is_none_or(|c| !is_ident(c)) && after.is_none_or(|c| !is_ident(c)) {
+            out.push_str(with);
+        } else {
+            out.push_str(name);
+        }
+        rest = &rest[idx + name.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `layout (location = N) in ...` -> `(N, "in")`.
+fn explicit_location(line: &str) -> Option<(u32, &str)> {
+    let code = line.trim();
+    if !code.starts_with("layout") {
+        return None;
+    }
+    let open = code.find('(')?;
+    let close = code.find(')')?;
+    let location = code[open + 1..close].split(',').find_map(|qualifier| {
+        let mut parts = qualifier.splitn(2, '=');
+        match (parts.next()?.trim(), parts.next()) {
+            ("location", Some(value)) => value.trim().parse().ok(),
+            _ => None,
+        }
+    })?;
+    let storage = code[close + 1..].split_whitespace().next()?;
+    match storage {
+        "in" | "out" => Some((location, storage)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shader_assets_validate() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../assets/shaders");
+        let diagnostics = validate_shader_dir(&dir).expect("Failed to read the shader assets");
+        let report: Vec<String> = diagnostics.iter().map(|diag| diag.to_string()).collect();
+        assert!(diagnostics.is_empty(), "shader diagnostics:\n{}", report.join("\n"));
+    }
+
+    #[test]
+    fn diagnostics_point_at_the_original_line() {
+        let src = "#version 330 core\nout vec4 FragColor;\n\nvoid main()\n{\n    FragColor = vec4(missing, 1.0);\n}\n";
+        let diagnostics = validate_glsl(Path::new("broken.frag"), src, ShaderType::FragmentShader);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, 6);
+        assert!(diagnostics[0].to_string().starts_with("broken.frag:6:"));
+    }
+
+    #[test]
+    fn free_locations_are_handed_out_in_order() {
+        let src = "#version 330 core\nlayout (location = 0) in vec3 aPos;\nin vec2 uv;\nuniform mat4 model;\n";
+        let (translated, shifts) = vulkan_dialect(src);
+        let lines: Vec<&str> = translated.lines().collect();
+        assert_eq!(lines[0], "#version 450 core");
+        assert_eq!(lines[2], "layout(location = 1) in vec2 uv;");
+        assert_eq!(lines[3], "layout(binding = 0) uniform mat4 model;");
+        assert_eq!(shifts, vec![0, 0, 21, 20]);
+    }
+
+    #[test]
+    fn type_errors_are_reported() {
+        let src = "#version 330 core\nout vec4 FragColor;\nuniform sampler2D tex;\n\nvoid main()\n{\n    FragColor = texture(tex, vec2(0.5)).rgb;\n}\n";
+        let diagnostics = validate_glsl(Path::new("mismatch.frag"), src, ShaderType::FragmentShader);
+        assert!(!diagnostics.is_empty());
+        assert_eq!(diagnostics[0].line, 7);
+    }
+}
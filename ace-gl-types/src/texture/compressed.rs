@@ -0,0 +1,72 @@
+use crate::gl;
+use crate::types::*;
+
+/// Block-compressed texel formats textures can be uploaded in as they are, without decoding them first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompressedFormat {
+    /// BC1 (DXT1): 4x4 blocks of RGB in 8 bytes.
+    Bc1,
+    /// BC1 holding sRGB colors, which the GPU converts to linear when sampling.
+    Bc1Srgb,
+    /// BC3 (DXT5): 4x4 blocks of RGBA in 16 bytes, the alpha stored apart from the colors.
+    Bc3,
+    /// BC3 holding sRGB colors; alpha is always linear.
+    Bc3Srgb,
+}
+
+impl CompressedFormat {
+    pub fn gl_format(self) -> u32 {
+        match self {
+            CompressedFormat::Bc1 => gl::COMPRESSED_RGB_S3TC_DXT1_EXT,
+            CompressedFormat::Bc1Srgb => gl::COMPRESSED_SRGB_S3TC_DXT1_EXT,
+            CompressedFormat::Bc3 => gl::COMPRESSED_RGBA_S3TC_DXT5_EXT,
+            CompressedFormat::Bc3Srgb => gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT,
+        }
+    }
+
+    /// The format with an alpha channel that holds the same colors.
+    pub fn with_alpha(self) -> CompressedFormat {
+        match self {
+            CompressedFormat::Bc1 | CompressedFormat::Bc3 => CompressedFormat::Bc3,
+            CompressedFormat::Bc1Srgb | CompressedFormat::Bc3Srgb => CompressedFormat::Bc3Srgb,
+        }
+    }
+
+    pub fn block_bytes(self) -> usize {
+        match self {
+            CompressedFormat::Bc1 | CompressedFormat::Bc1Srgb => 8,
+            CompressedFormat::Bc3 | CompressedFormat::Bc3Srgb => 16,
+        }
+    }
+
+    /// How many bytes one mip level of the given size takes. Partial blocks at the edges are stored whole.
+    pub fn level_bytes(self, width: u32, height: u32) -> usize {
+        let blocks = |texels: u32| texels.max(1).div_ceil(4) as usize;
+        blocks(width) * blocks(height) * self.block_bytes()
+    }
+
+    /// Whether the driver can sample the format.
+    pub fn is_supported(self, caps: &GLCapabilities) -> bool {
+        match self {
+            CompressedFormat::Bc1 | CompressedFormat::Bc3 => caps.has_extension("GL_EXT_texture_compression_s3tc"),
+            // sRGB S3TC comes from EXT_texture_sRGB, which core 2.1 only took the uncompressed formats from.
+            CompressedFormat::Bc1Srgb | CompressedFormat::Bc3Srgb => caps.has_extension("GL_EXT_texture_compression_s3tc")
+                && (caps.has_extension("GL_EXT_texture_sRGB") || caps.has_extension("GL_EXT_texture_compression_s3tc_srgb")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_blocks_are_stored_whole() {
+        assert_eq!(CompressedFormat::Bc1.level_bytes(4, 4), 8);
+        assert_eq!(CompressedFormat::Bc1.level_bytes(5, 4), 16);
+        assert_eq!(CompressedFormat::Bc1.level_bytes(1, 1), 8);
+        assert_eq!(CompressedFormat::Bc1Srgb.level_bytes(256, 128), 64 * 32 * 8);
+        assert_eq!(CompressedFormat::Bc3.level_bytes(5, 4), 32);
+        assert_eq!(CompressedFormat::Bc1Srgb.with_alpha(), CompressedFormat::Bc3Srgb);
+    }
+}
@@ -13,6 +13,12 @@ pub struct CubemapPaths {
 
 impl CubemapPaths {
 
+    /// The faces a cubemap in `dir_path` has, without checking they're there.
+    pub fn in_directory<T: AsRef<Path>>(dir_path: T) -> CubemapPaths {
+        let face = |name: &str| dir_path.as_ref().join(name);
+        CubemapPaths::from_raw_paths(face("top.jpg"), face("bottom.jpg"), face("left.jpg"), face("right.jpg"), face("front.jpg"), face("back.jpg"))
+    }
+
     pub fn from_directory<T: AsRef<Path>>(dir_path: T) -> Result<CubemapPaths, ::std::io::Error> {
         let path = dir_path.as_ref();
 
@@ -54,6 +60,11 @@ impl CubemapPaths {
         }
     }
 
+    /// Every face's image file.
+    pub fn files(&self) -> [&Path; 6] {
+        [&self.right, &self.left, &self.top, &self.bottom, &self.front, &self.back]
+    }
+
     pub(crate) fn cubemap_entries(self) -> Vec<(PathBuf, CubemapTextureType)> {
         let mut cm_e = Vec::new();
 
@@ -70,6 +81,10 @@ impl CubemapPaths {
     }
 }
 
+/// Decoded faces of a cubemap, ready to upload.
+pub type CubemapFaces = Vec<(image::RgbImage, CubemapTextureType)>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CubemapTextureType {
     TextureCubeMapPosX      = gl::TEXTURE_CUBE_MAP_POSITIVE_X as isize,
     TextureCubeMapNegX      = gl::TEXTURE_CUBE_MAP_NEGATIVE_X as isize,
@@ -79,4 +94,16 @@ pub enum CubemapTextureType {
 
     TextureCubeMapPosZ      = gl::TEXTURE_CUBE_MAP_POSITIVE_Z as isize,
     TextureCubeMapNegZ      = gl::TEXTURE_CUBE_MAP_NEGATIVE_Z as isize,
+}
+
+impl CubemapTextureType {
+    /// Every face, in the order GL numbers them.
+    pub const ALL: [CubemapTextureType; 6] = [
+        CubemapTextureType::TextureCubeMapPosX,
+        CubemapTextureType::TextureCubeMapNegX,
+        CubemapTextureType::TextureCubeMapPosY,
+        CubemapTextureType::TextureCubeMapNegY,
+        CubemapTextureType::TextureCubeMapPosZ,
+        CubemapTextureType::TextureCubeMapNegZ,
+    ];
 }
\ No newline at end of file
@@ -2,6 +2,7 @@ use crate::gl;
 
 #[allow(non_camel_case_types)]
 #[derive(Eq, PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum InternalStorage {
     Depth                       = gl::DEPTH_COMPONENT as isize,
     DepthStencil                = gl::DEPTH_STENCIL as isize,
@@ -97,4 +98,53 @@ pub enum InternalStorage {
     Float_Depth32Stencil8       = gl::DEPTH32F_STENCIL8 as isize,
     Stencil8                    = gl::STENCIL_INDEX8 as isize,
 
-}
\ No newline at end of file
+}
+impl InternalStorage {
+    /// The sized format the driver would pick for an unsized one.
+    /// Immutable storage (`glTextureStorage2D`, `glNamedRenderbufferStorage`) only accepts sized formats.
+    pub fn sized(&self) -> InternalStorage {
+        match self {
+            InternalStorage::Depth          => InternalStorage::Depth24,
+            InternalStorage::DepthStencil   => InternalStorage::Depth24Stencil8,
+            InternalStorage::Stencil        => InternalStorage::Stencil8,
+            InternalStorage::Red
+                | InternalStorage::Green
+                | InternalStorage::Blue     => InternalStorage::Red8,
+            InternalStorage::RG             => InternalStorage::RedGreen8,
+            InternalStorage::RGB            => InternalStorage::RedGreenBlue8,
+            InternalStorage::RGBA           => InternalStorage::RedGreenBlueAlpha8,
+            sized                           => *sized,
+        }
+    }
+
+    /// Roughly how many bytes one texel takes up on the GPU.
+    /// Unsized formats are assumed to be 8 bits per channel, which is what drivers pick in practice.
+    /// Only used for memory accounting, so padding the driver might add isn't counted.
+    pub fn bytes_per_pixel(&self) -> usize {
+        use InternalStorage::*;
+        match self {
+            Stencil | Red | Green | Blue | Red8 | Norm_Red8 | Red3Green3Blue2 | RedGreenBlueAlpha2 | Int_Red8 | UInt_Red8 | Stencil8 => 1,
+
+            RG | Red16 | Norm_Red16 | RedGreen8 | Norm_RedGreen8 | RedGreenBlue4 | RedGreenBlue5 | RedGreenBlueAlpha4 | RedGreenBlue5Alpha1
+                | Float_Red16 | Int_Red16 | UInt_Red16 | Int_RedGreen8 | UInt_RedGreen8 | Depth16 => 2,
+
+            RGB | RedGreenBlue8 | Norm_RedGreenBlue8 | Std_RedGreenBlue8 | Int_RedGreenBlue8 | UInt_RedGreenBlue8 => 3,
+
+            Depth | DepthStencil | RGBA | RedGreen16 | Norm_RedGreen16 | RedGreenBlue10 | RedGreenBlueAlpha8 | Norm_RedGreenBlueAlpha8
+                | RedGreenBlue10Alpha2 | UInt_RedGreenBlue10Alpha2 | Std_RedGreenBlueAlpha8 | Float_RedGreen16 | Float_Red32
+                | Float_Red11Green11Blue10 | RedGreenBlue9Shared5 | Int_Red32 | UInt_Red32 | Int_RedGreen16 | UInt_RedGreen16
+                | Int_RedGreenBlueAlpha8 | UInt_RedGreenBlueAlpha8 | Depth24 | Depth32 | Float_Depth32 | Depth24Stencil8 => 4,
+
+            RedGreenBlue12 => 5,
+
+            Norm_RedGreenBlue16 | RedGreenBlueAlpha12 | Float_RedGreenBlue16 | Int_RedGreenBlue16 | UInt_RedGreenBlue16 => 6,
+
+            RedGreenBlueAlpha16 | Float_RedGreenBlueAlpha16 | Float_RedGreen32 | Int_RedGreen32 | UInt_RedGreen32
+                | Int_RedGreenBlueAlpha16 | UInt_RedGreenBlueAlpha16 | Float_Depth32Stencil8 => 8,
+
+            Float_RedGreenBlue32 | Int_RedGreenBlue32 | UInt_RedGreenBlue32 => 12,
+
+            Float_RedGreenBlueAlpha32 | Int_RedGreenBlueAlpha32 | UInt_RedGreenBlueAlpha32 => 16,
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::gl;
 
 #[derive(Eq, PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TextureType {
     Texture2D               = gl::TEXTURE_2D as isize,
     ProxyTexture2D          = gl::PROXY_TEXTURE_2D as isize,
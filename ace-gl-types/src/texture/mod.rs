@@ -1,10 +1,12 @@
 use image;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use crate::gl;
 use std::path::Path;
 use thiserror::Error;
 use std::ptr;
 use crate::types::*;
+use std::fs;
 use std::io;
 
 mod gl_internalstorage;
@@ -12,129 +14,316 @@ mod pix_type;
 mod texture_configuration;
 mod gl_texturetype;
 mod cubemap;
+mod compressed;
+mod sampler_config;
 
 pub use self::gl_internalstorage::*;
 pub use self::pix_type::*;
 pub use self::texture_configuration::*;
 pub use self::gl_texturetype::*;
 pub use self::cubemap::*;
+pub use self::compressed::*;
+pub use self::sampler_config::*;
 
 pub struct Texture {
-    gl_ctx: Rc<gl::Gl>,
-    id: u32,
-    tex_cfg: TexConfig,
+    gl_ctx: Rc<GLContext>,
+    id: Cell<u32>,
+    tex_cfg: RefCell<TexConfig>,
+    size: Cell<usize>,
 }
 
 impl Texture {
-    pub fn from_file<P: AsRef<Path>>(gl_ctx: Rc<gl::Gl>, path: P, tex_cfg: TexConfig) -> Result<Texture, TextureError> {
+    /// Creates the texture object. Without DSA it's also bound to unit 0, which is what the legacy paths below edit through.
+    fn create(gl_ctx: Rc<GLContext>, tex_cfg: TexConfig) -> Texture {
+        let mut tex_id: u32 = 0;
+        unsafe {
+            if gl_ctx.dsa() {
+                gl_ctx.CreateTextures(tex_cfg.tex_type as u32, 1, &mut tex_id);
+            } else {
+                gl_ctx.GenTextures(1, &mut tex_id);
+            }
+        }
+        gl_ctx.track_object(GLObjectKind::Texture, tex_id);
+
+        let tex = Texture {
+            gl_ctx: gl_ctx,
+            id: Cell::new(tex_id),
+            tex_cfg: RefCell::new(tex_cfg),
+            size: Cell::new(0),
+        };
+        if !tex.gl_ctx.dsa() {
+            tex.gl_ctx.bind_texture(0, tex.target(), tex.id.get());
+        }
+        tex
+    }
+
+    fn target(&self) -> u32 {
+        self.tex_cfg.borrow().tex_type as u32
+    }
+
+    fn set_size(&self, bytes: usize) {
+        self.size.set(bytes);
+        self.gl_ctx.set_object_size(GLObjectKind::Texture, self.id.get(), bytes);
+    }
+
+    /// Roughly how much GPU memory the texture's storage takes, mip levels included.
+    pub fn gpu_bytes(&self) -> usize {
+        self.size.get()
+    }
+
+    /// Trades GL textures with `other`, so this texture shows the other's image and `other` deletes the old one when it drops.
+    /// Lets a texture be reloaded while everything holding on to it keeps drawing with it.
+    pub fn swap(&self, other: &Texture) {
+        self.id.swap(&other.id);
+        self.tex_cfg.swap(&other.tex_cfg);
+        self.size.swap(&other.size);
+    }
+
+    /// Sets the wrapping and filtering of a 2D texture with `levels` mip levels uploaded.
+    fn apply_sampler(&self, sampler: &SamplerConfig, levels: u32) {
+        self.set_parameter(gl::TEXTURE_WRAP_S, sampler.wrap as u32);
+        self.set_parameter(gl::TEXTURE_WRAP_T, sampler.wrap as u32);
+        // Without immutable storage, a chain that stops short of 1x1 is only complete if GL is told where it ends.
+        self.set_parameter(gl::TEXTURE_MAX_LEVEL, levels.max(1) - 1);
+        self.set_parameter(gl::TEXTURE_MIN_FILTER, sampler.min_filter());
+        self.set_parameter(gl::TEXTURE_MAG_FILTER, sampler.mag_filter());
+    }
+
+    fn set_parameter(&self, pname: u32, value: u32) {
+        unsafe {
+            if self.gl_ctx.dsa() {
+                self.gl_ctx.TextureParameteri(self.id.get(), pname, value as i32);
+            } else {
+                self.gl_ctx.bind_texture(0, self.target(), self.id.get());
+                self.gl_ctx.TexParameteri(self.target(), pname, value as i32);
+            }
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(gl_ctx: Rc<GLContext>, path: P, tex_cfg: TexConfig) -> Result<Texture, TextureError> {
+        tex_cfg.validate()?;
+        let dyn_img = image::open(path)?;
+        Texture::from_image(gl_ctx, dyn_img, tex_cfg)
+    }
+
+    /// Decodes an encoded image (PNG, JPEG, ...) that's already in memory, such as one embedded in a model file.
+    pub fn from_memory(gl_ctx: Rc<GLContext>, bytes: &[u8], tex_cfg: TexConfig) -> Result<Texture, TextureError> {
         tex_cfg.validate()?;
-        let mut dyn_img = image::open(path)?;
-        dyn_img = dyn_img.flipv();
+        let dyn_img = image::load_from_memory(bytes)?;
+        Texture::from_image(gl_ctx, dyn_img, tex_cfg)
+    }
+
+    /// Uploads an image that was already decoded, possibly on another thread.
+    pub fn from_image(gl_ctx: Rc<GLContext>, dyn_img: image::DynamicImage, tex_cfg: TexConfig) -> Result<Texture, TextureError> {
+        let dyn_img = dyn_img.flipv();
         let rgb_image = dyn_img.to_rgb();
-        let mut tex_id: u32 = 0;
+
+        // Load texture into memory.
+        // TODO: not all images are RGB. Switch based on the image properties and error out if invalid pix format is used.
+        // Right now, we don't intfer anything and have the user specify the data format. Maybe make this safer?
+        let width = rgb_image.width() as i32;
+        let height = rgb_image.height() as i32;
+        let bytes = rgb_image.into_vec();
+
+        let tex = Texture::create(gl_ctx, tex_cfg);
+        let cfg = tex.tex_cfg.borrow().clone();
+        let levels = if cfg.sampler.mipmaps { mip_levels(width, height) } else { 1 };
         unsafe {
-            gl_ctx.GenTextures(1, &mut tex_id);
-            gl_ctx.BindTexture(tex_cfg.tex_type as u32, tex_id);
-            
-            // Load texture into memory.
-            // TODO: not all images are RGB. Switch based on the image properties and error out if invalid pix format is used.
-            // Right now, we don't intfer anything and have the user specify the data format. Maybe make this safer?
-            let width = rgb_image.width() as i32;
-            let height = rgb_image.height() as i32;
-            let bytes = rgb_image.into_vec();
-            gl_ctx.TexImage2D(tex_cfg.tex_type as u32, 0, tex_cfg.internal_fmt as i32, width, height, 0, tex_cfg.pix_data_fmt as u32, tex_cfg.pix_type_fmt as u32, bytes.as_ptr() as *const _);
-            gl_ctx.GenerateMipmap(tex_cfg.tex_type as u32);
-
-            // Set texture wrap/filtering settings for _current_ texture
-            // TODO: Make this configurable if desired
-            gl_ctx.TexParameteri(tex_cfg.tex_type as u32, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
-            gl_ctx.TexParameteri(tex_cfg.tex_type as u32, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
-            gl_ctx.TexParameteri(tex_cfg.tex_type as u32, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-            gl_ctx.TexParameteri(tex_cfg.tex_type as u32, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            if tex.gl_ctx.dsa() {
+                tex.gl_ctx.TextureStorage2D(tex.id.get(), levels, cfg.internal_fmt.sized() as u32, width, height);
+                tex.gl_ctx.TextureSubImage2D(tex.id.get(), 0, 0, 0, width, height, cfg.pix_data_fmt as u32, cfg.pix_type_fmt as u32, bytes.as_ptr() as *const _);
+                if cfg.sampler.mipmaps {
+                    tex.gl_ctx.GenerateTextureMipmap(tex.id.get());
+                }
+            } else {
+                tex.gl_ctx.TexImage2D(tex.target(), 0, cfg.internal_fmt as i32, width, height, 0, cfg.pix_data_fmt as u32, cfg.pix_type_fmt as u32, bytes.as_ptr() as *const _);
+                if cfg.sampler.mipmaps {
+                    tex.gl_ctx.GenerateMipmap(tex.target());
+                }
+            }
+        }
+        // A full mip chain adds about a third on top of the base level.
+        let base_size = (width * height) as usize * cfg.internal_fmt.bytes_per_pixel();
+        tex.set_size(if cfg.sampler.mipmaps { base_size + base_size / 3 } else { base_size });
+
+        tex.apply_sampler(&cfg.sampler, levels as u32);
+
+        Ok(tex)
+    }
+
+    /// Uploads a block-compressed 2D texture along with its mip levels, largest first, each half the size of the one before.
+    /// Nothing is flipped; the levels are expected to be bottom row first, like `from_image` uploads.
+    pub fn from_compressed(gl_ctx: Rc<GLContext>, format: CompressedFormat, width: u32, height: u32, levels: &[Vec<u8>], tex_cfg: TexConfig) -> Result<Texture, TextureError> {
+        let level_size = |level: usize| ((width >> level).max(1), (height >> level).max(1));
+        let lines_up = levels.iter().enumerate().all(|(level, data)| {
+            let (level_width, level_height) = level_size(level);
+            data.len() == format.level_bytes(level_width, level_height)
+        });
+        if levels.is_empty() || levels.len() as i32 > mip_levels(width as i32, height as i32) || !lines_up {
+            return Err(TextureError::BadCompressedLevels);
         }
+        // A sampler without mipmaps only gets the full size level, however many were cooked.
+        let levels = if tex_cfg.sampler.mipmaps { levels } else { &levels[..1] };
 
-        Ok(
-            Texture {
-                gl_ctx: gl_ctx,
-                id: tex_id,
-                tex_cfg: tex_cfg,
+        let tex = Texture::create(gl_ctx, tex_cfg);
+        unsafe {
+            if tex.gl_ctx.dsa() {
+                tex.gl_ctx.TextureStorage2D(tex.id.get(), levels.len() as i32, format.gl_format(), width as i32, height as i32);
+            }
+            for (level, data) in levels.iter().enumerate() {
+                let (level_width, level_height) = level_size(level);
+                if tex.gl_ctx.dsa() {
+                    tex.gl_ctx.CompressedTextureSubImage2D(tex.id.get(), level as i32, 0, 0, level_width as i32, level_height as i32, format.gl_format(), data.len() as i32, data.as_ptr() as *const _);
+                } else {
+                    tex.gl_ctx.CompressedTexImage2D(tex.target(), level as i32, format.gl_format(), level_width as i32, level_height as i32, 0, data.len() as i32, data.as_ptr() as *const _);
+                }
             }
-        )
+        }
+        tex.set_size(levels.iter().map(Vec::len).sum());
+
+        let sampler = tex.tex_cfg.borrow().sampler;
+        tex.apply_sampler(&sampler, levels.len() as u32);
+
+        Ok(tex)
+    }
+
+    /// A 1x1 grey texture (or cubemap, depending on the config) to draw while the real one is still loading.
+    pub fn placeholder(gl_ctx: Rc<GLContext>, tex_cfg: TexConfig) -> Result<Texture, TextureError> {
+        let pixel = image::RgbImage::from_pixel(1, 1, image::Rgb([128, 128, 128]));
+        match tex_cfg.tex_type {
+            TextureType::TextureCubeMap => {
+                let faces = CubemapTextureType::ALL.iter().map(|&cm_type| (pixel.clone(), cm_type)).collect();
+                Texture::cubemap_from_images(gl_ctx, faces, tex_cfg)
+            },
+            _ => Texture::from_image(gl_ctx, image::DynamicImage::ImageRgb8(pixel), tex_cfg),
+        }
     }
 
-    pub fn cubemap_from_files(gl_ctx: Rc<gl::Gl>, paths: CubemapPaths, tex_cfg: TexConfig) -> Result<Texture, TextureError> {
+    pub fn cubemap_from_files(gl_ctx: Rc<GLContext>, paths: CubemapPaths, tex_cfg: TexConfig) -> Result<Texture, TextureError> {
         tex_cfg.validate()?;
-        let images = paths.cubemap_entries();
-        let mut tex_id: u32 = 0;
+        let faces = Texture::decode_cubemap(paths)?;
+        Texture::cubemap_from_images(gl_ctx, faces, tex_cfg)
+    }
+
+    /// Decodes every face of a cubemap without touching GL, so it can be done on any thread.
+    pub fn decode_cubemap(paths: CubemapPaths) -> Result<CubemapFaces, TextureError> {
+        Texture::decode_cubemap_with(paths, |path| fs::read(path))
+    }
+
+    /// Like `decode_cubemap`, with the faces' files read by `read` instead of from disk.
+    pub fn decode_cubemap_with<R: AsRef<[u8]>>(paths: CubemapPaths, mut read: impl FnMut(&Path) -> io::Result<R>) -> Result<CubemapFaces, TextureError> {
+        // Decode every face up front, so a missing or broken face doesn't leave a half-built texture behind.
+        let mut faces = Vec::new();
+        for (img_path, cm_type) in paths.cubemap_entries() {
+            let dyn_img = image::load_from_memory(read(&img_path)?.as_ref())?;
+            dyn_img.flipv();
+            faces.push((dyn_img.to_rgb(), cm_type));
+        }
+        Ok(faces)
+    }
+
+    pub fn cubemap_from_images(gl_ctx: Rc<GLContext>, faces: CubemapFaces, tex_cfg: TexConfig) -> Result<Texture, TextureError> {
+        // Every face of a cubemap has to be there, and they all have to be the same size.
+        let (width, height) = match faces.first() {
+            Some((img, _)) if faces.len() == 6 => img.dimensions(),
+            _ => return Err(TextureError::MismatchedCubemapFaces),
+        };
+        if faces.iter().any(|(img, _)| img.dimensions() != (width, height)) {
+            return Err(TextureError::MismatchedCubemapFaces);
+        }
+        let (width, height) = (width as i32, height as i32);
+
+        let tex = Texture::create(gl_ctx, tex_cfg);
+        let cfg = tex.tex_cfg.borrow().clone();
         unsafe {
-            gl_ctx.GenTextures(1, &mut tex_id);
-            gl_ctx.BindTexture(tex_cfg.tex_type as u32, tex_id);
-            
+            if tex.gl_ctx.dsa() {
+                tex.gl_ctx.TextureStorage2D(tex.id.get(), 1, cfg.internal_fmt.sized() as u32, width, height);
+            }
+
             // Load texture into memory.
             // TODO: not all images are RGB. Switch based on the image properties and error out if invalid pix format is used.
             // Right now, we don't intfer anything and have the user specify the data format. Maybe make this safer?
-            for (img_path, cm_type) in images {
-                let dyn_img = image::open(img_path)?;
-                dyn_img.flipv();
-                let rgb_image = dyn_img.to_rgb();
-
-                let width = rgb_image.width() as i32;
-                let height = rgb_image.height() as i32;
+            for (rgb_image, cm_type) in faces {
                 let bytes = rgb_image.into_vec();
                 // essentially for a cube-map we need to do this 6 times.
-                gl_ctx.TexImage2D(cm_type as u32, 0, tex_cfg.internal_fmt as i32, width, height, 0, tex_cfg.pix_data_fmt as u32, tex_cfg.pix_type_fmt as u32, bytes.as_ptr() as *const _);
+                if tex.gl_ctx.dsa() {
+                    // DSA addresses cubemap faces as layers of a 2D array, in +X, -X, +Y, -Y, +Z, -Z order.
+                    let layer = cm_type as i32 - gl::TEXTURE_CUBE_MAP_POSITIVE_X as i32;
+                    tex.gl_ctx.TextureSubImage3D(tex.id.get(), 0, 0, 0, layer, width, height, 1, cfg.pix_data_fmt as u32, cfg.pix_type_fmt as u32, bytes.as_ptr() as *const _);
+                } else {
+                    tex.gl_ctx.TexImage2D(cm_type as u32, 0, cfg.internal_fmt as i32, width, height, 0, cfg.pix_data_fmt as u32, cfg.pix_type_fmt as u32, bytes.as_ptr() as *const _);
+                }
             }
-
-            // Set texture wrap/filtering settings for _current_ texture
-            // TODO: Make this configurable if desired
-            gl_ctx.TexParameteri(tex_cfg.tex_type as u32, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
-            gl_ctx.TexParameteri(tex_cfg.tex_type as u32, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
-            gl_ctx.TexParameteri(tex_cfg.tex_type as u32, gl::TEXTURE_WRAP_R, gl::REPEAT as i32);
-            gl_ctx.TexParameteri(tex_cfg.tex_type as u32, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-            gl_ctx.TexParameteri(tex_cfg.tex_type as u32, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
         }
+        let face_size = (width * height) as usize * cfg.internal_fmt.bytes_per_pixel();
+        tex.set_size(6 * face_size);
+
+        // Set texture wrap/filtering settings
+        // TODO: Make this configurable if desired
+        tex.set_parameter(gl::TEXTURE_WRAP_S, gl::REPEAT);
+        tex.set_parameter(gl::TEXTURE_WRAP_T, gl::REPEAT);
+        tex.set_parameter(gl::TEXTURE_WRAP_R, gl::REPEAT);
+        tex.set_parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR);
+        tex.set_parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR);
 
-        Ok(Texture {
-            gl_ctx,
-            id: tex_id,
-            tex_cfg: tex_cfg,
-        })
+        Ok(tex)
     }
 
     // todo: this probably need a configuration structure as framebuffers can be configured
     // in multiple buffer sizes and styles
-    pub(crate) fn from_framebuffer(gl_ctx: Rc<gl::Gl>, width: i32, height: i32, tex_cfg: TexConfig, attachment: FrameBufferAttachment) -> Texture {
-        let mut tex_id: u32 = 0;
+    pub(crate) fn from_framebuffer(gl_ctx: Rc<GLContext>, fbo_id: u32, width: i32, height: i32, tex_cfg: TexConfig, attachment: FrameBufferAttachment) -> Texture {
+        let tex = Texture::create(gl_ctx, tex_cfg);
+        let cfg = tex.tex_cfg.borrow().clone();
         unsafe {
-            gl_ctx.GenTextures(1, &mut tex_id);
-            gl_ctx.BindTexture(tex_cfg.tex_type as u32, tex_id);
-
-            // NULL here since we're binding to the current frame buffer.
-            // make type configurable, since framebuffer types can be configurable
-            gl_ctx.TexImage2D(tex_cfg.tex_type as u32, 0, tex_cfg.internal_fmt as i32, width, height, 0, tex_cfg.pix_data_fmt as u32, tex_cfg.pix_type_fmt as u32,  ptr::null());
+            if tex.gl_ctx.dsa() {
+                tex.gl_ctx.TextureStorage2D(tex.id.get(), 1, cfg.internal_fmt.sized() as u32, width, height);
+            } else {
+                // NULL here since the framebuffer renders into it, there's nothing to upload.
+                // make type configurable, since framebuffer types can be configurable
+                tex.gl_ctx.TexImage2D(tex.target(), 0, cfg.internal_fmt as i32, width, height, 0, cfg.pix_data_fmt as u32, cfg.pix_type_fmt as u32, ptr::null());
+            }
+        }
+        let size = (width * height) as usize * cfg.internal_fmt.bytes_per_pixel();
+        tex.set_size(size);
 
-            // Set texture filtering for _current_ texture
-            // todo: make configurable, just like in from_file
-            gl_ctx.TexParameteri(tex_cfg.tex_type as u32, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-            gl_ctx.TexParameteri(tex_cfg.tex_type as u32, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+        // Set texture filtering
+        // todo: make configurable, just like in from_file
+        tex.set_parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR);
+        tex.set_parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR);
 
-            // configure texture onto framebuffer.
-            // make attachment parameter configurable
-            gl_ctx.FramebufferTexture2D(gl::FRAMEBUFFER, attachment.into(), tex_cfg.tex_type as u32, tex_id, 0);
+        // configure texture onto framebuffer.
+        // make attachment parameter configurable
+        unsafe {
+            if tex.gl_ctx.dsa() {
+                tex.gl_ctx.NamedFramebufferTexture(fbo_id, attachment.into(), tex.id.get(), 0);
+            } else {
+                tex.gl_ctx.bind_framebuffer(gl::FRAMEBUFFER, fbo_id);
+                tex.gl_ctx.FramebufferTexture2D(gl::FRAMEBUFFER, attachment.into(), tex.target(), tex.id.get(), 0);
+            }
         }
 
-        Texture {
-            gl_ctx: gl_ctx,
-            id: tex_id,
-            tex_cfg: tex_cfg,
-        }
+        tex
+    }
+
+    /// Names the object in debug output and captures.
+    pub fn set_label(&self, label: &str) {
+        self.gl_ctx.label_object(GLObjectKind::Texture, self.id.get(), label);
     }
 
     pub fn bind(&self, tex_unit: TextureUnit) {
-        unsafe {
-            self.gl_ctx.ActiveTexture(tex_unit as u32);
-            self.gl_ctx.BindTexture(self.tex_cfg.tex_type as u32, self.id);
-        }
+        self.gl_ctx.bind_texture(tex_unit.index(), self.tex_cfg.borrow().tex_type as u32, self.id.get());
+    }
+}
+
+/// How many levels a full mip chain down to 1x1 has.
+fn mip_levels(width: i32, height: i32) -> i32 {
+    let largest = width.max(height).max(1) as u32;
+    (32 - largest.leading_zeros()) as i32
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        self.gl_ctx.delete_object(GLObjectKind::Texture, self.id.get());
     }
 }
 
@@ -148,6 +337,10 @@ pub enum TextureError {
     IOError(io::Error),
     #[error("Bad 2D texture configuration generated")]
     BadTextureConfig,
+    #[error("Cubemap faces are missing or not all the same size")]
+    MismatchedCubemapFaces,
+    #[error("The compressed mip levels don't match the texture's size")]
+    BadCompressedLevels,
 }
 
 impl From<OpenGLError> for TextureError {
@@ -166,4 +359,17 @@ impl From<io::Error> for TextureError {
     fn from(err: io::Error) -> Self {
         TextureError::IOError(err)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::mip_levels;
+
+    #[test]
+    fn mip_chain_goes_down_to_one_pixel() {
+        assert_eq!(mip_levels(1, 1), 1);
+        assert_eq!(mip_levels(2, 1), 2);
+        assert_eq!(mip_levels(256, 256), 9);
+        assert_eq!(mip_levels(300, 17), 9);
+        assert_eq!(mip_levels(0, 0), 1);
+    }
+}
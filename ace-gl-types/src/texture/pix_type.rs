@@ -2,6 +2,7 @@ use crate::gl;
 
 #[allow(non_camel_case_types)]
 #[derive(Eq, PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PixelDataFormat {
     R            = gl::RED as isize,
     G            = gl::GREEN as isize,
@@ -25,6 +26,7 @@ pub enum PixelDataFormat {
 }
 
 #[derive(Eq, PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PixelDataType {
     UnsignedByte        = gl::UNSIGNED_BYTE as isize,
     Byte                = gl::BYTE as isize,
@@ -0,0 +1,70 @@
+use crate::gl;
+
+/// How a 2D texture is filtered and wrapped when it's sampled, and whether it has mipmaps at all.
+/// Cubemaps always clamp to their edges and filter linearly.
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct SamplerConfig {
+    pub filter:  TexFilter,
+    pub wrap:    TexWrap,
+    /// Without mipmaps, only the full size image is uploaded and minified texels are filtered from it directly.
+    pub mipmaps: bool,
+}
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TexFilter {
+    Linear,
+    /// Blocky, for pixel art and lookup tables.
+    Nearest,
+}
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TexWrap {
+    Repeat          = gl::REPEAT as isize,
+    MirroredRepeat  = gl::MIRRORED_REPEAT as isize,
+    ClampToEdge     = gl::CLAMP_TO_EDGE as isize,
+}
+
+impl Default for SamplerConfig {
+    fn default() -> Self {
+        SamplerConfig {
+            filter: TexFilter::Linear,
+            wrap: TexWrap::Repeat,
+            mipmaps: true,
+        }
+    }
+}
+
+impl SamplerConfig {
+    pub fn min_filter(&self) -> u32 {
+        match (self.filter, self.mipmaps) {
+            (TexFilter::Linear, true)   => gl::LINEAR_MIPMAP_LINEAR,
+            (TexFilter::Linear, false)  => gl::LINEAR,
+            (TexFilter::Nearest, true)  => gl::NEAREST_MIPMAP_NEAREST,
+            (TexFilter::Nearest, false) => gl::NEAREST,
+        }
+    }
+
+    pub fn mag_filter(&self) -> u32 {
+        match self.filter {
+            TexFilter::Linear   => gl::LINEAR,
+            TexFilter::Nearest  => gl::NEAREST,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mipmaps_are_only_sampled_when_there_are_some() {
+        let sampler = SamplerConfig::default();
+        assert_eq!(sampler.min_filter(), gl::LINEAR_MIPMAP_LINEAR);
+        let sampler = SamplerConfig { filter: TexFilter::Nearest, mipmaps: false, ..sampler };
+        assert_eq!((sampler.min_filter(), sampler.mag_filter()), (gl::NEAREST, gl::NEAREST));
+    }
+}
@@ -1,11 +1,15 @@
 use crate::types::*;
 
 #[derive(Eq, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TexConfig {
     pub tex_type:     TextureType,
     pub internal_fmt: InternalStorage,
     pub pix_data_fmt: PixelDataFormat,
     pub pix_type_fmt: PixelDataType,
+    /// Left out of manifests, it's the default: linear filtering, repeating, with mipmaps.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub sampler:      SamplerConfig,
 }
 
 impl TexConfig {
@@ -15,9 +19,15 @@ impl TexConfig {
             internal_fmt: internal_format,
             pix_data_fmt: pix_d_fmt,
             pix_type_fmt: pix_d_type,
+            sampler: SamplerConfig::default(),
         }
     }
 
+    pub fn with_sampler(mut self, sampler: SamplerConfig) -> TexConfig {
+        self.sampler = sampler;
+        self
+    }
+
     pub fn validate(&self) -> Result<(), TextureError> {
         if self.pix_data_fmt != PixelDataFormat::RGB {
             match self.pix_type_fmt {
@@ -1,5 +1,9 @@
 use std::ffi::c_void;
+use std::mem;
+use bytemuck::Pod;
+use half::f16;
 use crate::types::*;
+
 pub trait TypedBuffer {
     fn get_gl_type(&self) -> GLType;
     fn length(&self) -> usize;
@@ -7,46 +11,105 @@ pub trait TypedBuffer {
     fn ref_ptr(&self) -> *const c_void;
 }
 
-impl TypedBuffer for &[i32] {
+/// Marks the buffers that OpenGL accepts as element indices (unsigned bytes, shorts and ints).
+pub trait IndexBuffer: TypedBuffer {}
+
+macro_rules! impl_typed_buffer {
+    ($($ty:ty => $gl_ty:ident),* $(,)?) => {
+        $(
+            impl TypedBuffer for &[$ty] {
+                fn get_gl_type(&self) -> GLType {
+                    GLType::$gl_ty
+                }
+
+                fn length(&self) -> usize {
+                    self.len()
+                }
+
+                fn size(&self) -> usize {
+                    self.get_gl_type().sizeof() * self.length()
+                }
+
+                fn ref_ptr(&self) -> *const c_void {
+                    self.as_ptr() as *const _
+                }
+            }
+        )*
+    };
+}
+
+impl_typed_buffer! {
+    i8  => Byte,
+    u8  => UnsignedByte,
+    i16 => Short,
+    u16 => UnsignedShort,
+    i32 => Int,
+    u32 => UnsignedInt,
+    f16 => HalfFloat,
+    f32 => Float,
+    f64 => Double,
+}
+
+impl IndexBuffer for &[u8] {}
+impl IndexBuffer for &[u16] {}
+impl IndexBuffer for &[u32] {}
+
+/// Wraps a slice of arbitrary plain-old-data structs so it can be uploaded byte-for-byte.
+/// The GL type is reported as unsigned bytes, since the buffer has no single component type.
+pub struct PodBuffer<'a, T: Pod>(pub &'a [T]);
+
+impl<'a, T: Pod> TypedBuffer for PodBuffer<'a, T> {
     fn get_gl_type(&self) -> GLType {
-        GLType::Int
+        GLType::UnsignedByte
     }
 
     fn length(&self) -> usize {
-        self.len()
+        mem::size_of_val(self.0)
     }
 
     fn size(&self) -> usize {
-        self.get_gl_type().sizeof() * self.length()
+        self.length()
     }
 
     fn ref_ptr(&self) -> *const c_void {
-        self.as_ptr() as *const _
+        self.0.as_ptr() as *const _
     }
 }
 
-impl TypedBuffer for &[u32] {
-    fn get_gl_type(&self) -> GLType {
-        GLType::UnsignedInt
-    }
+/// Index data stored in the smallest type that can address every vertex.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexData {
+    UnsignedShort(Vec<u16>),
+    UnsignedInt(Vec<u32>),
+}
 
-    fn length(&self) -> usize {
-        self.len()
+impl IndexData {
+    /// Narrows the indices to u16 when every index fits, which halves the size of the element buffer.
+    pub fn compact(indices: &[u32]) -> IndexData {
+        match indices.iter().max() {
+            Some(&max) if max > u16::MAX as u32 => IndexData::UnsignedInt(indices.to_vec()),
+            _ => IndexData::UnsignedShort(indices.iter().map(|&idx| idx as u16).collect()),
+        }
     }
 
-    fn size(&self) -> usize {
-        self.get_gl_type().sizeof() * self.length()
+    pub fn len(&self) -> usize {
+        match self {
+            IndexData::UnsignedShort(indices) => indices.len(),
+            IndexData::UnsignedInt(indices) => indices.len(),
+        }
     }
 
-    fn ref_ptr(&self) -> *const c_void {
-        self.as_ptr() as *const _
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 }
 
-
-impl TypedBuffer for &[f32] {
+impl TypedBuffer for &IndexData {
     fn get_gl_type(&self) -> GLType {
-        GLType::Float
+        match self {
+            IndexData::UnsignedShort(_) => GLType::UnsignedShort,
+            IndexData::UnsignedInt(_) => GLType::UnsignedInt,
+        }
     }
 
     fn length(&self) -> usize {
@@ -58,25 +121,32 @@ impl TypedBuffer for &[f32] {
     }
 
     fn ref_ptr(&self) -> *const c_void {
-        self.as_ptr() as *const _
+        match self {
+            IndexData::UnsignedShort(indices) => indices.as_ptr() as *const _,
+            IndexData::UnsignedInt(indices) => indices.as_ptr() as *const _,
+        }
     }
 }
 
+impl IndexBuffer for &IndexData {}
 
-impl TypedBuffer for &[f64] {
-    fn get_gl_type(&self) -> GLType {
-        GLType::Double
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    fn length(&self) -> usize {
-        self.len()
-    }
-
-    fn size(&self) -> usize {
-        self.get_gl_type().sizeof() * self.length()
+    #[test]
+    fn compact_narrows_small_meshes() {
+        let indices: Vec<u32> = vec![0, 1, 2, 2, 3, 0];
+        let compacted = IndexData::compact(&indices);
+        assert_eq!(compacted, IndexData::UnsignedShort(vec![0, 1, 2, 2, 3, 0]));
+        assert_eq!((&compacted).size(), 12);
     }
 
-    fn ref_ptr(&self) -> *const c_void {
-        self.as_ptr() as *const _
+    #[test]
+    fn compact_keeps_large_meshes() {
+        let indices: Vec<u32> = vec![0, 70000, 1];
+        let compacted = IndexData::compact(&indices);
+        assert_eq!((&compacted).get_gl_type(), GLType::UnsignedInt);
+        assert_eq!((&compacted).size(), 12);
     }
 }
\ No newline at end of file
@@ -8,8 +8,10 @@ mod float;
 mod int;
 mod boolean;
 mod matrix;
+mod value;
 
 pub use self::float::*;
 pub use self::int::*;
 pub use self::boolean::*;
-pub use self::matrix::*;
\ No newline at end of file
+pub use self::matrix::*;
+pub use self::value::*;
\ No newline at end of file
@@ -0,0 +1,28 @@
+use crate::shaders::CompiledShaderProgram;
+
+/// A uniform value that isn't known until runtime, such as a material parameter read from a file.
+/// In a material file, numbers without a fractional part are ints, so floats need one (`1.0`, not `1`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(untagged))]
+pub enum UniformValue {
+    Bool(bool),
+    Int(i32),
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+}
+
+impl UniformValue {
+    /// Sets the uniform `name` of `program`, which has to be the program in use.
+    pub fn assign(&self, program: &CompiledShaderProgram, name: &str) {
+        match *self {
+            UniformValue::Bool(value) => program.set_uniform(name, &value),
+            UniformValue::Int(value) => program.set_uniform(name, &value),
+            UniformValue::Float(value) => program.set_uniform(name, &value),
+            UniformValue::Vec2([x, y]) => program.set_uniform(name, &nalgebra::Vector2::new(x, y)),
+            UniformValue::Vec3([x, y, z]) => program.set_uniform(name, &nalgebra::Vector3::new(x, y, z)),
+            UniformValue::Vec4([x, y, z, w]) => program.set_uniform(name, &nalgebra::Vector4::new(x, y, z, w)),
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use crate::types::*;
+
+/// Describes one attribute inside of an interleaved vertex.
+/// Offsets are in bytes from the start of the vertex.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VertexAttribute {
+    pub location: u32,
+    pub components: AttributeComponentSize,
+    pub attrib_type: GLType,
+    pub normalized: bool,
+    pub offset: usize,
+}
+
+/// The full attribute layout of a vertex type, as consumed by `VertexArrayObj::configure_from_layout`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VertexLayout {
+    stride: usize,
+    attributes: Vec<VertexAttribute>,
+}
+
+impl VertexLayout {
+    pub fn new(stride: usize) -> VertexLayout {
+        VertexLayout {
+            stride: stride,
+            attributes: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, attribute: VertexAttribute) {
+        self.attributes.push(attribute);
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    pub fn attributes(&self) -> &[VertexAttribute] {
+        &self.attributes
+    }
+
+    pub fn attribute_at(&self, location: u32) -> Option<&VertexAttribute> {
+        self.attributes.iter().find(|attr| attr.location == location)
+    }
+
+    /// Checks that every attribute the program actually reads is provided by this layout.
+    /// Extra attributes in the layout are fine; the shader just won't see them.
+    pub fn validate_against(&self, program: &CompiledShaderProgram) -> Result<(), OpenGLError> {
+        for active in program.active_attributes() {
+            if active.location < 0 {
+                continue;
+            }
+
+            if self.attribute_at(active.location as u32).is_none() {
+                return Err(OpenGLError::MissingVertexAttribute(active.name, active.location as u32));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A vertex that can be uploaded into a `VertexBufferObj` as-is.
+/// Use `#[derive(Vertex)]` on a `#[repr(C)]` struct instead of implementing this by hand.
+pub trait Vertex: Copy {
+    fn layout() -> VertexLayout;
+}
+
+/// Maps a Rust field type onto the GL description of a vertex attribute.
+pub trait VertexAttributeType {
+    const COMPONENTS: AttributeComponentSize;
+    const GL_TYPE: GLType;
+    /// How many consecutive locations the attribute takes up.
+    const LOCATIONS: u32 = attribute_locations(Self::GL_TYPE, Self::COMPONENTS);
+}
+
+/// Three and four component doubles are too big for one location and spill over into the next.
+const fn attribute_locations(attrib_type: GLType, components: AttributeComponentSize) -> u32 {
+    match (attrib_type, components) {
+        (GLType::Double, AttributeComponentSize::Three | AttributeComponentSize::Four) => 2,
+        _ => 1,
+    }
+}
+
+macro_rules! impl_vertex_attribute_type {
+    ($($ty:ty => $size:ident, $gl_ty:ident;)*) => {
+        $(
+            impl VertexAttributeType for $ty {
+                const COMPONENTS: AttributeComponentSize = AttributeComponentSize::$size;
+                const GL_TYPE: GLType = GLType::$gl_ty;
+            }
+        )*
+    };
+}
+
+impl_vertex_attribute_type! {
+    f32         => One,   Float;
+    [f32; 1]    => One,   Float;
+    [f32; 2]    => Two,   Float;
+    [f32; 3]    => Three, Float;
+    [f32; 4]    => Four,  Float;
+
+    f64         => One,   Double;
+    [f64; 2]    => Two,   Double;
+    [f64; 3]    => Three, Double;
+    [f64; 4]    => Four,  Double;
+
+    i32         => One,   Int;
+    [i32; 2]    => Two,   Int;
+    [i32; 3]    => Three, Int;
+    [i32; 4]    => Four,  Int;
+
+    u32         => One,   UnsignedInt;
+    [u32; 2]    => Two,   UnsignedInt;
+    [u32; 3]    => Three, UnsignedInt;
+    [u32; 4]    => Four,  UnsignedInt;
+
+    i16         => One,   Short;
+    [i16; 2]    => Two,   Short;
+    [i16; 3]    => Three, Short;
+    [i16; 4]    => Four,  Short;
+
+    u16         => One,   UnsignedShort;
+    [u16; 2]    => Two,   UnsignedShort;
+    [u16; 3]    => Three, UnsignedShort;
+    [u16; 4]    => Four,  UnsignedShort;
+
+    i8          => One,   Byte;
+    [i8; 2]     => Two,   Byte;
+    [i8; 3]     => Three, Byte;
+    [i8; 4]     => Four,  Byte;
+
+    u8          => One,   UnsignedByte;
+    [u8; 2]     => Two,   UnsignedByte;
+    [u8; 3]     => Three, UnsignedByte;
+    [u8; 4]     => Four,  UnsignedByte;
+
+    nalgebra::Vector2<f32> => Two,   Float;
+    nalgebra::Vector3<f32> => Three, Float;
+    nalgebra::Vector4<f32> => Four,  Float;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem;
+
+    #[repr(C)]
+    #[derive(Vertex, Clone, Copy)]
+    struct Mixed {
+        flags: u8,
+        position: [f32; 3],
+        #[vertex(normalized)]
+        color: [u8; 4],
+        #[vertex(skip)]
+        _padding: u32,
+        weights: nalgebra::Vector2<f32>,
+    }
+
+    #[repr(C)]
+    #[derive(Vertex, Clone, Copy)]
+    struct Relocated {
+        #[vertex(location = 3)]
+        id: u32,
+        offset: [i16; 2],
+        #[vertex(location = 1)]
+        precise: [f64; 2],
+        after: [i32; 3],
+    }
+
+    #[repr(C)]
+    #[derive(Vertex, Clone, Copy)]
+    struct Precise {
+        position: [f64; 3],
+        uv: [f32; 2],
+        #[vertex(location = 4)]
+        tangent: [f64; 4],
+        bitangent: [f64; 2],
+    }
+
+    #[test]
+    fn offsets_follow_the_repr_c_layout() {
+        let layout = Mixed::layout();
+        assert_eq!(layout.stride(), mem::size_of::<Mixed>());
+        let offsets: Vec<usize> = layout.attributes().iter().map(|attr| attr.offset).collect();
+        // The u8 is padded out to the f32 alignment; the skipped field still takes up room.
+        assert_eq!(offsets, vec![0, 4, 16, 24]);
+        assert_eq!(layout.stride(), 32);
+    }
+
+    #[test]
+    fn fields_map_onto_gl_types() {
+        let layout = Mixed::layout();
+        let attr = |location| *layout.attribute_at(location).unwrap();
+        assert_eq!((attr(0).components, attr(0).attrib_type, attr(0).normalized), (AttributeComponentSize::One, GLType::UnsignedByte, false));
+        assert_eq!((attr(1).components, attr(1).attrib_type), (AttributeComponentSize::Three, GLType::Float));
+        assert_eq!((attr(2).components, attr(2).attrib_type, attr(2).normalized), (AttributeComponentSize::Four, GLType::UnsignedByte, true));
+        assert_eq!((attr(3).components, attr(3).attrib_type), (AttributeComponentSize::Two, GLType::Float));
+        assert!(layout.attribute_at(4).is_none());
+    }
+
+    #[test]
+    fn locations_count_on_from_explicit_ones() {
+        let layout = Relocated::layout();
+        let locations: Vec<u32> = layout.attributes().iter().map(|attr| attr.location).collect();
+        assert_eq!(locations, vec![3, 4, 1, 2]);
+
+        let types: Vec<GLType> = layout.attributes().iter().map(|attr| attr.attrib_type).collect();
+        assert_eq!(types, vec![GLType::UnsignedInt, GLType::Short, GLType::Double, GLType::Int]);
+        let offsets: Vec<usize> = layout.attributes().iter().map(|attr| attr.offset).collect();
+        assert_eq!(offsets, vec![0, 4, 8, 24]);
+        assert_eq!(layout.stride(), 40);
+    }
+    #[test]
+    fn big_doubles_take_two_locations() {
+        assert_eq!(<[f64; 2] as VertexAttributeType>::LOCATIONS, 1);
+        assert_eq!(<[f64; 3] as VertexAttributeType>::LOCATIONS, 2);
+        assert_eq!(<[f32; 4] as VertexAttributeType>::LOCATIONS, 1);
+
+        let layout = Precise::layout();
+        let locations: Vec<u32> = layout.attributes().iter().map(|attr| attr.location).collect();
+        assert_eq!(locations, vec![0, 2, 4, 6]);
+    }
+}
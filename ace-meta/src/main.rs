@@ -0,0 +1,66 @@
+#![deny(nonstandard_style)]
+#![deny(rust_2018_idioms)]
+#![deny(future_incompatible)]
+
+//! Writes `.meta` sidecars (see `asset_management::meta`) for the assets in a directory that don't have one yet.
+
+use asset_management::meta::write_missing;
+use std::env;
+use std::io;
+use std::path::PathBuf;
+use std::process;
+use thiserror::Error;
+
+const USAGE: &str = "Usage: ace-meta <asset directory>
+
+Every texture, model, shader and sound without a sidecar gets one with a new GUID and the default import
+settings. Sidecars that are already there are checked: ones that don't parse, and GUIDs shared by more than
+one sidecar (usually from copying an asset along with its sidecar), are reported and make it exit with 1.";
+
+fn main() {
+    match run(env::args().skip(1).collect()) {
+        Ok(true) => {},
+        Ok(false) => process::exit(1),
+        Err(err) => {
+            eprintln!("ace-meta: {}", err);
+            process::exit(1);
+        },
+    }
+}
+
+/// Whether every sidecar is usable.
+fn run(args: Vec<String>) -> Result<bool, MetaCliError> {
+    let asset_dir = match <[String; 1]>::try_from(args) {
+        Ok([arg]) if !arg.starts_with('-') => PathBuf::from(arg),
+        _ => return Err(MetaCliError::Usage),
+    };
+    let report = write_missing(&asset_dir)?;
+
+    for path in &report.written {
+        println!("wrote    {}", path.display());
+    }
+    for err in &report.invalid {
+        eprintln!("invalid  {}", err);
+    }
+    for (guid, paths) in &report.duplicates {
+        let paths: Vec<_> = paths.iter().map(|path| path.display().to_string()).collect();
+        eprintln!("shared   {} by {}", guid, paths.join(", "));
+    }
+    println!("Wrote {} sidecars in {} ({} invalid, {} shared GUIDs)",
+        report.written.len(), asset_dir.display(), report.invalid.len(), report.duplicates.len());
+    Ok(report.invalid.is_empty() && report.duplicates.is_empty())
+}
+
+#[derive(Error, Debug)]
+enum MetaCliError {
+    #[error("{}", USAGE)]
+    Usage,
+    #[error("{0}")]
+    IOError(io::Error),
+}
+
+impl From<io::Error> for MetaCliError {
+    fn from(err: io::Error) -> Self {
+        MetaCliError::IOError(err)
+    }
+}
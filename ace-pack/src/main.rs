@@ -0,0 +1,121 @@
+#![deny(nonstandard_style)]
+#![deny(rust_2018_idioms)]
+#![deny(future_incompatible)]
+
+//! Packs an asset directory into a single archive that `AssetContainer::mount_archive` or the engine (as a resource pack) can read.
+
+use asset_management::archive::{ArchiveError, ArchiveWriter, Compression};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::process;
+use thiserror::Error;
+
+const USAGE: &str = "Usage: ace-pack <asset directory> <archive> [--store <extension>]...
+
+Every file under the asset directory is added under its path relative to it. Files are compressed,
+except for ones with an extension passed to --store (png and jpg by default), which are already
+compressed and are read straight out of the mapped archive instead.";
+
+fn main() {
+    if let Err(err) = run(env::args().skip(1).collect()) {
+        eprintln!("ace-pack: {}", err);
+        process::exit(1);
+    }
+}
+
+struct Options {
+    asset_dir: PathBuf,
+    archive: PathBuf,
+    stored: Vec<String>,
+}
+
+fn parse_args(args: Vec<String>) -> Result<Options, PackError> {
+    let mut positional = Vec::new();
+    let mut stored = vec!["png".to_string(), "jpg".to_string(), "jpeg".to_string()];
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Err(PackError::Usage),
+            "--store" => stored.push(args.next().ok_or(PackError::Usage)?.trim_start_matches('.').to_lowercase()),
+            _ => positional.push(PathBuf::from(arg)),
+        }
+    }
+    match <[PathBuf; 2]>::try_from(positional) {
+        Ok([asset_dir, archive]) => Ok(Options {
+            asset_dir: asset_dir,
+            archive: archive,
+            stored: stored,
+        }),
+        Err(_) => Err(PackError::Usage),
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), PackError> {
+    let options = parse_args(args)?;
+    let mut files = Vec::new();
+    collect_files(&options.asset_dir, &mut files)?;
+    // Sorted, so packing the same files twice gives the same archive.
+    files.sort();
+
+    // Packing into the asset directory mustn't pack the archive into itself.
+    let archive_path = options.archive.canonicalize().ok();
+    let mut writer = ArchiveWriter::new(BufWriter::new(File::create(&options.archive)?))?;
+    let mut total = 0;
+    let mut count = 0;
+    for file in files {
+        if file.canonicalize().ok().is_some_and(|file| Some(file) == archive_path) {
+            continue;
+        }
+        let relative = file.strip_prefix(&options.asset_dir).expect("collected files are under the asset directory");
+        let extension = file.extension().and_then(|ext| ext.to_str()).map(str::to_lowercase);
+        let compression = match extension {
+            Some(ext) if options.stored.contains(&ext) => Compression::None,
+            _ => Compression::Deflate,
+        };
+        let contents = fs::read(&file)?;
+        writer.add(relative, &contents, compression)?;
+        total += contents.len() as u64;
+        count += 1;
+    }
+
+    let data_len = writer.data_len();
+    writer.finish()?;
+    println!("Packed {} files ({} bytes) into {} ({} bytes of data)", count, total, options.archive.display(), data_len);
+    Ok(())
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+enum PackError {
+    #[error("{}", USAGE)]
+    Usage,
+    #[error("{0}")]
+    IOError(io::Error),
+    #[error("{0}")]
+    ArchiveError(ArchiveError),
+}
+
+impl From<io::Error> for PackError {
+    fn from(err: io::Error) -> Self {
+        PackError::IOError(err)
+    }
+}
+
+impl From<ArchiveError> for PackError {
+    fn from(err: ArchiveError) -> Self {
+        PackError::ArchiveError(err)
+    }
+}
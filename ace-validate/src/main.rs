@@ -0,0 +1,91 @@
+#![deny(nonstandard_style)]
+#![deny(rust_2018_idioms)]
+#![deny(future_incompatible)]
+
+//! Checks every asset in a directory (see `asset_management::validate`) without opening a window, for CI.
+
+use asset_management::validate::validate_directory;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process;
+use thiserror::Error;
+
+const USAGE: &str = "Usage: ace-validate <asset directory> [--report <file>]
+
+Decodes every texture and cubemap, parses every model, material library, material file, sidecar and the
+manifest, validates vertex and fragment shaders, and checks that what they refer to exists. Problems are
+listed on stderr and the full report is written as JSON to stdout, or to the --report file. Exits with 1
+if any asset has a problem.";
+
+fn main() {
+    match run(env::args().skip(1).collect()) {
+        Ok(true) => {},
+        Ok(false) => process::exit(1),
+        Err(err) => {
+            eprintln!("ace-validate: {}", err);
+            process::exit(1);
+        },
+    }
+}
+
+struct Options {
+    asset_dir: PathBuf,
+    report: Option<PathBuf>,
+}
+
+fn parse_args(args: Vec<String>) -> Result<Options, ValidateCliError> {
+    let mut positional = Vec::new();
+    let mut report = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--report" => report = Some(PathBuf::from(args.next().ok_or(ValidateCliError::Usage)?)),
+            _ if arg.starts_with('-') => return Err(ValidateCliError::Usage),
+            _ => positional.push(PathBuf::from(arg)),
+        }
+    }
+    match <[PathBuf; 1]>::try_from(positional) {
+        Ok([asset_dir]) => Ok(Options {
+            asset_dir: asset_dir,
+            report: report,
+        }),
+        Err(_) => Err(ValidateCliError::Usage),
+    }
+}
+
+/// Whether every asset passed.
+fn run(args: Vec<String>) -> Result<bool, ValidateCliError> {
+    let options = parse_args(args)?;
+    let report = validate_directory(&options.asset_dir)?;
+
+    for asset in &report.assets {
+        for problem in &asset.problems {
+            match problem.line {
+                Some(line) => eprintln!("{}:{}: {}", asset.path, line, problem.message),
+                None => eprintln!("{}: {}", asset.path, problem.message),
+            }
+        }
+    }
+    match &options.report {
+        Some(path) => fs::write(path, report.to_json())?,
+        None => println!("{}", report.to_json()),
+    }
+    eprintln!("Checked {} assets in {} ({} problems)", report.assets.len(), options.asset_dir.display(), report.problem_count());
+    Ok(report.is_ok())
+}
+
+#[derive(Error, Debug)]
+enum ValidateCliError {
+    #[error("{}", USAGE)]
+    Usage,
+    #[error("{0}")]
+    IOError(io::Error),
+}
+
+impl From<io::Error> for ValidateCliError {
+    fn from(err: io::Error) -> Self {
+        ValidateCliError::IOError(err)
+    }
+}
@@ -0,0 +1,374 @@
+//! Packed asset archives: every asset in one file, with a table of contents at the end.
+//!
+//! The layout, with every integer little endian:
+//!
+//! ```text
+//! header   magic "ACEPAK\0\0", version: u32, entry count: u32, table of contents offset: u64
+//! data     each entry's bytes, as stored
+//! toc      per entry: path length: u16, path (UTF-8, '/'-separated, relative to the asset root),
+//!          offset: u64, stored length: u64, length: u64, compression: u8, blake3 hash of the contents: [u8; 32]
+//! ```
+//!
+//! Entries with the same contents share their data.
+
+use memmap2::Mmap;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+
+const MAGIC: &[u8; 8] = b"ACEPAK\0\0";
+const VERSION: u32 = 1;
+const HEADER_LEN: u64 = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// Stored as is, and read straight out of the mapped file.
+    None,
+    Deflate,
+}
+
+impl Compression {
+    fn from_byte(byte: u8) -> Option<Compression> {
+        match byte {
+            0 => Some(Compression::None),
+            1 => Some(Compression::Deflate),
+            _ => None,
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Deflate => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    pub path: String,
+    pub offset: u64,
+    pub stored_len: u64,
+    pub len: u64,
+    pub compression: Compression,
+    pub hash: [u8; 32],
+}
+
+/// Turns a relative path into the name its entry has in an archive. `None` for paths that leave the asset root.
+pub fn entry_name<P: AsRef<Path>>(path: P) -> Option<String> {
+    let mut parts = Vec::new();
+    for component in path.as_ref().components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_str()?),
+            Component::CurDir => {},
+            _ => return None,
+        }
+    }
+    if parts.is_empty() {
+        return None;
+    }
+    Some(parts.join("/"))
+}
+
+/// Writes an archive entry by entry, then the table of contents once it's `finish`ed.
+pub struct ArchiveWriter<W: Write + Seek> {
+    out: W,
+    position: u64,
+    entries: Vec<ArchiveEntry>,
+    // Where contents that were already written went, so duplicates can point there too.
+    written: HashMap<[u8; 32], (u64, u64, Compression)>,
+}
+
+impl<W: Write + Seek> ArchiveWriter<W> {
+    pub fn new(mut out: W) -> Result<ArchiveWriter<W>, ArchiveError> {
+        // The header is filled in by `finish`, once the table of contents' offset is known.
+        out.write_all(&[0; HEADER_LEN as usize])?;
+        Ok(ArchiveWriter {
+            out: out,
+            position: HEADER_LEN,
+            entries: Vec::new(),
+            written: HashMap::new(),
+        })
+    }
+
+    /// Adds an entry under `path`, relative to the asset root. Compressed entries that don't end up smaller are stored instead.
+    pub fn add<P: AsRef<Path>>(&mut self, path: P, contents: &[u8], compression: Compression) -> Result<&ArchiveEntry, ArchiveError> {
+        let name = entry_name(&path)
+            .filter(|name| name.len() <= u16::MAX as usize)
+            .ok_or_else(|| ArchiveError::BadPath(path.as_ref().to_path_buf()))?;
+        if self.entries.iter().any(|entry| entry.path == name) {
+            return Err(ArchiveError::DuplicateEntry(name));
+        }
+
+        let hash = *blake3::hash(contents).as_bytes();
+        let (offset, stored_len, compression) = match self.written.get(&hash) {
+            Some(&written) => written,
+            None => {
+                let compressed = match compression {
+                    Compression::None => None,
+                    Compression::Deflate => Some(deflate(contents)?).filter(|compressed| compressed.len() < contents.len()),
+                };
+                let (stored, compression) = match &compressed {
+                    Some(compressed) => (compressed.as_slice(), Compression::Deflate),
+                    None => (contents, Compression::None),
+                };
+                self.out.write_all(stored)?;
+                let written = (self.position, stored.len() as u64, compression);
+                self.position += stored.len() as u64;
+                self.written.insert(hash, written);
+                written
+            },
+        };
+
+        self.entries.push(ArchiveEntry {
+            path: name,
+            offset: offset,
+            stored_len: stored_len,
+            len: contents.len() as u64,
+            compression: compression,
+            hash: hash,
+        });
+        Ok(&self.entries[self.entries.len() - 1])
+    }
+
+    /// Bytes of entry data written so far, after compression and deduplication.
+    pub fn data_len(&self) -> u64 {
+        self.position - HEADER_LEN
+    }
+
+    /// Writes the table of contents and the header, handing back the output.
+    pub fn finish(mut self) -> Result<W, ArchiveError> {
+        let toc_offset = self.position;
+        for entry in &self.entries {
+            self.out.write_all(&(entry.path.len() as u16).to_le_bytes())?;
+            self.out.write_all(entry.path.as_bytes())?;
+            self.out.write_all(&entry.offset.to_le_bytes())?;
+            self.out.write_all(&entry.stored_len.to_le_bytes())?;
+            self.out.write_all(&entry.len.to_le_bytes())?;
+            self.out.write_all(&[entry.compression.to_byte()])?;
+            self.out.write_all(&entry.hash)?;
+        }
+
+        self.out.seek(SeekFrom::Start(0))?;
+        self.out.write_all(MAGIC)?;
+        self.out.write_all(&VERSION.to_le_bytes())?;
+        self.out.write_all(&(self.entries.len() as u32).to_le_bytes())?;
+        self.out.write_all(&toc_offset.to_le_bytes())?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+fn deflate(contents: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::best());
+    encoder.write_all(contents)?;
+    encoder.finish()
+}
+
+/// An archive on disk, memory-mapped so uncompressed entries are read without copying.
+pub struct Archive {
+    path: PathBuf,
+    data: Mmap,
+    entries: HashMap<String, ArchiveEntry>,
+}
+
+impl Archive {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Archive, ArchiveError> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        // Safety: archives are only ever read. Modifying one while it's mounted is a bug on the packing side either way.
+        let data = unsafe { Mmap::map(&file)? };
+
+        let header = data.get(..HEADER_LEN as usize).ok_or(ArchiveError::Truncated)?;
+        if &header[..8] != MAGIC {
+            return Err(ArchiveError::NotAnArchive(path.to_path_buf()));
+        }
+        let version = u32::from_le_bytes(header[8..12].try_into().unwrap());
+        if version != VERSION {
+            return Err(ArchiveError::UnsupportedVersion(version));
+        }
+        let count = u32::from_le_bytes(header[12..16].try_into().unwrap());
+        let toc_offset = u64::from_le_bytes(header[16..24].try_into().unwrap());
+
+        let mut toc = data.get(toc_offset as usize..).ok_or(ArchiveError::Truncated)?;
+        let mut entries = HashMap::new();
+        for _ in 0..count {
+            let entry = read_entry(&mut toc)?;
+            if entry.offset.checked_add(entry.stored_len).is_none_or(|end| end > toc_offset) {
+                return Err(ArchiveError::Truncated);
+            }
+            entries.insert(entry.path.clone(), entry);
+        }
+
+        Ok(Archive {
+            path: path.to_path_buf(),
+            data: data,
+            entries: entries,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn entry<P: AsRef<Path>>(&self, path: P) -> Option<&ArchiveEntry> {
+        self.entries.get(&entry_name(path)?)
+    }
+
+    pub fn contains<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.entry(path).is_some()
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &ArchiveEntry> {
+        self.entries.values()
+    }
+
+    /// An entry's contents: borrowed from the mapped file if it's stored, decompressed (and checked against its hash) otherwise.
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> Result<Cow<'_, [u8]>, ArchiveError> {
+        let entry = self.entry(&path).ok_or_else(|| ArchiveError::MissingEntry(path.as_ref().to_path_buf()))?;
+        let stored = &self.data[entry.offset as usize..(entry.offset + entry.stored_len) as usize];
+        match entry.compression {
+            Compression::None => Ok(Cow::Borrowed(stored)),
+            Compression::Deflate => {
+                let mut contents = Vec::with_capacity(entry.len as usize);
+                flate2::read::DeflateDecoder::new(stored).read_to_end(&mut contents)?;
+                check_hash(entry, &contents)?;
+                Ok(Cow::Owned(contents))
+            },
+        }
+    }
+
+    /// Checks every entry against its hash, which `read` skips for stored entries.
+    pub fn verify(&self) -> Result<(), ArchiveError> {
+        for entry in self.entries.values() {
+            let contents = self.read(&entry.path)?;
+            check_hash(entry, &contents)?;
+        }
+        Ok(())
+    }
+}
+
+fn check_hash(entry: &ArchiveEntry, contents: &[u8]) -> Result<(), ArchiveError> {
+    if contents.len() as u64 != entry.len || blake3::hash(contents).as_bytes() != &entry.hash {
+        return Err(ArchiveError::Corrupted(entry.path.clone()));
+    }
+    Ok(())
+}
+
+fn read_entry(toc: &mut &[u8]) -> Result<ArchiveEntry, ArchiveError> {
+    let path_len = u16::from_le_bytes(take(toc)?) as usize;
+    if toc.len() < path_len {
+        return Err(ArchiveError::Truncated);
+    }
+    let (path, rest) = toc.split_at(path_len);
+    *toc = rest;
+    let path = String::from_utf8(path.to_vec()).map_err(|_| ArchiveError::Truncated)?;
+    let offset = u64::from_le_bytes(take(toc)?);
+    let stored_len = u64::from_le_bytes(take(toc)?);
+    let len = u64::from_le_bytes(take(toc)?);
+    let [compression] = take::<1>(toc)?;
+    let compression = Compression::from_byte(compression).ok_or(ArchiveError::UnknownCompression(compression))?;
+    let hash = take(toc)?;
+    Ok(ArchiveEntry {
+        path: path,
+        offset: offset,
+        stored_len: stored_len,
+        len: len,
+        compression: compression,
+        hash: hash,
+    })
+}
+
+fn take<const N: usize>(toc: &mut &[u8]) -> Result<[u8; N], ArchiveError> {
+    if toc.len() < N {
+        return Err(ArchiveError::Truncated);
+    }
+    let (bytes, rest) = toc.split_at(N);
+    *toc = rest;
+    Ok(bytes.try_into().unwrap())
+}
+
+#[derive(Error, Debug)]
+pub enum ArchiveError {
+    #[error("{0}")]
+    IOError(io::Error),
+    #[error("{} is not an asset archive", .0.display())]
+    NotAnArchive(PathBuf),
+    #[error("Archive version {0} is not supported")]
+    UnsupportedVersion(u32),
+    #[error("The archive is truncated or its table of contents is malformed")]
+    Truncated,
+    #[error("Unknown compression method {0}")]
+    UnknownCompression(u8),
+    #[error("{0} doesn't match its hash")]
+    Corrupted(String),
+    #[error("No entry for {}", .0.display())]
+    MissingEntry(PathBuf),
+    #[error("{0} was added twice")]
+    DuplicateEntry(String),
+    #[error("{} can't be stored in an archive; paths have to be relative and stay inside the asset root", .0.display())]
+    BadPath(PathBuf),
+}
+
+impl From<io::Error> for ArchiveError {
+    fn from(err: io::Error) -> Self {
+        ArchiveError::IOError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pack(path: &Path) {
+        let mut writer = ArchiveWriter::new(File::create(path).unwrap()).unwrap();
+        writer.add("shaders/basic.vert", &b"void main() {}\n".repeat(64), Compression::Deflate).unwrap();
+        writer.add("./textures/noise.bin", &[7, 1, 250, 3], Compression::Deflate).unwrap();
+        writer.add("shaders/copy.vert", &b"void main() {}\n".repeat(64), Compression::None).unwrap();
+        assert!(matches!(writer.add("shaders/basic.vert", b"", Compression::None), Err(ArchiveError::DuplicateEntry(_))));
+        assert!(matches!(writer.add("../secrets.txt", b"", Compression::None), Err(ArchiveError::BadPath(_))));
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn entries_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("assets.pak");
+        pack(&path);
+
+        let archive = Archive::open(&path).unwrap();
+        assert_eq!(archive.entries().count(), 3);
+        assert_eq!(archive.read("shaders/basic.vert").unwrap().as_ref(), b"void main() {}\n".repeat(64).as_slice());
+        // Incompressible data is stored, and read without a copy.
+        assert_eq!(archive.entry("textures/noise.bin").unwrap().compression, Compression::None);
+        assert!(matches!(archive.read("textures/noise.bin").unwrap(), Cow::Borrowed(&[7, 1, 250, 3])));
+        // Identical contents are only stored once.
+        let (basic, copy) = (archive.entry("shaders/basic.vert").unwrap(), archive.entry("shaders/copy.vert").unwrap());
+        assert_eq!((basic.offset, basic.compression), (copy.offset, copy.compression));
+        assert!(matches!(archive.read("shaders/missing.vert"), Err(ArchiveError::MissingEntry(_))));
+        archive.verify().unwrap();
+    }
+
+    #[test]
+    fn damaged_archives_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("assets.pak");
+        pack(&path);
+        let mut bytes = std::fs::read(&path).unwrap();
+
+        // Flip a byte of the stored entry's data.
+        let stored = Archive::open(&path).unwrap().entry("textures/noise.bin").unwrap().offset as usize;
+        bytes[stored] ^= 0xff;
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(matches!(Archive::open(&path).unwrap().verify(), Err(ArchiveError::Corrupted(_))));
+
+        std::fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(matches!(Archive::open(&path), Err(ArchiveError::Truncated)));
+        std::fs::write(&path, b"not an archive, just some text").unwrap();
+        assert!(matches!(Archive::open(&path), Err(ArchiveError::NotAnArchive(_))));
+    }
+}
@@ -0,0 +1,265 @@
+use crate::types::*;
+use crate::hl_assets::*;
+use crate::handle::Handle;
+use super::{AssetContainer, StoredAsset};
+use std::collections::BTreeMap;
+use std::mem;
+use std::ops::AddAssign;
+use std::rc::Rc;
+
+/// Bytes an asset keeps in video memory and in main memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MemoryUsage {
+    pub gpu_bytes: usize,
+    pub cpu_bytes: usize,
+}
+
+impl MemoryUsage {
+    pub fn gpu(bytes: usize) -> MemoryUsage {
+        MemoryUsage { gpu_bytes: bytes, cpu_bytes: 0 }
+    }
+
+    pub fn cpu(bytes: usize) -> MemoryUsage {
+        MemoryUsage { gpu_bytes: 0, cpu_bytes: bytes }
+    }
+
+    pub fn total(&self) -> usize {
+        self.gpu_bytes + self.cpu_bytes
+    }
+}
+
+impl AddAssign for MemoryUsage {
+    fn add_assign(&mut self, other: MemoryUsage) {
+        self.gpu_bytes += other.gpu_bytes;
+        self.cpu_bytes += other.cpu_bytes;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AssetCategory {
+    Textures,
+    Models,
+    Programs,
+    Materials,
+    /// Node hierarchies, skins and animations of glTF files.
+    Scenes,
+}
+
+/// What the loaded assets of each category take up. Evicted assets don't count.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MemoryReport {
+    categories: BTreeMap<AssetCategory, MemoryUsage>,
+}
+
+impl MemoryReport {
+    pub fn add(&mut self, category: AssetCategory, usage: MemoryUsage) {
+        *self.categories.entry(category).or_default() += usage;
+    }
+
+    pub fn category(&self, category: AssetCategory) -> MemoryUsage {
+        self.categories.get(&category).copied().unwrap_or_default()
+    }
+
+    pub fn categories(&self) -> impl Iterator<Item = (AssetCategory, MemoryUsage)> + '_ {
+        self.categories.iter().map(|(category, usage)| (*category, *usage))
+    }
+
+    pub fn total(&self) -> MemoryUsage {
+        let mut total = MemoryUsage::default();
+        for usage in self.categories.values() {
+            total += *usage;
+        }
+        total
+    }
+}
+
+/// How much memory an asset holds on to.
+pub trait MemoryFootprint {
+    fn memory_usage(&self) -> MemoryUsage;
+}
+
+impl MemoryFootprint for Texture {
+    fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage::gpu(self.gpu_bytes())
+    }
+}
+
+impl MemoryFootprint for dyn Model {
+    fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage::gpu(self.gpu_bytes())
+    }
+}
+
+// Linked programs are small, and the driver doesn't say how small.
+impl MemoryFootprint for CompiledShaderProgram {
+    fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage::default()
+    }
+}
+
+impl MemoryFootprint for Material {
+    fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage::cpu(mem::size_of::<Material>())
+    }
+}
+
+impl MemoryFootprint for GltfAsset {
+    fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage::cpu(self.cpu_bytes())
+    }
+}
+
+/// Picks which candidates to evict, least recently used first, until at least `over_by` bytes are freed.
+/// Candidates are `(key, last used, bytes)`. Returns fewer than needed if they don't add up to `over_by`.
+pub fn select_evictions<K>(mut candidates: Vec<(K, u64, usize)>, over_by: usize) -> Vec<K> {
+    candidates.sort_by_key(|&(_, last_used, _)| last_used);
+    let mut freed = 0;
+    let mut selected = Vec::new();
+    for (key, _, bytes) in candidates {
+        if freed >= over_by {
+            break;
+        }
+        freed += bytes;
+        selected.push(key);
+    }
+    selected
+}
+
+/// Assets that can be evicted to stay under the budget.
+enum Evictable {
+    Texture(Handle<Texture>),
+    Model(Handle<dyn Model>),
+    Scene(Handle<GltfAsset>),
+}
+
+impl AssetContainer {
+    pub fn memory_usage(&self) -> MemoryReport {
+        let mut report = MemoryReport::default();
+        self.report::<Texture>(&mut report);
+        self.report::<dyn Model>(&mut report);
+        self.report::<CompiledShaderProgram>(&mut report);
+        self.report::<Material>(&mut report);
+        self.report::<GltfAsset>(&mut report);
+        report
+    }
+
+    fn report<T: StoredAsset + ?Sized>(&self, report: &mut MemoryReport) {
+        for (_, asset) in T::storage(self).iter() {
+            report.add(T::CATEGORY, asset.memory_usage());
+        }
+    }
+
+    /// Caps how many bytes (GPU and CPU together) loaded assets may take, evicting right away if they're over.
+    /// `None`, the default, never evicts anything.
+    pub fn set_memory_budget(&mut self, budget: Option<usize>) {
+        self.memory_budget = budget;
+        self.enforce_budget();
+    }
+
+    pub fn memory_budget(&self) -> Option<usize> {
+        self.memory_budget
+    }
+
+    /// Unloads every asset that nothing but the container holds on to, along with anything that only they held.
+    /// Their handles go stale. Returns how many assets were unloaded.
+    pub fn collect_unused(&mut self) -> usize {
+        let mut collected = 0;
+        loop {
+            // Unloading a glTF file or a model can leave its textures, materials and primitives unused in turn.
+            let round = self.collect::<GltfAsset>()
+                + self.collect::<dyn Model>()
+                + self.collect::<Material>()
+                + self.collect::<Texture>()
+                + self.collect::<CompiledShaderProgram>();
+            if round == 0 {
+                return collected;
+            }
+            collected += round;
+        }
+    }
+
+    fn collect<T: StoredAsset + ?Sized>(&mut self) -> usize {
+        // Nobody has had the chance to pick up a placeholder yet.
+        let unused: Vec<_> = T::storage(self).unused().into_iter()
+            .filter(|&handle| !T::storage(self).reference(handle).is_some_and(|reference| self.loads.is_loading(reference)))
+            .collect();
+        for &handle in &unused {
+            if let Some(reference) = T::storage(self).reference(handle).cloned() {
+                T::storage_mut(self).remove(handle);
+                // The recipe may still be needed by another asset loaded from the same place.
+                if T::storage(self).find_reference(&reference).is_none() {
+                    self.recipes.remove(&reference);
+                    self.dependencies.remove(&reference);
+                    self.loads.forget(&reference);
+                }
+            }
+        }
+        unused.len()
+    }
+
+    /// Evicts the least recently used assets until everything fits in the memory budget again. Only assets that nothing
+    /// outside the container is using and that can be reloaded are evicted; `load` brings them back.
+    /// Returns how many assets were evicted.
+    pub fn enforce_budget(&mut self) -> usize {
+        let budget = match self.memory_budget {
+            Some(budget) => budget,
+            None => return 0,
+        };
+        let used = self.memory_usage().total().total();
+        if used <= budget {
+            return 0;
+        }
+
+        let mut candidates = Vec::new();
+        self.eviction_candidates(&mut candidates, Evictable::Texture);
+        self.eviction_candidates(&mut candidates, Evictable::Model);
+        self.eviction_candidates(&mut candidates, Evictable::Scene);
+        let evictions = select_evictions(candidates, used - budget);
+        for evictable in &evictions {
+            match *evictable {
+                Evictable::Texture(handle) => { self.textures.evict(handle); },
+                Evictable::Model(handle) => { self.models.evict(handle); },
+                Evictable::Scene(handle) => { self.gltf_assets.evict(handle); },
+            }
+        }
+        evictions.len()
+    }
+
+    fn eviction_candidates<T: StoredAsset + ?Sized>(&self, candidates: &mut Vec<(Evictable, u64, usize)>, wrap: fn(Handle<T>) -> Evictable) {
+        let storage = T::storage(self);
+        for (handle, asset) in storage.iter() {
+            let reloadable = storage.reference(handle).is_some_and(|reference| self.recipes.contains_key(reference));
+            let bytes = asset.memory_usage().total();
+            if Rc::strong_count(asset) == 1 && reloadable && bytes > 0 {
+                let last_used = storage.last_used(handle).unwrap_or(0);
+                candidates.push((wrap(handle), last_used, bytes));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn least_recently_used_go_first() {
+        let candidates = vec![("grass", 7, 300), ("stone", 2, 100), ("dirt", 5, 100), ("sand", 9, 50)];
+        assert_eq!(select_evictions(candidates.clone(), 150), vec!["stone", "dirt"]);
+        assert_eq!(select_evictions(candidates.clone(), 0), Vec::<&str>::new());
+        assert_eq!(select_evictions(candidates, 10_000).len(), 4);
+    }
+
+    #[test]
+    fn reports_add_up_per_category() {
+        let mut report = MemoryReport::default();
+        report.add(AssetCategory::Textures, MemoryUsage::gpu(1024));
+        report.add(AssetCategory::Textures, MemoryUsage::gpu(512));
+        report.add(AssetCategory::Scenes, MemoryUsage::cpu(64));
+
+        assert_eq!(report.category(AssetCategory::Textures), MemoryUsage::gpu(1536));
+        assert_eq!(report.category(AssetCategory::Models), MemoryUsage::default());
+        assert_eq!(report.total(), MemoryUsage { gpu_bytes: 1536, cpu_bytes: 64 });
+        assert_eq!(report.categories().map(|(category, _)| category).collect::<Vec<_>>(), vec![AssetCategory::Textures, AssetCategory::Scenes]);
+    }
+}
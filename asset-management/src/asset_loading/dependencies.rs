@@ -0,0 +1,94 @@
+use crate::namespace::AssetReference;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Which assets use which: a model uses its material, a material its textures, a program its shader files.
+/// Loaders record what they load along the way, so an asset's dependencies are only ever loaded once,
+/// and a reload can find everything that was built on top of what changed.
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    uses: HashMap<AssetReference, Vec<AssetReference>>,
+    used_by: HashMap<AssetReference, HashSet<AssetReference>>,
+}
+
+impl DependencyGraph {
+    /// Records that `asset` uses `dependency`. Recording the same edge twice does nothing.
+    pub fn add(&mut self, asset: AssetReference, dependency: AssetReference) {
+        let uses = self.uses.entry(asset.clone()).or_default();
+        if !uses.contains(&dependency) {
+            uses.push(dependency.clone());
+            self.used_by.entry(dependency).or_default().insert(asset);
+        }
+    }
+
+    /// Forgets everything `asset` uses, once it's unloaded. What uses it is kept, so a dependency that's loaded
+    /// again still knows about them.
+    pub fn remove(&mut self, asset: &AssetReference) {
+        for dependency in self.uses.remove(asset).unwrap_or_default() {
+            if let Some(users) = self.used_by.get_mut(&dependency) {
+                users.remove(asset);
+                if users.is_empty() {
+                    self.used_by.remove(&dependency);
+                }
+            }
+        }
+    }
+
+    /// What `asset` uses directly, in the order it was recorded.
+    pub fn uses(&self, asset: &AssetReference) -> &[AssetReference] {
+        self.uses.get(asset).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// What uses `asset` directly.
+    pub fn used_by(&self, asset: &AssetReference) -> impl Iterator<Item = &AssetReference> {
+        self.used_by.get(asset).into_iter().flatten()
+    }
+
+    /// Everything that uses `asset`, directly or through other assets, nearest first.
+    pub fn all_used_by(&self, asset: &AssetReference) -> Vec<AssetReference> {
+        let mut found = Vec::new();
+        let mut seen = HashSet::new();
+        seen.insert(asset);
+        let mut queue = VecDeque::new();
+        queue.push_back(asset);
+        while let Some(next) = queue.pop_front() {
+            for user in self.used_by(next) {
+                if seen.insert(user) {
+                    found.push(user.clone());
+                    queue.push_back(user);
+                }
+            }
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset_types::AssetType;
+
+    #[test]
+    fn users_are_found_through_the_graph() {
+        let texture = AssetReference::new("textures/brick.png#srgb", AssetType::Texture);
+        let material = AssetReference::new("models/house.obj#brick", AssetType::Material);
+        let walls = AssetReference::new("models/house.obj#walls", AssetType::Model);
+        let chimney = AssetReference::new("models/house.obj#chimney", AssetType::Model);
+
+        let mut graph = DependencyGraph::default();
+        graph.add(material.clone(), texture.clone());
+        graph.add(walls.clone(), material.clone());
+        graph.add(chimney.clone(), material.clone());
+        graph.add(chimney.clone(), material.clone());
+
+        assert_eq!(graph.uses(&chimney).to_vec(), vec![material.clone()]);
+        assert_eq!(graph.used_by(&texture).collect::<Vec<_>>(), vec![&material]);
+        let mut users = graph.all_used_by(&texture);
+        assert_eq!(users.remove(0), material);
+        users.sort_by(|a, b| a.location().cmp(b.location()));
+        assert_eq!(users, vec![chimney.clone(), walls.clone()]);
+
+        graph.remove(&chimney);
+        assert!(graph.uses(&chimney).is_empty());
+        assert_eq!(graph.all_used_by(&texture), vec![material, walls]);
+    }
+}
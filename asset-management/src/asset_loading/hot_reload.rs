@@ -0,0 +1,133 @@
+use crate::types::*;
+use crate::hl_assets::*;
+use crate::handle::{AssetStorage, Handle};
+use crate::namespace::AssetReference;
+use super::{AssetContainer, AssetLoadFailure, Recipe, StoredAsset};
+use std::rc::Rc;
+
+/// What one `AssetContainer::poll_changes` did.
+#[derive(Debug, Default)]
+pub struct ReloadReport {
+    /// Names of the assets that were rebuilt from changed files.
+    pub reloaded: Vec<String>,
+    /// Assets whose files changed but that failed to rebuild. They keep the version they had.
+    pub failures: Vec<AssetLoadFailure>,
+    /// Loaded assets that use a rebuilt one, directly or through others, without having been rebuilt themselves,
+    /// like the materials of a reloaded texture. They draw the new version already; this is for anything that
+    /// keeps something it worked out from them.
+    pub affected: Vec<AssetReference>,
+}
+
+impl ReloadReport {
+    pub fn is_empty(&self) -> bool {
+        self.reloaded.is_empty() && self.failures.is_empty()
+    }
+}
+
+impl AssetContainer {
+    /// Rebuilds every loaded asset whose source files changed since it was loaded (or last rebuilt),
+    /// including ones that a layer mounted or unmounted since then overrides or stopped overriding.
+    ///
+    /// Textures, programs and models are rebuilt in place, so `Rc`s handed out earlier draw the new version too.
+    /// The exceptions are models whose vertex layout changed, glTF scenes and material files, which only the container's handles see.
+    /// Evicted assets are left alone, since they're loaded from the changed files anyway once they're needed again.
+    /// Meant to be called once a frame, on the thread that owns the GL context.
+    pub fn poll_changes(&mut self) -> ReloadReport {
+        let mut report = ReloadReport::default();
+        let changed = self.recipes.changed_files(&self.vfs);
+        if changed.is_empty() {
+            return report;
+        }
+
+        let mut rebuilds = self.recipes.built_from(&changed);
+        // Materials look up their programs and textures,
+        // and glTF scenes reuse their primitives, so those go first.
+        rebuilds.sort_by_key(|(_, recipe)| match recipe {
+            Recipe::Texture { .. } | Recipe::Cubemap { .. } | Recipe::GltfImage { .. } => 0,
+            Recipe::Program { .. } => 1,
+            Recipe::ObjMesh { .. } | Recipe::GltfPrimitive { .. } | Recipe::Material { .. } => 2,
+            Recipe::Gltf { .. } => 3,
+        });
+        let mut rebuilt = Vec::new();
+        for (reference, recipe) in rebuilds {
+            let reloaded = report.reloaded.len();
+            match &recipe {
+                Recipe::Texture { .. } | Recipe::Cubemap { .. } | Recipe::GltfImage { .. } => {
+                    self.reload::<Texture>(&reference, &recipe, &mut report, |_, _, old, new| old.swap(&new));
+                },
+                // A program that fails to compile never gets this far, so the old one stays in use.
+                Recipe::Program { .. } => {
+                    self.reload::<CompiledShaderProgram>(&reference, &recipe, &mut report, |_, _, old, new| old.swap(&new));
+                },
+                Recipe::ObjMesh { .. } | Recipe::GltfPrimitive { .. } => {
+                    self.reload::<dyn Model>(&reference, &recipe, &mut report, |storage, handle, old, new| {
+                        if !old.swap_buffers(new.as_ref()) {
+                            storage.replace(handle, new);
+                        }
+                    });
+                },
+                Recipe::Material { .. } => {
+                    self.reload::<Material>(&reference, &recipe, &mut report, |storage, handle, _, new| { storage.replace(handle, new); });
+                },
+                Recipe::Gltf { .. } => {
+                    self.reload::<GltfAsset>(&reference, &recipe, &mut report, |storage, handle, old, mut new| {
+                        if let Some(new) = Rc::get_mut(&mut new) {
+                            reuse_primitives(old, new);
+                        }
+                        storage.replace(handle, new);
+                    });
+                },
+            }
+            if report.reloaded.len() > reloaded {
+                rebuilt.push(reference);
+            }
+        }
+
+        for reference in &rebuilt {
+            for user in self.dependencies.all_used_by(reference) {
+                if !rebuilt.contains(&user) && !report.affected.contains(&user) {
+                    report.affected.push(user);
+                }
+            }
+        }
+        report
+    }
+
+    fn reload<T: StoredAsset + ?Sized>(
+        &mut self,
+        reference: &AssetReference,
+        recipe: &Recipe,
+        report: &mut ReloadReport,
+        apply: impl FnOnce(&mut AssetStorage<T>, Handle<T>, &Rc<T>, Rc<T>),
+    ) {
+        let storage = T::storage(self);
+        let (handle, old) = match storage.find_reference(reference).and_then(|handle| Some((handle, storage.peek(handle)?.clone()))) {
+            Some(loaded) => loaded,
+            None => return,
+        };
+        let name = storage.name(handle).unwrap_or_default().to_string();
+        match T::rebuild(self, &name, recipe) {
+            Ok(new) => {
+                apply(T::storage_mut(self), handle, &old, new);
+                report.reloaded.push(name);
+            },
+            Err(err) => report.failures.push(AssetLoadFailure {
+                name: name,
+                error: err,
+            }),
+        }
+    }
+}
+
+/// A scene's primitives are also the container's models, which were already rebuilt in place by the time the scene is.
+/// The new scene keeps those instead of the copies it uploaded, so it and the models stay the same objects.
+fn reuse_primitives(old: &GltfAsset, new: &mut GltfAsset) {
+    for (mesh, old_mesh) in new.meshes.iter_mut().zip(&old.meshes) {
+        for (primitive, old_primitive) in mesh.primitives.iter_mut().zip(&old_mesh.primitives) {
+            // A primitive whose layout changed was replaced rather than rebuilt in place.
+            if primitive.model.get_vertex_layout() == old_primitive.model.get_vertex_layout() {
+                primitive.model = old_primitive.model.clone();
+            }
+        }
+    }
+}
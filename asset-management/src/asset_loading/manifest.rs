@@ -0,0 +1,300 @@
+use crate::types::*;
+use crate::hl_assets::*;
+use crate::asset_types::AssetType;
+use crate::namespace::AssetId;
+use super::AssetContainer;
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Everything a game needs loaded up front, so it doesn't have to be spelled out in code.
+/// Every path is relative to the same directory the matching `AssetContainer::add_*` call uses.
+/// Assets are stored under their ids (`core:textures/stone`); entries that leave out the path are loaded from the
+/// file the id names.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AssetManifest {
+    pub textures: Vec<TextureEntry>,
+    pub cubemaps: Vec<TextureEntry>,
+    pub programs: Vec<ProgramEntry>,
+    /// MTL libraries; their materials are added as `library.mtl#material`.
+    pub materials: Vec<PathBuf>,
+    /// Material files (see `MaterialFile`), stored under their ids.
+    pub material_files: Vec<AssetId>,
+    pub models: Vec<ModelEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TextureEntry {
+    pub name: AssetId,
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    pub config: TexConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProgramEntry {
+    pub name: AssetId,
+    pub vertex: PathBuf,
+    pub fragment: PathBuf,
+    #[serde(default)]
+    pub geometry: Option<PathBuf>,
+}
+
+/// An .obj, .gltf or .glb model.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelEntry {
+    pub name: AssetId,
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    /// One of the manifest's programs, for the materials of an OBJ file's MTL libraries, which can't name one.
+    pub program: AssetId,
+}
+
+impl AssetManifest {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<AssetManifest, ManifestError> {
+        AssetManifest::from_slice(&fs::read(path)?)
+    }
+
+    pub fn from_slice(json: &[u8]) -> Result<AssetManifest, ManifestError> {
+        Ok(serde_json::from_slice(json)?)
+    }
+}
+
+impl AssetContainer {
+    /// Loads every asset a manifest (relative to the asset root) lists. A failed asset doesn't stop the rest from loading;
+    /// every failure is reported together once everything has been tried, and whatever did load stays in the container.
+    pub fn load_manifest<S: AsRef<Path>>(&mut self, manifest_location: S) -> Result<(), ManifestError> {
+        let manifest = AssetManifest::from_slice(&self.vfs.read(manifest_location)?)?;
+        self.load_manifest_entries(&manifest)
+    }
+
+    pub fn load_manifest_entries(&mut self, manifest: &AssetManifest) -> Result<(), ManifestError> {
+        let mut failures = Vec::new();
+        let mut record = |name: &str, result: Result<(), AssetLoadError>| {
+            if let Err(err) = result {
+                failures.push(AssetLoadFailure {
+                    name: name.to_string(),
+                    error: err,
+                });
+            }
+        };
+
+        // Programs go first, since models are drawn with them.
+        for program in &manifest.programs {
+            let result = self.add_program(program.name.to_string(), &program.vertex, &program.fragment, program.geometry.as_ref());
+            record(&program.name.to_string(), result.map(|_| ()).map_err(AssetLoadError::from));
+        }
+        for texture in &manifest.textures {
+            let result = match &texture.path {
+                Some(path) => self.add_texture(texture.name.to_string(), path, texture.config.clone()),
+                None => self.add_texture_id(&texture.name, texture.config.clone()),
+            };
+            record(&texture.name.to_string(), result.map(|_| ()).map_err(AssetLoadError::from));
+        }
+        for cubemap in &manifest.cubemaps {
+            let result = match &cubemap.path {
+                Some(path) => self.add_cubemap(cubemap.name.to_string(), path, cubemap.config.clone()),
+                None => self.add_cubemap_id(&cubemap.name, cubemap.config.clone()),
+            };
+            record(&cubemap.name.to_string(), result.map(|_| ()).map_err(AssetLoadError::from));
+        }
+        for library in &manifest.materials {
+            let result = self.add_mtl(library);
+            record(&library.display().to_string(), result.map(|_| ()));
+        }
+        // After the textures and cubemaps, so material files can use them by name.
+        for material in &manifest.material_files {
+            let result = self.add_material_file(material);
+            record(&material.to_string(), result.map(|_| ()));
+        }
+        for model in &manifest.models {
+            let result = self.add_manifest_model(model);
+            record(&model.name.to_string(), result);
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(ManifestError::LoadFailures(failures))
+        }
+    }
+
+    fn add_manifest_model(&mut self, model: &ModelEntry) -> Result<(), AssetLoadError> {
+        let program = self.find_program(&model.program.to_string())
+            .and_then(|handle| self.get(handle))
+            .ok_or_else(|| AssetLoadError::UnknownProgram(model.program.to_string()))?;
+        let path = match &model.path {
+            Some(path) => path.clone(),
+            None => model.name.find_file(AssetType::Model, self.vfs())?,
+        };
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("obj") => self.add_obj_model(model.name.to_string(), &path, program).map(|_| ())?,
+            Some("gltf") | Some("glb") => self.add_gltf(model.name.to_string(), &path).map(|_| ())?,
+            _ => return Err(AssetLoadError::UnknownModelFormat(path)),
+        }
+        Ok(())
+    }
+}
+
+/// Why one manifest entry didn't load.
+#[derive(Error, Debug)]
+pub enum AssetLoadError {
+    #[error("{0}")]
+    TextureError(TextureError),
+    #[error("{0}")]
+    ShaderCompileError(ShaderCompileError),
+    #[error("{0}")]
+    ObjLoadError(ObjLoadError),
+    #[error("{0}")]
+    GltfError(GltfError),
+    #[error("{0}")]
+    IOError(io::Error),
+    #[error("Loading the material library failed: {0}")]
+    MaterialError(tobj::LoadError),
+    #[error("The material file is malformed: {0}")]
+    MaterialFileError(serde_json::Error),
+    #[error("No program named {0} was loaded")]
+    UnknownProgram(String),
+    #[error("Don't know how to load {}; models have to be .obj, .gltf or .glb", .0.display())]
+    UnknownModelFormat(PathBuf),
+    #[error("The asset was unloaded")]
+    StaleHandle,
+    #[error("{0} was evicted, but there's no way to load it again")]
+    NotReloadable(String),
+    #[error("{0} is still loading")]
+    StillLoading(String),
+    #[error("{0} failed to load")]
+    LoadFailed(String),
+}
+
+impl From<TextureError> for AssetLoadError {
+    fn from(err: TextureError) -> Self {
+        AssetLoadError::TextureError(err)
+    }
+}
+
+impl From<ShaderCompileError> for AssetLoadError {
+    fn from(err: ShaderCompileError) -> Self {
+        AssetLoadError::ShaderCompileError(err)
+    }
+}
+
+impl From<ObjLoadError> for AssetLoadError {
+    fn from(err: ObjLoadError) -> Self {
+        AssetLoadError::ObjLoadError(err)
+    }
+}
+
+impl From<GltfError> for AssetLoadError {
+    fn from(err: GltfError) -> Self {
+        AssetLoadError::GltfError(err)
+    }
+}
+
+impl From<serde_json::Error> for AssetLoadError {
+    fn from(err: serde_json::Error) -> Self {
+        AssetLoadError::MaterialFileError(err)
+    }
+}
+
+impl From<io::Error> for AssetLoadError {
+    fn from(err: io::Error) -> Self {
+        AssetLoadError::IOError(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct AssetLoadFailure {
+    pub name: String,
+    pub error: AssetLoadError,
+}
+
+impl fmt::Display for AssetLoadFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.error)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ManifestError {
+    #[error("Reading the asset manifest failed: {0}")]
+    IOError(io::Error),
+    #[error("The asset manifest is malformed: {0}")]
+    ParseError(serde_json::Error),
+    #[error("{} asset(s) failed to load:{}", .0.len(), list_failures(.0))]
+    LoadFailures(Vec<AssetLoadFailure>),
+}
+
+fn list_failures(failures: &[AssetLoadFailure]) -> String {
+    failures.iter().map(|failure| format!("\n  {}", failure)).collect()
+}
+
+impl From<io::Error> for ManifestError {
+    fn from(err: io::Error) -> Self {
+        ManifestError::IOError(err)
+    }
+}
+
+impl From<serde_json::Error> for ManifestError {
+    fn from(err: serde_json::Error) -> Self {
+        ManifestError::ParseError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn engine_manifest_parses() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../assets/manifest.json");
+        let manifest = AssetManifest::from_file(path).expect("failed to parse the engine's manifest");
+
+        assert_eq!(manifest.textures.len(), 2);
+        assert_eq!(manifest.textures[0].config, TexConfig::new(TextureType::Texture2D, InternalStorage::RGB, PixelDataFormat::RGB, PixelDataType::UnsignedByte));
+        assert_eq!(manifest.cubemaps[0].config.tex_type, TextureType::TextureCubeMap);
+        assert_eq!(manifest.textures[0].name.to_string(), "core:textures/texture1");
+        let skybox_shader: AssetId = "core:skybox_shader".parse().unwrap();
+        let skybox = manifest.programs.iter().find(|program| program.name == skybox_shader).expect("no skybox program");
+        assert_eq!(skybox.vertex, PathBuf::from("skybox/skybox.vert"));
+        assert_eq!(skybox.geometry, None);
+        assert!(manifest.material_files.iter().any(|material| material.to_string() == "core:materials/skybox"));
+    }
+
+    #[test]
+    fn sections_are_optional() {
+        let manifest = AssetManifest::from_slice(br#"{ "materials": ["test/cube.mtl"] }"#).unwrap();
+        assert_eq!(manifest, AssetManifest {
+            materials: vec![PathBuf::from("test/cube.mtl")],
+            ..AssetManifest::default()
+        });
+    }
+
+    #[test]
+    fn bad_ids_are_rejected() {
+        let result = AssetManifest::from_slice(br#"{ "programs": [{ "name": "core:Basic", "vertex": "a.vert", "fragment": "a.frag" }] }"#);
+        assert!(matches!(result, Err(ManifestError::ParseError(_))));
+    }
+
+    #[test]
+    fn typos_are_rejected() {
+        let result = AssetManifest::from_slice(br#"{ "programs": [{ "name": "p", "vertex": "a.vert", "fragmnet": "a.frag" }] }"#);
+        assert!(matches!(result, Err(ManifestError::ParseError(_))));
+    }
+
+    #[test]
+    fn failures_are_listed_together() {
+        let err = ManifestError::LoadFailures(vec![
+            AssetLoadFailure { name: "backpack".to_string(), error: AssetLoadError::UnknownProgram("shader_pbr".to_string()) },
+            AssetLoadFailure { name: "level".to_string(), error: AssetLoadError::UnknownModelFormat(PathBuf::from("level.fbx")) },
+        ]);
+        assert_eq!(err.to_string(), "2 asset(s) failed to load:\n  backpack: No program named shader_pbr was loaded\n  level: Don't know how to load level.fbx; models have to be .obj, .gltf or .glb");
+    }
+}
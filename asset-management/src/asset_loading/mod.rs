@@ -1,120 +1,699 @@
-use std::collections::HashMap;
 use std::rc::Rc;
 use crate::types::*;
+use crate::hl_assets::*;
+use crate::handle::*;
+use crate::namespace::{AssetId, AssetReference};
+use crate::asset_types::AssetType;
+use crate::archive::{Archive, ArchiveError};
+use crate::cook::{cooked_model_path, cooked_texture_path, CookedModel, CookedTexture};
+use crate::meta::AssetMeta;
+use crate::vfs::{normalize, Layer, Vfs};
 use ace_gl_types::gl;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io;
 use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+mod budget;
+mod dependencies;
+mod hot_reload;
+mod manifest;
+mod recipe;
+mod streaming;
+
+pub use self::budget::*;
+pub use self::dependencies::DependencyGraph;
+pub use self::hot_reload::*;
+pub use self::manifest::*;
+pub use self::recipe::Recipe;
+pub use self::streaming::LoadReport;
+use self::recipe::RecipeBook;
+use self::streaming::LoadQueue;
 
 pub struct AssetContainer {
-    asset_root: PathBuf,
-    // How in the hell do we free unused data?
-    // Implement a GC? ;)
-    gl_context: Rc<gl::Gl>,
-    models: HashMap<String, Rc<dyn Model>>,
-    materials: HashMap<String, Rc<Material>>,
+    vfs: Vfs,
+    gl_context: Rc<GLContext>,
+    models: AssetStorage<dyn Model>,
+    materials: AssetStorage<Material>,
     // is this necessary?
     // potentially if we wish to re-use shaders to recompile new programs
     // look into if major games keep shaders resident in memory
     // they shouldn't take up too much space, but you never know...
     //shaders: HashMap<String, Rc<Shader>>,
-    shader_programs: HashMap<String, Rc<CompiledShaderProgram>>,
-    textures: HashMap<String, Rc<Texture>>,
-
+    shader_programs: AssetStorage<CompiledShaderProgram>,
+    textures: AssetStorage<Texture>,
+    gltf_assets: AssetStorage<GltfAsset>,
+    recipes: RecipeBook,
+    dependencies: DependencyGraph,
+    memory_budget: Option<usize>,
+    loads: LoadQueue,
+    guids: HashMap<Uuid, AssetReference>,
 }
 
 impl AssetContainer {
+    /// The files under `asset_container_location` are mounted as the `base` layer, under everything mounted later.
     pub fn new<S: AsRef<Path>>(asset_container_location: S, gl_context: gl::Gl) -> AssetContainer {
+        let mut vfs = Vfs::new();
+        vfs.mount("base", Layer::Directory(asset_container_location.as_ref().to_path_buf()));
         AssetContainer {
-            asset_root: asset_container_location.as_ref().to_path_buf(),
-            gl_context: Rc::new(gl_context),
-            models: HashMap::new(),
-            materials: HashMap::new(),
-            shader_programs: HashMap::new(),
-            textures: HashMap::new(),
+            vfs: vfs,
+            gl_context: Rc::new(GLContext::new(gl_context)),
+            models: AssetStorage::new(),
+            materials: AssetStorage::new(),
+            shader_programs: AssetStorage::new(),
+            textures: AssetStorage::new(),
+            gltf_assets: AssetStorage::new(),
+            recipes: RecipeBook::default(),
+            dependencies: DependencyGraph::default(),
+            memory_budget: None,
+            loads: LoadQueue::default(),
+            guids: HashMap::new(),
         }
     }
 
-    pub fn gl_ctx(&self) -> Rc<gl::Gl> {
+    pub fn gl_ctx(&self) -> Rc<GLContext> {
         self.gl_context.clone()
     }
 
-    pub fn add_program<S: AsRef<Path>, V: ToString>(&mut self, name: V, vertex_name: S, fragment_name: S, geometry_name: Option<S>) -> Result<Rc<CompiledShaderProgram>, ShaderCompileError> {
-        // Initialize new shader program to attach shaders to
-        let mut shdr_prog = ShaderProgram::new(self.gl_ctx());
+    /// Resolves a handle, or `None` if what it pointed at has since been unloaded, reloaded or evicted.
+    pub fn get<T: StoredAsset + ?Sized>(&self, handle: Handle<T>) -> Option<Rc<T>> {
+        T::storage(self).get(handle).cloned()
+    }
 
+    /// Resolves a handle like `get`, loading the asset again first if it was evicted to stay under the memory budget.
+    pub fn load<T: StoredAsset + ?Sized>(&mut self, handle: Handle<T>) -> Result<Rc<T>, AssetLoadError> {
+        if let Some(asset) = self.get(handle) {
+            return Ok(asset);
+        }
+        let storage = T::storage(self);
+        let (name, reference) = match (storage.name(handle), storage.reference(handle)) {
+            (Some(name), Some(reference)) => (name.to_string(), reference.clone()),
+            _ => return Err(AssetLoadError::StaleHandle),
+        };
+        if self.loads.is_loading(&reference) {
+            return Err(AssetLoadError::StillLoading(name));
+        }
+        if self.loads.is_failed(&reference) {
+            return Err(AssetLoadError::LoadFailed(name));
+        }
+        let recipe = self.recipes.get(&reference).cloned().ok_or_else(|| AssetLoadError::NotReloadable(name.clone()))?;
+        let asset = T::rebuild(self, &name, &recipe)?;
+        T::storage_mut(self).restore(handle, asset.clone());
+        self.enforce_budget();
+        Ok(asset)
+    }
+
+    pub fn is_live<T: StoredAsset + ?Sized>(&self, handle: Handle<T>) -> bool {
+        T::storage(self).is_live(handle)
+    }
+
+    /// Drops the container's copy of an asset; it's freed once nothing else holds on to it. Its handles go stale either way.
+    pub fn unload<T: StoredAsset + ?Sized>(&mut self, handle: Handle<T>) -> Option<Rc<T>> {
+        let reference = T::storage(self).reference(handle).cloned()?;
+        let asset = T::storage_mut(self).remove(handle);
+        if T::storage(self).find_reference(&reference).is_none() {
+            self.recipes.remove(&reference);
+            self.dependencies.remove(&reference);
+            self.loads.forget(&reference);
+        }
+        asset
+    }
+
+    /// Where the asset behind a handle was loaded from.
+    pub fn reference<T: StoredAsset + ?Sized>(&self, handle: Handle<T>) -> Option<AssetReference> {
+        T::storage(self).reference(handle).cloned()
+    }
+
+    /// The handle of the asset currently loaded from `reference`, if any.
+    pub fn resolve<T: StoredAsset + ?Sized>(&self, reference: &AssetReference) -> Option<Handle<T>> {
+        T::storage(self).find_reference(reference)
+    }
+
+    pub fn storage<T: StoredAsset + ?Sized>(&self) -> &AssetStorage<T> {
+        T::storage(self)
+    }
+
+    /// Which loaded assets use which, by reference.
+    pub fn dependencies(&self) -> &DependencyGraph {
+        &self.dependencies
+    }
+
+    /// Every file assets are loaded from is read through this.
+    pub fn vfs(&self) -> &Vfs {
+        &self.vfs
+    }
+
+    /// Mounts a layer over the asset files, such as a resource pack. Its files replace the ones with the same path
+    /// in every layer mounted before it; `poll_changes` reloads the assets that were loaded from those.
+    pub fn mount<S: ToString>(&mut self, name: S, layer: Layer) {
+        self.vfs.mount(name, layer);
+    }
+
+    /// Takes a layer off again; `poll_changes` reloads the assets it overrode from the layers below.
+    pub fn unmount(&mut self, name: &str) -> bool {
+        self.vfs.unmount(name)
+    }
+
+    /// Mounts a packed archive (see `ace-pack`) as a layer named after its path.
+    pub fn mount_archive<P: AsRef<Path>>(&mut self, archive_path: P) -> Result<(), ArchiveError> {
+        let archive = Archive::open(archive_path.as_ref())?;
+        self.mount(archive_path.as_ref().display(), Layer::Archive(archive));
+        Ok(())
+    }
+
+    /// Reads a file out of the highest mounted layer that has it.
+    pub fn read_asset<P: AsRef<Path>>(&self, location: P) -> io::Result<Cow<'_, [u8]>> {
+        self.vfs.read(location)
+    }
+
+    /// The asset a GUID from a `.meta` sidecar (see `meta`) belongs to, if it has been loaded since the container was made.
+    /// For files that hold several assets, like OBJ and glTF files, it's the reference of the file, which the assets
+    /// in it are named after (`location#name`).
+    pub fn find_guid(&self, guid: &Uuid) -> Option<&AssetReference> {
+        self.guids.get(guid)
+    }
+
+    /// The GUID from the sidecar of a loaded asset, if it has one.
+    pub fn guid_of(&self, reference: &AssetReference) -> Option<Uuid> {
+        self.guids.iter().find(|(_, known)| *known == reference).map(|(guid, _)| *guid)
+    }
+
+    /// Records the GUID of the file an asset was loaded from. Loading already read the sidecar, so a broken one
+    /// was reported then.
+    fn remember_guid(&mut self, path: &Path, reference: &AssetReference) {
+        if let Ok(Some(meta)) = AssetMeta::read(&self.vfs, path) {
+            self.guids.entry(meta.guid).or_insert_with(|| reference.clone());
+        }
+    }
+
+    fn reference_for(&self, path: &Path, ty: AssetType) -> AssetReference {
+        match normalize(path) {
+            Some(location) => AssetReference::new(location, ty),
+            None => AssetReference::new(path.to_string_lossy(), ty),
+        }
+    }
+
+    pub fn add_program<S: AsRef<Path>, V: ToString>(&mut self, name: V, vertex_name: S, fragment_name: S, geometry_name: Option<S>) -> Result<Handle<CompiledShaderProgram>, ShaderCompileError> {
         // Generate the asset paths
-        let mut vs_path = self.asset_root.clone();
-        vs_path.push("shaders");
-        vs_path.push(vertex_name);
+        let shader_path = |shader_name: S| Path::new("shaders").join(shader_name);
+        let vs_path = shader_path(vertex_name);
+        let fs_path = shader_path(fragment_name);
+        let gs_path = geometry_name.map(shader_path);
+
+        self.insert_program(name.to_string(), vs_path, fs_path, gs_path, Vec::new())
+    }
+
+    /// The program `name` compiled with extra `#define`s, stored as `name#A=1,B`. Compiled the first time it's asked for,
+    /// from the same shader files as `name`, which has to be loaded already. Without defines this is `name` itself.
+    pub fn program_variant(&mut self, name: &str, defines: &[(String, String)]) -> Result<Handle<CompiledShaderProgram>, AssetLoadError> {
+        let base = self.find_program(name).ok_or_else(|| AssetLoadError::UnknownProgram(name.to_string()))?;
+        if defines.is_empty() {
+            return Ok(base);
+        }
+        let variant_name = format!("{}#{}", name, defines.iter()
+            .map(|(define, value)| if value.is_empty() { define.clone() } else { format!("{}={}", define, value) })
+            .collect::<Vec<_>>()
+            .join(","));
+        if let Some(variant) = self.find_program(&variant_name) {
+            return Ok(variant);
+        }
+
+        let recipe = self.reference(base).and_then(|reference| self.recipes.get(&reference).cloned());
+        match recipe {
+            Some(Recipe::Program { vertex, fragment, geometry, defines: base_defines }) => {
+                let mut all_defines = base_defines;
+                all_defines.extend(defines.iter().cloned());
+                Ok(self.insert_program(variant_name, vertex, fragment, geometry, all_defines)?)
+            },
+            _ => Err(AssetLoadError::NotReloadable(name.to_string())),
+        }
+    }
 
-        let mut fs_path = self.asset_root.clone();
-        fs_path.push("shaders");
-        fs_path.push(fragment_name);
+    fn insert_program(&mut self, name: String, vs_path: PathBuf, fs_path: PathBuf, gs_path: Option<PathBuf>, defines: Vec<(String, String)>) -> Result<Handle<CompiledShaderProgram>, ShaderCompileError> {
+        let csp = self.compile_program(&name, &vs_path, &fs_path, gs_path.as_deref(), &defines)?;
+        let reference = AssetReference::new(name.clone(), AssetType::Program);
+        let shaders = [(&vs_path, ShaderType::VertexShader), (&fs_path, ShaderType::FragmentShader)];
+        for (path, ty) in shaders.into_iter().chain(gs_path.as_ref().map(|path| (path, ShaderType::GeometryShader))) {
+            let shader = self.reference_for(path, AssetType::Shader(ty));
+            self.dependencies.add(reference.clone(), shader);
+        }
+        self.recipes.insert(reference.clone(), Recipe::Program {
+            vertex: vs_path,
+            fragment: fs_path,
+            geometry: gs_path,
+            defines: defines,
+        }, &self.vfs);
+        Ok(self.shader_programs.insert(name, reference, Rc::new(csp)))
+    }
+
+    fn compile_program(&self, name: &str, vs_path: &Path, fs_path: &Path, gs_path: Option<&Path>, defines: &[(String, String)]) -> Result<CompiledShaderProgram, ShaderCompileError> {
+        let read_source = |path: &Path| -> io::Result<String> {
+            Ok(inject_defines(&self.vfs.read_to_string(path)?, defines))
+        };
 
         // Build and compile the vertex shader
-        let vs_shdr = Shader::from_path(self.gl_ctx().clone(), vs_path, ShaderType::VertexShader)?;
+        let vs_shdr = Shader::new(self.gl_ctx().clone(), &read_source(vs_path)?, ShaderType::VertexShader);
         vs_shdr.compile_shader()?;
 
         // Build and compile the fragment shader
-        let fs_shdr = Shader::from_path(self.gl_ctx().clone(), fs_path, ShaderType::FragmentShader)?;
+        let fs_shdr = Shader::new(self.gl_ctx().clone(), &read_source(fs_path)?, ShaderType::FragmentShader);
         fs_shdr.compile_shader()?;
 
-        // Attach the vertex and fragment shader
+        // Build the geometry shader, if there is one
+        let gs_shdr = match gs_path {
+            Some(gs_path) => Some(Shader::new(self.gl_ctx().clone(), &read_source(gs_path)?, ShaderType::GeometryShader)),
+            None => None,
+        };
+
+        // Initialize new shader program to attach shaders to
+        // This has to come after the shaders, since the program borrows them and deletes itself on drop.
+        let mut shdr_prog = ShaderProgram::new(self.gl_ctx());
+
+        // Attach the vertex, fragment and geometry shader
         shdr_prog.attach_shader(&vs_shdr)?;
         shdr_prog.attach_shader(&fs_shdr)?;
+        if let Some(gs_shdr) = &gs_shdr {
+            shdr_prog.attach_shader(gs_shdr)?;
+        }
 
-        match geometry_name {
-            Some(geo_internal_name) => {
-                let mut gs_loc = self.asset_root.clone();
-                gs_loc.push("shaders");
-                gs_loc.push(geo_internal_name);
-                let gs_shdr = Shader::from_path(self.gl_ctx().clone(), gs_loc, ShaderType::GeometryShader)?;
-                shdr_prog.attach_shader(&gs_shdr)?;
-                let csp = CompiledShaderProgram::compile_shader(self.gl_ctx().clone(), shdr_prog).map_err(|(err, _)| err)?;
-                let csp_rc = Rc::new(csp);
-                self.shader_programs.insert(name.to_string(), csp_rc.clone());
-                Ok(csp_rc)
-            },
+        let csp = CompiledShaderProgram::compile_shader(self.gl_ctx().clone(), shdr_prog).map_err(|(err, _)| err)?;
+        csp.set_label(name);
+        Ok(csp)
+    }
+
+    pub fn find_program(&self, name: &str) -> Option<Handle<CompiledShaderProgram>> {
+        self.shader_programs.find(name)
+    }
+
+    pub fn add_texture<S: AsRef<Path>, V: ToString>(&mut self, name: V, texture_name: S, texture_cfg: TexConfig) -> Result<Handle<Texture>, TextureError> {
+        // Generate the asset path
+        let tex_path = Path::new("textures").join(texture_name);
+        self.add_texture_file(name.to_string(), tex_path, texture_cfg)
+    }
+
+    /// Loads the texture an id names, stored under the id.
+    pub fn add_texture_id(&mut self, id: &AssetId, texture_cfg: TexConfig) -> Result<Handle<Texture>, TextureError> {
+        let tex_path = id.find_file(AssetType::Texture, &self.vfs)?;
+        self.add_texture_file(id.to_string(), tex_path, texture_cfg)
+    }
+
+    fn add_texture_file(&mut self, name: String, tex_path: PathBuf, texture_cfg: TexConfig) -> Result<Handle<Texture>, TextureError> {
+        let new_tex = self.read_texture(&tex_path, texture_cfg.clone())?;
+        new_tex.set_label(&name);
+
+        let reference = self.reference_for(&tex_path, AssetType::Texture);
+        self.remember_guid(&tex_path, &reference);
+        self.recipes.insert(reference.clone(), Recipe::Texture { path: tex_path, config: texture_cfg }, &self.vfs);
+        Ok(self.textures.insert(name, reference, Rc::new(new_tex)))
+    }
+
+    pub fn add_cubemap<S: AsRef<Path>, V: ToString>(&mut self, name: V, cm_location: S, texture_cfg: TexConfig) -> Result<Handle<Texture>, TextureError> {
+        let tex_path = Path::new("textures").join(cm_location);
+        self.add_cubemap_directory(name.to_string(), tex_path, texture_cfg)
+    }
+
+    /// Loads the cubemap whose faces are in the directory an id names, stored under the id.
+    pub fn add_cubemap_id(&mut self, id: &AssetId, texture_cfg: TexConfig) -> Result<Handle<Texture>, TextureError> {
+        let tex_path = id.find_file(AssetType::Cubemap, &self.vfs)?;
+        self.add_cubemap_directory(id.to_string(), tex_path, texture_cfg)
+    }
+
+    fn add_cubemap_directory(&mut self, name: String, tex_path: PathBuf, texture_cfg: TexConfig) -> Result<Handle<Texture>, TextureError> {
+        texture_cfg.validate()?;
+        let new_tex = Texture::cubemap_from_images(self.gl_ctx(), read_cubemap(&self.vfs, &tex_path)?, texture_cfg.clone())?;
+        new_tex.set_label(&name);
+
+        let reference = self.reference_for(&tex_path, AssetType::Cubemap);
+        self.recipes.insert(reference.clone(), Recipe::Cubemap { directory: tex_path, config: texture_cfg }, &self.vfs);
+        Ok(self.textures.insert(name, reference, Rc::new(new_tex)))
+    }
+
+    pub fn find_texture(&self, name: &str) -> Option<Handle<Texture>> {
+        self.textures.find(name)
+    }
+
+    /// Loads the texture's cooked form instead of decoding it (see `cook`), if there is one the driver can sample.
+    /// The texture's sidecar, if it has one, has the last word on the config.
+    fn read_texture(&self, tex_path: &Path, texture_cfg: TexConfig) -> Result<Texture, TextureError> {
+        let texture_cfg = self.texture_import(tex_path, texture_cfg)?;
+        if let Some((cooked_path, format)) = self.cooked_texture(tex_path, &texture_cfg) {
+            let cooked = CookedTexture::from_bytes(&self.vfs.read(&cooked_path)?)?;
+            return upload_cooked(self.gl_ctx(), format, &cooked, texture_cfg);
+        }
+        Texture::from_memory(self.gl_ctx(), &self.vfs.read(tex_path)?, texture_cfg)
+    }
+
+    /// `texture_cfg` with the import settings from the texture's sidecar applied.
+    fn texture_import(&self, tex_path: &Path, texture_cfg: TexConfig) -> Result<TexConfig, TextureError> {
+        Ok(match AssetMeta::read(&self.vfs, tex_path)? {
+            Some(meta) => meta.texture_import().apply(texture_cfg),
+            None => texture_cfg,
+        })
+    }
+
+    /// Where the cooked form of a texture is and the format to upload it in, if it should be loaded instead of the texture.
+    /// The format is the opaque one; see `upload_cooked` for cooked textures with alpha.
+    fn cooked_texture(&self, tex_path: &Path, texture_cfg: &TexConfig) -> Option<(PathBuf, CompressedFormat)> {
+        let format = match texture_cfg.internal_fmt {
+            InternalStorage::Std_RedGreenBlue8 | InternalStorage::Std_RedGreenBlueAlpha8 => CompressedFormat::Bc1Srgb,
+            _ => CompressedFormat::Bc1,
+        };
+        let cooked_path = cooked_texture_path(tex_path);
+        let usable = texture_cfg.tex_type == TextureType::Texture2D
+            && format.is_supported(self.gl_context.caps())
+            && self.prefers_cooked(tex_path, &cooked_path);
+        Some((cooked_path, format)).filter(|_| usable)
+    }
+
+    /// Whether to load a file's cooked form rather than the file itself: the cooked file is there, and it isn't on a lower
+    /// layer than the source, which would mean the source was overridden after it was cooked.
+    fn prefers_cooked(&self, source: &Path, cooked: &Path) -> bool {
+        match (self.vfs.layer_index(cooked), self.vfs.layer_index(source)) {
+            (Some(cooked), Some(source)) => cooked >= source,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+
+    /// Adds a material that wasn't loaded from a file, referred to by its name, along with its textures.
+    /// Its texture paths, like those of one converted from a `tobj::Material`, are relative to `texture_dir`
+    /// (relative to the asset root).
+    pub fn add_material<P: AsRef<Path>>(&mut self, mat: Material, texture_dir: P) -> Result<Handle<Material>, AssetLoadError> {
+        let reference = AssetReference::new(mat.name.clone(), AssetType::Material);
+        let mat = Rc::new(mat);
+        self.add_material_textures(&reference, &mat, texture_dir.as_ref())?;
+        Ok(self.materials.insert(mat.name.clone(), reference, mat))
+    }
+
+    /// Adds every material of an MTL library (relative to the asset root) as `library.mtl#material`, along with their
+    /// textures. Keying them by their library keeps same-named materials from different files apart.
+    pub fn add_mtl<S: AsRef<Path>>(&mut self, mtl_location: S) -> Result<Vec<Handle<Material>>, AssetLoadError> {
+        let mtl_path = mtl_location.as_ref();
+
+        let (materials, _) = read_mtl(&self.vfs, mtl_path).map_err(AssetLoadError::MaterialError)?;
+        let library = self.reference_for(mtl_path, AssetType::Material);
+        let mtl_dir = mtl_path.parent().unwrap_or_else(|| Path::new(""));
+        let mut added = Vec::new();
+        for mat in materials {
+            let reference = AssetReference::new(format!("{}#{}", library.location(), mat.name), AssetType::Material);
+            let mat = Rc::new(Material::from(mat));
+            self.add_material_textures(&reference, &mat, mtl_dir)?;
+            added.push(self.materials.insert(reference.location().to_string(), reference, mat));
+        }
+        Ok(added)
+    }
+
+    /// Loads the textures a material from a library in `mtl_dir` refers to, unless they're loaded already.
+    fn add_material_textures(&mut self, reference: &AssetReference, mat: &Material, mtl_dir: &Path) -> Result<(), TextureError> {
+        for &slot in MaterialSlot::ALL.iter() {
+            let tex_path = match mat.texture_path(slot) {
+                Some(tex_path) => mtl_dir.join(tex_path),
+                None => continue,
+            };
+            let color_space = if slot.is_color() { ColorSpace::Srgb } else { ColorSpace::Linear };
+            let (texture, tex) = self.shared_texture(&tex_path, color_space)?;
+            mat.set_texture(slot.sampler(), tex);
+            self.dependencies.add(reference.clone(), texture);
+        }
+        Ok(())
+    }
+
+    /// A texture that several assets can use, named after its file like the images of glTF files are.
+    fn shared_texture(&mut self, tex_path: &Path, color_space: ColorSpace) -> Result<(AssetReference, Rc<Texture>), TextureError> {
+        let location = normalize(tex_path).unwrap_or_else(|| tex_path.to_string_lossy().into_owned());
+        let location = match color_space {
+            ColorSpace::Srgb => format!("{}#srgb", location),
+            ColorSpace::Linear => location,
+        };
+        let reference = AssetReference::new(location.clone(), AssetType::Texture);
+        let existing = self.textures.find_reference(&reference);
+        if let Some(tex) = existing.and_then(|handle| self.textures.get(handle)) {
+            return Ok((reference, tex.clone()));
+        }
+
+        let tex = Rc::new(self.read_texture(tex_path, color_space.tex_config())?);
+        tex.set_label(&location);
+        // An evicted texture comes back under its old handle.
+        match existing {
+            Some(handle) => { self.textures.restore(handle, tex.clone()); },
             None => {
-                let csp = CompiledShaderProgram::compile_shader(self.gl_ctx().clone(), shdr_prog).map_err(|(err, _)| err)?;
-                let csp_rc = Rc::new(csp);
-                self.shader_programs.insert(name.to_string(), csp_rc.clone());
-                Ok(csp_rc)
+                self.remember_guid(tex_path, &reference);
+                self.recipes.insert(reference.clone(), Recipe::Texture { path: tex_path.to_path_buf(), config: color_space.tex_config() }, &self.vfs);
+                self.textures.insert(location, reference.clone(), tex.clone());
             },
         }
+        Ok((reference, tex))
     }
 
-    pub fn find_program(&self, name: &str) -> Option<Rc<CompiledShaderProgram>> {
-        self.shader_programs.get(name).map(|csp| csp.clone())
+    /// Loads the material file (see `MaterialFile`) an id names, stored under the id. Its program has to be loaded already;
+    /// the variant its defines ask for and any of its textures that aren't loaded yet are loaded along with it.
+    pub fn add_material_file(&mut self, id: &AssetId) -> Result<Handle<Material>, AssetLoadError> {
+        let path = id.find_file(AssetType::Material, &self.vfs)?;
+        let reference = self.reference_for(&path, AssetType::Material);
+        let material = self.read_material_file(&id.to_string(), &reference, &path)?;
+        self.recipes.insert(reference.clone(), Recipe::Material { path: path }, &self.vfs);
+        Ok(self.materials.insert(id.to_string(), reference, material))
     }
 
-    pub fn add_texture<S: AsRef<Path>, V: ToString>(&mut self, name: V, texture_name: S, texture_cfg: TexConfig) -> Result<Rc<Texture>, TextureError> {
-        // Generate the asset path
-        let mut tex_path = self.asset_root.clone();
-        tex_path.push("textures");
-        tex_path.push(texture_name);
+    /// Builds the material a material file describes, recording what it uses.
+    fn read_material_file(&mut self, name: &str, reference: &AssetReference, path: &Path) -> Result<Rc<Material>, AssetLoadError> {
+        let file = MaterialFile::from_slice(&self.vfs.read(path)?)?;
+        let program_handle = self.program_variant(&file.program.to_string(), &file.define_list())?;
+        let program = self.load(program_handle)?;
+        let program_reference = self.reference(program_handle).ok_or(AssetLoadError::StaleHandle)?;
+
+        let mut material = Material::new(name).with_render_state(file.render_state.render_state());
+        for (parameter, value) in &file.parameters {
+            material = material.with_parameter(parameter, *value);
+        }
+        material.set_program(program);
 
-        let new_tex = Rc::new(Texture::from_file(self.gl_ctx(), tex_path, texture_cfg)?);
+        // What it used before it was rebuilt may not be what it uses now.
+        self.dependencies.remove(reference);
+        self.dependencies.add(reference.clone(), program_reference);
+        for (sampler, binding) in &file.textures {
+            let (texture, tex) = match self.find_texture(&binding.texture.to_string()) {
+                Some(handle) => {
+                    let tex = self.load(handle)?;
+                    let texture = self.reference(handle).ok_or(AssetLoadError::StaleHandle)?;
+                    (texture, tex)
+                },
+                None => {
+                    let tex_path = binding.texture.find_file(AssetType::Texture, &self.vfs)?;
+                    let color_space = if binding.srgb { ColorSpace::Srgb } else { ColorSpace::Linear };
+                    self.shared_texture(&tex_path, color_space)?
+                },
+            };
+            material.set_texture(sampler, tex);
+            self.dependencies.add(reference.clone(), texture);
+        }
+        Ok(Rc::new(material))
+    }
+
+    pub fn find_material(&self, name: &str) -> Option<Handle<Material>> {
+        self.materials.find(name)
+    }
+
+    /// Loads every mesh of an OBJ file (relative to the asset root). Meshes are stored as `name/mesh`, and the materials
+    /// from the file's MTL libraries are added as `file.obj#material`, drawn with `program` since MTL can't name one.
+    pub fn add_obj_model<S: AsRef<Path>, V: ToString>(&mut self, name: V, model_location: S, program: Rc<CompiledShaderProgram>) -> Result<Vec<Handle<dyn Model>>, ObjLoadError> {
+        let model_path = model_location.as_ref().to_path_buf();
+
+        let (models, materials) = self.read_obj(&model_path)?;
+        let file = self.reference_for(&model_path, AssetType::Model);
+        self.remember_guid(&model_path, &file);
+        let obj_dir = model_path.parent().unwrap_or_else(|| Path::new(""));
+        for mat in materials {
+            mat.set_program(program.clone());
+            let reference = AssetReference::new(format!("{}#{}", file.location(), mat.name), AssetType::Material);
+            self.add_material_textures(&reference, &mat, obj_dir)?;
+            self.materials.insert(reference.location().to_string(), reference, mat);
+        }
+
+        let mut added = Vec::new();
+        for model in models {
+            let model_name = format!("{}/{}", name.to_string(), model.name());
+            let reference = AssetReference::new(format!("{}#{}", file.location(), model.name()), AssetType::Model);
+            if let Some(mat) = model.material() {
+                let material = AssetReference::new(format!("{}#{}", file.location(), mat.name), AssetType::Material);
+                self.dependencies.add(reference.clone(), material);
+            }
+            self.recipes.insert(reference.clone(), Recipe::ObjMesh {
+                path: model_path.clone(),
+                mesh: model.name().to_string(),
+            }, &self.vfs);
+            let model: Rc<dyn Model> = Rc::new(model);
+            added.push(self.models.insert(model_name, reference, model));
+        }
+        Ok(added)
+    }
 
-        self.textures.insert(name.to_string(), new_tex.clone());
-        Ok(new_tex)
+    pub fn find_model(&self, name: &str) -> Option<Handle<dyn Model>> {
+        self.models.find(name)
     }
 
-    pub fn add_cubemap<S: AsRef<Path>, V: ToString>(&mut self, name: V, cm_location: S, texture_cfg: TexConfig) -> Result<Rc<Texture>, TextureError> {
-        let mut tex_path = self.asset_root.clone();
-        tex_path.push("textures");
-        tex_path.push(cm_location.as_ref());
+    /// Reads an OBJ file along with the material libraries it refers to, which are looked for next to it.
+    /// Its meshes are converted as its sidecar says, or its cooked form, which was converted when it was cooked, is read
+    /// instead if there is one (see `cook`).
+    fn read_obj(&self, model_path: &Path) -> Result<(Vec<ObjModel>, Vec<Rc<Material>>), ObjLoadError> {
+        let obj_dir = model_path.parent().unwrap_or_else(|| Path::new(""));
+        let load_mtl = |mtl: &Path| read_mtl(&self.vfs, &obj_dir.join(mtl));
+        let cooked_path = cooked_model_path(model_path);
+        if self.prefers_cooked(model_path, &cooked_path) {
+            let cooked = CookedModel::from_bytes(&self.vfs.read(&cooked_path)?)?;
+            return ObjModel::from_cooked(self.gl_ctx(), &cooked, load_mtl);
+        }
+        let import = AssetMeta::read(&self.vfs, model_path)?.map(|meta| meta.mesh_import()).unwrap_or_default();
+        ObjModel::from_bytes(self.gl_ctx(), &self.vfs.read(model_path)?, load_mtl, &import)
+    }
 
-        let new_tex = Rc::new(Texture::cubemap_from_files(self.gl_ctx(), CubemapPaths::from_directory(tex_path)?, texture_cfg)?);
+    /// Loads a .gltf or .glb file (relative to the asset root).
+    /// Images go through the texture store, named after their reference (`file#imageN` when embedded, with `#srgb`
+    /// added for color textures), so files that share an image share the texture. Primitives are stored as models named `name/mesh#N`.
+    pub fn add_gltf<S: AsRef<Path>, V: ToString>(&mut self, name: V, gltf_location: S) -> Result<Handle<GltfAsset>, GltfError> {
+        let gltf_path = gltf_location.as_ref().to_path_buf();
 
-        self.textures.insert(name.to_string(), new_tex.clone());
-        Ok(new_tex)
+        let decoded = DecodedGltf::new(open_gltf(&self.vfs, &gltf_path)?)?;
+        let asset = Rc::new(self.load_gltf_file(&gltf_path, decoded)?);
+        let file = self.reference_for(&gltf_path, AssetType::Model);
+        self.remember_guid(&gltf_path, &file);
+        self.add_gltf_primitives(&name.to_string(), &gltf_path, &file, &asset);
+        self.recipes.insert(file.clone(), Recipe::Gltf { path: gltf_path }, &self.vfs);
+        Ok(self.gltf_assets.insert(name.to_string(), file, asset))
     }
 
-    pub fn find_texture(&self, name: &str) -> Option<Rc<Texture>> {
-        self.textures.get(name).map(|tex| tex.clone())
+    /// Stores every primitive of a glTF file as a model.
+    fn add_gltf_primitives(&mut self, name: &str, gltf_path: &Path, file: &AssetReference, asset: &GltfAsset) {
+        for (mesh_idx, mesh) in asset.meshes.iter().enumerate() {
+            for (idx, primitive) in mesh.primitives.iter().enumerate() {
+                let model: Rc<dyn Model> = primitive.model.clone();
+                let reference = AssetReference::new(format!("{}#{}/{}", file.location(), mesh.name, idx), AssetType::Model);
+                self.dependencies.add(file.clone(), reference.clone());
+                self.recipes.insert(reference.clone(), Recipe::GltfPrimitive {
+                    gltf: gltf_path.to_path_buf(),
+                    mesh: mesh_idx,
+                    primitive: idx,
+                }, &self.vfs);
+                self.models.insert(format!("{}/{}#{}", name, mesh.name, idx), reference, model);
+            }
+        }
     }
 
-    pub fn add_material(&mut self, mat: Material) {
-        self.materials.insert(mat.name.clone(), Rc::new(mat));
+    /// Uploads a read glTF file, resolving its images through the texture store. Images it already decoded are used as they are.
+    fn load_gltf_file(&mut self, gltf_path: &Path, mut decoded: DecodedGltf) -> Result<GltfAsset, GltfError> {
+        let mut images = decoded.take_images();
+        let gl_ctx = self.gl_ctx();
+        let file = self.reference_for(gltf_path, AssetType::Model);
+        let vfs = &self.vfs;
+        let textures = &mut self.textures;
+        let recipes = &mut self.recipes;
+        let dependencies = &mut self.dependencies;
+        let mut resolve_texture = |image: GltfImage<'_>, color_space: ColorSpace| -> Result<Rc<Texture>, TextureError> {
+            let source = image.source();
+            let location = normalize(&source).unwrap_or_else(|| source.to_string_lossy().into_owned());
+            // The same image can't be shared between color spaces, since the conversion happens in the texture format.
+            let location = match color_space {
+                ColorSpace::Srgb => format!("{}#srgb", location),
+                ColorSpace::Linear => location,
+            };
+            dependencies.add(file.clone(), AssetReference::new(location.clone(), AssetType::Texture));
+            let existing = textures.find(&location);
+            if let Some(tex) = existing.and_then(|handle| textures.get(handle)) {
+                return Ok(tex.clone());
+            }
+
+            let (new_tex, recipe) = match (image, images.remove(&source)) {
+                (GltfImage::File(path), decoded) => (
+                    match decoded {
+                        Some(decoded) => Texture::from_image(gl_ctx.clone(), decoded, color_space.tex_config())?,
+                        None => Texture::from_memory(gl_ctx.clone(), &vfs.read(&path)?, color_space.tex_config())?,
+                    },
+                    Recipe::Texture { path: path, config: color_space.tex_config() },
+                ),
+                (GltfImage::Embedded { index, bytes, .. }, decoded) => (
+                    match decoded {
+                        Some(decoded) => Texture::from_image(gl_ctx.clone(), decoded, color_space.tex_config())?,
+                        None => Texture::from_memory(gl_ctx.clone(), &bytes, color_space.tex_config())?,
+                    },
+                    Recipe::GltfImage { gltf: gltf_path.to_path_buf(), image: index, color_space: color_space },
+                ),
+            };
+            new_tex.set_label(&location);
+            let new_tex = Rc::new(new_tex);
+            // An evicted texture comes back under its old handle.
+            match existing {
+                Some(handle) => { textures.restore(handle, new_tex.clone()); },
+                None => {
+                    let reference = AssetReference::new(location.clone(), AssetType::Texture);
+                    recipes.insert(reference.clone(), recipe, vfs);
+                    textures.insert(location, reference, new_tex.clone());
+                },
+            }
+            Ok(new_tex)
+        };
+
+        GltfAsset::upload(gl_ctx.clone(), decoded, &mut resolve_texture)
+    }
+
+    pub fn find_gltf(&self, name: &str) -> Option<Handle<GltfAsset>> {
+        self.gltf_assets.find(name)
     }
-}
\ No newline at end of file
+}
+
+/// Reads the faces of a cubemap in a directory.
+fn read_cubemap(vfs: &Vfs, directory: &Path) -> Result<CubemapFaces, TextureError> {
+    Texture::decode_cubemap_with(CubemapPaths::in_directory(directory), |face| vfs.read(face))
+}
+
+/// Uploads a cooked texture, in `format`'s BC3 counterpart if it was cooked with alpha.
+fn upload_cooked(gl_ctx: Rc<GLContext>, format: CompressedFormat, cooked: &CookedTexture, texture_cfg: TexConfig) -> Result<Texture, TextureError> {
+    let format = if cooked.alpha { format.with_alpha() } else { format };
+    Texture::from_compressed(gl_ctx, format, cooked.width, cooked.height, &cooked.levels, texture_cfg)
+}
+
+fn read_mtl(vfs: &Vfs, mtl_path: &Path) -> tobj::MTLLoadResult {
+    let mtl = vfs.read(mtl_path).map_err(|_| tobj::LoadError::OpenFileFailed)?;
+    tobj::load_mtl_buf(&mut &mtl[..])
+}
+
+/// Opens a glTF file, with its external buffers and images read from the same layers.
+fn open_gltf(vfs: &Vfs, gltf_path: &Path) -> Result<GltfDocument, GltfError> {
+    GltfDocument::from_slice(gltf_path, &vfs.read(gltf_path)?, &|file| vfs.read(file).map(Cow::into_owned))
+}
+
+/// The asset types an `AssetContainer` keeps, where it keeps them, and how it loads them again.
+pub trait StoredAsset: MemoryFootprint {
+    /// Which part of the memory report the asset counts towards.
+    const CATEGORY: AssetCategory;
+
+    fn storage(assets: &AssetContainer) -> &AssetStorage<Self>;
+    fn storage_mut(assets: &mut AssetContainer) -> &mut AssetStorage<Self>;
+    fn rebuild(assets: &mut AssetContainer, name: &str, recipe: &Recipe) -> Result<Rc<Self>, AssetLoadError>;
+}
+
+macro_rules! stored_asset {
+    ($ty:ty, $field:ident, $category:ident, $build:ident) => {
+        impl StoredAsset for $ty {
+            const CATEGORY: AssetCategory = AssetCategory::$category;
+
+            fn storage(assets: &AssetContainer) -> &AssetStorage<Self> {
+                &assets.$field
+            }
+
+            fn storage_mut(assets: &mut AssetContainer) -> &mut AssetStorage<Self> {
+                &mut assets.$field
+            }
+
+            fn rebuild(assets: &mut AssetContainer, name: &str, recipe: &Recipe) -> Result<Rc<Self>, AssetLoadError> {
+                assets.$build(name, recipe)
+            }
+        }
+    };
+}
+
+stored_asset!(Texture, textures, Textures, build_texture);
+stored_asset!(CompiledShaderProgram, shader_programs, Programs, build_program);
+stored_asset!(dyn Model, models, Models, build_model);
+stored_asset!(Material, materials, Materials, build_material);
+stored_asset!(GltfAsset, gltf_assets, Scenes, build_gltf);
@@ -0,0 +1,228 @@
+use crate::types::*;
+use crate::hl_assets::*;
+use crate::namespace::AssetReference;
+use crate::asset_types::AssetType;
+use crate::cook::{cooked_model_path, cooked_texture_path};
+use crate::meta::meta_path;
+use crate::vfs::{FileVersion, Vfs};
+use super::{open_gltf, read_cubemap, AssetContainer, AssetLoadError};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// How to load an asset again from its source files. The container keeps one for everything it can reload,
+/// keyed by the asset's reference. Paths are read through the container's `Vfs`.
+#[derive(Clone)]
+pub enum Recipe {
+    Texture { path: PathBuf, config: TexConfig },
+    Cubemap { directory: PathBuf, config: TexConfig },
+    /// An image embedded in a glTF file.
+    GltfImage { gltf: PathBuf, image: usize, color_space: ColorSpace },
+    /// `defines` are added to every shader, for variants of a program.
+    Program { vertex: PathBuf, fragment: PathBuf, geometry: Option<PathBuf>, defines: Vec<(String, String)> },
+    ObjMesh { path: PathBuf, mesh: String },
+    Gltf { path: PathBuf },
+    GltfPrimitive { gltf: PathBuf, mesh: usize, primitive: usize },
+    /// A material file; MTL materials are loaded together with their libraries and models, and have no recipe.
+    Material { path: PathBuf },
+}
+
+impl Recipe {
+    /// The files the asset is built from, including the cooked forms and `.meta` sidecars of textures and OBJ files
+    /// whether or not they're there.
+    /// External glTF buffers and OBJ material libraries aren't tracked.
+    pub fn sources(&self) -> Vec<PathBuf> {
+        match self {
+            Recipe::Texture { path, .. } => vec![path.clone(), cooked_texture_path(path), meta_path(path)],
+            Recipe::Cubemap { directory, .. } => {
+                CubemapPaths::in_directory(directory).files().iter().map(|face| face.to_path_buf()).collect()
+            },
+            Recipe::GltfImage { gltf, .. } => vec![gltf.clone()],
+            Recipe::Program { vertex, fragment, geometry, .. } => {
+                let mut sources = vec![vertex.clone(), fragment.clone()];
+                sources.extend(geometry.clone());
+                sources
+            },
+            Recipe::ObjMesh { path, .. } => vec![path.clone(), cooked_model_path(path), meta_path(path)],
+            Recipe::Gltf { path, .. } => vec![path.clone()],
+            Recipe::GltfPrimitive { gltf, .. } => vec![gltf.clone()],
+            Recipe::Material { path } => vec![path.clone()],
+        }
+    }
+}
+
+/// The container's recipes by asset reference, along with which version of each of their source files they were built from.
+#[derive(Default)]
+pub(crate) struct RecipeBook {
+    recipes: HashMap<AssetReference, Recipe>,
+    // None for files no layer has.
+    versions: HashMap<PathBuf, Option<FileVersion>>,
+}
+
+impl RecipeBook {
+    pub fn insert(&mut self, reference: AssetReference, recipe: Recipe, vfs: &Vfs) {
+        for source in recipe.sources() {
+            let version = vfs.version(&source);
+            self.versions.insert(source, version);
+        }
+        self.recipes.insert(reference, recipe);
+    }
+
+    pub fn get(&self, reference: &AssetReference) -> Option<&Recipe> {
+        self.recipes.get(reference)
+    }
+
+    pub fn contains_key(&self, reference: &AssetReference) -> bool {
+        self.recipes.contains_key(reference)
+    }
+
+    pub fn remove(&mut self, reference: &AssetReference) -> Option<Recipe> {
+        self.recipes.remove(reference)
+    }
+
+    /// Source files that were modified, created or deleted since they were last looked at, or that come from
+    /// a different layer now because one was mounted or unmounted.
+    pub fn changed_files(&mut self, vfs: &Vfs) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        for (path, version) in self.versions.iter_mut() {
+            let now = vfs.version(path);
+            if now != *version {
+                *version = now;
+                changed.push(path.clone());
+            }
+        }
+        changed
+    }
+
+    /// Every recipe built from any of `files`.
+    pub fn built_from(&self, files: &[PathBuf]) -> Vec<(AssetReference, Recipe)> {
+        self.recipes.iter()
+            .filter(|(_, recipe)| recipe.sources().iter().any(|source| files.contains(source)))
+            .map(|(reference, recipe)| (reference.clone(), recipe.clone()))
+            .collect()
+    }
+}
+
+impl AssetContainer {
+    pub(crate) fn build_texture(&mut self, name: &str, recipe: &Recipe) -> Result<Rc<Texture>, AssetLoadError> {
+        let tex = match recipe {
+            Recipe::Texture { path, config } => self.read_texture(path, config.clone())?,
+            Recipe::Cubemap { directory, config } => {
+                config.validate()?;
+                Texture::cubemap_from_images(self.gl_ctx(), read_cubemap(&self.vfs, directory)?, config.clone())?
+            },
+            Recipe::GltfImage { gltf, image, color_space } => match open_gltf(&self.vfs, gltf)?.image(*image)? {
+                GltfImage::File(path) => self.read_texture(&path, color_space.tex_config())?,
+                GltfImage::Embedded { bytes, .. } => Texture::from_memory(self.gl_ctx(), &bytes, color_space.tex_config())?,
+            },
+            _ => return Err(AssetLoadError::NotReloadable(name.to_string())),
+        };
+        tex.set_label(name);
+        Ok(Rc::new(tex))
+    }
+
+    pub(crate) fn build_program(&mut self, name: &str, recipe: &Recipe) -> Result<Rc<CompiledShaderProgram>, AssetLoadError> {
+        match recipe {
+            Recipe::Program { vertex, fragment, geometry, defines } => {
+                Ok(Rc::new(self.compile_program(name, vertex, fragment, geometry.as_deref(), defines)?))
+            },
+            _ => Err(AssetLoadError::NotReloadable(name.to_string())),
+        }
+    }
+
+    pub(crate) fn build_model(&mut self, name: &str, recipe: &Recipe) -> Result<Rc<dyn Model>, AssetLoadError> {
+        match recipe {
+            Recipe::ObjMesh { path, mesh } => {
+                // tobj can only load whole files; the other meshes are dropped again straight away.
+                let (models, _) = self.read_obj(path)?;
+                let model = models.into_iter()
+                    .find(|model| model.name() == mesh)
+                    .ok_or_else(|| AssetLoadError::NotReloadable(name.to_string()))?;
+                Ok(Rc::new(model))
+            },
+            Recipe::GltfPrimitive { gltf, mesh, primitive } => {
+                let model = open_gltf(&self.vfs, gltf)?.load_primitive(self.gl_ctx(), *mesh, *primitive)?;
+                Ok(Rc::new(model))
+            },
+            _ => Err(AssetLoadError::NotReloadable(name.to_string())),
+        }
+    }
+
+    pub(crate) fn build_gltf(&mut self, name: &str, recipe: &Recipe) -> Result<Rc<GltfAsset>, AssetLoadError> {
+        match recipe {
+            Recipe::Gltf { path } => {
+                let decoded = DecodedGltf::new(open_gltf(&self.vfs, path)?)?;
+                Ok(Rc::new(self.load_gltf_file(path, decoded)?))
+            },
+            _ => Err(AssetLoadError::NotReloadable(name.to_string())),
+        }
+    }
+
+    pub(crate) fn build_material(&mut self, name: &str, recipe: &Recipe) -> Result<Rc<Material>, AssetLoadError> {
+        match recipe {
+            Recipe::Material { path } => {
+                let reference = self.reference_for(path, AssetType::Material);
+                self.read_material_file(name, &reference, path)
+            },
+            _ => Err(AssetLoadError::NotReloadable(name.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::Layer;
+    use std::fs::{self, File};
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+
+    fn touch(path: &Path, seconds: u64) {
+        let file = File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds)).unwrap();
+    }
+
+    #[test]
+    fn touched_sources_are_picked_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let vertex = PathBuf::from("basic.vert");
+        let fragment = PathBuf::from("basic.frag");
+        fs::write(dir.path().join(&vertex), "void main() {}").unwrap();
+        fs::write(dir.path().join(&fragment), "void main() {}").unwrap();
+        touch(&dir.path().join(&vertex), 1000);
+        touch(&dir.path().join(&fragment), 1000);
+        let mut vfs = Vfs::new();
+        vfs.mount("base", Layer::Directory(dir.path().to_path_buf()));
+
+        let mut recipes = RecipeBook::default();
+        let reference = AssetReference::new("basic", AssetType::Program);
+        recipes.insert(reference.clone(), Recipe::Program { vertex: vertex.clone(), fragment: fragment.clone(), geometry: None, defines: Vec::new() }, &vfs);
+        assert_eq!(recipes.changed_files(&vfs), Vec::<PathBuf>::new());
+
+        touch(&dir.path().join(&fragment), 2000);
+        let changed = recipes.changed_files(&vfs);
+        assert_eq!(changed, vec![fragment.clone()]);
+        let rebuilt: Vec<AssetReference> = recipes.built_from(&changed).into_iter().map(|(reference, _)| reference).collect();
+        assert_eq!(rebuilt, vec![reference]);
+        // Only reported once.
+        assert_eq!(recipes.changed_files(&vfs), Vec::<PathBuf>::new());
+
+        fs::remove_file(dir.path().join(&vertex)).unwrap();
+        assert_eq!(recipes.changed_files(&vfs), vec![vertex]);
+    }
+
+    #[test]
+    fn mounting_an_override_changes_its_sources() {
+        let mut vfs = Vfs::new();
+        vfs.mount("base", Layer::embedded(&[("shaders/basic.vert", b"base"), ("shaders/basic.frag", b"base")]));
+        let mut recipes = RecipeBook::default();
+        let recipe = Recipe::Program { vertex: PathBuf::from("shaders/basic.vert"), fragment: PathBuf::from("shaders/basic.frag"), geometry: None, defines: Vec::new() };
+        recipes.insert(AssetReference::new("basic", AssetType::Program), recipe, &vfs);
+
+        vfs.mount("pack", Layer::embedded(&[("shaders/basic.frag", b"pack")]));
+        assert_eq!(recipes.changed_files(&vfs), vec![PathBuf::from("shaders/basic.frag")]);
+        vfs.unmount("pack");
+        assert_eq!(recipes.changed_files(&vfs), vec![PathBuf::from("shaders/basic.frag")]);
+        assert_eq!(recipes.changed_files(&vfs), Vec::<PathBuf>::new());
+    }
+}
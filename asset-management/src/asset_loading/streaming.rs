@@ -0,0 +1,365 @@
+use crate::types::*;
+use crate::hl_assets::*;
+use crate::handle::Handle;
+use crate::loaded::Loaded;
+use crate::namespace::AssetReference;
+use crate::asset_types::AssetType;
+use super::{open_gltf, read_cubemap, upload_cooked, AssetContainer, AssetLoadError, Recipe, StoredAsset};
+use crate::cook::CookedTexture;
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+type Job<R> = Box<dyn FnOnce() -> R + Send>;
+
+/// Threads that run jobs in the background and hand back what they returned, tagged with the id they were submitted under.
+pub(crate) struct LoadWorkers<R> {
+    jobs: Option<mpsc::Sender<(u64, Job<R>)>>,
+    finished: mpsc::Receiver<(u64, R)>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl<R: Send + 'static> LoadWorkers<R> {
+    pub fn new(count: usize) -> LoadWorkers<R> {
+        let (jobs, queue) = mpsc::channel::<(u64, Job<R>)>();
+        let (done, finished) = mpsc::channel();
+        let queue = Arc::new(Mutex::new(queue));
+        let threads = (0..count).map(|idx| {
+            let queue = queue.clone();
+            let done = done.clone();
+            thread::Builder::new()
+                .name(format!("asset-loader-{}", idx))
+                .spawn(move || loop {
+                    // The lock is only held while waiting for a job, not while running it.
+                    let next = queue.lock().map_err(|_| ()).and_then(|queue| queue.recv().map_err(|_| ()));
+                    let (id, job) = match next {
+                        Ok(job) => job,
+                        Err(()) => return,
+                    };
+                    if done.send((id, job())).is_err() {
+                        return;
+                    }
+                })
+                .expect("failed to spawn an asset loading thread")
+        }).collect();
+
+        LoadWorkers {
+            jobs: Some(jobs),
+            finished: finished,
+            threads: threads,
+        }
+    }
+
+    pub fn submit(&self, id: u64, job: Job<R>) {
+        if let Some(jobs) = &self.jobs {
+            // Only fails if every thread is gone, in which case the load just never finishes.
+            let _ = jobs.send((id, job));
+        }
+    }
+
+    /// Results of jobs that finished since the last call, without waiting for the rest.
+    pub fn finished(&self) -> mpsc::TryIter<'_, (u64, R)> {
+        self.finished.try_iter()
+    }
+}
+
+impl<R> Drop for LoadWorkers<R> {
+    fn drop(&mut self) {
+        // Closing the queue lets every thread finish its current job and exit.
+        self.jobs.take();
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// How many loading threads to start: one fewer than the machine has cores, leaving one to the render thread, and at most four.
+fn worker_count() -> usize {
+    let cores = thread::available_parallelism().map(|cores| cores.get()).unwrap_or(1);
+    cores.saturating_sub(1).clamp(1, 4)
+}
+
+/// What a loading thread hands back for upload.
+pub(crate) enum Decoded {
+    Image(Result<image::DynamicImage, TextureError>),
+    Compressed(CompressedFormat, Result<CookedTexture, TextureError>),
+    Cubemap(Result<CubemapFaces, TextureError>),
+    // Boxed, since a document is much bigger than a decoded image.
+    Gltf(Result<Box<DecodedGltf>, GltfError>),
+}
+
+/// A load that was handed to the loading threads, with what's needed to store it once it's decoded.
+enum PendingLoad {
+    Texture { handle: Handle<Texture>, reference: AssetReference, recipe: Recipe, config: TexConfig },
+    Gltf { handle: Handle<GltfAsset>, reference: AssetReference, path: PathBuf },
+}
+
+enum LoadStatus {
+    Loading,
+    Failed(Rc<AssetLoadError>),
+}
+
+/// Background loads an `AssetContainer` is waiting on, and the state of every asset that was loaded in the background.
+#[derive(Default)]
+pub(crate) struct LoadQueue {
+    // Started with the first background load.
+    workers: Option<LoadWorkers<Decoded>>,
+    next_id: u64,
+    pending: HashMap<u64, PendingLoad>,
+    // Decoded, but not uploaded yet because an earlier frame's upload budget ran out.
+    ready: VecDeque<(u64, Decoded)>,
+    // Only assets that are loading or failed to load; anything else is loaded if the storage has it.
+    states: HashMap<AssetReference, LoadStatus>,
+}
+
+impl LoadQueue {
+    fn submit(&mut self, pending: PendingLoad, job: Job<Decoded>) {
+        let workers = self.workers.get_or_insert_with(|| LoadWorkers::new(worker_count()));
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending.insert(id, pending);
+        workers.submit(id, job);
+    }
+
+    pub fn is_loading(&self, reference: &AssetReference) -> bool {
+        matches!(self.states.get(reference), Some(LoadStatus::Loading))
+    }
+
+    pub fn is_failed(&self, reference: &AssetReference) -> bool {
+        matches!(self.states.get(reference), Some(LoadStatus::Failed(_)))
+    }
+
+    pub fn forget(&mut self, reference: &AssetReference) {
+        self.states.remove(reference);
+    }
+}
+
+/// What one `AssetContainer::finish_loads` did.
+#[derive(Debug, Default)]
+pub struct LoadReport {
+    /// Names of the assets that were uploaded.
+    pub loaded: Vec<String>,
+    /// Assets that failed to load, by name. `load_state` keeps reporting the error afterwards.
+    pub failed: Vec<(String, Rc<AssetLoadError>)>,
+    /// Loads still being decoded or waiting for their upload.
+    pub pending: usize,
+}
+
+impl AssetContainer {
+    /// Like `add_texture`, but the image is read and decoded on a loading thread. Until `finish_loads` uploads it,
+    /// the handle resolves to a grey placeholder, which then turns into the texture in place.
+    pub fn add_texture_async<S: AsRef<Path>, V: ToString>(&mut self, name: V, texture_name: S, texture_cfg: TexConfig) -> Result<Handle<Texture>, TextureError> {
+        let tex_path = Path::new("textures").join(texture_name);
+        // The sidecar is small enough to read right away, and the placeholder needs the config it ends up with.
+        let import_cfg = self.texture_import(&tex_path, texture_cfg.clone())?;
+        import_cfg.validate()?;
+
+        let reference = self.reference_for(&tex_path, AssetType::Texture);
+        self.remember_guid(&tex_path, &reference);
+        let handle = self.add_placeholder(name.to_string(), reference.clone(), import_cfg.clone())?;
+        // Rebuilds read the sidecar again, in case it changed.
+        let recipe = Recipe::Texture { path: tex_path.clone(), config: texture_cfg };
+        let vfs = self.vfs.clone();
+        let job: Job<Decoded> = match self.cooked_texture(&tex_path, &import_cfg) {
+            Some((cooked_path, format)) => Box::new(move || {
+                Decoded::Compressed(format, vfs.read(&cooked_path).and_then(|bytes| CookedTexture::from_bytes(&bytes)).map_err(TextureError::from))
+            }),
+            None => Box::new(move || Decoded::Image(vfs.read(&tex_path).map_err(TextureError::from).and_then(|bytes| image::load_from_memory(&bytes).map_err(TextureError::from)))),
+        };
+        self.loads.submit(PendingLoad::Texture { handle: handle, reference: reference, recipe: recipe, config: import_cfg }, job);
+        Ok(handle)
+    }
+
+    /// Like `add_cubemap`, but the faces are read and decoded on a loading thread, with a placeholder until then.
+    pub fn add_cubemap_async<S: AsRef<Path>, V: ToString>(&mut self, name: V, cm_location: S, texture_cfg: TexConfig) -> Result<Handle<Texture>, TextureError> {
+        texture_cfg.validate()?;
+        let tex_path = Path::new("textures").join(cm_location);
+
+        let reference = self.reference_for(&tex_path, AssetType::Cubemap);
+        let handle = self.add_placeholder(name.to_string(), reference.clone(), texture_cfg.clone())?;
+        let recipe = Recipe::Cubemap { directory: tex_path.clone(), config: texture_cfg.clone() };
+        let vfs = self.vfs.clone();
+        self.loads.submit(
+            PendingLoad::Texture { handle: handle, reference: reference, recipe: recipe, config: texture_cfg },
+            Box::new(move || Decoded::Cubemap(read_cubemap(&vfs, &tex_path))),
+        );
+        Ok(handle)
+    }
+
+    fn add_placeholder(&mut self, name: String, reference: AssetReference, texture_cfg: TexConfig) -> Result<Handle<Texture>, TextureError> {
+        let placeholder = Texture::placeholder(self.gl_ctx(), texture_cfg)?;
+        placeholder.set_label(&name);
+        self.loads.states.insert(reference.clone(), LoadStatus::Loading);
+        Ok(self.textures.insert(name, reference, Rc::new(placeholder)))
+    }
+
+    /// Like `add_gltf`, but the file is read and its primitives and images decoded on a loading thread.
+    /// There's no placeholder for a whole scene: the handle doesn't resolve until `finish_loads` uploads it.
+    pub fn add_gltf_async<S: AsRef<Path>, V: ToString>(&mut self, name: V, gltf_location: S) -> Handle<GltfAsset> {
+        let gltf_path = gltf_location.as_ref().to_path_buf();
+
+        let reference = self.reference_for(&gltf_path, AssetType::Model);
+        let handle = self.gltf_assets.reserve(name.to_string(), reference.clone());
+        self.loads.states.insert(reference.clone(), LoadStatus::Loading);
+        let path = gltf_path.clone();
+        let vfs = self.vfs.clone();
+        self.loads.submit(
+            PendingLoad::Gltf { handle: handle, reference: reference, path: gltf_path },
+            Box::new(move || Decoded::Gltf(open_gltf(&vfs, &path).and_then(DecodedGltf::new).map(|mut decoded| {
+                decoded.decode_images(&|file| vfs.read(file).map(Cow::into_owned));
+                Box::new(decoded)
+            }))),
+        );
+        handle
+    }
+
+    /// Whether the asset behind a handle is loaded, still loading or failed to. Doesn't count as using it.
+    pub fn load_state<T: StoredAsset + ?Sized>(&self, handle: Handle<T>) -> Loaded<T> {
+        let storage = T::storage(self);
+        let reference = match storage.reference(handle) {
+            Some(reference) => reference,
+            None => return Loaded::Unloaded,
+        };
+        match self.loads.states.get(reference) {
+            Some(LoadStatus::Loading) => Loaded::Loading(storage.peek(handle).cloned()),
+            Some(LoadStatus::Failed(err)) => Loaded::Failed(err.clone()),
+            None => match storage.peek(handle) {
+                Some(asset) => Loaded::Loaded(asset.clone()),
+                None => Loaded::Unloaded,
+            },
+        }
+    }
+
+    /// Uploads what the loading threads finished decoding until `budget` is used up; the rest waits for the next call.
+    /// At least one asset is uploaded per call, so loading always gets somewhere.
+    /// Meant to be called once a frame, on the thread that owns the GL context.
+    pub fn finish_loads(&mut self, budget: Duration) -> LoadReport {
+        let start = Instant::now();
+        let mut report = LoadReport::default();
+        if let Some(workers) = &self.loads.workers {
+            self.loads.ready.extend(workers.finished());
+        }
+        while let Some((id, decoded)) = self.loads.ready.pop_front() {
+            if let Some(pending) = self.loads.pending.remove(&id) {
+                self.finish_load(pending, decoded, &mut report);
+            }
+            if start.elapsed() >= budget {
+                break;
+            }
+        }
+        report.pending = self.loads.pending.len();
+        if !report.loaded.is_empty() {
+            self.enforce_budget();
+        }
+        report
+    }
+
+    fn finish_load(&mut self, pending: PendingLoad, decoded: Decoded, report: &mut LoadReport) {
+        match (pending, decoded) {
+            (PendingLoad::Texture { handle, reference, recipe, config }, decoded) => {
+                let name = match self.textures.name(handle) {
+                    Some(name) => name.to_string(),
+                    // Unloaded or loaded again while this was decoding.
+                    None => return self.forget_if_unused::<Texture>(&reference),
+                };
+                let gl_ctx = self.gl_ctx();
+                let result = match decoded {
+                    Decoded::Image(image) => image.and_then(|image| Texture::from_image(gl_ctx, image, config)),
+                    Decoded::Compressed(format, cooked) => cooked.and_then(|cooked| upload_cooked(gl_ctx, format, &cooked, config)),
+                    Decoded::Cubemap(faces) => faces.and_then(|faces| Texture::cubemap_from_images(gl_ctx, faces, config)),
+                    Decoded::Gltf(_) => unreachable!("a texture load decoded a glTF file"),
+                };
+                match result {
+                    Ok(tex) => {
+                        tex.set_label(&name);
+                        // Everything holding the placeholder sees the texture from now on.
+                        match self.textures.peek(handle) {
+                            Some(placeholder) => placeholder.swap(&tex),
+                            None => { self.textures.restore(handle, Rc::new(tex)); },
+                        }
+                        self.finished_loading(reference, recipe, name, report);
+                    },
+                    Err(err) => self.failed_loading(reference, name, err.into(), report),
+                }
+            },
+            (PendingLoad::Gltf { handle, reference, path }, Decoded::Gltf(decoded)) => {
+                let name = match self.gltf_assets.name(handle) {
+                    Some(name) => name.to_string(),
+                    None => return self.forget_if_unused::<GltfAsset>(&reference),
+                };
+                match decoded.and_then(|decoded| self.load_gltf_file(&path, *decoded)) {
+                    Ok(asset) => {
+                        let asset = Rc::new(asset);
+                        self.add_gltf_primitives(&name, &path, &reference, &asset);
+                        self.gltf_assets.restore(handle, asset);
+                        self.finished_loading(reference, Recipe::Gltf { path: path }, name, report);
+                    },
+                    Err(err) => self.failed_loading(reference, name, err.into(), report),
+                }
+            },
+            (PendingLoad::Gltf { .. }, _) => unreachable!("a glTF load decoded a texture"),
+        }
+    }
+
+    fn finished_loading(&mut self, reference: AssetReference, recipe: Recipe, name: String, report: &mut LoadReport) {
+        self.loads.forget(&reference);
+        self.recipes.insert(reference, recipe, &self.vfs);
+        report.loaded.push(name);
+    }
+
+    fn failed_loading(&mut self, reference: AssetReference, name: String, err: AssetLoadError, report: &mut LoadReport) {
+        let err = Rc::new(err);
+        self.loads.states.insert(reference, LoadStatus::Failed(err.clone()));
+        report.failed.push((name, err));
+    }
+
+    fn forget_if_unused<T: StoredAsset + ?Sized>(&mut self, reference: &AssetReference) {
+        if T::storage(self).find_reference(reference).is_none() {
+            self.loads.forget(reference);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GenericImageView;
+
+    #[test]
+    fn workers_hand_back_every_result() {
+        let workers = LoadWorkers::new(3);
+        for id in 0..20u64 {
+            workers.submit(id, Box::new(move || id * 2));
+        }
+
+        let mut results = Vec::new();
+        while results.len() < 20 {
+            results.extend(workers.finished());
+            thread::yield_now();
+        }
+        results.sort_unstable();
+        assert_eq!(results, (0..20).map(|id| (id, id * 2)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn fixture_images_decode_off_the_main_thread() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../assets/textures/texture1.jpg");
+        let workers = LoadWorkers::new(1);
+        workers.submit(0, Box::new(move || Decoded::Image(image::open(&path).map_err(TextureError::from))));
+
+        let decoded = loop {
+            if let Some((_, decoded)) = workers.finished().next() {
+                break decoded;
+            }
+            thread::yield_now();
+        };
+        match decoded {
+            Decoded::Image(Ok(image)) => assert!(image.width() > 0),
+            _ => panic!("the fixture image didn't decode"),
+        }
+    }
+}
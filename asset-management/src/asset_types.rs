@@ -1,6 +1,6 @@
 use ace_gl_types::ShaderType;
 
-#[derive(Debug, Eq, PartialEq, Hash)]
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
 pub enum AssetType {
     Model,
     Material,
@@ -8,4 +8,36 @@ pub enum AssetType {
     Cubemap,
     Audio,
     Shader(ShaderType),
-}
\ No newline at end of file
+    /// Linked from several shader files, so a program is referred to by name rather than by any one file.
+    Program,
+}
+
+impl AssetType {
+    /// File extensions assets of this type are loaded from, most preferred first. Empty for types that aren't one file.
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            AssetType::Model => &["gltf", "glb", "obj"],
+            AssetType::Material => &["json"],
+            AssetType::Texture => &["png", "jpg", "jpeg"],
+            AssetType::Audio => &["ogg", "wav"],
+            AssetType::Shader(ShaderType::VertexShader) => &["vert"],
+            AssetType::Shader(ShaderType::TessControlShader) => &["tesc"],
+            AssetType::Shader(ShaderType::TessEvaluationShader) => &["tese"],
+            AssetType::Shader(ShaderType::GeometryShader) => &["geom"],
+            AssetType::Shader(ShaderType::FragmentShader) => &["frag"],
+            AssetType::Cubemap | AssetType::Program => &[],
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AssetType::Model => "model",
+            AssetType::Material => "material",
+            AssetType::Texture => "texture",
+            AssetType::Cubemap => "cubemap",
+            AssetType::Audio => "audio",
+            AssetType::Shader(_) => "shader",
+            AssetType::Program => "program",
+        }
+    }
+}
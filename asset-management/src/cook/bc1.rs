@@ -0,0 +1,122 @@
+//! A small BC1 (DXT1) encoder. It fits each block's colors between the corners of their bounding box, which is quick
+//! and good enough for color textures; anything that needs better than that should be compressed by a dedicated tool.
+
+/// Compresses tightly packed RGB texels into BC1 blocks, a row of blocks at a time, in the order the rows are stored.
+/// Blocks that hang over the edge repeat the last row or column.
+pub fn compress(rgb: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let (width, height) = (width as usize, height as usize);
+    let mut out = Vec::with_capacity(width.div_ceil(4) * height.div_ceil(4) * 8);
+    for block_y in (0..height).step_by(4) {
+        for block_x in (0..width).step_by(4) {
+            let mut texels = [[0; 3]; 16];
+            for (idx, texel) in texels.iter_mut().enumerate() {
+                let x = (block_x + idx % 4).min(width - 1);
+                let y = (block_y + idx / 4).min(height - 1);
+                let start = (y * width + x) * 3;
+                texel.copy_from_slice(&rgb[start..start + 3]);
+            }
+            out.extend_from_slice(&encode_block(&texels));
+        }
+    }
+    out
+}
+
+pub(super) fn encode_block(texels: &[[u8; 3]; 16]) -> [u8; 8] {
+    let mut min = [u8::MAX; 3];
+    let mut max = [0; 3];
+    for texel in texels {
+        for channel in 0..3 {
+            min[channel] = min[channel].min(texel[channel]);
+            max[channel] = max[channel].max(texel[channel]);
+        }
+    }
+
+    // The first endpoint has to be the larger one, or the block is decoded in three-color mode.
+    let (color0, color1) = (to_565(max), to_565(min));
+    let mut block = [0; 8];
+    block[0..2].copy_from_slice(&color0.to_le_bytes());
+    block[2..4].copy_from_slice(&color1.to_le_bytes());
+    if color0 == color1 {
+        // Every texel is the first endpoint.
+        return block;
+    }
+
+    let (end0, end1) = (from_565(color0), from_565(color1));
+    let mut palette = [end0, end1, [0; 3], [0; 3]];
+    for channel in 0..3 {
+        let (a, b) = (end0[channel] as u32, end1[channel] as u32);
+        palette[2][channel] = ((2 * a + b) / 3) as u8;
+        palette[3][channel] = ((a + 2 * b) / 3) as u8;
+    }
+
+    let mut indices = 0u32;
+    for (idx, texel) in texels.iter().enumerate() {
+        let distance = |color: &[u8; 3]| -> u32 {
+            (0..3).map(|channel| (texel[channel] as i32 - color[channel] as i32).pow(2) as u32).sum()
+        };
+        let nearest = (0..4).min_by_key(|&entry| distance(&palette[entry])).unwrap_or(0);
+        indices |= (nearest as u32) << (idx * 2);
+    }
+    block[4..8].copy_from_slice(&indices.to_le_bytes());
+    block
+}
+
+fn to_565(rgb: [u8; 3]) -> u16 {
+    let scale = |value: u8, max: u16| (value as u16 * max + 127) / 255;
+    (scale(rgb[0], 31) << 11) | (scale(rgb[1], 63) << 5) | scale(rgb[2], 31)
+}
+
+fn from_565(color: u16) -> [u8; 3] {
+    let (r, g, b) = ((color >> 11) as u8, ((color >> 5) & 0x3f) as u8, (color & 0x1f) as u8);
+    [(r << 3) | (r >> 2), (g << 2) | (g >> 4), (b << 3) | (b >> 2)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decodes one four-color block back into its texels.
+    fn decode_block(block: &[u8]) -> [[u8; 3]; 16] {
+        let color0 = u16::from_le_bytes([block[0], block[1]]);
+        let color1 = u16::from_le_bytes([block[2], block[3]]);
+        let (end0, end1) = (from_565(color0), from_565(color1));
+        let mut palette = [end0, end1, [0; 3], [0; 3]];
+        for channel in 0..3 {
+            let (a, b) = (end0[channel] as u32, end1[channel] as u32);
+            palette[2][channel] = ((2 * a + b) / 3) as u8;
+            palette[3][channel] = ((a + 2 * b) / 3) as u8;
+        }
+        let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+        let mut texels = [[0; 3]; 16];
+        for (idx, texel) in texels.iter_mut().enumerate() {
+            *texel = palette[((indices >> (idx * 2)) & 3) as usize];
+        }
+        texels
+    }
+
+    #[test]
+    fn two_color_blocks_survive() {
+        // A 4x4 checkerboard of black and white, which the endpoints can hold exactly.
+        let mut rgb = Vec::new();
+        for idx in 0..16 {
+            let value = if (idx % 4 + idx / 4) % 2 == 0 { 255 } else { 0 };
+            rgb.extend_from_slice(&[value, value, value]);
+        }
+        let compressed = compress(&rgb, 4, 4);
+        assert_eq!(compressed.len(), 8);
+        let decoded: Vec<u8> = decode_block(&compressed).iter().flatten().copied().collect();
+        assert_eq!(decoded, rgb);
+    }
+
+    #[test]
+    fn edges_are_padded() {
+        // 5x1 takes two blocks; both are one flat color, so they decode to it everywhere.
+        let rgb = [255, 0, 0].repeat(5);
+        let compressed = compress(&rgb, 5, 1);
+        assert_eq!(compressed.len(), 16);
+        for block in compressed.chunks(8) {
+            assert_eq!(u16::from_le_bytes([block[0], block[1]]), 0xf800);
+            assert!(decode_block(block).iter().all(|texel| *texel == [255, 0, 0]));
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! A small BC3 (DXT5) encoder for textures with an alpha channel: each block is an alpha block followed by a BC1 color block.
+//! Alpha is fit between the block's smallest and largest values, the same way `bc1` fits colors.
+
+use super::bc1;
+
+/// Compresses tightly packed RGBA texels into BC3 blocks, a row of blocks at a time, in the order the rows are stored.
+/// Blocks that hang over the edge repeat the last row or column.
+pub fn compress(rgba: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let (width, height) = (width as usize, height as usize);
+    let mut out = Vec::with_capacity(width.div_ceil(4) * height.div_ceil(4) * 16);
+    for block_y in (0..height).step_by(4) {
+        for block_x in (0..width).step_by(4) {
+            let mut texels = [[0; 3]; 16];
+            let mut alphas = [0; 16];
+            for idx in 0..16 {
+                let x = (block_x + idx % 4).min(width - 1);
+                let y = (block_y + idx / 4).min(height - 1);
+                let start = (y * width + x) * 4;
+                texels[idx].copy_from_slice(&rgba[start..start + 3]);
+                alphas[idx] = rgba[start + 3];
+            }
+            out.extend_from_slice(&encode_alpha_block(&alphas));
+            // BC3 always decodes its color block in four-color mode, which is the only one `bc1` writes.
+            out.extend_from_slice(&bc1::encode_block(&texels));
+        }
+    }
+    out
+}
+
+fn encode_alpha_block(alphas: &[u8; 16]) -> [u8; 8] {
+    let max = alphas.iter().copied().max().unwrap_or(u8::MAX);
+    let min = alphas.iter().copied().min().unwrap_or(0);
+
+    // The first endpoint has to be the larger one for the eight-value palette.
+    let mut block = [0; 8];
+    block[0] = max;
+    block[1] = min;
+    if max == min {
+        // Every texel is the first endpoint.
+        return block;
+    }
+
+    let palette = alpha_palette(max, min);
+    let mut indices = 0u64;
+    for (idx, &alpha) in alphas.iter().enumerate() {
+        let nearest = (0..8).min_by_key(|&entry| (alpha as i32 - palette[entry] as i32).abs()).unwrap_or(0);
+        indices |= (nearest as u64) << (idx * 3);
+    }
+    block[2..8].copy_from_slice(&indices.to_le_bytes()[..6]);
+    block
+}
+
+/// The eight alpha values a block with `alpha0 > alpha1` picks from.
+fn alpha_palette(alpha0: u8, alpha1: u8) -> [u8; 8] {
+    let (a, b) = (alpha0 as u32, alpha1 as u32);
+    let mut palette = [alpha0, alpha1, 0, 0, 0, 0, 0, 0];
+    for (step, entry) in palette.iter_mut().enumerate().skip(2) {
+        let step = step as u32;
+        *entry = (((8 - step) * a + (step - 1) * b) / 7) as u8;
+    }
+    palette
+}
+
+/// Decodes the alpha half of a block back into its texels' alpha.
+#[cfg(test)]
+pub(super) fn decode_alpha(block: &[u8]) -> [u8; 16] {
+    let (alpha0, alpha1) = (block[0], block[1]);
+    let mut bytes = [0; 8];
+    bytes[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(bytes);
+    let palette = alpha_palette(alpha0, alpha1);
+    let mut alphas = [alpha0; 16];
+    if alpha0 > alpha1 {
+        for (idx, alpha) in alphas.iter_mut().enumerate() {
+            *alpha = palette[((indices >> (idx * 3)) & 7) as usize];
+        }
+    }
+    alphas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alpha_ramps_stay_close() {
+        // A ramp from transparent to opaque across the block; every palette entry is within 255 / 14 of a texel.
+        let mut rgba = Vec::new();
+        for idx in 0..16u32 {
+            rgba.extend_from_slice(&[200, 100, 50, (idx * 17) as u8]);
+        }
+        let compressed = compress(&rgba, 4, 4);
+        assert_eq!(compressed.len(), 16);
+        for (idx, alpha) in decode_alpha(&compressed).iter().enumerate() {
+            assert!((*alpha as i32 - (idx * 17) as i32).abs() <= 255 / 14, "texel {} decoded to {}", idx, alpha);
+        }
+    }
+
+    #[test]
+    fn flat_alpha_is_exact() {
+        let rgba = [0, 0, 255, 128].repeat(5);
+        let compressed = compress(&rgba, 5, 1);
+        assert_eq!(compressed.len(), 32);
+        for block in compressed.chunks(16) {
+            assert_eq!(decode_alpha(block), [128; 16]);
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use super::{read_string, read_u32, write_string, write_u32};
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+
+const MAGIC: &[u8; 4] = b"ACMS";
+const VERSION: u32 = 1;
+
+/// The meshes of an OBJ file, interleaved and ready to copy into vertex buffers as they are.
+/// Materials still come from the file's MTL libraries when it's loaded, which are listed as the OBJ named them.
+///
+/// Stored as magic "ACMS", version: u32, library count: u32, the libraries, mesh count: u32, then per mesh its name,
+/// material name (empty for none), attribute count: u32, each attribute's float count: u32, vertex count: u32,
+/// the vertices as f32s, index count: u32 and the indices as u32s. Strings are a u32 length and UTF-8, and every
+/// number is little endian.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CookedModel {
+    pub material_libraries: Vec<PathBuf>,
+    pub meshes: Vec<CookedMesh>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CookedMesh {
+    pub name: String,
+    pub material: Option<String>,
+    /// How many floats each attribute has, in location order, starting from the position.
+    pub components: Vec<usize>,
+    pub vertices: Vec<f32>,
+    pub indices: Vec<u32>,
+}
+
+impl CookedMesh {
+    pub fn stride(&self) -> usize {
+        self.components.iter().sum()
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len() / self.stride().max(1)
+    }
+}
+
+impl CookedModel {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        write_u32(&mut out, VERSION);
+        write_u32(&mut out, self.material_libraries.len() as u32);
+        for library in &self.material_libraries {
+            write_string(&mut out, &library.to_string_lossy());
+        }
+        write_u32(&mut out, self.meshes.len() as u32);
+        for mesh in &self.meshes {
+            write_string(&mut out, &mesh.name);
+            write_string(&mut out, mesh.material.as_deref().unwrap_or(""));
+            write_u32(&mut out, mesh.components.len() as u32);
+            for &count in &mesh.components {
+                write_u32(&mut out, count as u32);
+            }
+            write_u32(&mut out, mesh.vertex_count() as u32);
+            for value in &mesh.vertices {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+            write_u32(&mut out, mesh.indices.len() as u32);
+            for &index in &mesh.indices {
+                write_u32(&mut out, index);
+            }
+        }
+        out
+    }
+
+    pub fn from_bytes(mut bytes: &[u8]) -> io::Result<CookedModel> {
+        let input = &mut bytes;
+        if input.len() < MAGIC.len() || &input[..MAGIC.len()] != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a cooked model"));
+        }
+        *input = &input[MAGIC.len()..];
+        if read_u32(input)? != VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "cooked with a different version; cook it again"));
+        }
+
+        let mut model = CookedModel::default();
+        for _ in 0..read_u32(input)? {
+            model.material_libraries.push(PathBuf::from(read_string(input)?));
+        }
+        for _ in 0..read_u32(input)? {
+            let name = read_string(input)?;
+            let material = Some(read_string(input)?).filter(|material| !material.is_empty());
+            let mut components = Vec::new();
+            for _ in 0..read_u32(input)? {
+                components.push(read_u32(input)? as usize);
+            }
+            let float_count = read_u32(input)? as usize * components.iter().sum::<usize>();
+            let mut vertices = Vec::with_capacity(float_count);
+            for _ in 0..float_count {
+                vertices.push(f32::from_bits(read_u32(input)?));
+            }
+            let mut indices = Vec::new();
+            for _ in 0..read_u32(input)? {
+                indices.push(read_u32(input)?);
+            }
+            model.meshes.push(CookedMesh {
+                name: name,
+                material: material,
+                components: components,
+                vertices: vertices,
+                indices: indices,
+            });
+        }
+        Ok(model)
+    }
+}
+
+/// Merges vertices that are exactly the same, then renumbers the rest in the order the indices first use them,
+/// so the GPU reads the vertex buffer front to back instead of jumping around in it. `stride` is in floats.
+pub fn optimize_mesh(vertices: &[f32], stride: usize, indices: &[u32]) -> (Vec<f32>, Vec<u32>) {
+    let mut remap: HashMap<Vec<u32>, u32> = HashMap::new();
+    let mut optimized = Vec::with_capacity(vertices.len());
+    let mut new_indices = Vec::with_capacity(indices.len());
+    for &index in indices {
+        let vertex = &vertices[index as usize * stride..(index as usize + 1) * stride];
+        // Compared by their bits, since floats aren't Eq; the only vertices merged are ones that are exactly the same.
+        let key: Vec<u32> = vertex.iter().map(|value| value.to_bits()).collect();
+        let next = remap.len() as u32;
+        let new_index = *remap.entry(key).or_insert_with(|| {
+            optimized.extend_from_slice(vertex);
+            next
+        });
+        new_indices.push(new_index);
+    }
+    (optimized, new_indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicates_merge_and_vertices_follow_their_first_use() {
+        // Vertex 3 repeats vertex 0, and vertex 4 is never drawn.
+        let vertices = [0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 0.0, 9.0, 9.0];
+        let (vertices, indices) = optimize_mesh(&vertices, 2, &[2, 1, 3, 3, 1, 0]);
+        assert_eq!(vertices, vec![1.0, 1.0, 1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(indices, vec![0, 1, 2, 2, 1, 2]);
+    }
+
+    #[test]
+    fn models_round_trip() {
+        let model = CookedModel {
+            material_libraries: vec![PathBuf::from("cube.mtl")],
+            meshes: vec![CookedMesh {
+                name: "Cube".to_string(),
+                material: Some("Material".to_string()),
+                components: vec![3, 2],
+                vertices: vec![0.0, 1.0, 2.0, 0.5, 0.25, -1.0, -2.0, -3.0, 1.0, 1.0],
+                indices: vec![0, 1, 1],
+            }, CookedMesh {
+                name: "Loose".to_string(),
+                material: None,
+                components: vec![3],
+                vertices: vec![0.0, 0.0, 0.0],
+                indices: vec![0, 0, 0],
+            }],
+        };
+        assert_eq!(model.meshes[0].vertex_count(), 2);
+        assert_eq!(CookedModel::from_bytes(&model.to_bytes()).unwrap(), model);
+    }
+}
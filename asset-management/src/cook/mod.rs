@@ -0,0 +1,473 @@
+//! Offline asset cooking: turns source assets into forms the engine can upload without decoding them first.
+//!
+//! - Textures become BC1, or BC3 if they have any transparent texels, with their mip chain worked out ahead of time,
+//!   written next to their source path with `.ctex` appended (`textures/stone.png.ctex`).
+//! - OBJ files become interleaved, index-optimized meshes in `.cmesh` files. Their MTL libraries are still read
+//!   when they're loaded.
+//! - Shaders are validated, and written under their own path with their comments stripped.
+//!
+//! The output directory is meant to be mounted as a `Layer::Directory` over the source assets. Everything that isn't
+//! cooked, like material files and MTL libraries, comes from the layers below, and the container prefers a cooked
+//! file over its source as long as it's on the same layer as the source or a higher one. Cubemaps aren't cooked.
+//!
+//! Cooked files are loaded in place of their sources whether or not they're up to date, so cook again after
+//! editing assets (or unmount the cooked layer while working on them).
+//!
+//! What was cooked is recorded in `.cook-cache.json` in the output directory, keyed by a hash of the source file,
+//! its `.meta` sidecar (see `meta`), the settings and the cooker's version, so cooking again only redoes the files that changed.
+
+mod bc1;
+mod bc3;
+mod mesh;
+mod shader;
+mod texture;
+
+pub use self::mesh::*;
+pub use self::shader::*;
+pub use self::texture::*;
+
+use crate::types::*;
+use crate::archive::entry_name;
+use crate::hl_assets::{ObjLoadError, ObjModel};
+use crate::meta::{meta_path, AssetMeta};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Bumped whenever a cooked format or the way something's cooked changes, which recooks everything.
+const COOK_VERSION: u32 = 2;
+const CACHE_FILE: &str = ".cook-cache.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CookSettings {
+    /// Store a full mip chain with every texture, rather than just the full size image.
+    pub generate_mips: bool,
+    /// Merge duplicate vertices and reorder them by first use.
+    pub optimize_meshes: bool,
+    /// Fail a shader that naga finds problems in, instead of writing it out as it is.
+    pub validate_shaders: bool,
+    pub strip_shader_comments: bool,
+}
+
+impl Default for CookSettings {
+    fn default() -> Self {
+        CookSettings {
+            generate_mips: true,
+            optimize_meshes: true,
+            validate_shaders: true,
+            strip_shader_comments: true,
+        }
+    }
+}
+
+/// What a source file gets cooked into, if anything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CookKind {
+    Texture,
+    ObjModel,
+    Shader(ShaderType),
+}
+
+impl CookKind {
+    pub fn of<P: AsRef<Path>>(path: P) -> Option<CookKind> {
+        let extension = path.as_ref().extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "png" | "jpg" | "jpeg" => Some(CookKind::Texture),
+            "obj" => Some(CookKind::ObjModel),
+            _ => ShaderType::from_extension(path).map(CookKind::Shader),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            CookKind::Texture => "texture",
+            CookKind::ObjModel => "obj",
+            CookKind::Shader(_) => "shader",
+        }
+    }
+
+    /// Where the cooked form of `source` goes, relative to the same root.
+    pub fn cooked_path<P: AsRef<Path>>(self, source: P) -> PathBuf {
+        match self {
+            CookKind::Texture => cooked_texture_path(source),
+            CookKind::ObjModel => cooked_model_path(source),
+            CookKind::Shader(_) => source.as_ref().to_path_buf(),
+        }
+    }
+}
+
+pub fn cooked_texture_path<P: AsRef<Path>>(source: P) -> PathBuf {
+    with_suffix(source.as_ref(), ".ctex")
+}
+
+pub fn cooked_model_path<P: AsRef<Path>>(source: P) -> PathBuf {
+    with_suffix(source.as_ref(), ".cmesh")
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path);
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CookCache {
+    version: u32,
+    /// By source path, as `archive::entry_name` gives it.
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CacheEntry {
+    /// Hash of everything that went into cooking the file.
+    key: String,
+    outputs: Vec<PathBuf>,
+}
+
+impl CookCache {
+    /// A cache that can't be read, or was written by another version of the cooker, is as good as none.
+    fn load(path: &Path) -> CookCache {
+        fs::read(path).ok()
+            .and_then(|json| serde_json::from_slice::<CookCache>(&json).ok())
+            .filter(|cache| cache.version == COOK_VERSION)
+            .unwrap_or_else(|| CookCache { version: COOK_VERSION, entries: BTreeMap::new() })
+    }
+
+    fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+    }
+}
+
+/// What happened to a single file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CookOutcome {
+    Cooked,
+    /// Already cooked from the same contents with the same settings.
+    Cached,
+    /// Not something the cooker handles.
+    Skipped,
+}
+
+#[derive(Debug, Default)]
+pub struct CookReport {
+    pub cooked: Vec<PathBuf>,
+    pub cached: Vec<PathBuf>,
+    /// Cooked outputs deleted because their source is gone.
+    pub removed: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, CookError)>,
+}
+
+/// Cooks the assets under one directory into another.
+pub struct Cooker {
+    source: PathBuf,
+    output: PathBuf,
+    settings: CookSettings,
+    cache: CookCache,
+}
+
+impl Cooker {
+    pub fn new<S: AsRef<Path>, O: AsRef<Path>>(source: S, output: O, settings: CookSettings) -> Cooker {
+        let output = output.as_ref().to_path_buf();
+        Cooker {
+            source: source.as_ref().to_path_buf(),
+            cache: CookCache::load(&output.join(CACHE_FILE)),
+            output: output,
+            settings: settings,
+        }
+    }
+
+    /// Forgets what was cooked before, so everything is cooked again.
+    pub fn clear_cache(&mut self) {
+        self.cache.entries.clear();
+    }
+
+    /// Cooks every file under the source directory that needs it, and deletes the outputs of files that are gone.
+    /// A file that fails to cook is reported and doesn't stop the rest.
+    pub fn cook_all(&mut self) -> Result<CookReport, CookError> {
+        let mut files = Vec::new();
+        collect_files(&self.source, &mut files)?;
+        // Sorted, so reports come out the same every run.
+        files.sort();
+
+        // Cooking into the asset directory mustn't cook the output again.
+        let output = self.output.canonicalize().ok();
+        let mut report = CookReport::default();
+        let mut seen = Vec::new();
+        for file in files {
+            if output.as_ref().is_some_and(|output| file.canonicalize().is_ok_and(|file| file.starts_with(output))) {
+                continue;
+            }
+            let relative = file.strip_prefix(&self.source).expect("collected files are under the source directory").to_path_buf();
+            if is_cubemap_face(&file) {
+                continue;
+            }
+            seen.extend(entry_name(&relative));
+            match self.cook_file(&relative) {
+                Ok(CookOutcome::Cooked) => report.cooked.push(relative),
+                Ok(CookOutcome::Cached) => report.cached.push(relative),
+                Ok(CookOutcome::Skipped) => {},
+                Err(err) => report.failed.push((relative, err)),
+            }
+        }
+
+        let gone: Vec<String> = self.cache.entries.keys().filter(|source| !seen.contains(source)).cloned().collect();
+        for source in gone {
+            for output in self.cache.entries.remove(&source).map(|entry| entry.outputs).unwrap_or_default() {
+                match fs::remove_file(self.output.join(&output)) {
+                    Ok(()) => report.removed.push(output),
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {},
+                    Err(err) => return Err(err.into()),
+                }
+            }
+        }
+
+        self.save_cache()?;
+        Ok(report)
+    }
+
+    /// Cooks one file, relative to the source directory, unless it was already cooked from the same contents.
+    /// Call `save_cache` afterwards to remember it; `cook_all` does.
+    pub fn cook_file<P: AsRef<Path>>(&mut self, relative: P) -> Result<CookOutcome, CookError> {
+        let relative = relative.as_ref();
+        let kind = match CookKind::of(relative) {
+            Some(kind) => kind,
+            None => return Ok(CookOutcome::Skipped),
+        };
+        let name = entry_name(relative).ok_or_else(|| CookError::BadPath(relative.to_path_buf()))?;
+        let contents = fs::read(self.source.join(relative))?;
+        let sidecar = meta_path(relative);
+        let meta_bytes = match fs::read(self.source.join(&sidecar)) {
+            Ok(bytes) => Some(bytes),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+        let key = self.cache_key(kind, &contents, meta_bytes.as_deref());
+
+        if let Some(entry) = self.cache.entries.get(&name) {
+            if entry.key == key && entry.outputs.iter().all(|output| self.output.join(output).is_file()) {
+                return Ok(CookOutcome::Cached);
+            }
+        }
+
+        let meta = meta_bytes.map(|bytes| AssetMeta::from_slice(&sidecar, &bytes)).transpose();
+        let cooked = match meta.map_err(CookError::from).and_then(|meta| self.cook_contents(kind, relative, contents, meta)) {
+            Ok(cooked) => cooked,
+            Err(err) => {
+                // Whatever was cooked from an older version would otherwise still be loaded in place of the source.
+                for output in self.cache.entries.remove(&name).map(|entry| entry.outputs).unwrap_or_default() {
+                    let _ = fs::remove_file(self.output.join(output));
+                }
+                return Err(err);
+            },
+        };
+
+        let output = kind.cooked_path(relative);
+        let output_path = self.output.join(&output);
+        if let Some(dir) = output_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&output_path, cooked)?;
+        self.cache.entries.insert(name, CacheEntry {
+            key: key,
+            outputs: vec![output],
+        });
+        Ok(CookOutcome::Cooked)
+    }
+
+    /// Cooks a file with the import settings of its sidecar, if it has one.
+    fn cook_contents(&self, kind: CookKind, relative: &Path, contents: Vec<u8>, meta: Option<AssetMeta>) -> Result<Vec<u8>, CookError> {
+        Ok(match kind {
+            CookKind::Texture => {
+                // Only the sidecar's sampler is known here; a config passed in code that turns mipmaps off just skips the cooked ones.
+                let import = meta.map(|meta| meta.texture_import()).unwrap_or_default();
+                let sampler = import.sampler.or_else(|| import.config.map(|config| config.sampler)).unwrap_or_default();
+                let settings = CookSettings { generate_mips: self.settings.generate_mips && sampler.mipmaps, ..self.settings.clone() };
+                CookedTexture::cook(&contents, &settings)?.to_bytes()
+            },
+            CookKind::ObjModel => {
+                let obj_dir = self.source.join(relative.parent().unwrap_or_else(|| Path::new("")));
+                let load_mtl = |mtl: &Path| tobj::load_mtl(obj_dir.join(mtl));
+                let import = meta.map(|meta| meta.mesh_import()).unwrap_or_default();
+                ObjModel::cook(&contents, load_mtl, &import, self.settings.optimize_meshes)?.to_bytes()
+            },
+            CookKind::Shader(ty) => {
+                let src = String::from_utf8(contents).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                cook_shader(relative, &src, ty, &self.settings)?.into_bytes()
+            },
+        })
+    }
+
+    pub fn save_cache(&self) -> Result<(), CookError> {
+        fs::create_dir_all(&self.output)?;
+        Ok(self.cache.save(&self.output.join(CACHE_FILE))?)
+    }
+
+    fn cache_key(&self, kind: CookKind, contents: &[u8], meta: Option<&[u8]>) -> String {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&COOK_VERSION.to_le_bytes());
+        hasher.update(kind.name().as_bytes());
+        hasher.update(&serde_json::to_vec(&self.settings).expect("settings always serialize"));
+        hasher.update(contents);
+        if let Some(meta) = meta {
+            hasher.update(meta);
+        }
+        hasher.finalize().to_hex().to_string()
+    }
+}
+
+/// Cubemaps are loaded from the images in their directory as they are, so their faces aren't cooked.
+pub(crate) fn is_cubemap_face(path: &Path) -> bool {
+    let dir = match path.parent() {
+        Some(dir) => dir,
+        None => return false,
+    };
+    let faces = CubemapPaths::in_directory(dir);
+    faces.files().contains(&path) && faces.files().iter().all(|face| face.is_file())
+}
+
+pub(crate) fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn write_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn read_u32(input: &mut &[u8]) -> io::Result<u32> {
+    if input.len() < 4 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let (bytes, rest) = input.split_at(4);
+    *input = rest;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn write_string(out: &mut Vec<u8>, value: &str) {
+    write_u32(out, value.len() as u32);
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn read_string(input: &mut &[u8]) -> io::Result<String> {
+    let len = read_u32(input)? as usize;
+    if input.len() < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    String::from_utf8(bytes.to_vec()).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+#[derive(Error, Debug)]
+pub enum CookError {
+    #[error("{0}")]
+    IOError(io::Error),
+    #[error("Decoding the texture failed: {0}")]
+    ImageError(image::ImageError),
+    #[error("{0}")]
+    ObjLoadError(ObjLoadError),
+    #[error("{}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n"))]
+    ShaderError(Vec<ShaderDiagnostic>),
+    #[error("{} can't be cooked; paths have to be relative, stay inside the asset root and be UTF-8", .0.display())]
+    BadPath(PathBuf),
+}
+
+impl From<io::Error> for CookError {
+    fn from(err: io::Error) -> Self {
+        CookError::IOError(err)
+    }
+}
+
+impl From<image::ImageError> for CookError {
+    fn from(err: image::ImageError) -> Self {
+        CookError::ImageError(err)
+    }
+}
+
+impl From<ObjLoadError> for CookError {
+    fn from(err: ObjLoadError) -> Self {
+        CookError::ObjLoadError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_png(path: &Path, color: [u8; 3]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        image::RgbImage::from_pixel(4, 4, image::Rgb(color)).save(path).unwrap();
+    }
+
+    #[test]
+    fn only_changed_files_are_cooked_again() {
+        let source = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+        write_png(&source.path().join("textures/stone.png"), [10, 20, 30]);
+        write_png(&source.path().join("textures/grass.png"), [30, 200, 30]);
+        fs::write(source.path().join("manifest.json"), "{}").unwrap();
+
+        let report = Cooker::new(source.path(), output.path(), CookSettings::default()).cook_all().unwrap();
+        assert_eq!(report.cooked, vec![PathBuf::from("textures/grass.png"), PathBuf::from("textures/stone.png")]);
+        assert!(output.path().join("textures/stone.png.ctex").is_file());
+        assert!(!output.path().join("manifest.json").exists());
+
+        // A new cooker picks the cache up from the output directory.
+        write_png(&source.path().join("textures/stone.png"), [200, 20, 30]);
+        let report = Cooker::new(source.path(), output.path(), CookSettings::default()).cook_all().unwrap();
+        assert_eq!(report.cooked, vec![PathBuf::from("textures/stone.png")]);
+        assert_eq!(report.cached, vec![PathBuf::from("textures/grass.png")]);
+
+        // Different settings make for different outputs.
+        let settings = CookSettings { generate_mips: false, ..CookSettings::default() };
+        assert_eq!(Cooker::new(source.path(), output.path(), settings).cook_all().unwrap().cooked.len(), 2);
+
+        // So does a sidecar, here one that turns mipmaps off.
+        let meta = br#"{ "guid": "0f7c1e36-5bd4-4b0e-a5b4-5d1f1f3f3c7e", "texture": { "sampler": { "mipmaps": false } } }"#;
+        fs::write(source.path().join("textures/stone.png.meta"), meta).unwrap();
+        let report = Cooker::new(source.path(), output.path(), CookSettings::default()).cook_all().unwrap();
+        assert!(report.cooked.contains(&PathBuf::from("textures/stone.png")));
+        let cooked = CookedTexture::from_bytes(&fs::read(output.path().join("textures/stone.png.ctex")).unwrap()).unwrap();
+        assert_eq!(cooked.levels.len(), 1);
+
+        fs::remove_file(source.path().join("textures/grass.png")).unwrap();
+        fs::remove_file(output.path().join("textures/stone.png.ctex")).unwrap();
+        let report = Cooker::new(source.path(), output.path(), CookSettings::default()).cook_all().unwrap();
+        assert_eq!(report.cooked, vec![PathBuf::from("textures/stone.png")]);
+        assert_eq!(report.removed, vec![PathBuf::from("textures/grass.png.ctex")]);
+        assert!(!output.path().join("textures/grass.png.ctex").exists());
+    }
+
+    #[test]
+    fn failures_are_reported_without_stopping_the_rest() {
+        let source = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+        write_png(&source.path().join("textures/stone.png"), [10, 20, 30]);
+        fs::write(source.path().join("textures/broken.png"), b"not a png").unwrap();
+
+        let report = Cooker::new(source.path(), output.path(), CookSettings::default()).cook_all().unwrap();
+        assert_eq!(report.cooked, vec![PathBuf::from("textures/stone.png")]);
+        assert!(matches!(report.failed.as_slice(), [(path, CookError::ImageError(_))] if path == Path::new("textures/broken.png")));
+    }
+
+    #[test]
+    fn cooked_paths_keep_the_source_name() {
+        assert_eq!(CookKind::of("textures/stone.PNG"), Some(CookKind::Texture));
+        assert_eq!(CookKind::of("materials/cube.json"), None);
+        assert_eq!(CookKind::Texture.cooked_path("textures/stone.png"), PathBuf::from("textures/stone.png.ctex"));
+        assert_eq!(CookKind::ObjModel.cooked_path("models/cube.obj"), PathBuf::from("models/cube.obj.cmesh"));
+        assert_eq!(CookKind::Shader(ShaderType::VertexShader).cooked_path("shaders/a.vert"), PathBuf::from("shaders/a.vert"));
+    }
+}
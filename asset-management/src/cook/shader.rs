@@ -0,0 +1,67 @@
+use super::{CookError, CookSettings};
+use crate::types::*;
+use std::path::Path;
+
+/// Checks a shader and gets it ready to ship. Only vertex and fragment shaders can be validated offline;
+/// other stages are passed through unchecked.
+///
+/// Comments are blanked out rather than removed, so line numbers in the driver's compile errors still point
+/// into the source file.
+pub fn cook_shader(path: &Path, src: &str, shader_type: ShaderType, settings: &CookSettings) -> Result<String, CookError> {
+    let can_validate = matches!(shader_type, ShaderType::VertexShader | ShaderType::FragmentShader);
+    if settings.validate_shaders && can_validate {
+        let diagnostics = validate_glsl(path, src, shader_type);
+        if !diagnostics.is_empty() {
+            return Err(CookError::ShaderError(diagnostics));
+        }
+    }
+    if settings.strip_shader_comments {
+        Ok(strip_comments(src))
+    } else {
+        Ok(src.to_string())
+    }
+}
+
+/// Drops `//` and `/* */` comments and trailing whitespace, keeping every line break.
+pub fn strip_comments(src: &str) -> String {
+    let mut out = String::with_capacity(src.len());
+    let mut chars = src.chars().peekable();
+    let mut in_block = false;
+    while let Some(c) = chars.next() {
+        if in_block {
+            if c == '*' && chars.peek() == Some(&'/') {
+                chars.next();
+                in_block = false;
+                // A block comment separates the tokens around it.
+                out.push(' ');
+            } else if c == '\n' {
+                out.push('\n');
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('/', Some('/')) => {
+                while chars.peek().is_some_and(|&next| next != '\n') {
+                    chars.next();
+                }
+            },
+            ('/', Some('*')) => {
+                chars.next();
+                in_block = true;
+            },
+            _ => out.push(c),
+        }
+    }
+    out.lines().map(str::trim_end).collect::<Vec<_>>().join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comments_go_but_lines_stay() {
+        let src = "#version 330 core // core profile\n/* inputs\n   from the vertex shader */\nin vec2 uv;/* x */out vec4 color;\n";
+        assert_eq!(strip_comments(src), "#version 330 core\n\n\nin vec2 uv; out vec4 color;\n");
+    }
+}
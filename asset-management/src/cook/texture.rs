@@ -0,0 +1,147 @@
+use super::{bc1, bc3, read_u32, write_u32, CookSettings};
+use image::imageops::{self, FilterType};
+use std::io;
+
+const MAGIC: &[u8; 4] = b"ACTX";
+const VERSION: u32 = 2;
+/// Set in the flags when the levels are BC3 rather than BC1.
+const FLAG_ALPHA: u32 = 1;
+
+/// A 2D texture cooked into BC1, or BC3 if it has any transparent texels, with its mip levels worked out ahead of time.
+/// Rows are bottom first, the way `Texture::from_image` flips them before uploading.
+///
+/// Stored as magic "ACTX", version: u32, width: u32, height: u32, flags: u32 (bit 0 set for BC3), level count: u32,
+/// then per level its length: u32 and its blocks, largest level first. Integers are little endian.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CookedTexture {
+    pub width: u32,
+    pub height: u32,
+    /// Whether the levels are BC3 with an alpha block, rather than opaque BC1.
+    pub alpha: bool,
+    pub levels: Vec<Vec<u8>>,
+}
+
+impl CookedTexture {
+    /// Decodes an encoded image (PNG, JPEG, ...) and compresses it, along with a full mip chain unless the settings say not to.
+    pub fn cook(bytes: &[u8], settings: &CookSettings) -> Result<CookedTexture, image::ImageError> {
+        let base = image::load_from_memory(bytes)?.flipv().to_rgba8();
+        let (width, height) = base.dimensions();
+        // Opaque images with an alpha channel still go to BC1, which is half the size.
+        let alpha = base.pixels().any(|texel| texel[3] < u8::MAX);
+        let compress = |rgba: &image::RgbaImage, width: u32, height: u32| {
+            if alpha {
+                bc3::compress(rgba, width, height)
+            } else {
+                let rgb: Vec<u8> = rgba.pixels().flat_map(|texel| texel.0[..3].iter().copied()).collect();
+                bc1::compress(&rgb, width, height)
+            }
+        };
+        let mut levels = vec![compress(&base, width, height)];
+        if settings.generate_mips {
+            let (mut level_width, mut level_height) = (width, height);
+            while level_width > 1 || level_height > 1 {
+                level_width = (level_width / 2).max(1);
+                level_height = (level_height / 2).max(1);
+                // Every level is filtered down from the full image, so errors don't pile up level after level.
+                let level = imageops::resize(&base, level_width, level_height, FilterType::Triangle);
+                levels.push(compress(&level, level_width, level_height));
+            }
+        }
+        Ok(CookedTexture {
+            width: width,
+            height: height,
+            alpha: alpha,
+            levels: levels,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        write_u32(&mut out, VERSION);
+        write_u32(&mut out, self.width);
+        write_u32(&mut out, self.height);
+        write_u32(&mut out, if self.alpha { FLAG_ALPHA } else { 0 });
+        write_u32(&mut out, self.levels.len() as u32);
+        for level in &self.levels {
+            write_u32(&mut out, level.len() as u32);
+            out.extend_from_slice(level);
+        }
+        out
+    }
+
+    pub fn from_bytes(mut bytes: &[u8]) -> io::Result<CookedTexture> {
+        let input = &mut bytes;
+        if input.len() < MAGIC.len() || &input[..MAGIC.len()] != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a cooked texture"));
+        }
+        *input = &input[MAGIC.len()..];
+        if read_u32(input)? != VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "cooked with a different version; cook it again"));
+        }
+        let width = read_u32(input)?;
+        let height = read_u32(input)?;
+        let flags = read_u32(input)?;
+        let count = read_u32(input)?;
+        let mut levels = Vec::new();
+        for _ in 0..count {
+            let len = read_u32(input)? as usize;
+            if input.len() < len {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            levels.push(input[..len].to_vec());
+            *input = &input[len..];
+        }
+        Ok(CookedTexture {
+            width: width,
+            height: height,
+            alpha: flags & FLAG_ALPHA != 0,
+            levels: levels,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mips_go_down_to_one_pixel() {
+        let image = image::RgbImage::from_pixel(8, 2, image::Rgb([10, 200, 30]));
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgb8(image).write_to(&mut png, image::ImageOutputFormat::Png).unwrap();
+
+        let cooked = CookedTexture::cook(&png, &CookSettings::default()).unwrap();
+        // 8x2, 4x1, 2x1, 1x1, each at least one block.
+        assert_eq!((cooked.width, cooked.height), (8, 2));
+        assert!(!cooked.alpha);
+        assert_eq!(cooked.levels.iter().map(Vec::len).collect::<Vec<_>>(), vec![16, 8, 8, 8]);
+        assert_eq!(CookedTexture::from_bytes(&cooked.to_bytes()).unwrap(), cooked);
+
+        let settings = CookSettings { generate_mips: false, ..CookSettings::default() };
+        assert_eq!(CookedTexture::cook(&png, &settings).unwrap().levels.len(), 1);
+        assert!(CookedTexture::from_bytes(b"ACTX").is_err());
+    }
+
+    #[test]
+    fn transparent_texels_keep_their_alpha() {
+        // Every other column is see-through, so flipping the rows doesn't change where.
+        let image = image::RgbaImage::from_fn(4, 4, |x, _| image::Rgba([255, 255, 255, if x % 2 == 0 { 0 } else { 255 }]));
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(image).write_to(&mut png, image::ImageOutputFormat::Png).unwrap();
+
+        let cooked = CookedTexture::from_bytes(&CookedTexture::cook(&png, &CookSettings::default()).unwrap().to_bytes()).unwrap();
+        assert!(cooked.alpha);
+        // 4x4, 2x2 and 1x1, one BC3 block each.
+        assert_eq!(cooked.levels.iter().map(Vec::len).collect::<Vec<_>>(), vec![16, 16, 16]);
+        let expected: Vec<u8> = (0..16).map(|idx| if idx % 2 == 0 { 0 } else { 255 }).collect();
+        assert_eq!(bc3::decode_alpha(&cooked.levels[0]).to_vec(), expected);
+
+        // An alpha channel that's opaque everywhere is still cooked to BC1.
+        let opaque = image::RgbaImage::from_pixel(4, 4, image::Rgba([255, 0, 0, 255]));
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(opaque).write_to(&mut png, image::ImageOutputFormat::Png).unwrap();
+        let cooked = CookedTexture::cook(&png, &CookSettings::default()).unwrap();
+        assert!(!cooked.alpha);
+        assert_eq!(cooked.levels[0].len(), 8);
+    }
+}
@@ -0,0 +1,385 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::mem;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::namespace::AssetReference;
+
+/// A cheap, typed reference to an asset inside an `AssetStorage`.
+///
+/// A handle is an index plus the generation of the slot when it was handed out. Unloading or reloading the asset
+/// bumps the slot's generation, so a handle that outlived its asset stops resolving instead of pointing at whatever
+/// took the slot over.
+pub struct Handle<T: ?Sized> {
+    index: u32,
+    generation: u32,
+    // fn() -> T keeps the handle Send, Sync and covariant no matter what T is.
+    _asset: PhantomData<fn() -> T>,
+}
+
+impl<T: ?Sized> Handle<T> {
+    fn new(index: u32, generation: u32) -> Handle<T> {
+        Handle {
+            index: index,
+            generation: generation,
+            _asset: PhantomData,
+        }
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+// Derives would require T itself to be Copy, Eq and so on, which assets like textures never are.
+impl<T: ?Sized> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for Handle<T> {}
+
+impl<T: ?Sized> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T: ?Sized> Eq for Handle<T> {}
+
+impl<T: ?Sized> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Handle({}v{})", self.index, self.generation)
+    }
+}
+
+struct Entry<T: ?Sized> {
+    name: String,
+    reference: AssetReference,
+    // None while evicted.
+    asset: Option<Rc<T>>,
+    last_used: Cell<u64>,
+}
+
+struct Slot<T: ?Sized> {
+    generation: u32,
+    entry: Option<Entry<T>>,
+}
+
+// Shared by every storage, so how recently assets of different types were used can be compared.
+static USE_CLOCK: AtomicU64 = AtomicU64::new(0);
+
+fn tick() -> u64 {
+    USE_CLOCK.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// Assets of one type, addressed by handle. Each asset also has a name and the reference it was loaded from,
+/// and either can be turned back into its handle.
+///
+/// An asset can be evicted, which drops the storage's copy but keeps its slot, so handles to it stay valid and
+/// start resolving again once it's restored. Unloading it (`remove`) frees the slot and makes its handles stale.
+pub struct AssetStorage<T: ?Sized> {
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
+    by_name: HashMap<String, Handle<T>>,
+    by_reference: HashMap<AssetReference, Handle<T>>,
+}
+
+impl<T: ?Sized> AssetStorage<T> {
+    pub fn new() -> AssetStorage<T> {
+        AssetStorage {
+            slots: Vec::new(),
+            free: Vec::new(),
+            by_name: HashMap::new(),
+            by_reference: HashMap::new(),
+        }
+    }
+
+    /// Stores an asset under `name`. If there already is one by that name, this is a reload:
+    /// the old asset is unloaded and handles to it go stale.
+    pub fn insert<S: ToString>(&mut self, name: S, reference: AssetReference, asset: Rc<T>) -> Handle<T> {
+        self.insert_entry(name.to_string(), reference, Some(asset))
+    }
+
+    /// Hands out a handle for an asset that isn't there yet, such as one still loading. Until it's `restore`d,
+    /// the handle is live but doesn't resolve, like an evicted asset's.
+    pub fn reserve<S: ToString>(&mut self, name: S, reference: AssetReference) -> Handle<T> {
+        self.insert_entry(name.to_string(), reference, None)
+    }
+
+    fn insert_entry(&mut self, name: String, reference: AssetReference, asset: Option<Rc<T>>) -> Handle<T> {
+        if let Some(old) = self.by_name.get(&name).copied() {
+            self.remove(old);
+        }
+
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    entry: None,
+                });
+                (self.slots.len() - 1) as u32
+            },
+        };
+        let slot = &mut self.slots[index as usize];
+        let handle = Handle::new(index, slot.generation);
+        self.by_name.insert(name.clone(), handle);
+        self.by_reference.insert(reference.clone(), handle);
+        slot.entry = Some(Entry {
+            name: name,
+            reference: reference,
+            asset: asset,
+            last_used: Cell::new(tick()),
+        });
+        handle
+    }
+
+    /// Unloads an asset, returning it if the handle was still live and the asset wasn't evicted.
+    pub fn remove(&mut self, handle: Handle<T>) -> Option<Rc<T>> {
+        self.entry(handle)?;
+        let slot = &mut self.slots[handle.index as usize];
+        let entry = slot.entry.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(handle.index);
+        self.by_name.remove(&entry.name);
+        // Another asset may have been loaded from the same place since; only forget the reference if it's still ours.
+        if self.by_reference.get(&entry.reference) == Some(&handle) {
+            self.by_reference.remove(&entry.reference);
+        }
+        entry.asset
+    }
+
+    /// Drops the storage's copy of an asset while keeping its handles valid.
+    pub fn evict(&mut self, handle: Handle<T>) -> Option<Rc<T>> {
+        self.entry_mut(handle)?.asset.take()
+    }
+
+    /// Puts an evicted asset back. Returns false if the handle is stale or the asset wasn't evicted.
+    pub fn restore(&mut self, handle: Handle<T>, asset: Rc<T>) -> bool {
+        match self.entry_mut(handle) {
+            Some(entry) if entry.asset.is_none() => {
+                entry.asset = Some(asset);
+                entry.last_used.set(tick());
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Resolves a handle, counting as a use of the asset. `None` if the handle is stale or the asset is evicted.
+    pub fn get(&self, handle: Handle<T>) -> Option<&Rc<T>> {
+        let entry = self.entry(handle)?;
+        entry.last_used.set(tick());
+        entry.asset.as_ref()
+    }
+
+    /// Like `get`, without counting as a use.
+    pub fn peek(&self, handle: Handle<T>) -> Option<&Rc<T>> {
+        self.entry(handle)?.asset.as_ref()
+    }
+
+    /// Swaps in a new version of a loaded asset, keeping its handles valid. Returns the old version,
+    /// or `None` (and stores nothing) if the handle is stale or the asset is evicted.
+    pub fn replace(&mut self, handle: Handle<T>, asset: Rc<T>) -> Option<Rc<T>> {
+        let current = self.entry_mut(handle)?.asset.as_mut()?;
+        Some(mem::replace(current, asset))
+    }
+
+    /// Whether the handle still refers to something, evicted or not.
+    pub fn is_live(&self, handle: Handle<T>) -> bool {
+        self.entry(handle).is_some()
+    }
+
+    pub fn is_evicted(&self, handle: Handle<T>) -> bool {
+        self.entry(handle).is_some_and(|entry| entry.asset.is_none())
+    }
+
+    /// When the asset was last resolved, on a clock shared by every storage. Larger is more recent.
+    pub fn last_used(&self, handle: Handle<T>) -> Option<u64> {
+        self.entry(handle).map(|entry| entry.last_used.get())
+    }
+
+    pub fn find(&self, name: &str) -> Option<Handle<T>> {
+        self.by_name.get(name).copied()
+    }
+
+    pub fn find_reference(&self, reference: &AssetReference) -> Option<Handle<T>> {
+        self.by_reference.get(reference).copied()
+    }
+
+    pub fn name(&self, handle: Handle<T>) -> Option<&str> {
+        self.entry(handle).map(|entry| entry.name.as_str())
+    }
+
+    pub fn reference(&self, handle: Handle<T>) -> Option<&AssetReference> {
+        self.entry(handle).map(|entry| &entry.reference)
+    }
+
+    /// How many assets are stored, evicted ones included.
+    pub fn len(&self) -> usize {
+        self.by_name.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every asset that's currently loaded. Doesn't count as using them.
+    pub fn iter(&self) -> impl Iterator<Item = (Handle<T>, &Rc<T>)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let asset = slot.entry.as_ref()?.asset.as_ref()?;
+            Some((Handle::new(index as u32, slot.generation), asset))
+        })
+    }
+
+    /// Loaded assets nothing outside this storage holds on to.
+    pub fn unused(&self) -> Vec<Handle<T>> {
+        self.iter()
+            .filter(|(_, asset)| Rc::strong_count(asset) == 1)
+            .map(|(handle, _)| handle)
+            .collect()
+    }
+
+    fn entry(&self, handle: Handle<T>) -> Option<&Entry<T>> {
+        self.slots.get(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.entry.as_ref())
+    }
+
+    fn entry_mut(&mut self, handle: Handle<T>) -> Option<&mut Entry<T>> {
+        self.slots.get_mut(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.entry.as_mut())
+    }
+}
+
+impl<T: ?Sized> Default for AssetStorage<T> {
+    fn default() -> Self {
+        AssetStorage::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset_types::AssetType;
+    use std::collections::HashSet;
+
+    fn texture_ref(location: &str) -> AssetReference {
+        AssetReference::new(location, AssetType::Texture)
+    }
+
+    #[test]
+    fn handles_resolve_by_name_and_reference() {
+        let mut storage = AssetStorage::new();
+        let grass = storage.insert("grass", texture_ref("textures/grass.png"), Rc::new(1));
+        let dirt = storage.insert("dirt", texture_ref("textures/dirt.png"), Rc::new(2));
+
+        assert_eq!(storage.get(grass).map(|asset| **asset), Some(1));
+        assert_eq!(storage.find("dirt"), Some(dirt));
+        assert_eq!(storage.find_reference(&texture_ref("textures/grass.png")), Some(grass));
+        assert_eq!(storage.reference(dirt), Some(&texture_ref("textures/dirt.png")));
+        assert_eq!(storage.name(grass), Some("grass"));
+
+        let handles: HashSet<Handle<i32>> = vec![grass, dirt, grass].into_iter().collect();
+        assert_eq!(handles.len(), 2);
+    }
+
+    #[test]
+    fn unloading_leaves_stale_handles() {
+        let mut storage = AssetStorage::new();
+        let grass = storage.insert("grass", texture_ref("textures/grass.png"), Rc::new(1));
+        assert_eq!(storage.remove(grass).map(|asset| *asset), Some(1));
+        assert!(!storage.is_live(grass));
+        assert_eq!(storage.find("grass"), None);
+        assert_eq!(storage.remove(grass), None);
+
+        // The slot gets reused, but the old handle must not resolve to the new asset.
+        let dirt = storage.insert("dirt", texture_ref("textures/dirt.png"), Rc::new(2));
+        assert_eq!(dirt.index(), grass.index());
+        assert_ne!(dirt, grass);
+        assert_eq!(storage.get(grass), None);
+        assert_eq!(storage.get(dirt).map(|asset| **asset), Some(2));
+    }
+
+    #[test]
+    fn reloading_replaces_the_handle() {
+        let mut storage = AssetStorage::new();
+        let old = storage.insert("grass", texture_ref("textures/grass.png"), Rc::new(1));
+        let new = storage.insert("grass", texture_ref("textures/grass.png"), Rc::new(10));
+
+        assert!(!storage.is_live(old));
+        assert_eq!(storage.find("grass"), Some(new));
+        assert_eq!(storage.find_reference(&texture_ref("textures/grass.png")), Some(new));
+        assert_eq!(storage.len(), 1);
+        assert_eq!(storage.iter().map(|(handle, asset)| (handle, **asset)).collect::<Vec<_>>(), vec![(new, 10)]);
+    }
+
+    #[test]
+    fn evicted_assets_keep_their_handles() {
+        let mut storage = AssetStorage::new();
+        let grass = storage.insert("grass", texture_ref("textures/grass.png"), Rc::new(1));
+        assert_eq!(storage.evict(grass).map(|asset| *asset), Some(1));
+
+        assert!(storage.is_live(grass));
+        assert!(storage.is_evicted(grass));
+        assert_eq!(storage.get(grass), None);
+        assert_eq!(storage.iter().count(), 0);
+        assert_eq!(storage.find_reference(&texture_ref("textures/grass.png")), Some(grass));
+        assert_eq!(storage.replace(grass, Rc::new(2)), None);
+
+        assert!(storage.restore(grass, Rc::new(1)));
+        assert!(!storage.restore(grass, Rc::new(2)));
+        assert_eq!(storage.get(grass).map(|asset| **asset), Some(1));
+    }
+
+    #[test]
+    fn reserved_handles_resolve_once_restored() {
+        let mut storage = AssetStorage::new();
+        let grass = storage.reserve("grass", texture_ref("textures/grass.png"));
+        assert!(storage.is_live(grass));
+        assert_eq!(storage.get(grass), None);
+        assert_eq!(storage.find("grass"), Some(grass));
+
+        assert!(storage.restore(grass, Rc::new(1)));
+        assert_eq!(storage.get(grass).map(|asset| **asset), Some(1));
+    }
+
+    #[test]
+    fn replacing_keeps_the_handle() {
+        let mut storage = AssetStorage::new();
+        let grass = storage.insert("grass", texture_ref("textures/grass.png"), Rc::new(1));
+        assert_eq!(storage.replace(grass, Rc::new(2)).map(|asset| *asset), Some(1));
+        assert_eq!(storage.get(grass).map(|asset| **asset), Some(2));
+    }
+
+    #[test]
+    fn unused_and_recently_used_assets() {
+        let mut storage = AssetStorage::new();
+        let held = Rc::new(1);
+        let grass = storage.insert("grass", texture_ref("textures/grass.png"), held.clone());
+        let dirt = storage.insert("dirt", texture_ref("textures/dirt.png"), Rc::new(2));
+        assert_eq!(storage.unused(), vec![dirt]);
+
+        storage.get(grass);
+        assert!(storage.last_used(grass) > storage.last_used(dirt));
+        drop(held);
+        assert_eq!(storage.unused(), vec![grass, dirt]);
+    }
+}
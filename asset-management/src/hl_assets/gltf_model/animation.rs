@@ -0,0 +1,54 @@
+use nalgebra_glm as glm;
+
+/// The joints a skinned mesh is bound to. `joints` are node indices; a vertex's JOINTS_0 values index into it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skin {
+    pub name: Option<String>,
+    pub joints: Vec<usize>,
+    /// One per joint. glTF treats a missing accessor as all identity matrices, and so do we.
+    pub inverse_bind_matrices: Vec<glm::Mat4>,
+    pub skeleton: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    Linear,
+    Step,
+    /// Every keyframe has an in-tangent, a value and an out-tangent, in that order.
+    CubicSpline,
+}
+
+/// Keyframe values of one channel. With cubic spline interpolation there are three per keyframe.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChannelValues {
+    Translations(Vec<glm::Vec3>),
+    Rotations(Vec<glm::Quat>),
+    Scales(Vec<glm::Vec3>),
+    /// Flattened; every keyframe holds one weight per morph target of the node's mesh.
+    MorphWeights(Vec<f32>),
+}
+
+/// Animates one property of one node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Channel {
+    pub node: usize,
+    pub interpolation: Interpolation,
+    /// Keyframe times in seconds.
+    pub times: Vec<f32>,
+    pub values: ChannelValues,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    pub name: Option<String>,
+    pub channels: Vec<Channel>,
+}
+
+impl Animation {
+    /// When the last keyframe of any channel happens.
+    pub fn duration(&self) -> f32 {
+        self.channels.iter()
+            .filter_map(|channel| channel.times.last().copied())
+            .fold(0.0, f32::max)
+    }
+}
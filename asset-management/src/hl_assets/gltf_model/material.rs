@@ -0,0 +1,50 @@
+use crate::types::*;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlphaMode {
+    Opaque,
+    /// Fully opaque or fully transparent, depending on `alpha_cutoff`.
+    Mask,
+    Blend,
+}
+
+/// A texture a material samples, and which TEXCOORD_n set it samples with.
+pub struct MaterialTexture {
+    pub texture: Rc<Texture>,
+    pub tex_coord: u32,
+}
+
+/// A glTF metallic-roughness material. Factors multiply whatever their texture samples,
+/// or stand alone when there is no texture.
+pub struct PbrMaterial {
+    pub name: Option<String>,
+    pub base_color_factor: [f32; 4],
+    pub base_color_texture: Option<MaterialTexture>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    /// Roughness in the green channel, metalness in the blue.
+    pub metallic_roughness_texture: Option<MaterialTexture>,
+    pub normal_texture: Option<MaterialTexture>,
+    pub normal_scale: f32,
+    pub occlusion_texture: Option<MaterialTexture>,
+    pub occlusion_strength: f32,
+    pub emissive_factor: [f32; 3],
+    pub emissive_texture: Option<MaterialTexture>,
+    pub alpha_mode: AlphaMode,
+    pub alpha_cutoff: f32,
+    pub double_sided: bool,
+    pub render_state: RenderState,
+}
+
+/// Blended materials draw as transparent, and single-sided ones cull their back faces.
+pub(crate) fn material_render_state(alpha_mode: AlphaMode, double_sided: bool) -> RenderState {
+    let render_state = match alpha_mode {
+        AlphaMode::Blend => RenderState::transparent(),
+        AlphaMode::Opaque | AlphaMode::Mask => RenderState::opaque(),
+    };
+    RenderState {
+        cull: if double_sided { None } else { Some(CullFace::Back) },
+        ..render_state
+    }
+}
@@ -0,0 +1,93 @@
+use crate::types::*;
+use std::mem;
+use std::rc::Rc;
+
+// Where each glTF vertex attribute is fed to the shader. The locations are fixed rather than packed, so one
+// shader can draw any primitive that has the attributes it reads. The first three match the OBJ layout.
+pub const POSITION_LOCATION: u32     = 0;
+pub const NORMAL_LOCATION: u32       = 1;
+pub const TEXCOORD_0_LOCATION: u32   = 2;
+pub const TANGENT_LOCATION: u32      = 3;
+pub const COLOR_0_LOCATION: u32      = 4;
+/// Joint indices are uploaded as floats; cast them back with `int(...)` in the shader.
+pub const JOINTS_0_LOCATION: u32     = 5;
+pub const WEIGHTS_0_LOCATION: u32    = 6;
+pub const TEXCOORD_1_LOCATION: u32   = 7;
+
+/// One drawable part of a mesh. glTF allows a material per primitive, so each is its own model.
+pub struct GltfPrimitive {
+    pub model: Rc<ResidentModel>,
+    /// Index into `GltfAsset::materials`.
+    pub material: Option<usize>,
+}
+
+pub struct GltfMesh {
+    pub name: String,
+    pub primitives: Vec<GltfPrimitive>,
+}
+
+/// A primitive's vertices, interleaved in location order, before it's uploaded.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PrimitiveData {
+    pub vertices: Vec<f32>,
+    pub layout: VertexLayout,
+    pub indices: Vec<u32>,
+    pub material: Option<usize>,
+}
+
+/// Collects the attributes a primitive has, then interleaves them.
+pub(crate) struct AttributeSet {
+    vertex_count: usize,
+    attributes: Vec<(u32, usize, Vec<f32>)>,
+}
+
+impl AttributeSet {
+    pub fn new(vertex_count: usize) -> AttributeSet {
+        AttributeSet {
+            vertex_count: vertex_count,
+            attributes: Vec::new(),
+        }
+    }
+
+    /// Adds an attribute, returning false if it doesn't have one value per vertex.
+    pub fn push<const N: usize>(&mut self, location: u32, values: impl Iterator<Item = [f32; N]>) -> bool {
+        let flat: Vec<f32> = values.flat_map(|value| value.to_vec()).collect();
+        if flat.len() != self.vertex_count * N {
+            return false;
+        }
+        self.attributes.push((location, N, flat));
+        true
+    }
+
+    pub fn interleave(mut self) -> (Vec<f32>, VertexLayout) {
+        self.attributes.sort_by_key(|&(location, _, _)| location);
+        let float_size = mem::size_of::<f32>();
+        let components_per_vertex: usize = self.attributes.iter().map(|&(_, components, _)| components).sum();
+
+        let mut layout = VertexLayout::new(components_per_vertex * float_size);
+        let mut offset = 0;
+        for &(location, components, _) in &self.attributes {
+            layout.push(VertexAttribute {
+                location: location,
+                components: match components {
+                    1 => AttributeComponentSize::One,
+                    2 => AttributeComponentSize::Two,
+                    3 => AttributeComponentSize::Three,
+                    _ => AttributeComponentSize::Four,
+                },
+                attrib_type: GLType::Float,
+                normalized: false,
+                offset: offset,
+            });
+            offset += components * float_size;
+        }
+
+        let mut vertices = Vec::with_capacity(self.vertex_count * components_per_vertex);
+        for vertex in 0..self.vertex_count {
+            for (_, components, values) in &self.attributes {
+                vertices.extend_from_slice(&values[vertex * components..(vertex + 1) * components]);
+            }
+        }
+        (vertices, layout)
+    }
+}
@@ -0,0 +1,653 @@
+use crate::types::*;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use nalgebra_glm as glm;
+use thiserror::Error;
+
+mod animation;
+mod material;
+mod mesh;
+mod scene;
+
+pub use self::animation::*;
+pub use self::material::{AlphaMode, MaterialTexture, PbrMaterial};
+pub use self::mesh::*;
+pub use self::scene::*;
+use self::material::material_render_state;
+
+/// An encoded image a glTF file refers to. Decoding it is left to whoever resolves textures,
+/// so that images shared between files (or already loaded) end up as one texture.
+pub enum GltfImage<'a> {
+    /// An image file next to the glTF, already resolved against the glTF's directory.
+    File(PathBuf),
+    /// An image stored in the glTF itself, in a buffer view or a data URI. `key` names it uniquely;
+    /// `index` is the image's index in the file.
+    Embedded { key: String, index: usize, bytes: Cow<'a, [u8]> },
+}
+
+impl GltfImage<'_> {
+    /// The image file, or the key of an embedded image; either names the image uniquely.
+    pub fn source(&self) -> PathBuf {
+        match self {
+            GltfImage::File(path) => path.clone(),
+            GltfImage::Embedded { key, .. } => PathBuf::from(key),
+        }
+    }
+
+    /// Decodes the image without touching GL, so it can be done on any thread. Image files are read with `read`.
+    pub fn decode(&self, read: &ReadFile<'_>) -> Result<image::DynamicImage, TextureError> {
+        Ok(match self {
+            GltfImage::File(path) => image::load_from_memory(&read(path)?)?,
+            GltfImage::Embedded { bytes, .. } => image::load_from_memory(bytes)?,
+        })
+    }
+}
+
+/// Reads a file a glTF file refers to, given its path resolved against the glTF file's directory.
+pub type ReadFile<'r> = dyn Fn(&Path) -> io::Result<Vec<u8>> + 'r;
+
+/// Turns an image into a texture in the given color space, or hands back one it already made from the same image.
+pub type TextureResolver<'r> = dyn FnMut(GltfImage<'_>, ColorSpace) -> Result<Rc<Texture>, TextureError> + 'r;
+
+/// Base color and emissive textures hold sRGB colors; everything else is linear data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSpace {
+    Srgb,
+    Linear,
+}
+
+impl ColorSpace {
+    /// An RGB texture configuration that stores the texture in this color space, so sRGB textures are linearized when sampled.
+    pub fn tex_config(&self) -> TexConfig {
+        let internal_fmt = match self {
+            ColorSpace::Srgb => InternalStorage::Std_RedGreenBlue8,
+            ColorSpace::Linear => InternalStorage::RGB,
+        };
+        TexConfig::new(TextureType::Texture2D, internal_fmt, PixelDataFormat::RGB, PixelDataType::UnsignedByte)
+    }
+}
+
+/// A parsed .gltf or .glb file with every buffer resolved, but nothing uploaded yet.
+pub struct GltfDocument {
+    path: PathBuf,
+    document: gltf::Document,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl GltfDocument {
+    /// Parses the file and loads its buffers: the binary chunk of a .glb, base64 data URIs and external files.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<GltfDocument, GltfError> {
+        let path = path.as_ref();
+        GltfDocument::from_slice(path, &fs::read(path)?, &|file| fs::read(file))
+    }
+
+    /// Like `open`, for a file that was already read from `path`. External buffers are read with `read`.
+    pub fn from_slice(path: &Path, bytes: &[u8], read: &ReadFile<'_>) -> Result<GltfDocument, GltfError> {
+        let gltf::Gltf { document, mut blob } = gltf::Gltf::from_slice(bytes)?;
+
+        let mut buffers = Vec::new();
+        for buffer in document.buffers() {
+            let mut data = match buffer.source() {
+                gltf::buffer::Source::Bin => blob.take().ok_or(GltfError::MissingBinaryChunk(buffer.index()))?,
+                gltf::buffer::Source::Uri(uri) => match decode_data_uri(uri) {
+                    Some(decoded) => decoded.ok_or(GltfError::BadDataUri(buffer.index()))?,
+                    None => read(&resolve_uri(path, uri))?,
+                },
+            };
+            if data.len() < buffer.length() {
+                return Err(GltfError::BufferTooShort(buffer.index()));
+            }
+            // The binary chunk is padded to 4 bytes; only the declared length is the buffer.
+            data.truncate(buffer.length());
+            buffers.push(data);
+        }
+
+        Ok(GltfDocument {
+            path: path.to_owned(),
+            document: document,
+            buffers: buffers,
+        })
+    }
+
+    pub fn nodes(&self) -> Vec<SceneNode> {
+        let mut nodes: Vec<SceneNode> = self.document.nodes().map(|node| {
+            let (translation, rotation, scale) = node.transform().decomposed();
+            SceneNode {
+                name: node.name().map(str::to_owned),
+                transform: Transform {
+                    translation: glm::make_vec3(&translation),
+                    rotation: glm::quat(rotation[0], rotation[1], rotation[2], rotation[3]),
+                    scale: glm::make_vec3(&scale),
+                },
+                parent: None,
+                children: node.children().map(|child| child.index()).collect(),
+                mesh: node.mesh().map(|mesh| mesh.index()),
+                skin: node.skin().map(|skin| skin.index()),
+            }
+        }).collect();
+
+        // glTF only stores the downward links.
+        for idx in 0..nodes.len() {
+            for child in nodes[idx].children.clone() {
+                nodes[child].parent = Some(idx);
+            }
+        }
+        nodes
+    }
+
+    pub fn scenes(&self) -> Vec<GltfScene> {
+        self.document.scenes().map(|scene| GltfScene {
+            name: scene.name().map(str::to_owned),
+            roots: scene.nodes().map(|node| node.index()).collect(),
+        }).collect()
+    }
+
+    pub fn default_scene(&self) -> Option<usize> {
+        self.document.default_scene().map(|scene| scene.index())
+    }
+
+    pub fn skins(&self) -> Vec<Skin> {
+        self.document.skins().map(|skin| {
+            let joints: Vec<usize> = skin.joints().map(|joint| joint.index()).collect();
+            let inverse_bind_matrices = match skin.reader(|buffer| self.buffer(buffer)).read_inverse_bind_matrices() {
+                Some(matrices) => matrices.map(glm::Mat4::from).collect(),
+                None => vec![glm::Mat4::identity(); joints.len()],
+            };
+            Skin {
+                name: skin.name().map(str::to_owned),
+                joints: joints,
+                inverse_bind_matrices: inverse_bind_matrices,
+                skeleton: skin.skeleton().map(|node| node.index()),
+            }
+        }).collect()
+    }
+
+    pub fn animations(&self) -> Vec<Animation> {
+        use gltf::animation::util::ReadOutputs;
+
+        self.document.animations().map(|animation| {
+            let channels = animation.channels().filter_map(|channel| {
+                let reader = channel.reader(|buffer| self.buffer(buffer));
+                let values = match reader.read_outputs()? {
+                    ReadOutputs::Translations(values) => ChannelValues::Translations(values.map(|v| glm::make_vec3(&v)).collect()),
+                    ReadOutputs::Rotations(values) => ChannelValues::Rotations(values.into_f32().map(|q| glm::quat(q[0], q[1], q[2], q[3])).collect()),
+                    ReadOutputs::Scales(values) => ChannelValues::Scales(values.map(|v| glm::make_vec3(&v)).collect()),
+                    ReadOutputs::MorphTargetWeights(values) => ChannelValues::MorphWeights(values.into_f32().collect()),
+                };
+                Some(Channel {
+                    node: channel.target().node().index(),
+                    interpolation: match channel.sampler().interpolation() {
+                        gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                        gltf::animation::Interpolation::Step => Interpolation::Step,
+                        gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+                    },
+                    times: reader.read_inputs()?.collect(),
+                    values: values,
+                })
+            }).collect();
+            Animation {
+                name: animation.name().map(str::to_owned),
+                channels: channels,
+            }
+        }).collect()
+    }
+
+    /// Reads every primitive of a mesh into interleaved vertices. Texture coordinates are flipped vertically,
+    /// since glTF puts their origin at the top left and textures are uploaded bottom row first.
+    pub(crate) fn primitives(&self, mesh: &gltf::Mesh<'_>) -> Result<Vec<PrimitiveData>, GltfError> {
+        let mut primitives = Vec::new();
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                return Err(GltfError::UnsupportedMode(mesh.index(), primitive.mode()));
+            }
+            let reader = primitive.reader(|buffer| self.buffer(buffer));
+            let positions: Vec<[f32; 3]> = reader.read_positions().ok_or(GltfError::MissingPositions(mesh.index()))?.collect();
+            let vertex_count = positions.len();
+            let flip = |[u, v]: [f32; 2]| [u, 1.0 - v];
+
+            let mut attributes = AttributeSet::new(vertex_count);
+            let mut lines_up = attributes.push(POSITION_LOCATION, positions.into_iter());
+            if let Some(normals) = reader.read_normals() {
+                lines_up &= attributes.push(NORMAL_LOCATION, normals);
+            }
+            if let Some(tex_coords) = reader.read_tex_coords(0) {
+                lines_up &= attributes.push(TEXCOORD_0_LOCATION, tex_coords.into_f32().map(flip));
+            }
+            if let Some(tangents) = reader.read_tangents() {
+                lines_up &= attributes.push(TANGENT_LOCATION, tangents);
+            }
+            if let Some(colors) = reader.read_colors(0) {
+                lines_up &= attributes.push(COLOR_0_LOCATION, colors.into_rgba_f32());
+            }
+            if let Some(joints) = reader.read_joints(0) {
+                lines_up &= attributes.push(JOINTS_0_LOCATION, joints.into_u16().map(|j| [j[0] as f32, j[1] as f32, j[2] as f32, j[3] as f32]));
+            }
+            if let Some(weights) = reader.read_weights(0) {
+                lines_up &= attributes.push(WEIGHTS_0_LOCATION, weights.into_f32());
+            }
+            if let Some(tex_coords) = reader.read_tex_coords(1) {
+                lines_up &= attributes.push(TEXCOORD_1_LOCATION, tex_coords.into_f32().map(flip));
+            }
+            if !lines_up {
+                return Err(GltfError::MismatchedAttributes(mesh.index()));
+            }
+
+            let (vertices, layout) = attributes.interleave();
+            primitives.push(PrimitiveData {
+                vertices: vertices,
+                layout: layout,
+                // Non-indexed primitives draw their vertices in order.
+                indices: match reader.read_indices() {
+                    Some(indices) => indices.into_u32().collect(),
+                    None => (0..vertex_count as u32).collect(),
+                },
+                material: primitive.material().index(),
+            });
+        }
+        Ok(primitives)
+    }
+
+    pub fn image_count(&self) -> usize {
+        self.document.images().count()
+    }
+
+    /// Where an image's encoded data lives.
+    pub fn image(&self, index: usize) -> Result<GltfImage<'_>, GltfError> {
+        let image = self.document.images().nth(index).ok_or(GltfError::MissingImage(index))?;
+        let key = format!("{}#image{}", self.path.display(), index);
+        match image.source() {
+            gltf::image::Source::View { view, .. } => {
+                let buffer = &self.buffers[view.buffer().index()];
+                let end = view.offset() + view.length();
+                if end > buffer.len() {
+                    return Err(GltfError::BufferTooShort(view.buffer().index()));
+                }
+                Ok(GltfImage::Embedded { key: key, index: index, bytes: Cow::Borrowed(&buffer[view.offset()..end]) })
+            },
+            gltf::image::Source::Uri { uri, .. } => match decode_data_uri(uri) {
+                Some(decoded) => Ok(GltfImage::Embedded { key: key, index: index, bytes: Cow::Owned(decoded.ok_or(GltfError::BadImageUri(index))?) }),
+                None => Ok(GltfImage::File(resolve_uri(&self.path, uri))),
+            },
+        }
+    }
+
+    /// Uploads a single primitive, without loading the rest of the file or any textures.
+    pub fn load_primitive(&self, gl_ctx: Rc<GLContext>, mesh: usize, primitive: usize) -> Result<ResidentModel, GltfError> {
+        let gltf_mesh = self.document.meshes().nth(mesh).ok_or(GltfError::MissingPrimitive(mesh, primitive))?;
+        let data = self.primitives(&gltf_mesh)?.into_iter().nth(primitive).ok_or(GltfError::MissingPrimitive(mesh, primitive))?;
+        let render_state = match data.material.and_then(|idx| self.document.materials().nth(idx)) {
+            Some(mat) => material_render_state(convert_alpha_mode(mat.alpha_mode()), mat.double_sided()),
+            None => material_render_state(AlphaMode::Opaque, false),
+        };
+        Ok(upload_primitive(gl_ctx, &mesh_name(&gltf_mesh), data, render_state))
+    }
+
+    fn buffer(&self, buffer: gltf::Buffer<'_>) -> Option<&[u8]> {
+        self.buffers.get(buffer.index()).map(|data| data.as_slice())
+    }
+}
+
+/// Everything in a glTF file, uploaded and ready to draw. Nodes, skins and animations are plain data
+/// that refer to each other (and to meshes and materials) by index.
+pub struct GltfAsset {
+    pub meshes: Vec<GltfMesh>,
+    pub materials: Vec<Rc<PbrMaterial>>,
+    pub nodes: Vec<SceneNode>,
+    pub scenes: Vec<GltfScene>,
+    pub default_scene: Option<usize>,
+    pub skins: Vec<Skin>,
+    pub animations: Vec<Animation>,
+}
+
+/// A glTF file with its primitives read and, optionally, its images decoded: everything that doesn't need GL,
+/// so it can be done on any thread before `GltfAsset::upload`.
+pub struct DecodedGltf {
+    doc: GltfDocument,
+    primitives: Vec<Vec<PrimitiveData>>,
+    // Decoded images by `GltfImage::source`.
+    images: HashMap<PathBuf, image::DynamicImage>,
+}
+
+impl DecodedGltf {
+    /// Opens the file and reads every primitive. Images are left to the texture resolver.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<DecodedGltf, GltfError> {
+        DecodedGltf::new(GltfDocument::open(path)?)
+    }
+
+    /// Reads every primitive of a document that was already opened.
+    pub fn new(doc: GltfDocument) -> Result<DecodedGltf, GltfError> {
+        let mut primitives = Vec::new();
+        for mesh in doc.document.meshes() {
+            primitives.push(doc.primitives(&mesh)?);
+        }
+        Ok(DecodedGltf {
+            doc: doc,
+            primitives: primitives,
+            images: HashMap::new(),
+        })
+    }
+
+    /// Decodes every image up front as well, reading image files with `read`. Images that fail to decode are skipped;
+    /// resolving them later reports the error.
+    pub fn decode_images(&mut self, read: &ReadFile<'_>) {
+        for index in 0..self.doc.document.images().count() {
+            if let Ok(image) = self.doc.image(index) {
+                if let Ok(decoded) = image.decode(read) {
+                    self.images.insert(image.source(), decoded);
+                }
+            }
+        }
+    }
+
+    /// The images `decode_images` decoded, by `GltfImage::source`, for the texture resolver to use.
+    pub fn take_images(&mut self) -> HashMap<PathBuf, image::DynamicImage> {
+        mem::take(&mut self.images)
+    }
+}
+
+impl GltfAsset {
+    /// Loads and uploads a .gltf or .glb file.
+    /// Images are handed to `resolve_texture` rather than decoded here, so the caller can share them.
+    pub fn load<P: AsRef<Path>>(
+        gl_ctx: Rc<GLContext>,
+        path: P,
+        resolve_texture: &mut TextureResolver<'_>,
+    ) -> Result<GltfAsset, GltfError> {
+        GltfAsset::upload(gl_ctx, DecodedGltf::read(path)?, resolve_texture)
+    }
+
+    /// Uploads a file that was already read, possibly on another thread.
+    pub fn upload(
+        gl_ctx: Rc<GLContext>,
+        decoded: DecodedGltf,
+        resolve_texture: &mut TextureResolver<'_>,
+    ) -> Result<GltfAsset, GltfError> {
+        let DecodedGltf { doc, primitives: mesh_primitives, .. } = decoded;
+
+        let mut materials = Vec::new();
+        for mat in doc.document.materials() {
+            materials.push(Rc::new(doc.load_material(&mat, resolve_texture)?));
+        }
+
+        let mut meshes = Vec::new();
+        for (mesh, mesh_data) in doc.document.meshes().zip(mesh_primitives) {
+            let name = mesh_name(&mesh);
+            let mut primitives = Vec::new();
+            for data in mesh_data {
+                let material = data.material;
+                let render_state = material
+                    .and_then(|idx| materials.get(idx))
+                    .map(|mat: &Rc<PbrMaterial>| mat.render_state)
+                    .unwrap_or_else(|| material_render_state(AlphaMode::Opaque, false));
+                primitives.push(GltfPrimitive {
+                    model: Rc::new(upload_primitive(gl_ctx.clone(), &name, data, render_state)),
+                    material: material,
+                });
+            }
+            meshes.push(GltfMesh {
+                name: name,
+                primitives: primitives,
+            });
+        }
+
+        Ok(GltfAsset {
+            meshes: meshes,
+            materials: materials,
+            nodes: doc.nodes(),
+            scenes: doc.scenes(),
+            default_scene: doc.default_scene(),
+            skins: doc.skins(),
+            animations: doc.animations(),
+        })
+    }
+
+    /// Roughly how much memory the plain data takes. Meshes live on the GPU and textures are shared, so neither counts.
+    pub fn cpu_bytes(&self) -> usize {
+        let nodes: usize = self.nodes.iter()
+            .map(|node| mem::size_of::<SceneNode>() + node.children.len() * mem::size_of::<usize>())
+            .sum();
+        let skins: usize = self.skins.iter()
+            .map(|skin| mem::size_of::<Skin>() + skin.joints.len() * mem::size_of::<usize>() + skin.inverse_bind_matrices.len() * mem::size_of::<glm::Mat4>())
+            .sum();
+        let keyframes: usize = self.animations.iter()
+            .flat_map(|animation| animation.channels.iter())
+            .map(|channel| mem::size_of::<Channel>() + channel.times.len() * mem::size_of::<f32>() + match &channel.values {
+                ChannelValues::Translations(values) | ChannelValues::Scales(values) => values.len() * mem::size_of::<glm::Vec3>(),
+                ChannelValues::Rotations(values) => values.len() * mem::size_of::<glm::Quat>(),
+                ChannelValues::MorphWeights(values) => values.len() * mem::size_of::<f32>(),
+            })
+            .sum();
+        mem::size_of::<GltfAsset>() + nodes + skins + keyframes
+    }
+}
+
+fn mesh_name(mesh: &gltf::Mesh<'_>) -> String {
+    mesh.name().map(str::to_owned).unwrap_or_else(|| format!("mesh{}", mesh.index()))
+}
+
+fn upload_primitive(gl_ctx: Rc<GLContext>, name: &str, data: PrimitiveData, render_state: RenderState) -> ResidentModel {
+    let model = ResidentModel::from_layout(gl_ctx, data.layout, data.vertices.as_slice(), &IndexData::compact(&data.indices))
+        .with_render_state(render_state);
+    model.set_label(name);
+    model
+}
+
+fn convert_alpha_mode(alpha_mode: gltf::material::AlphaMode) -> AlphaMode {
+    match alpha_mode {
+        gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+        gltf::material::AlphaMode::Mask => AlphaMode::Mask,
+        gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+    }
+}
+
+impl GltfDocument {
+    fn load_material(&self, mat: &gltf::Material<'_>, resolve_texture: &mut TextureResolver<'_>) -> Result<PbrMaterial, GltfError> {
+        let mut load = |texture: gltf::Texture<'_>, tex_coord: u32, color_space: ColorSpace| -> Result<MaterialTexture, GltfError> {
+            // TODO: glTF samplers (wrap and filter modes) are ignored, like everywhere else textures get loaded.
+            let image = self.image(texture.source().index())?;
+            Ok(MaterialTexture {
+                texture: resolve_texture(image, color_space)?,
+                tex_coord: tex_coord,
+            })
+        };
+
+        let pbr = mat.pbr_metallic_roughness();
+        let alpha_mode = convert_alpha_mode(mat.alpha_mode());
+        Ok(PbrMaterial {
+            name: mat.name().map(str::to_owned),
+            base_color_factor: pbr.base_color_factor(),
+            base_color_texture: pbr.base_color_texture().map(|info| load(info.texture(), info.tex_coord(), ColorSpace::Srgb)).transpose()?,
+            metallic_factor: pbr.metallic_factor(),
+            roughness_factor: pbr.roughness_factor(),
+            metallic_roughness_texture: pbr.metallic_roughness_texture().map(|info| load(info.texture(), info.tex_coord(), ColorSpace::Linear)).transpose()?,
+            normal_texture: mat.normal_texture().map(|info| load(info.texture(), info.tex_coord(), ColorSpace::Linear)).transpose()?,
+            normal_scale: mat.normal_texture().map_or(1.0, |info| info.scale()),
+            occlusion_texture: mat.occlusion_texture().map(|info| load(info.texture(), info.tex_coord(), ColorSpace::Linear)).transpose()?,
+            occlusion_strength: mat.occlusion_texture().map_or(1.0, |info| info.strength()),
+            emissive_factor: mat.emissive_factor(),
+            emissive_texture: mat.emissive_texture().map(|info| load(info.texture(), info.tex_coord(), ColorSpace::Srgb)).transpose()?,
+            alpha_mode: alpha_mode,
+            alpha_cutoff: mat.alpha_cutoff(),
+            double_sided: mat.double_sided(),
+            render_state: material_render_state(alpha_mode, mat.double_sided()),
+        })
+    }
+}
+
+/// `Some` for `data:` URIs (with `None` inside if it isn't valid base64), `None` for anything else.
+fn decode_data_uri(uri: &str) -> Option<Option<Vec<u8>>> {
+    if !uri.starts_with("data:") {
+        return None;
+    }
+    let (header, data) = match uri.find(',') {
+        Some(comma) => (&uri[..comma], &uri[comma + 1..]),
+        None => return Some(None),
+    };
+    if !header.ends_with(";base64") {
+        return Some(None);
+    }
+    Some(base64::decode(data).ok())
+}
+
+/// Relative URIs are relative to the glTF file. Spaces and the like arrive percent-encoded.
+fn resolve_uri(gltf_path: &Path, uri: &str) -> PathBuf {
+    let mut decoded = Vec::with_capacity(uri.len());
+    let bytes = uri.as_bytes();
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let hex = bytes.get(idx + 1..idx + 3).and_then(|hex| std::str::from_utf8(hex).ok()).and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[idx], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                idx += 3;
+            },
+            (byte, _) => {
+                decoded.push(byte);
+                idx += 1;
+            },
+        }
+    }
+    let relative = String::from_utf8_lossy(&decoded).into_owned();
+    gltf_path.parent().unwrap_or_else(|| Path::new("")).join(relative)
+}
+
+#[derive(Error, Debug)]
+pub enum GltfError {
+    #[error("Parsing the glTF file failed: {0}")]
+    GltfError(gltf::Error),
+    #[error("Reading a glTF buffer failed: {0}")]
+    IOError(io::Error),
+    #[error("Buffer {0} refers to the binary chunk, but the file doesn't have one")]
+    MissingBinaryChunk(usize),
+    #[error("Buffer {0} has a data URI that isn't base64")]
+    BadDataUri(usize),
+    #[error("Buffer {0} is shorter than the data it is supposed to hold")]
+    BufferTooShort(usize),
+    #[error("Image {0} has a data URI that isn't base64")]
+    BadImageUri(usize),
+    #[error("The file has no image {0}")]
+    MissingImage(usize),
+    #[error("Mesh {0} has a primitive without positions")]
+    MissingPositions(usize),
+    #[error("Mesh {0} has a primitive whose attributes have different vertex counts")]
+    MismatchedAttributes(usize),
+    #[error("Mesh {0} has no primitive {1}")]
+    MissingPrimitive(usize, usize),
+    #[error("Mesh {0} uses {1:?} primitives, only triangles are supported")]
+    UnsupportedMode(usize, gltf::mesh::Mode),
+    #[error("Loading a glTF texture failed: {0}")]
+    TextureError(TextureError),
+    #[error("Uploading a glTF mesh failed: {0}")]
+    OpenGLError(OpenGLError),
+}
+
+impl From<gltf::Error> for GltfError {
+    fn from(err: gltf::Error) -> Self {
+        GltfError::GltfError(err)
+    }
+}
+
+impl From<io::Error> for GltfError {
+    fn from(err: io::Error) -> Self {
+        GltfError::IOError(err)
+    }
+}
+
+impl From<TextureError> for GltfError {
+    fn from(err: TextureError) -> Self {
+        GltfError::TextureError(err)
+    }
+}
+
+impl From<OpenGLError> for GltfError {
+    fn from(err: OpenGLError) -> Self {
+        GltfError::OpenGLError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> GltfDocument {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../assets/test").join(name);
+        GltfDocument::open(path).expect("failed to open fixture")
+    }
+
+    #[test]
+    fn node_hierarchy_and_world_transforms() {
+        let doc = fixture("rigged_triangle.gltf");
+        let nodes = doc.nodes();
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(nodes[0].children, vec![1, 2]);
+        assert_eq!(nodes[1].parent, Some(0));
+        assert_eq!(nodes[1].mesh, Some(0));
+        assert_eq!(nodes[1].skin, Some(0));
+        assert_eq!(doc.scenes()[0].roots, vec![0]);
+
+        // The root is moved along x, the joint along y on top of that.
+        let world = world_transforms(&nodes);
+        let joint_origin = world[2] * glm::vec4(0.0, 0.0, 0.0, 1.0);
+        assert_eq!(joint_origin, glm::vec4(1.0, 2.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn primitives_interleave_every_attribute() {
+        let doc = fixture("rigged_triangle.gltf");
+        let mesh = doc.document.meshes().next().unwrap();
+        let primitives = doc.primitives(&mesh).expect("failed to read primitives");
+        let data = &primitives[0];
+
+        // position, normal, texcoord, joints and weights: 3 + 3 + 2 + 4 + 4 floats.
+        assert_eq!(data.layout.stride(), 16 * 4);
+        let locations: Vec<u32> = data.layout.attributes().iter().map(|attr| attr.location).collect();
+        assert_eq!(locations, vec![POSITION_LOCATION, NORMAL_LOCATION, TEXCOORD_0_LOCATION, JOINTS_0_LOCATION, WEIGHTS_0_LOCATION]);
+        assert_eq!(data.indices, vec![0, 1, 2]);
+        assert_eq!(data.material, Some(0));
+
+        // Second vertex: its texcoord (1, 0) comes out flipped, and it's bound to joint 1 only.
+        let second = &data.vertices[16..32];
+        assert_eq!(&second[0..3], &[1.0, 0.0, 0.0]);
+        assert_eq!(&second[6..8], &[1.0, 1.0]);
+        assert_eq!(&second[8..12], &[1.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn skins_and_animations() {
+        let doc = fixture("rigged_triangle.gltf");
+        let skins = doc.skins();
+        assert_eq!(skins[0].joints, vec![0, 2]);
+        assert_eq!(skins[0].inverse_bind_matrices.len(), 2);
+        assert_eq!(skins[0].inverse_bind_matrices[1] * glm::vec4(1.0, 2.0, 0.0, 1.0), glm::vec4(0.0, 0.0, 0.0, 1.0));
+
+        let animations = doc.animations();
+        assert_eq!(animations[0].name.as_deref(), Some("wave"));
+        let channel = &animations[0].channels[0];
+        assert_eq!(channel.node, 2);
+        assert_eq!(channel.interpolation, Interpolation::Linear);
+        assert_eq!(channel.times, vec![0.0, 1.5]);
+        match &channel.values {
+            ChannelValues::Rotations(rotations) => assert_eq!(rotations.len(), 2),
+            other => panic!("expected rotations, got {:?}", other),
+        }
+        assert_eq!(animations[0].duration(), 1.5);
+    }
+
+    #[test]
+    fn binary_chunk_and_external_images() {
+        let glb = fixture("rigged_triangle.glb");
+        assert_eq!(glb.nodes(), fixture("rigged_triangle.gltf").nodes());
+        let mesh = glb.document.meshes().next().unwrap();
+        assert_eq!(glb.primitives(&mesh).unwrap()[0].indices, vec![0, 1, 2]);
+
+        match glb.image(0).unwrap() {
+            GltfImage::File(path) => assert!(path.ends_with("test/checker texture.png")),
+            GltfImage::Embedded { .. } => panic!("expected the image to be external"),
+        }
+    }
+}
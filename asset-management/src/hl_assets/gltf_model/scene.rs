@@ -0,0 +1,64 @@
+use nalgebra_glm as glm;
+
+/// A node's local transform, relative to its parent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: glm::Vec3,
+    pub rotation: glm::Quat,
+    pub scale: glm::Vec3,
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform {
+            translation: glm::vec3(0.0, 0.0, 0.0),
+            rotation: glm::quat_identity(),
+            scale: glm::vec3(1.0, 1.0, 1.0),
+        }
+    }
+
+    /// Translation * rotation * scale, the order glTF composes them in.
+    pub fn matrix(&self) -> glm::Mat4 {
+        glm::translation(&self.translation) * glm::quat_to_mat4(&self.rotation) * glm::scaling(&self.scale)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Transform {
+        Transform::identity()
+    }
+}
+
+/// One node of the transform hierarchy. Meshes, skins, children and parents are indices into the
+/// lists of the `GltfAsset` they were loaded with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneNode {
+    pub name: Option<String>,
+    pub transform: Transform,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub mesh: Option<usize>,
+    pub skin: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfScene {
+    pub name: Option<String>,
+    pub roots: Vec<usize>,
+}
+
+/// The world matrix of every node, indexed like `nodes`.
+pub fn world_transforms(nodes: &[SceneNode]) -> Vec<glm::Mat4> {
+    let mut world = vec![glm::Mat4::identity(); nodes.len()];
+    // Walk down from every root so parents are always resolved before their children.
+    let mut pending: Vec<(usize, glm::Mat4)> = nodes.iter().enumerate()
+        .filter(|(_, node)| node.parent.is_none())
+        .map(|(idx, _)| (idx, glm::Mat4::identity()))
+        .collect();
+    while let Some((idx, parent_world)) = pending.pop() {
+        let node_world = parent_world * nodes[idx].transform.matrix();
+        world[idx] = node_world;
+        pending.extend(nodes[idx].children.iter().map(|&child| (child, node_world)));
+    }
+    world
+}
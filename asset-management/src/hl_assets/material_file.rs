@@ -0,0 +1,110 @@
+use crate::types::*;
+use crate::namespace::AssetId;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// A material as it's written in a `.json` material file:
+///
+/// ```json
+/// {
+///     "program": "core:shader_basic",
+///     "defines": { "USE_FOG": "" },
+///     "textures": { "albedo": { "texture": "core:textures/stone", "srgb": true } },
+///     "parameters": { "tint": [1.0, 0.9, 0.8], "roughness": 0.5 },
+///     "render_state": "opaque"
+/// }
+/// ```
+///
+/// Everything but the program is optional. Textures are bound to the sampler they're listed under.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MaterialFile {
+    pub program: AssetId,
+    /// Compiled into the program's shaders, which makes the material use a variant of the program.
+    /// An empty value defines the name without a value.
+    #[serde(default)]
+    pub defines: BTreeMap<String, String>,
+    #[serde(default)]
+    pub textures: BTreeMap<String, TextureBinding>,
+    #[serde(default)]
+    pub parameters: BTreeMap<String, UniformValue>,
+    #[serde(default)]
+    pub render_state: RenderPreset,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TextureBinding {
+    /// Used as it is if a texture with this name is loaded already, or loaded from the file the id names.
+    pub texture: AssetId,
+    /// Whether a texture loaded for the binding holds colors, rather than data like normals.
+    #[serde(default)]
+    pub srgb: bool,
+}
+
+/// The render states a material file can pick from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RenderPreset {
+    #[default]
+    Opaque,
+    Transparent,
+    Skybox,
+    Screenspace,
+}
+
+impl RenderPreset {
+    pub fn render_state(self) -> RenderState {
+        match self {
+            RenderPreset::Opaque => RenderState::opaque(),
+            RenderPreset::Transparent => RenderState::transparent(),
+            RenderPreset::Skybox => RenderState::skybox(),
+            RenderPreset::Screenspace => RenderState::screenspace(),
+        }
+    }
+}
+
+impl MaterialFile {
+    pub fn from_slice(json: &[u8]) -> Result<MaterialFile, serde_json::Error> {
+        serde_json::from_slice(json)
+    }
+
+    /// The defines as the shaders get them, in a stable order.
+    pub fn define_list(&self) -> Vec<(String, String)> {
+        self.defines.iter().map(|(name, value)| (name.clone(), value.clone())).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn material_files_parse() {
+        let file = MaterialFile::from_slice(br#"{
+            "program": "shader_basic",
+            "defines": { "USE_FOG": "", "LIGHTS": "4" },
+            "textures": { "albedo": { "texture": "core:textures/stone", "srgb": true }, "normals": { "texture": "textures/stone_n" } },
+            "parameters": { "tint": [1.0, 0.9, 0.8], "roughness": 0.5, "layers": 2, "lit": true },
+            "render_state": "transparent"
+        }"#).unwrap();
+
+        assert_eq!(file.program.to_string(), "core:shader_basic");
+        assert_eq!(file.define_list(), vec![("LIGHTS".to_string(), "4".to_string()), ("USE_FOG".to_string(), String::new())]);
+        assert!(file.textures["albedo"].srgb);
+        assert!(!file.textures["normals"].srgb);
+        assert_eq!(file.parameters["tint"], UniformValue::Vec3([1.0, 0.9, 0.8]));
+        assert_eq!(file.parameters["roughness"], UniformValue::Float(0.5));
+        assert_eq!(file.parameters["layers"], UniformValue::Int(2));
+        assert_eq!(file.parameters["lit"], UniformValue::Bool(true));
+        assert_eq!(file.render_state, RenderPreset::Transparent);
+    }
+
+    #[test]
+    fn only_the_program_is_required() {
+        let file = MaterialFile::from_slice(br#"{ "program": "core:shader_basic" }"#).unwrap();
+        assert!(file.defines.is_empty() && file.textures.is_empty() && file.parameters.is_empty());
+        assert_eq!(file.render_state, RenderPreset::Opaque);
+        assert!(MaterialFile::from_slice(br#"{ "textures": {} }"#).is_err());
+    }
+}
@@ -1,3 +1,7 @@
 mod obj_model;
+mod gltf_model;
+mod material_file;
 
-pub use obj_model::*;
\ No newline at end of file
+pub use obj_model::*;
+pub use gltf_model::*;
+pub use material_file::*;
@@ -1,132 +1,331 @@
 use crate::types::*;
-use ace_gl_types::gl;
+use crate::cook::{optimize_mesh, CookedMesh, CookedModel};
+use crate::meta::MeshImport;
+use std::cell::RefCell;
 use std::rc::Rc;
+use std::mem;
 use tobj;
+use std::io;
 use std::path::Path;
 use nalgebra;
+use thiserror::Error;
+
+/// Attribute locations an OBJ mesh is uploaded with. Position is always at 0, then normal and texture coordinates
+/// take the next free locations in that order, so a position + texcoord mesh reads its texcoords from 1.
+const POSITION_LOCATION: u32 = 0;
 
 pub struct ObjModel {
-    vertices: nalgebra::DMatrix<f32>,
-    indices: Vec<u32>,
-    material: Option<String>,
+    name: String,
+    material: Option<Rc<Material>>,
 
+    layout: VertexLayout,
     vao: VertexArrayObj,
     vbo: VertexBufferObj,
     eao: ElementArrayObj,
-
-    shader: CompiledShaderProgram,
+    render_state: RenderState,
 }
 
 impl ObjModel {
-    /// Loads all of the models and materials associated with one object file
-    pub fn from_file<S: AsRef<Path> + ::std::fmt::Debug> (gl_ctx: Rc<gl::Gl>, loc: S) -> Result<(Vec<ObjModel>, Vec<Material>), tobj::LoadError> {
+    /// Loads all of the models and materials associated with one object file.
+    /// Every mesh gets its own model, with the render state of its material. The materials don't have a program yet.
+    pub fn from_file<S: AsRef<Path> + ::std::fmt::Debug> (gl_ctx: Rc<GLContext>, loc: S) -> Result<(Vec<ObjModel>, Vec<Rc<Material>>), ObjLoadError> {
 
         let (models, materials) = tobj::load_obj(loc, true)?;
+        ObjModel::upload_all(gl_ctx, models, materials, &MeshImport::default())
+    }
 
-        let mut model_result: Vec<ObjModel> = Vec::new();
-        let material_result: Vec<Material> = materials.into_iter().map(|mat| Material::from(mat)).collect();
+    /// Like `from_file`, for an OBJ file that was already read, with its meshes converted by `import`. `load_mtl` is
+    /// handed the material libraries the file refers to, as written in it.
+    pub fn from_bytes(gl_ctx: Rc<GLContext>, obj: &[u8], load_mtl: impl Fn(&Path) -> tobj::MTLLoadResult, import: &MeshImport) -> Result<(Vec<ObjModel>, Vec<Rc<Material>>), ObjLoadError> {
+        let (models, materials) = tobj::load_obj_buf(&mut &obj[..], true, load_mtl)?;
+        ObjModel::upload_all(gl_ctx, models, materials, import)
+    }
 
-        // first we iterate over all of the models, then all of the materials
-        // maybe should be backwards, so that we can reference the material name for the model
-        for model in models {
-            let mesh = model.mesh;
-            let mat_name = mesh.material_id.and_then(|id| material_result.get(id)).map(|x| x.name.clone());
-            // big branch here; generate an object model based on the vertex data provided by the mesh
-            if mesh.normals.len() == 0 && mesh.texcoords.len() == 0 {
-                model_result.push(ObjModel::from_mesh(gl_ctx.clone(), mesh, mat_name.clone()));
-            } else if mesh.normals.len() == 0 {
-                model_result.push(ObjModel::from_mesh_tex(gl_ctx.clone(), mesh, mat_name.clone()));
-            } else if mesh.texcoords.len() == 0 {
-                model_result.push(ObjModel::from_mesh_norm(gl_ctx.clone(), mesh, mat_name.clone()));
-            } else {
-                model_result.push(ObjModel::from_mesh_texnorm(gl_ctx.clone(), mesh, mat_name.clone()));
-            }
-            
+    /// Uploads an OBJ file cooked by `cook`, which already converted its meshes. `load_mtl` is handed its material libraries, as they were written in the OBJ file.
+    pub fn from_cooked(gl_ctx: Rc<GLContext>, cooked: &CookedModel, load_mtl: impl Fn(&Path) -> tobj::MTLLoadResult) -> Result<(Vec<ObjModel>, Vec<Rc<Material>>), ObjLoadError> {
+        let mut materials = Vec::new();
+        for library in &cooked.material_libraries {
+            materials.extend(load_mtl(library)?.0);
+        }
+        let material_result = ObjModel::convert_materials(materials);
+
+        let mut model_result = Vec::new();
+        for mesh in &cooked.meshes {
+            let material = mesh.material.as_ref()
+                .and_then(|name| material_result.iter().find(|mat| &mat.name == name))
+                .cloned();
+            let mesh_data = MeshData::from_cooked(mesh)?;
+            model_result.push(ObjModel::upload(gl_ctx.clone(), mesh.name.clone(), mesh_data, &mesh.indices, material));
         }
 
         Ok((model_result, material_result))
     }
 
-    fn from_mesh_texnorm(gl_ctx: Rc<gl::Gl>, mesh: tobj::Mesh, mat_name: Option<String>) -> ObjModel {
-        let num_rows = mesh.positions.len() / 3;
-        let internal_matrix = nalgebra::DMatrix::from_column_slice(3, num_rows, &mesh.positions);
-        let tex_matrix = nalgebra::DMatrix::from_column_slice(2, num_rows, &mesh.texcoords);
-        let norm_matrix = nalgebra::DMatrix::from_column_slice(3, num_rows, &mesh.normals);
+    /// Interleaves every mesh of an OBJ file the way it's uploaded, after converting it with `import`, so loading it again
+    /// is a straight copy. With `optimize`, duplicate vertices are merged and the rest reordered by first use (see `cook::optimize_mesh`).
+    /// `load_mtl` is only used to work out the names of the materials the meshes use.
+    pub fn cook(obj: &[u8], load_mtl: impl Fn(&Path) -> tobj::MTLLoadResult, import: &MeshImport, optimize: bool) -> Result<CookedModel, ObjLoadError> {
+        let libraries = RefCell::new(Vec::new());
+        let (models, materials) = tobj::load_obj_buf(&mut &obj[..], true, |mtl: &Path| {
+            libraries.borrow_mut().push(mtl.to_path_buf());
+            load_mtl(mtl)
+        })?;
+
+        let mut meshes = Vec::new();
+        for mut model in models {
+            import.apply(&mut model.mesh);
+            let mesh_data = MeshData::from_mesh(&model.name, &model.mesh)?;
+            let stride = mesh_data.vertices.nrows();
+            let (vertices, indices) = if optimize {
+                optimize_mesh(mesh_data.vertices.as_slice(), stride, &model.mesh.indices)
+            } else {
+                (mesh_data.vertices.as_slice().to_vec(), model.mesh.indices.clone())
+            };
+            meshes.push(CookedMesh {
+                name: model.name,
+                material: model.mesh.material_id.and_then(|id| materials.get(id)).map(|mat| mat.name.clone()),
+                components: mesh_data.components,
+                vertices: vertices,
+                indices: indices,
+            });
+        }
 
-        let internal_matrix = augment_bottom(internal_matrix, norm_matrix)
-            .map(|mat| augment_bottom(mat, tex_matrix)).expect("dims did not match");
+        Ok(CookedModel {
+            material_libraries: libraries.into_inner(),
+            meshes: meshes,
+        })
+    }
 
-        unimplemented!()
+    fn convert_materials(materials: Vec<tobj::Material>) -> Vec<Rc<Material>> {
+        materials.into_iter().map(|mat| Rc::new(Material::from(mat))).collect()
     }
 
-    fn from_mesh_tex(gl_ctx: Rc<gl::Gl>, mesh: tobj::Mesh, mat_name: Option<String>) -> ObjModel {
-        unimplemented!()
+    fn upload_all(gl_ctx: Rc<GLContext>, models: Vec<tobj::Model>, materials: Vec<tobj::Material>, import: &MeshImport) -> Result<(Vec<ObjModel>, Vec<Rc<Material>>), ObjLoadError> {
+        let mut model_result: Vec<ObjModel> = Vec::new();
+        let material_result = ObjModel::convert_materials(materials);
+
+        // first we iterate over all of the models, then all of the materials
+        // maybe should be backwards, so that we can reference the material name for the model
+        for model in models {
+            let mut mesh = model.mesh;
+            import.apply(&mut mesh);
+            let material = mesh.material_id.and_then(|id| material_result.get(id)).cloned();
+            let mesh_data = MeshData::from_mesh(&model.name, &mesh)?;
+            model_result.push(ObjModel::upload(gl_ctx.clone(), model.name, mesh_data, &mesh.indices, material));
+        }
+
+        Ok((model_result, material_result))
     }
 
-    fn from_mesh_norm(gl_ctx: Rc<gl::Gl>, mesh: tobj::Mesh, mat_name: Option<String>) -> ObjModel {
-        unimplemented!()
+    fn upload(gl_ctx: Rc<GLContext>, name: String, mesh_data: MeshData, indices: &[u32], material: Option<Rc<Material>>) -> ObjModel {
+        let mut vertex_array = VertexArrayObj::new(gl_ctx.clone());
+        let vertex_buffer = VertexBufferObj::new(gl_ctx.clone());
+        let mut element_buffer = ElementArrayObj::new(gl_ctx.clone());
+
+        // DMatrix is column-major and every column is one vertex, so its storage is already interleaved.
+        vertex_buffer.copy_to_buffer(mesh_data.vertices.as_slice(), DrawMode::StaticDraw);
+        vertex_array.attach_vertex_buffer(0, &vertex_buffer, &mesh_data.layout);
+        vertex_array.attach_element_buffer(&element_buffer);
+        element_buffer.copy_to_buffer(&IndexData::compact(indices), DrawMode::StaticDraw);
+
+        vertex_array.set_label(&name);
+        vertex_buffer.set_label(&name);
+        element_buffer.set_label(&name);
+
+        let render_state = material.as_ref().map(|mat| mat.render_state).unwrap_or_default();
+        ObjModel {
+            name: name,
+            material: material,
+            layout: mesh_data.layout,
+            vao: vertex_array,
+            vbo: vertex_buffer,
+            eao: element_buffer,
+            render_state: render_state,
+        }
     }
 
-    fn from_mesh(gl_ctx: Rc<gl::Gl>, mesh: tobj::Mesh, mat_name: Option<String>) -> ObjModel {
-        unimplemented!()
+    pub fn name(&self) -> &str {
+        &self.name
     }
-}
 
-impl Model for ObjModel {
-    fn get_vertices(&self) -> &Vec<f32> {
-        unimplemented!()
+    pub fn material(&self) -> Option<&Rc<Material>> {
+        self.material.as_ref()
     }
+}
 
-    fn get_indices(&self) -> &Vec<u32> {
-        unimplemented!()
+impl Model for ObjModel {
+    fn get_vertex_layout(&self) -> &VertexLayout {
+        &self.layout
     }
 
     fn get_vert_array_obj(&self) -> &VertexArrayObj {
-        unimplemented!()
+        &self.vao
     }
 
     fn get_vert_buffer_obj(&self) -> &VertexBufferObj {
-        unimplemented!()
+        &self.vbo
     }
 
     fn get_elem_array_obj(&self) -> &ElementArrayObj {
-        unimplemented!()
+        &self.eao
     }
 
-    fn get_shader(&self) -> &Rc<CompiledShaderProgram> {
-        unimplemented!()
+    fn get_render_state(&self) -> &RenderState {
+        &self.render_state
     }
 }
 
-fn augment_right<N: nalgebra::Scalar + Copy>(me: nalgebra::DMatrix<N>, other: nalgebra::DMatrix<N>) -> Option<nalgebra::DMatrix<N>> {
-    // augmenting to the right (number of rows is constant, adding new columns)
-    let (num_rows, num_columns) = me.shape();
+/// The vertices of one mesh, interleaved one vertex per column, and the layout they follow.
+struct MeshData {
+    vertices: nalgebra::DMatrix<f32>,
+    // How many floats each attribute has, which the layout is made from.
+    components: Vec<usize>,
+    layout: VertexLayout,
+}
 
-    if num_rows == 0 || num_columns == 0 {
-        return None;
+impl MeshData {
+    fn new(vertices: nalgebra::DMatrix<f32>, components: &[usize]) -> MeshData {
+        MeshData {
+            vertices: vertices,
+            components: components.to_vec(),
+            layout: float_layout(components),
+        }
     }
 
-    let (aug_rows, aug_columns) = other.shape();
-    if num_rows != aug_rows {
-        return None;
+    fn from_cooked(mesh: &CookedMesh) -> Result<MeshData, ObjLoadError> {
+        if mesh.vertices.is_empty() {
+            return Err(ObjLoadError::EmptyMesh(mesh.name.clone()));
+        }
+        let stride = mesh.stride();
+        let num_verts = mesh.vertex_count();
+        let lines_up = mesh.components.iter().all(|count| (1..=4).contains(count))
+            && mesh.vertices.len() == num_verts * stride
+            && mesh.indices.iter().all(|&index| (index as usize) < num_verts);
+        if !lines_up {
+            return Err(ObjLoadError::MismatchedAttributes(mesh.name.clone()));
+        }
+        Ok(MeshData::new(nalgebra::DMatrix::from_column_slice(stride, num_verts, &mesh.vertices), &mesh.components))
     }
 
-    // insert new columns to place stuff into
-    let insert_val_temp = *me.get((0,0)).expect("totally empty matrix passed into augment and further than initial check, big rip");
-    let mut me = me.insert_columns(num_rows, aug_columns, insert_val_temp);
-
-    for column_index in num_columns..aug_columns+num_columns {
-        // normalize to other
-        let aug_column_index = column_index - num_columns;
-        let column_slice = other.column(aug_column_index);
-        for row_index in 0..num_rows {
-            me[(row_index, column_index)] = column_slice[row_index];
+    fn from_mesh(name: &str, mesh: &tobj::Mesh) -> Result<MeshData, ObjLoadError> {
+        if mesh.positions.is_empty() {
+            return Err(ObjLoadError::EmptyMesh(name.to_owned()));
+        }
+        // tobj unifies the indices, so every attribute array has exactly one entry per position.
+        let num_verts = mesh.positions.len() / 3;
+        let lines_up = |attr: &[f32], components: usize| attr.is_empty() || attr.len() == num_verts * components;
+        if !mesh.positions.len().is_multiple_of(3) || !lines_up(&mesh.normals, 3) || !lines_up(&mesh.texcoords, 2) {
+            return Err(ObjLoadError::MismatchedAttributes(name.to_owned()));
         }
 
+        // big branch here; generate the vertex data based on the attributes provided by the mesh
+        let mesh_data = if mesh.normals.is_empty() && mesh.texcoords.is_empty() {
+            MeshData::from_positions(mesh)
+        } else if mesh.normals.is_empty() {
+            MeshData::from_positions_tex(mesh)
+        } else if mesh.texcoords.is_empty() {
+            MeshData::from_positions_norm(mesh)
+        } else {
+            MeshData::from_positions_texnorm(mesh)
+        };
+        mesh_data.ok_or_else(|| ObjLoadError::MismatchedAttributes(name.to_owned()))
     }
 
-    Some(me)
+    fn from_positions_texnorm(mesh: &tobj::Mesh) -> Option<MeshData> {
+        let num_rows = mesh.positions.len() / 3;
+        let internal_matrix = nalgebra::DMatrix::from_column_slice(3, num_rows, &mesh.positions);
+        let tex_matrix = nalgebra::DMatrix::from_column_slice(2, num_rows, &mesh.texcoords);
+        let norm_matrix = nalgebra::DMatrix::from_column_slice(3, num_rows, &mesh.normals);
+
+        let internal_matrix = augment_bottom(internal_matrix, norm_matrix)
+            .and_then(|mat| augment_bottom(mat, tex_matrix))?;
+
+        Some(MeshData::new(internal_matrix, &[3, 3, 2]))
+    }
+
+    fn from_positions_tex(mesh: &tobj::Mesh) -> Option<MeshData> {
+        let num_rows = mesh.positions.len() / 3;
+        let internal_matrix = nalgebra::DMatrix::from_column_slice(3, num_rows, &mesh.positions);
+        let tex_matrix = nalgebra::DMatrix::from_column_slice(2, num_rows, &mesh.texcoords);
 
+        Some(MeshData::new(augment_bottom(internal_matrix, tex_matrix)?, &[3, 2]))
+    }
+
+    fn from_positions_norm(mesh: &tobj::Mesh) -> Option<MeshData> {
+        let num_rows = mesh.positions.len() / 3;
+        let internal_matrix = nalgebra::DMatrix::from_column_slice(3, num_rows, &mesh.positions);
+        let norm_matrix = nalgebra::DMatrix::from_column_slice(3, num_rows, &mesh.normals);
+
+        Some(MeshData::new(augment_bottom(internal_matrix, norm_matrix)?, &[3, 3]))
+    }
+
+    fn from_positions(mesh: &tobj::Mesh) -> Option<MeshData> {
+        let num_rows = mesh.positions.len() / 3;
+        Some(MeshData::new(nalgebra::DMatrix::from_column_slice(3, num_rows, &mesh.positions), &[3]))
+    }
+}
+
+/// Lays out tightly packed float attributes of the given sizes at consecutive locations, starting from the position.
+fn float_layout(components: &[usize]) -> VertexLayout {
+    let float_size = mem::size_of::<f32>();
+    let mut layout = VertexLayout::new(components.iter().sum::<usize>() * float_size);
+    let mut offset = 0;
+    for (idx, &count) in components.iter().enumerate() {
+        layout.push(VertexAttribute {
+            location: POSITION_LOCATION + idx as u32,
+            components: match count {
+                1 => AttributeComponentSize::One,
+                2 => AttributeComponentSize::Two,
+                3 => AttributeComponentSize::Three,
+                _ => AttributeComponentSize::Four,
+            },
+            attrib_type: GLType::Float,
+            normalized: false,
+            offset: offset,
+        });
+        offset += count * float_size;
+    }
+    layout
+}
+
+#[derive(Error, Debug)]
+pub enum ObjLoadError {
+    #[error("Loading the OBJ file failed: {0}")]
+    LoadError(tobj::LoadError),
+    #[error("Reading the OBJ file failed: {0}")]
+    IOError(io::Error),
+    #[error("Mesh {0} has no vertices")]
+    EmptyMesh(String),
+    #[error("Mesh {0} has normals or texture coordinates that don't line up with its positions")]
+    MismatchedAttributes(String),
+    #[error("Uploading the mesh failed: {0}")]
+    OpenGLError(OpenGLError),
+    #[error("Loading a material's texture failed: {0}")]
+    TextureError(TextureError),
+}
+
+impl From<tobj::LoadError> for ObjLoadError {
+    fn from(err: tobj::LoadError) -> Self {
+        ObjLoadError::LoadError(err)
+    }
+}
+
+impl From<TextureError> for ObjLoadError {
+    fn from(err: TextureError) -> Self {
+        ObjLoadError::TextureError(err)
+    }
+}
+
+impl From<io::Error> for ObjLoadError {
+    fn from(err: io::Error) -> Self {
+        ObjLoadError::IOError(err)
+    }
+}
+
+impl From<OpenGLError> for ObjLoadError {
+    fn from(err: OpenGLError) -> Self {
+        ObjLoadError::OpenGLError(err)
+    }
 }
 
 fn augment_bottom<N: nalgebra::Scalar + Copy>(me: nalgebra::DMatrix<N>, other: nalgebra::DMatrix<N>) -> Option<nalgebra::DMatrix<N>> {
@@ -158,6 +357,89 @@ fn augment_bottom<N: nalgebra::Scalar + Copy>(me: nalgebra::DMatrix<N>, other: n
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    fn load_fixture(name: &str) -> Vec<tobj::Model> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../assets/test").join(name);
+        tobj::load_obj(path, true).expect("failed to load fixture").0
+    }
+
+    #[test]
+    fn positions_only_mesh() {
+        let models = load_fixture("triangle.obj");
+        let data = MeshData::from_mesh(&models[0].name, &models[0].mesh).expect("failed to build mesh data");
+        assert_eq!(data.vertices.shape(), (3, 3));
+        assert_eq!(data.layout, float_layout(&[3]));
+        assert_eq!(models[0].mesh.indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn texcoords_follow_positions() {
+        let models = load_fixture("quad_tex.obj");
+        let data = MeshData::from_mesh(&models[0].name, &models[0].mesh).expect("failed to build mesh data");
+        assert_eq!(data.vertices.nrows(), 5);
+        assert_eq!(data.layout.stride(), 20);
+        assert_eq!(data.layout.attribute_at(1).map(|attr| attr.offset), Some(12));
+        // Two triangles sharing an edge, so four unique vertices.
+        assert_eq!(data.vertices.ncols(), 4);
+        assert_eq!(models[0].mesh.indices.len(), 6);
+    }
+
+    #[test]
+    fn normals_follow_positions() {
+        let models = load_fixture("quad_norm.obj");
+        let data = MeshData::from_mesh(&models[0].name, &models[0].mesh).expect("failed to build mesh data");
+        assert_eq!(data.layout, float_layout(&[3, 3]));
+        assert_eq!(&data.vertices.as_slice()[3..6], &[0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn full_vertices_interleave() {
+        let models = load_fixture("cube.obj");
+        let mesh = &models[0].mesh;
+        let data = MeshData::from_mesh(&models[0].name, mesh).expect("failed to build mesh data");
+        assert_eq!(data.layout, float_layout(&[3, 3, 2]));
+        assert_eq!(data.layout.stride(), 32);
+
+        // Each vertex is position, normal then texcoord, back to back.
+        let first_vertex = &data.vertices.as_slice()[0..8];
+        assert_eq!(&first_vertex[0..3], &mesh.positions[0..3]);
+        assert_eq!(&first_vertex[3..6], &mesh.normals[0..3]);
+        assert_eq!(&first_vertex[6..8], &mesh.texcoords[0..2]);
+        assert_eq!(mesh.indices.len(), 36);
+        assert_eq!(mesh.material_id, Some(0));
+    }
+
+    #[test]
+    fn cooked_meshes_load_like_their_source() {
+        let obj = std::fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("../assets/test/cube.obj")).unwrap();
+        let load_mtl = |mtl: &Path| tobj::load_mtl(Path::new(env!("CARGO_MANIFEST_DIR")).join("../assets/test").join(mtl));
+        let cooked = ObjModel::cook(&obj, load_mtl, &MeshImport::default(), true).expect("failed to cook the cube");
+        assert_eq!(cooked.material_libraries, vec![std::path::PathBuf::from("cube.mtl")]);
+
+        let mesh = &cooked.meshes[0];
+        assert_eq!(mesh.material.as_deref(), Some("cube"));
+        assert_eq!(mesh.indices.len(), 36);
+        // Each face of the cube has its own normal, so only the vertices within a face are shared.
+        assert_eq!(mesh.vertex_count(), 24);
+
+        let data = MeshData::from_cooked(mesh).expect("failed to build mesh data");
+        assert_eq!(data.layout, float_layout(&[3, 3, 2]));
+        assert_eq!(data.vertices.shape(), (8, 24));
+
+        let mut broken = mesh.clone();
+        broken.indices.push(24);
+        assert!(matches!(MeshData::from_cooked(&broken), Err(ObjLoadError::MismatchedAttributes(_))));
+    }
+
+    #[test]
+    fn mismatched_attributes_are_rejected() {
+        let models = load_fixture("quad_tex.obj");
+        let mut mesh = models[0].mesh.clone();
+        mesh.texcoords.pop();
+        assert!(matches!(MeshData::from_mesh("broken", &mesh), Err(ObjLoadError::MismatchedAttributes(_))));
+    }
+
     #[test]
     fn augment_bottom_works() {
         use nalgebra;
@@ -175,21 +457,4 @@ mod tests {
         let new_matrix = new_matrix.expect("failed to augment");
         assert_eq!(new_matrix, augmented_matrix);
     }
-
-    #[test]
-    fn augment_right_works() {
-        use nalgebra;
-        use super::augment_right;
-
-        let matrix_one = nalgebra::DMatrix::identity(3, 3);
-        let augment_matrix = nalgebra::DMatrix::from_vec(3, 1, vec![2, 3, 4]);
-        let augmented_matrix = nalgebra::Matrix3x4::new(
-            1, 0, 0, 2,
-            0, 1, 0, 3,
-            0, 0, 1, 4,
-        );
-        let new_matrix = augment_right(matrix_one, augment_matrix);
-        let new_matrix = new_matrix.expect("failed to augment");
-        assert_eq!(new_matrix, augmented_matrix);
-    }
 }
\ No newline at end of file
@@ -4,9 +4,15 @@
 
 use ace_gl_types as types;
 
+pub mod archive;
+pub mod vfs;
 pub mod loaded;
 pub mod namespace;
+pub mod handle;
 pub mod container;
 pub mod asset_loading;
 pub mod asset_types;
-pub mod hl_assets;
\ No newline at end of file
+pub mod hl_assets;
+pub mod cook;
+pub mod meta;
+pub mod validate;
\ No newline at end of file
@@ -1,6 +1,28 @@
 use std::rc::Rc;
+use crate::asset_loading::AssetLoadError;
 
+/// Where an asset behind a handle is in loading, as `AssetContainer::load_state` reports it.
 pub enum Loaded<T: ?Sized> {
+    /// Never loaded, unloaded or evicted.
     Unloaded,
+    /// Being read and decoded in the background. Textures come with a placeholder to draw in the meantime,
+    /// which turns into the real texture once it's uploaded.
+    Loading(Option<Rc<T>>),
     Loaded(Rc<T>),
-}
\ No newline at end of file
+    /// Loading failed. Textures keep their placeholder.
+    Failed(Rc<AssetLoadError>),
+}
+
+impl<T: ?Sized> Loaded<T> {
+    pub fn is_loaded(&self) -> bool {
+        matches!(self, Loaded::Loaded(_))
+    }
+
+    /// The asset, or its placeholder while it's loading.
+    pub fn asset(&self) -> Option<&Rc<T>> {
+        match self {
+            Loaded::Loaded(asset) | Loaded::Loading(Some(asset)) => Some(asset),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,319 @@
+//! Import settings kept next to an asset, in a JSON sidecar named after it with `.meta` added (`textures/stone.png.meta`):
+//!
+//! ```json
+//! {
+//!     "guid": "0f7c1e36-5bd4-4b0e-a5b4-5d1f1f3f3c7e",
+//!     "texture": { "config": null, "srgb": true, "sampler": { "filter": "Nearest", "wrap": "ClampToEdge", "mipmaps": false } },
+//!     "mesh": { "scale": 0.01, "up_axis": "z", "generate_normals": true }
+//! }
+//! ```
+//!
+//! Sidecars are optional. Loaders read them whenever they load the asset, and use the defaults for anything they
+//! leave out. The GUID stays with the asset when it's moved or renamed, as long as the sidecar goes with it;
+//! `ace-meta` writes sidecars for assets that don't have one yet.
+
+use crate::types::*;
+use crate::vfs::Vfs;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Where the sidecar of `asset` is.
+pub fn meta_path<P: AsRef<Path>>(asset: P) -> PathBuf {
+    let mut name = OsString::from(asset.as_ref());
+    name.push(".meta");
+    PathBuf::from(name)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AssetMeta {
+    pub guid: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub texture: Option<TextureImport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<MeshImport>,
+}
+
+/// How a texture is uploaded. Everything left unset keeps what the code loading the texture asked for.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TextureImport {
+    /// Replaces the config the texture is loaded with.
+    pub config: Option<TexConfig>,
+    /// Whether the texture holds colors, which are stored in sRGB, rather than data like normals.
+    pub srgb: Option<bool>,
+    pub sampler: Option<SamplerConfig>,
+}
+
+/// How the meshes of an OBJ file are converted when they're loaded. glTF files are already Y up and in meters,
+/// and are loaded as they are.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MeshImport {
+    /// Positions are multiplied by this, for files made in other units than meters.
+    pub scale: f32,
+    /// The axis that points up in the file. The engine's is Y.
+    pub up_axis: UpAxis,
+    /// Work out smooth normals for meshes that don't have any.
+    pub generate_normals: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpAxis {
+    Y,
+    /// Like Blender and most CAD tools, with -Y pointing forward.
+    Z,
+}
+
+impl Default for MeshImport {
+    fn default() -> Self {
+        MeshImport {
+            scale: 1.0,
+            up_axis: UpAxis::Y,
+            generate_normals: false,
+        }
+    }
+}
+
+impl AssetMeta {
+    /// A sidecar with a new GUID and default settings for the kind of asset `asset` is, or `None` for files that
+    /// aren't assets of their own. Textures, models, shaders and sounds get one.
+    pub fn new_for<P: AsRef<Path>>(asset: P) -> Option<AssetMeta> {
+        let extension = asset.as_ref().extension()?.to_str()?.to_lowercase();
+        let mut meta = AssetMeta {
+            guid: Uuid::new_v4(),
+            texture: None,
+            mesh: None,
+        };
+        match extension.as_str() {
+            "png" | "jpg" | "jpeg" => meta.texture = Some(TextureImport::default()),
+            "obj" => meta.mesh = Some(MeshImport::default()),
+            "gltf" | "glb" | "ogg" | "wav" => {},
+            _ if ShaderType::from_extension(asset.as_ref()).is_some() => {},
+            _ => return None,
+        }
+        Some(meta)
+    }
+
+    /// Parses a sidecar; `path` is only used to say which one is broken.
+    pub fn from_slice(path: &Path, json: &[u8]) -> io::Result<AssetMeta> {
+        serde_json::from_slice(json).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), err)))
+    }
+
+    pub fn to_json(&self) -> Vec<u8> {
+        serde_json::to_vec_pretty(self).expect("sidecars always serialize")
+    }
+
+    /// Reads the sidecar of `asset` through the layers. `None` if there isn't one, but a sidecar that doesn't parse
+    /// is an error, so a typo in one isn't quietly ignored.
+    pub fn read<P: AsRef<Path>>(vfs: &Vfs, asset: P) -> io::Result<Option<AssetMeta>> {
+        let path = meta_path(asset);
+        if !vfs.exists(&path) {
+            return Ok(None);
+        }
+        AssetMeta::from_slice(&path, &vfs.read(&path)?).map(Some)
+    }
+
+    pub fn texture_import(&self) -> TextureImport {
+        self.texture.clone().unwrap_or_default()
+    }
+
+    pub fn mesh_import(&self) -> MeshImport {
+        self.mesh.clone().unwrap_or_default()
+    }
+}
+
+impl TextureImport {
+    pub fn apply(&self, config: TexConfig) -> TexConfig {
+        let mut config = self.config.clone().unwrap_or(config);
+        if let Some(srgb) = self.srgb {
+            config.internal_fmt = match (srgb, config.internal_fmt) {
+                (true, InternalStorage::RGBA) | (true, InternalStorage::RedGreenBlueAlpha8) => InternalStorage::Std_RedGreenBlueAlpha8,
+                (true, InternalStorage::Std_RedGreenBlueAlpha8) => InternalStorage::Std_RedGreenBlueAlpha8,
+                (true, _) => InternalStorage::Std_RedGreenBlue8,
+                (false, InternalStorage::Std_RedGreenBlue8) => InternalStorage::RGB,
+                (false, InternalStorage::Std_RedGreenBlueAlpha8) => InternalStorage::RGBA,
+                (false, other) => other,
+            };
+        }
+        if let Some(sampler) = self.sampler {
+            config.sampler = sampler;
+        }
+        config
+    }
+}
+
+impl MeshImport {
+    pub fn apply(&self, mesh: &mut tobj::Mesh) {
+        for position in mesh.positions.chunks_exact_mut(3) {
+            let [x, y, z] = self.up_axis.to_y_up([position[0], position[1], position[2]]);
+            position.copy_from_slice(&[x * self.scale, y * self.scale, z * self.scale]);
+        }
+        for normal in mesh.normals.chunks_exact_mut(3) {
+            normal.copy_from_slice(&self.up_axis.to_y_up([normal[0], normal[1], normal[2]]));
+        }
+        if self.generate_normals && mesh.normals.is_empty() {
+            mesh.normals = smooth_normals(&mesh.positions, &mesh.indices);
+        }
+    }
+}
+
+impl UpAxis {
+    fn to_y_up(self, [x, y, z]: [f32; 3]) -> [f32; 3] {
+        match self {
+            UpAxis::Y => [x, y, z],
+            // A quarter turn around X, which takes +Z to +Y and -Y (forward) to -Z.
+            UpAxis::Z => [x, z, -y],
+        }
+    }
+}
+
+/// Every vertex gets the average of the normals of the triangles it's in, weighted by their area.
+fn smooth_normals(positions: &[f32], indices: &[u32]) -> Vec<f32> {
+    let position = |index: u32| {
+        let start = index as usize * 3;
+        [positions[start], positions[start + 1], positions[start + 2]]
+    };
+    let mut normals = vec![0.0; positions.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [position(triangle[0]), position(triangle[1]), position(triangle[2])];
+        let (ab, ac) = ([b[0] - a[0], b[1] - a[1], b[2] - a[2]], [c[0] - a[0], c[1] - a[1], c[2] - a[2]]);
+        // Not normalized, so bigger triangles count for more.
+        let face = [ab[1] * ac[2] - ab[2] * ac[1], ab[2] * ac[0] - ab[0] * ac[2], ab[0] * ac[1] - ab[1] * ac[0]];
+        for &index in triangle {
+            for axis in 0..3 {
+                normals[index as usize * 3 + axis] += face[axis];
+            }
+        }
+    }
+    for normal in normals.chunks_exact_mut(3) {
+        let length = (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt();
+        if length > 0.0 {
+            normal.iter_mut().for_each(|axis| *axis /= length);
+        } else {
+            // Only in degenerate triangles, or vertices no triangle uses.
+            normal.copy_from_slice(&[0.0, 1.0, 0.0]);
+        }
+    }
+    normals
+}
+
+/// What `write_missing` found.
+#[derive(Debug, Default)]
+pub struct MetaReport {
+    /// Sidecars that were written, relative to the asset root.
+    pub written: Vec<PathBuf>,
+    /// Sidecars that don't parse.
+    pub invalid: Vec<io::Error>,
+    /// GUIDs that more than one sidecar has, usually because an asset was copied along with its sidecar.
+    pub duplicates: Vec<(Uuid, Vec<PathBuf>)>,
+}
+
+/// Writes a sidecar with default settings for every asset under `root` that doesn't have one, and checks the ones
+/// that are already there.
+pub fn write_missing<P: AsRef<Path>>(root: P) -> io::Result<MetaReport> {
+    let root = root.as_ref();
+    let mut files = Vec::new();
+    crate::cook::collect_files(root, &mut files)?;
+    files.sort();
+
+    let mut report = MetaReport::default();
+    let mut guids: BTreeMap<Uuid, Vec<PathBuf>> = BTreeMap::new();
+    for file in files {
+        if crate::cook::is_cubemap_face(&file) {
+            continue;
+        }
+        let sidecar = meta_path(&file);
+        let relative = sidecar.strip_prefix(root).expect("collected files are under the asset root").to_path_buf();
+        let guid = if sidecar.is_file() {
+            match AssetMeta::from_slice(&relative, &fs::read(&sidecar)?) {
+                Ok(meta) => meta.guid,
+                Err(err) => {
+                    report.invalid.push(err);
+                    continue;
+                },
+            }
+        } else {
+            match AssetMeta::new_for(&file) {
+                Some(meta) => {
+                    fs::write(&sidecar, meta.to_json())?;
+                    report.written.push(relative.clone());
+                    meta.guid
+                },
+                None => continue,
+            }
+        };
+        guids.entry(guid).or_default().push(relative);
+    }
+    report.duplicates = guids.into_iter().filter(|(_, sidecars)| sidecars.len() > 1).collect();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::Layer;
+
+    #[test]
+    fn sidecars_are_optional_but_must_parse() {
+        let mut vfs = Vfs::new();
+        vfs.mount("base", Layer::embedded(&[
+            ("textures/plain.png", b""),
+            ("textures/pixel.png", b""),
+            ("textures/pixel.png.meta", br#"{
+                "guid": "0f7c1e36-5bd4-4b0e-a5b4-5d1f1f3f3c7e",
+                "texture": { "srgb": true, "sampler": { "filter": "Nearest", "mipmaps": false } }
+            }"#),
+            ("textures/typo.png.meta", br#"{ "guid": "0f7c1e36-5bd4-4b0e-a5b4-5d1f1f3f3c7e", "textur": {} }"#),
+        ]));
+        assert_eq!(AssetMeta::read(&vfs, "textures/plain.png").unwrap(), None);
+        assert!(AssetMeta::read(&vfs, "textures/typo.png").is_err());
+
+        let meta = AssetMeta::read(&vfs, "textures/pixel.png").unwrap().unwrap();
+        assert_eq!(meta.guid.to_string(), "0f7c1e36-5bd4-4b0e-a5b4-5d1f1f3f3c7e");
+        assert_eq!(meta.mesh_import(), MeshImport::default());
+        let config = meta.texture_import().apply(TexConfig::new(TextureType::Texture2D, InternalStorage::RGB, PixelDataFormat::RGB, PixelDataType::UnsignedByte));
+        assert_eq!(config.internal_fmt, InternalStorage::Std_RedGreenBlue8);
+        assert_eq!(config.sampler, SamplerConfig { filter: TexFilter::Nearest, wrap: TexWrap::Repeat, mipmaps: false });
+    }
+
+    #[test]
+    fn z_up_meshes_are_turned_and_scaled() {
+        let mut mesh = tobj::Mesh {
+            positions: vec![0.0, 0.0, 0.0, 100.0, 0.0, 0.0, 0.0, 100.0, 0.0],
+            indices: vec![0, 1, 2],
+            ..tobj::Mesh::empty()
+        };
+        let import = MeshImport { scale: 0.01, up_axis: UpAxis::Z, generate_normals: true };
+        import.apply(&mut mesh);
+        // +Y (forward in the file) becomes -Z, and the triangle's +Z normal becomes +Y.
+        assert_eq!(mesh.positions, vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, -1.0]);
+        assert_eq!(mesh.normals, vec![0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn missing_sidecars_are_written_once() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("textures")).unwrap();
+        fs::write(dir.path().join("textures/stone.png"), b"").unwrap();
+        fs::write(dir.path().join("textures/grass.png"), b"").unwrap();
+        fs::write(dir.path().join("manifest.json"), b"{}").unwrap();
+
+        let report = write_missing(dir.path()).unwrap();
+        assert_eq!(report.written, vec![PathBuf::from("textures/grass.png.meta"), PathBuf::from("textures/stone.png.meta")]);
+        let meta = AssetMeta::from_slice(Path::new("stone"), &fs::read(dir.path().join("textures/stone.png.meta")).unwrap()).unwrap();
+        assert_eq!(meta.texture, Some(TextureImport::default()));
+        assert!(write_missing(dir.path()).unwrap().written.is_empty());
+
+        // A copied asset brings its sidecar, and with it the same GUID.
+        fs::copy(dir.path().join("textures/stone.png.meta"), dir.path().join("textures/grass.png.meta")).unwrap();
+        let report = write_missing(dir.path()).unwrap();
+        assert_eq!(report.duplicates, vec![(meta.guid, vec![PathBuf::from("textures/grass.png.meta"), PathBuf::from("textures/stone.png.meta")])]);
+    }
+}
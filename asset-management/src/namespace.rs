@@ -1,11 +1,17 @@
+use std::fmt;
+use std::io;
 use std::path;
+use std::str::FromStr;
 use crate::asset_types::AssetType;
+use crate::vfs::Vfs;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
 
 /// Reference to a specific asset
 // Right now assets can't be mixed with one another, since polymorphic interactions in Rust are _difficult_ (for good reason! raw pointer casting is dangerous)
 // But eventually the plan is to unify them.
 // Asset references should be able to be constructed from a path and converted back into that path (with a little help)
-#[derive(Debug, Eq, PartialEq, Hash)]
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
 pub struct AssetReference {
     asset_location: String,
     asset_type: AssetType,
@@ -13,15 +19,236 @@ pub struct AssetReference {
 
 impl AssetReference {
 
-    fn new<T: Into<String>>(loc: T, ty: AssetType) -> AssetReference {
+    /// `loc` is relative to the asset root, with `/` separators. Assets that come from inside a file
+    /// (like one mesh of a model) add a `#fragment` naming the part.
+    pub fn new<T: Into<String>>(loc: T, ty: AssetType) -> AssetReference {
         AssetReference {
             asset_location: loc.into(),
             asset_type: ty,
         }
     }
 
-    fn into(self, mut asset_root: path::PathBuf) -> path::PathBuf {
-        asset_root.push(self.asset_location);
-        asset_root
+    /// The reference for a file under `asset_root`, or `None` if it's somewhere else.
+    pub fn from_path<P: AsRef<path::Path>, R: AsRef<path::Path>>(asset_root: R, file: P, ty: AssetType) -> Option<AssetReference> {
+        let relative = file.as_ref().strip_prefix(asset_root).ok()?;
+        let components: Option<Vec<&str>> = relative.components().map(|component| component.as_os_str().to_str()).collect();
+        Some(AssetReference::new(components?.join("/"), ty))
     }
-}
\ No newline at end of file
+
+    pub fn location(&self) -> &str {
+        &self.asset_location
+    }
+
+    pub fn asset_type(&self) -> AssetType {
+        self.asset_type
+    }
+
+    /// The file this asset was loaded from, without any `#fragment`.
+    pub fn to_path<R: AsRef<path::Path>>(&self, asset_root: R) -> path::PathBuf {
+        let file = match self.asset_location.find('#') {
+            Some(fragment) => &self.asset_location[..fragment],
+            None => &self.asset_location,
+        };
+        let mut path = asset_root.as_ref().to_path_buf();
+        path.extend(file.split('/'));
+        path
+    }
+}
+
+/// The namespace ids without one are in: the engine's own assets.
+pub const DEFAULT_NAMESPACE: &str = "core";
+
+/// The directory every namespace but `core` has its own directory under. `core` paths can't start with it,
+/// so no two ids share a file.
+pub const NAMESPACES_DIR: &str = "mods";
+
+/// Names an asset as `namespace:path`, like `core:textures/stone`. The namespace keeps the assets of the base game
+/// and of every mod or pack apart, so two of them can both have a `textures/stone` without clashing.
+///
+/// Namespaces are lowercase letters, digits, `_`, `-` and `.`; paths are `/`-separated segments of the same,
+/// without empty, `.` or `..` segments. The path leaves out the file extension, which depends on the type of asset.
+/// `core` paths can't start with `NAMESPACES_DIR`, where the other namespaces are.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, PartialOrd, Ord)]
+pub struct AssetId {
+    namespace: String,
+    path: String,
+}
+
+impl AssetId {
+    pub fn new<N: Into<String>, P: Into<String>>(namespace: N, path: P) -> Result<AssetId, AssetIdError> {
+        let (namespace, path) = (namespace.into(), path.into());
+        if !valid_namespace(&namespace) {
+            return Err(AssetIdError::InvalidNamespace(namespace));
+        }
+        if !valid_path(&path) {
+            return Err(AssetIdError::InvalidPath(path));
+        }
+        if namespace == DEFAULT_NAMESPACE && path.split('/').next() == Some(NAMESPACES_DIR) {
+            return Err(AssetIdError::ReservedPath(path));
+        }
+        Ok(AssetId {
+            namespace: namespace,
+            path: path,
+        })
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Where the id's files are in the `Vfs`, without an extension. The `core` namespace is the asset root itself;
+    /// every other namespace has a directory of its own under `NAMESPACES_DIR`, so a mod ships its textures under `mods/mymod/textures`.
+    pub fn vfs_path(&self) -> String {
+        if self.namespace == DEFAULT_NAMESPACE {
+            self.path.clone()
+        } else {
+            format!("{}/{}/{}", NAMESPACES_DIR, self.namespace, self.path)
+        }
+    }
+
+    /// The file the id names as an asset of type `ty`: its `vfs_path` with the first of the type's extensions
+    /// any layer has a file for. Types without extensions (cubemaps are directories) get the `vfs_path` as it is.
+    pub fn find_file(&self, ty: AssetType, vfs: &Vfs) -> io::Result<path::PathBuf> {
+        let base = self.vfs_path();
+        if ty.extensions().is_empty() {
+            return Ok(path::PathBuf::from(base));
+        }
+        ty.extensions().iter()
+            .map(|ext| path::PathBuf::from(format!("{}.{}", base, ext)))
+            .find(|file| vfs.exists(file))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} has no {} file ({})", self, ty.name(), ty.extensions().join(", "))))
+    }
+}
+
+fn valid_char(byte: u8) -> bool {
+    matches!(byte, b'a'..=b'z' | b'0'..=b'9' | b'_' | b'-' | b'.')
+}
+
+fn valid_namespace(namespace: &str) -> bool {
+    !namespace.is_empty() && namespace.bytes().all(valid_char)
+}
+
+fn valid_path(path: &str) -> bool {
+    path.split('/').all(|segment| !segment.is_empty() && segment != "." && segment != ".." && segment.bytes().all(valid_char))
+}
+
+impl fmt::Display for AssetId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.namespace, self.path)
+    }
+}
+
+/// Parses `namespace:path`, or just `path` for an id in the `core` namespace.
+impl FromStr for AssetId {
+    type Err = AssetIdError;
+
+    fn from_str(id: &str) -> Result<AssetId, AssetIdError> {
+        match id.split_once(':') {
+            Some((namespace, path)) => AssetId::new(namespace, path),
+            None => AssetId::new(DEFAULT_NAMESPACE, id),
+        }
+    }
+}
+
+impl Serialize for AssetId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for AssetId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<AssetId, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(de::Error::custom)
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum AssetIdError {
+    #[error("Bad namespace {0:?}: namespaces are lowercase letters, digits, '_', '-' and '.'")]
+    InvalidNamespace(String),
+    #[error("Bad asset path {0:?}: paths are '/'-separated lowercase letters, digits, '_', '-' and '.', without empty, '.' or '..' parts")]
+    InvalidPath(String),
+    #[error("Bad asset path {0:?}: core paths can't start with '{}', which holds the other namespaces", NAMESPACES_DIR)]
+    ReservedPath(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn references_round_trip_through_paths() {
+        let root = path::Path::new("assets");
+        let reference = AssetReference::from_path(root, root.join("textures").join("texture1.jpg"), AssetType::Texture).unwrap();
+        assert_eq!(reference.location(), "textures/texture1.jpg");
+        assert_eq!(reference.to_path(root), root.join("textures").join("texture1.jpg"));
+        assert_eq!(AssetReference::from_path(root, reference.to_path(root), AssetType::Texture), Some(reference));
+
+        assert_eq!(AssetReference::from_path(root, "elsewhere/texture1.jpg", AssetType::Texture), None);
+    }
+
+    #[test]
+    fn fragments_point_at_the_containing_file() {
+        let reference = AssetReference::new("test/cube.obj#Cube", AssetType::Model);
+        assert_eq!(reference.to_path("assets"), path::Path::new("assets").join("test").join("cube.obj"));
+    }
+
+    #[test]
+    fn ids_parse_and_print() {
+        let id: AssetId = "mymod:textures/blocks/stone".parse().unwrap();
+        assert_eq!((id.namespace(), id.path()), ("mymod", "textures/blocks/stone"));
+        assert_eq!(id.to_string(), "mymod:textures/blocks/stone");
+        assert_eq!(id.vfs_path(), "mods/mymod/textures/blocks/stone");
+
+        let core: AssetId = "textures/stone".parse().unwrap();
+        assert_eq!(core, AssetId::new("core", "textures/stone").unwrap());
+        assert_eq!(core.vfs_path(), "textures/stone");
+        assert_ne!(core, AssetId::new("mymod", "textures/stone").unwrap());
+
+        assert_eq!("Core:stone".parse::<AssetId>(), Err(AssetIdError::InvalidNamespace("Core".to_string())));
+        assert_eq!(":stone".parse::<AssetId>(), Err(AssetIdError::InvalidNamespace(String::new())));
+        for path in &["", "textures//stone", "textures/../stone", "/stone", "stone/", "st one", "a:b:c"] {
+            assert!(AssetId::new("core", *path).is_err(), "{:?} was accepted", path);
+        }
+    }
+
+    #[test]
+    fn core_ids_cant_reach_into_other_namespaces() {
+        // Would otherwise both be mods/mymod/textures/stone.
+        let reserved = "mods/mymod/textures/stone";
+        assert_eq!(reserved.parse::<AssetId>(), Err(AssetIdError::ReservedPath(reserved.to_string())));
+        assert_eq!(format!("core:{}", reserved).parse::<AssetId>(), Err(AssetIdError::ReservedPath(reserved.to_string())));
+        assert_eq!(AssetId::new("mymod", "textures/stone").unwrap().vfs_path(), reserved);
+
+        // Only the first segment is reserved, and only in core.
+        assert_eq!(AssetId::new("core", "textures/mods/stone").unwrap().vfs_path(), "textures/mods/stone");
+        assert_eq!(AssetId::new("core", "modsy/stone").unwrap().vfs_path(), "modsy/stone");
+        assert_eq!(AssetId::new("mymod", "mods/stone").unwrap().vfs_path(), "mods/mymod/mods/stone");
+    }
+
+    #[test]
+    fn ids_are_strings_in_json() {
+        let ids: Vec<AssetId> = serde_json::from_str(r#"["core:textures/stone", "shaders/basic"]"#).unwrap();
+        assert_eq!(ids[1], AssetId::new("core", "shaders/basic").unwrap());
+        assert_eq!(serde_json::to_string(&ids).unwrap(), r#"["core:textures/stone","core:shaders/basic"]"#);
+        assert!(serde_json::from_str::<AssetId>(r#""core:Textures/Stone""#).is_err());
+    }
+
+    #[test]
+    fn ids_find_their_files() {
+        use crate::vfs::Layer;
+        let mut vfs = Vfs::new();
+        vfs.mount("base", Layer::embedded(&[("textures/stone.jpg", b""), ("mods/mymod/textures/stone.png", b""), ("mods/mymod/models/rock.glb", b"")]));
+
+        let find = |id: &str, ty| AssetId::from_str(id).unwrap().find_file(ty, &vfs).map_err(|err| err.kind());
+        assert_eq!(find("textures/stone", AssetType::Texture), Ok(path::PathBuf::from("textures/stone.jpg")));
+        assert_eq!(find("mymod:textures/stone", AssetType::Texture), Ok(path::PathBuf::from("mods/mymod/textures/stone.png")));
+        assert_eq!(find("mymod:models/rock", AssetType::Model), Ok(path::PathBuf::from("mods/mymod/models/rock.glb")));
+        assert_eq!(find("mymod:models/rock", AssetType::Texture), Err(io::ErrorKind::NotFound));
+        assert_eq!(find("textures/skybox", AssetType::Cubemap), Ok(path::PathBuf::from("textures/skybox")));
+    }
+}
@@ -0,0 +1,497 @@
+//! Checks every asset in a directory without a window or GPU, so broken assets are caught in CI rather than when
+//! the game loads them:
+//!
+//! - Textures are decoded, and so are the faces of cubemaps: directories with all six faces, and the cubemaps the
+//!   manifest lists.
+//! - OBJ files are parsed and their meshes converted the way they're uploaded, with their sidecar's import settings.
+//!   glTF files have their buffers, primitives and images read.
+//! - MTL libraries are parsed, and the texture maps of their materials have to exist.
+//! - Material files have to parse, name textures that exist, and name a program from the manifest.
+//! - Vertex and fragment shaders are validated; other stages only have to be text.
+//! - The manifest (`manifest.json`) and `.meta` sidecars have to parse, and what the manifest lists has to exist.
+//!
+//! Everything is read from the directory as it is, without cooked files or resource packs over it.
+
+use crate::types::*;
+use crate::hl_assets::*;
+use crate::asset_loading::AssetManifest;
+use crate::asset_types::AssetType;
+use crate::namespace::AssetId;
+use crate::cook::{collect_files, is_cubemap_face};
+use crate::meta::{AssetMeta, MeshImport};
+use crate::vfs::{normalize, Layer, Vfs};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const MANIFEST: &str = "manifest.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckKind {
+    Texture,
+    Cubemap,
+    Model,
+    MaterialLibrary,
+    MaterialFile,
+    Shader,
+    Manifest,
+    Sidecar,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Problem {
+    pub message: String,
+    /// Where in the file the problem is, when that's known (for shaders).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AssetCheck {
+    /// Relative to the asset root, with `/` separators. Cubemaps are their directory.
+    pub path: String,
+    pub kind: CheckKind,
+    pub problems: Vec<Problem>,
+}
+
+/// Every asset that was checked, by path, whether or not it had problems.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ValidationReport {
+    pub assets: Vec<AssetCheck>,
+}
+
+impl Problem {
+    fn new<M: ToString>(message: M) -> Problem {
+        Problem {
+            message: message.to_string(),
+            line: None,
+            column: None,
+        }
+    }
+}
+
+impl From<ShaderDiagnostic> for Problem {
+    fn from(diagnostic: ShaderDiagnostic) -> Self {
+        Problem {
+            message: diagnostic.message,
+            line: Some(diagnostic.line),
+            column: Some(diagnostic.column),
+        }
+    }
+}
+
+impl ValidationReport {
+    pub fn problem_count(&self) -> usize {
+        self.assets.iter().map(|asset| asset.problems.len()).sum()
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.problem_count() == 0
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("reports always serialize")
+    }
+}
+
+/// Checks every asset under `root`. Only failing to walk the directory is an error; problems with the assets
+/// themselves are in the report.
+pub fn validate_directory<P: AsRef<Path>>(root: P) -> io::Result<ValidationReport> {
+    let root = root.as_ref();
+    let mut files = Vec::new();
+    collect_files(root, &mut files)?;
+    let mut files: Vec<PathBuf> = files.iter().map(|file| file.strip_prefix(root).expect("collected files are under the asset root").to_path_buf()).collect();
+    files.sort();
+
+    let mut vfs = Vfs::new();
+    vfs.mount("base", Layer::Directory(root.to_path_buf()));
+    let mut validator = Validator {
+        vfs: vfs,
+        manifest: None,
+        cubemaps: BTreeSet::new(),
+        report: ValidationReport::default(),
+    };
+
+    // The manifest first, since material files and cubemaps are checked against it.
+    if validator.vfs.exists(MANIFEST) {
+        validator.check_manifest();
+    }
+    for file in &files {
+        if is_cubemap_face(&root.join(file)) {
+            validator.cubemaps.insert(file.parent().expect("faces are in a directory").to_path_buf());
+        }
+    }
+    for file in &files {
+        let is_face = file.parent().is_some_and(|dir| {
+            validator.cubemaps.contains(dir) && CubemapPaths::in_directory(dir).files().contains(&file.as_path())
+        });
+        if !is_face {
+            validator.check_file(file);
+        }
+    }
+    for directory in validator.cubemaps.clone() {
+        validator.check_cubemap(&directory);
+    }
+
+    let mut report = validator.report;
+    report.assets.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(report)
+}
+
+struct Validator {
+    vfs: Vfs,
+    manifest: Option<AssetManifest>,
+    /// Directories to check as cubemaps.
+    cubemaps: BTreeSet<PathBuf>,
+    report: ValidationReport,
+}
+
+impl Validator {
+    fn record(&mut self, path: &Path, kind: CheckKind, problems: Vec<Problem>) {
+        self.report.assets.push(AssetCheck {
+            path: normalize(path).unwrap_or_else(|| path.to_string_lossy().into_owned()),
+            kind: kind,
+            problems: problems,
+        });
+    }
+
+    fn check_file(&mut self, path: &Path) {
+        let extension = path.extension().and_then(|ext| ext.to_str()).map(str::to_lowercase).unwrap_or_default();
+        let (kind, problems) = match extension.as_str() {
+            "png" | "jpg" | "jpeg" => (CheckKind::Texture, self.check_texture(path)),
+            "obj" => (CheckKind::Model, self.check_obj(path)),
+            "gltf" | "glb" => (CheckKind::Model, self.check_gltf(path)),
+            "mtl" => (CheckKind::MaterialLibrary, self.check_mtl(path)),
+            "json" if path == Path::new(MANIFEST) => return,
+            "json" => (CheckKind::MaterialFile, self.check_material_file(path)),
+            "meta" => (CheckKind::Sidecar, self.check_sidecar(path)),
+            _ => match ShaderType::from_extension(path) {
+                Some(shader_type) => (CheckKind::Shader, self.check_shader(path, shader_type)),
+                None => return,
+            },
+        };
+        self.record(path, kind, problems);
+    }
+
+    fn check_texture(&self, path: &Path) -> Vec<Problem> {
+        let decoded = self.vfs.read(path).map_err(Problem::new).and_then(|bytes| image::load_from_memory(&bytes).map(|_| ()).map_err(Problem::new));
+        decoded.err().into_iter().collect()
+    }
+
+    fn check_cubemap(&mut self, directory: &Path) {
+        let decoded = Texture::decode_cubemap_with(CubemapPaths::in_directory(directory), |face| self.vfs.read(face));
+        let problems = decoded.err().map(Problem::new).into_iter().collect();
+        self.record(directory, CheckKind::Cubemap, problems);
+    }
+
+    fn check_obj(&self, path: &Path) -> Vec<Problem> {
+        let obj_dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let load_mtl = |mtl: &Path| {
+            let bytes = self.vfs.read(obj_dir.join(mtl)).map_err(|_| tobj::LoadError::OpenFileFailed)?;
+            tobj::load_mtl_buf(&mut &bytes[..])
+        };
+        let import = match AssetMeta::read(&self.vfs, path) {
+            Ok(meta) => meta.map(|meta| meta.mesh_import()).unwrap_or_default(),
+            // Reported with the sidecar itself.
+            Err(_) => MeshImport::default(),
+        };
+        let cooked = self.vfs.read(path).map_err(ObjLoadError::from).and_then(|obj| ObjModel::cook(&obj, load_mtl, &import, false));
+        cooked.err().map(Problem::new).into_iter().collect()
+    }
+
+    fn check_gltf(&self, path: &Path) -> Vec<Problem> {
+        let read = |file: &Path| self.vfs.read(file).map(|bytes| bytes.into_owned());
+        let doc = match self.vfs.read(path).map_err(GltfError::from).and_then(|bytes| GltfDocument::from_slice(path, &bytes, &read)) {
+            Ok(doc) => doc,
+            Err(err) => return vec![Problem::new(err)],
+        };
+        let mut problems = Vec::new();
+        for index in 0..doc.image_count() {
+            let decoded = doc.image(index).map_err(Problem::new).and_then(|image| image.decode(&read).map(|_| ()).map_err(Problem::new));
+            problems.extend(decoded.err());
+        }
+        problems.extend(DecodedGltf::new(doc).err().map(Problem::new));
+        problems
+    }
+
+    fn check_mtl(&self, path: &Path) -> Vec<Problem> {
+        let materials = match self.vfs.read(path).map_err(Problem::new).and_then(|bytes| tobj::load_mtl_buf(&mut &bytes[..]).map_err(Problem::new)) {
+            Ok((materials, _)) => materials,
+            Err(problem) => return vec![problem],
+        };
+        let mtl_dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut problems = Vec::new();
+        for mat in materials {
+            let mat = Material::from(mat);
+            for &slot in MaterialSlot::ALL.iter() {
+                if let Some(tex_path) = mat.texture_path(slot) {
+                    if !self.vfs.exists(mtl_dir.join(tex_path)) {
+                        problems.push(Problem::new(format!("{}: the {:?} map {} doesn't exist", mat.name, slot, tex_path.display())));
+                    }
+                }
+            }
+        }
+        problems
+    }
+
+    fn check_material_file(&self, path: &Path) -> Vec<Problem> {
+        let file = match self.vfs.read(path).map_err(Problem::new).and_then(|bytes| MaterialFile::from_slice(&bytes).map_err(Problem::new)) {
+            Ok(file) => file,
+            Err(problem) => return vec![problem],
+        };
+        let mut problems = Vec::new();
+        if let Some(manifest) = &self.manifest {
+            if !manifest.programs.iter().any(|program| program.name == file.program) {
+                problems.push(Problem::new(format!("the program {} isn't in the manifest", file.program)));
+            }
+        }
+        for (sampler, binding) in &file.textures {
+            if !self.texture_exists(&binding.texture) {
+                problems.push(Problem::new(format!("{}: there's no texture or cubemap {}", sampler, binding.texture)));
+            }
+        }
+        problems
+    }
+
+    /// Whether a material file can bind the texture an id names: the manifest loads one under that name, or the id
+    /// names a texture file or a cubemap directory.
+    fn texture_exists(&self, id: &AssetId) -> bool {
+        let in_manifest = self.manifest.iter()
+            .flat_map(|manifest| manifest.textures.iter().chain(manifest.cubemaps.iter()))
+            .any(|entry| &entry.name == id);
+        in_manifest || id.find_file(AssetType::Texture, &self.vfs).is_ok() || self.cubemap_exists(Path::new(&id.vfs_path()))
+    }
+
+    fn cubemap_exists(&self, directory: &Path) -> bool {
+        CubemapPaths::in_directory(directory).files().iter().all(|face| self.vfs.exists(face))
+    }
+
+    fn check_shader(&self, path: &Path, shader_type: ShaderType) -> Vec<Problem> {
+        let src = match self.vfs.read_to_string(path) {
+            Ok(src) => src,
+            Err(err) => return vec![Problem::new(err)],
+        };
+        match shader_type {
+            ShaderType::VertexShader | ShaderType::FragmentShader => validate_glsl(path, &src, shader_type).into_iter().map(Problem::from).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn check_sidecar(&self, path: &Path) -> Vec<Problem> {
+        let parsed = self.vfs.read(path).and_then(|bytes| AssetMeta::from_slice(path, &bytes));
+        parsed.err().map(Problem::new).into_iter().collect()
+    }
+
+    /// Parses the manifest and checks that everything it lists is there; cubemaps it lists are checked later,
+    /// along with the ones found in the tree.
+    fn check_manifest(&mut self) {
+        let manifest = match self.vfs.read(MANIFEST).map_err(Problem::new).and_then(|bytes| AssetManifest::from_slice(&bytes).map_err(Problem::new)) {
+            Ok(manifest) => manifest,
+            Err(problem) => return self.record(Path::new(MANIFEST), CheckKind::Manifest, vec![problem]),
+        };
+
+        // What each file is needed for, and where it should be.
+        let mut required: Vec<(String, PathBuf)> = Vec::new();
+        let mut problems = Vec::new();
+        for program in &manifest.programs {
+            let shaders = [Some(&program.vertex), Some(&program.fragment), program.geometry.as_ref()];
+            required.extend(shaders.iter().flatten().map(|shader| (program.name.to_string(), Path::new("shaders").join(shader))));
+        }
+        for texture in &manifest.textures {
+            match &texture.path {
+                Some(path) => required.push((texture.name.to_string(), Path::new("textures").join(path))),
+                None => problems.extend(texture.name.find_file(AssetType::Texture, &self.vfs).err().map(Problem::new)),
+            }
+        }
+        required.extend(manifest.materials.iter().map(|library| (library.display().to_string(), library.clone())));
+        for material in &manifest.material_files {
+            problems.extend(material.find_file(AssetType::Material, &self.vfs).err().map(Problem::new));
+        }
+        for model in &manifest.models {
+            match &model.path {
+                Some(path) => required.push((model.name.to_string(), path.clone())),
+                None => problems.extend(model.name.find_file(AssetType::Model, &self.vfs).err().map(Problem::new)),
+            }
+            if !manifest.programs.iter().any(|program| program.name == model.program) {
+                problems.push(Problem::new(format!("{}: the program {} isn't in the manifest", model.name, model.program)));
+            }
+        }
+        for (what, path) in required {
+            if !self.vfs.exists(&path) {
+                problems.push(Problem::new(format!("{}: {} doesn't exist", what, path.display())));
+            }
+        }
+        for cubemap in &manifest.cubemaps {
+            let directory = match &cubemap.path {
+                Some(path) => Path::new("textures").join(path),
+                None => PathBuf::from(cubemap.name.vfs_path()),
+            };
+            self.cubemaps.insert(directory);
+        }
+
+        self.record(Path::new(MANIFEST), CheckKind::Manifest, problems);
+        self.manifest = Some(manifest);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn write(root: &Path, path: &str, contents: &[u8]) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn problems<'r>(report: &'r ValidationReport, path: &str) -> &'r [Problem] {
+        &report.assets.iter().find(|asset| asset.path == path).unwrap_or_else(|| panic!("{} wasn't checked", path)).problems
+    }
+
+    fn encoded(format: image::ImageOutputFormat) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        image::DynamicImage::ImageRgb8(image::RgbImage::new(2, 2)).write_to(&mut bytes, format).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn textures_are_decoded() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(root, "textures/stone.png", &encoded(image::ImageOutputFormat::Png));
+        write(root, "textures/broken.png", b"not a png");
+
+        let report = validate_directory(root).unwrap();
+        assert!(problems(&report, "textures/stone.png").is_empty());
+        assert_eq!(problems(&report, "textures/broken.png").len(), 1);
+        assert_eq!(report.problem_count(), 1);
+        assert!(!report.is_ok());
+    }
+
+    #[test]
+    fn cubemap_directories_are_found_without_a_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let jpg = encoded(image::ImageOutputFormat::Jpeg(90));
+        for face in &["top", "bottom", "left", "right", "front", "back"] {
+            write(root, &format!("textures/sky/{}.jpg", face), &jpg);
+            let contents: &[u8] = if *face == "back" { b"not a jpeg" } else { &jpg };
+            write(root, &format!("textures/night/{}.jpg", face), contents);
+        }
+        // Without all six faces it's just a directory of textures.
+        write(root, "textures/half/top.jpg", &jpg);
+
+        let report = validate_directory(root).unwrap();
+        assert!(report.assets.iter().all(|asset| !asset.path.starts_with("textures/sky/") && !asset.path.starts_with("textures/night/")));
+        assert!(problems(&report, "textures/sky").is_empty());
+        assert_eq!(problems(&report, "textures/night").len(), 1);
+        let half = report.assets.iter().find(|asset| asset.path == "textures/half/top.jpg").unwrap();
+        assert_eq!(half.kind, CheckKind::Texture);
+        assert!(report.to_json().contains(r#""kind": "cubemap""#));
+    }
+
+    #[test]
+    fn manifest_cubemaps_are_checked_as_a_whole() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(root, "textures/sky/top.jpg", b"");
+        write(root, MANIFEST, br#"{
+            "cubemaps": [{ "name": "core:textures/sky", "config": { "tex_type": "TextureCubeMap", "internal_fmt": "RGB", "pix_data_fmt": "RGB", "pix_type_fmt": "UnsignedByte" } }]
+        }"#);
+
+        let report = validate_directory(root).unwrap();
+        assert!(report.assets.iter().all(|asset| asset.path != "textures/sky/top.jpg"));
+        assert_eq!(problems(&report, "textures/sky").len(), 1);
+        assert!(problems(&report, MANIFEST).is_empty());
+    }
+
+    #[test]
+    fn shader_diagnostics_have_line_numbers() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(root, "shaders/ok.vert", b"#version 330 core\nlayout (location = 0) in vec3 aPos;\n\nvoid main()\n{\n    gl_Position = vec4(aPos, 1.0);\n}\n");
+        write(root, "shaders/broken.frag", b"#version 330 core\nout vec4 FragColor;\n\nvoid main()\n{\n    FragColor = vec4(missing, 1.0);\n}\n");
+        // Only vertex and fragment shaders are validated.
+        write(root, "shaders/unchecked.geom", b"not glsl at all");
+
+        let report = validate_directory(root).unwrap();
+        assert!(problems(&report, "shaders/ok.vert").is_empty());
+        assert!(problems(&report, "shaders/unchecked.geom").is_empty());
+        let broken = problems(&report, "shaders/broken.frag");
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].line, Some(6));
+        assert!(broken[0].column.is_some());
+        assert!(report.to_json().contains(r#""line": 6"#));
+    }
+
+    #[test]
+    fn material_libraries_need_their_maps() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(root, "textures/stone.png", &encoded(image::ImageOutputFormat::Png));
+        write(root, "models/crate.mtl", b"newmtl crate\nmap_Kd ../textures/stone.png\nmap_Bump ../textures/missing.png\n");
+
+        let report = validate_directory(root).unwrap();
+        let crate_problems = problems(&report, "models/crate.mtl");
+        assert_eq!(crate_problems.len(), 1);
+        assert!(crate_problems[0].message.contains("missing.png"));
+        assert!(report.to_json().contains(r#""kind": "material_library""#));
+    }
+
+    #[test]
+    fn material_files_need_their_program_and_textures() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(root, "textures/stone.png", &encoded(image::ImageOutputFormat::Png));
+        write(root, "shaders/lit.vert", b"#version 330 core\nvoid main()\n{\n    gl_Position = vec4(0.0);\n}\n");
+        write(root, "shaders/lit.frag", b"#version 330 core\nout vec4 FragColor;\nvoid main()\n{\n    FragColor = vec4(1.0);\n}\n");
+        write(root, "materials/stone.json", br#"{ "program": "core:lit", "textures": { "albedo": { "texture": "core:textures/stone" } } }"#);
+        write(root, "materials/grass.json", br#"{ "program": "core:unlit", "textures": { "albedo": { "texture": "core:textures/grass" } } }"#);
+        write(root, "materials/broken.json", b"{");
+        write(root, MANIFEST, br#"{ "programs": [{ "name": "core:lit", "vertex": "lit.vert", "fragment": "lit.frag" }] }"#);
+
+        let report = validate_directory(root).unwrap();
+        assert!(problems(&report, "materials/stone.json").is_empty());
+        // Neither its program nor its texture is there.
+        assert_eq!(problems(&report, "materials/grass.json").len(), 2);
+        assert_eq!(problems(&report, "materials/broken.json").len(), 1);
+        assert!(report.assets.iter().all(|asset| asset.path != MANIFEST || asset.problems.is_empty()));
+    }
+
+    #[test]
+    fn manifest_entries_have_to_exist() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(root, MANIFEST, br#"{
+            "programs": [{ "name": "core:lit", "vertex": "lit.vert", "fragment": "lit.frag" }],
+            "models": [{ "name": "core:models/crate", "program": "core:unlit" }]
+        }"#);
+
+        let report = validate_directory(root).unwrap();
+        // Both shaders, the model's file and its program.
+        assert_eq!(problems(&report, MANIFEST).len(), 4);
+
+        write(root, MANIFEST, b"{ \"programs\": 3 }");
+        let report = validate_directory(root).unwrap();
+        assert_eq!(problems(&report, MANIFEST).len(), 1);
+        assert_eq!(report.assets.len(), 1);
+    }
+
+    #[test]
+    fn sidecars_have_to_parse() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(root, "textures/stone.png", &encoded(image::ImageOutputFormat::Png));
+        write(root, "textures/stone.png.meta", b"{}");
+
+        let report = validate_directory(root).unwrap();
+        // A sidecar needs at least its guid.
+        assert_eq!(problems(&report, "textures/stone.png.meta").len(), 1);
+        assert_eq!(report.assets.iter().find(|asset| asset.path == "textures/stone.png.meta").unwrap().kind, CheckKind::Sidecar);
+    }
+}
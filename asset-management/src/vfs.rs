@@ -0,0 +1,287 @@
+//! One view over every place assets can come from. Layers are stacked in the order they're mounted, and a file in a
+//! higher layer hides the same path in every layer below it, so a resource pack mounted over the base assets replaces
+//! just the files it has.
+//!
+//! Paths are relative to the asset root of every layer, like `textures/grass.png`.
+
+use crate::archive::Archive;
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+/// Turns a path into the form every layer addresses files by: '/'-separated, without `.` or `..`.
+/// `None` for absolute paths and ones that climb out of the asset root.
+pub fn normalize<P: AsRef<Path>>(path: P) -> Option<String> {
+    let mut parts: Vec<&str> = Vec::new();
+    for component in path.as_ref().components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_str()?),
+            Component::CurDir => {},
+            Component::ParentDir => { parts.pop()?; },
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(parts.join("/"))
+}
+
+pub enum Layer {
+    /// Loose files under a directory.
+    Directory(PathBuf),
+    /// A packed archive written by `ace-pack`.
+    Archive(Archive),
+    /// Files compiled into the binary, by path. See `embed_assets!`.
+    Embedded(HashMap<String, &'static [u8]>),
+}
+
+impl Layer {
+    pub fn embedded(files: &[(&str, &'static [u8])]) -> Layer {
+        Layer::Embedded(files.iter().filter_map(|&(path, bytes)| Some((normalize(path)?, bytes))).collect())
+    }
+
+    /// `None` if the layer doesn't have the file, so the layers below get a look.
+    fn read(&self, path: &str) -> Option<io::Result<Cow<'_, [u8]>>> {
+        match self {
+            Layer::Directory(dir) => match fs::read(dir.join(path)) {
+                Ok(bytes) => Some(Ok(Cow::Owned(bytes))),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+                Err(err) => Some(Err(err)),
+            },
+            Layer::Archive(archive) if archive.contains(path) => {
+                Some(archive.read(path).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)))
+            },
+            Layer::Archive(_) => None,
+            Layer::Embedded(files) => files.get(path).map(|&bytes| Ok(Cow::Borrowed(bytes))),
+        }
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        match self {
+            Layer::Directory(dir) => dir.join(path).is_file(),
+            Layer::Archive(archive) => archive.contains(path),
+            Layer::Embedded(files) => files.contains_key(path),
+        }
+    }
+
+    /// Names of the files directly inside `dir`.
+    fn list(&self, dir: &str, names: &mut BTreeSet<String>) {
+        let direct_child = |path: &str| {
+            let rest = if dir.is_empty() { Some(path) } else { path.strip_prefix(dir)?.strip_prefix('/') };
+            rest.filter(|rest| !rest.contains('/')).map(str::to_owned)
+        };
+        match self {
+            Layer::Directory(root) => {
+                if let Ok(entries) = fs::read_dir(root.join(dir)) {
+                    for entry in entries.flatten() {
+                        if entry.path().is_file() {
+                            names.extend(entry.file_name().to_str().map(str::to_owned));
+                        }
+                    }
+                }
+            },
+            Layer::Archive(archive) => names.extend(archive.entries().filter_map(|entry| direct_child(&entry.path))),
+            Layer::Embedded(files) => names.extend(files.keys().filter_map(|path| direct_child(path))),
+        }
+    }
+
+    fn modified(&self, path: &str) -> Option<SystemTime> {
+        match self {
+            Layer::Directory(dir) => fs::metadata(dir.join(path)).and_then(|metadata| metadata.modified()).ok(),
+            // Neither changes while it's mounted.
+            Layer::Archive(_) | Layer::Embedded(_) => None,
+        }
+    }
+}
+
+/// Which mounted layer a file is read from, and when it was last modified there. Any change to either means the file
+/// changed: it was edited, or a layer that overrides it was mounted or unmounted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileVersion {
+    mount: u64,
+    modified: Option<SystemTime>,
+}
+
+// Every mount gets its own id, so remounting a layer under the same name still counts as a change.
+static MOUNT_IDS: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone)]
+struct Mount {
+    name: String,
+    id: u64,
+    layer: Arc<Layer>,
+}
+
+/// The mounted layers, lowest first. Cloning is cheap and shares the layers, so background loads can take a copy along.
+#[derive(Clone, Default)]
+pub struct Vfs {
+    mounts: Vec<Mount>,
+}
+
+impl Vfs {
+    pub fn new() -> Vfs {
+        Vfs::default()
+    }
+
+    /// Mounts a layer on top of the others. Mounting under a name that's already mounted replaces that layer,
+    /// keeping its place in the stack.
+    pub fn mount<S: ToString>(&mut self, name: S, layer: Layer) {
+        let mount = Mount {
+            name: name.to_string(),
+            id: MOUNT_IDS.fetch_add(1, Ordering::Relaxed),
+            layer: Arc::new(layer),
+        };
+        match self.mounts.iter_mut().find(|existing| existing.name == mount.name) {
+            Some(existing) => *existing = mount,
+            None => self.mounts.push(mount),
+        }
+    }
+
+    pub fn unmount(&mut self, name: &str) -> bool {
+        let before = self.mounts.len();
+        self.mounts.retain(|mount| mount.name != name);
+        self.mounts.len() != before
+    }
+
+    /// Names of the mounted layers, lowest first.
+    pub fn mounts(&self) -> impl Iterator<Item = &str> {
+        self.mounts.iter().map(|mount| mount.name.as_str())
+    }
+
+    /// The highest layer that has the file.
+    fn find(&self, path: &str) -> Option<&Mount> {
+        self.mounts.iter().rev().find(|mount| mount.layer.contains(path))
+    }
+
+    /// Reads a file from the highest layer that has it. Files in archives and embedded files are borrowed where they can be.
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Cow<'_, [u8]>> {
+        let path = path.as_ref();
+        let normalized = normalize(path).ok_or_else(|| not_found(path))?;
+        for mount in self.mounts.iter().rev() {
+            if let Some(result) = mount.layer.read(&normalized) {
+                return result;
+            }
+        }
+        Err(not_found(path))
+    }
+
+    pub fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        let bytes = self.read(path)?.into_owned();
+        String::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        normalize(path).is_some_and(|path| self.find(&path).is_some())
+    }
+
+    /// Names of the files directly inside a directory, across every layer.
+    pub fn list<P: AsRef<Path>>(&self, dir: P) -> Vec<String> {
+        let mut names = BTreeSet::new();
+        if let Some(dir) = normalize(dir) {
+            for mount in &self.mounts {
+                mount.layer.list(&dir, &mut names);
+            }
+        }
+        names.into_iter().collect()
+    }
+
+    /// Position of the highest layer that has the file, counting up from the lowest at 0.
+    /// Tells whether one file is as much of an override as another, like a cooked file and its source.
+    pub fn layer_index<P: AsRef<Path>>(&self, path: P) -> Option<usize> {
+        let path = normalize(path)?;
+        self.mounts.iter().rposition(|mount| mount.layer.contains(&path))
+    }
+
+    /// `None` if no layer has the file.
+    pub fn version<P: AsRef<Path>>(&self, path: P) -> Option<FileVersion> {
+        let path = normalize(path)?;
+        let mount = self.find(&path)?;
+        Some(FileVersion {
+            mount: mount.id,
+            modified: mount.layer.modified(&path),
+        })
+    }
+
+    /// Where the file is on disk, if the layer it's read from is a directory.
+    pub fn real_path<P: AsRef<Path>>(&self, path: P) -> Option<PathBuf> {
+        let path = normalize(path)?;
+        match self.find(&path)?.layer.as_ref() {
+            Layer::Directory(dir) => Some(dir.join(path)),
+            _ => None,
+        }
+    }
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("{} isn't in any mounted layer", path.display()))
+}
+
+/// Builds a `Layer::Embedded` out of files under `root` (a path `include_bytes!` understands, such as
+/// `concat!(env!("CARGO_MANIFEST_DIR"), "/assets")`), compiled into the binary under their paths relative to it.
+///
+/// ```ignore
+/// let layer = embed_assets!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets"), ["shaders/basic.vert", "shaders/basic.frag"]);
+/// ```
+#[macro_export]
+macro_rules! embed_assets {
+    ($root:expr, [$($path:literal),* $(,)?]) => {
+        $crate::vfs::Layer::embedded(&[$(($path, include_bytes!(concat!($root, "/", $path)) as &'static [u8])),*])
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directory(files: &[(&str, &str)]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (path, contents) in files {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn paths_are_normalized() {
+        assert_eq!(normalize("./textures/../shaders/basic.vert"), Some("shaders/basic.vert".to_string()));
+        assert_eq!(normalize("textures/blocks/grass.png"), Some("textures/blocks/grass.png".to_string()));
+        assert_eq!(normalize("../outside.png"), None);
+        assert_eq!(normalize("/etc/passwd"), None);
+    }
+
+    #[test]
+    fn higher_layers_override_lower_ones() {
+        let base = directory(&[("textures/grass.png", "base grass"), ("textures/dirt.png", "base dirt"), ("shaders/basic.vert", "base shader")]);
+        let pack = directory(&[("textures/grass.png", "pack grass"), ("textures/flowers.png", "pack flowers")]);
+
+        let mut vfs = Vfs::new();
+        vfs.mount("base", Layer::Directory(base.path().to_path_buf()));
+        vfs.mount("builtin", Layer::embedded(&[("shaders/basic.vert", b"builtin shader"), ("shaders/basic.frag", b"builtin fragment")]));
+        let before = vfs.version("textures/grass.png");
+        vfs.mount("pack", Layer::Directory(pack.path().to_path_buf()));
+
+        assert_eq!(vfs.read("textures/grass.png").unwrap().as_ref(), b"pack grass");
+        assert_eq!(vfs.read("textures/dirt.png").unwrap().as_ref(), b"base dirt");
+        assert_eq!(vfs.read_to_string("./shaders/basic.vert").unwrap(), "builtin shader");
+        assert_eq!(vfs.read("textures/missing.png").unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(vfs.list("textures"), vec!["dirt.png", "flowers.png", "grass.png"]);
+        assert_eq!(vfs.list("shaders"), vec!["basic.frag", "basic.vert"]);
+        assert_eq!(vfs.real_path("textures/grass.png"), Some(pack.path().join("textures/grass.png")));
+        assert_eq!(vfs.real_path("shaders/basic.vert"), None);
+        assert_eq!(vfs.layer_index("textures/dirt.png"), Some(0));
+        assert_eq!(vfs.layer_index("textures/grass.png"), Some(2));
+        assert_eq!(vfs.layer_index("textures/missing.png"), None);
+
+        // Mounting the pack changed which layer the texture comes from; unmounting it changes it back.
+        assert_ne!(vfs.version("textures/grass.png"), before);
+        assert!(vfs.unmount("pack"));
+        assert_eq!(vfs.version("textures/grass.png"), before);
+        assert_eq!(vfs.read("textures/grass.png").unwrap().as_ref(), b"base grass");
+        assert_eq!(vfs.mounts().collect::<Vec<_>>(), vec!["base", "builtin"]);
+    }
+}